@@ -0,0 +1,130 @@
+//! Benchmarks for the message pipeline, i.e. what happens to each message that goes
+//! through the gossipsub or request-response paths.
+//!
+//! Run with `cargo bench -p dkn-compute`, see `docs/NODE_PERFORMANCE.md` for a baseline.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dkn_compute::{
+    payloads::{TaskResponsePayload, TaskStats},
+    utils::{crypto::public_key_to_peer_id, DriaMessage},
+};
+use dkn_p2p::DriaP2PProtocol;
+use libsecp256k1::{PublicKey, SecretKey};
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+
+/// Payload sizes in bytes, a small pingpong-like message and a large task result.
+const PAYLOAD_SIZES: [usize; 2] = [256, 64 * 1024];
+
+/// Number of messages sent through the channel per iteration.
+const CHANNEL_MESSAGES: usize = 1000;
+
+/// Returns a JSON string payload of roughly the given size.
+fn new_payload(size: usize) -> String {
+    serde_json::to_string(&"a".repeat(size)).unwrap()
+}
+
+fn new_message(size: usize, secret_key: &SecretKey) -> DriaMessage {
+    DriaMessage::new(
+        new_payload(size),
+        "bench",
+        &DriaP2PProtocol::default(),
+        secret_key,
+    )
+}
+
+fn bench_serialization(c: &mut Criterion) {
+    let secret_key = SecretKey::random(&mut rand::thread_rng());
+    let mut group = c.benchmark_group("serialization");
+    for size in PAYLOAD_SIZES {
+        let message = new_message(size, &secret_key);
+        let bytes = message.to_bytes().unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("serialize", size), &message, |b, m| {
+            b.iter(|| black_box(m.to_bytes().unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("deserialize", size), &bytes, |b, data| {
+            b.iter(|| black_box(serde_json::from_slice::<DriaMessage>(data).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("parse_payload", size), &message, |b, m| {
+            b.iter(|| black_box(m.parse_payload::<String>().unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_signing(c: &mut Criterion) {
+    let secret_key = SecretKey::random(&mut rand::thread_rng());
+    let public_key = PublicKey::from_secret_key(&secret_key);
+    let authorized = HashSet::from([public_key_to_peer_id(&public_key)]);
+
+    let mut group = c.benchmark_group("signing");
+    for size in PAYLOAD_SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        let payload = new_payload(size);
+
+        group.bench_with_input(BenchmarkId::new("sign", size), &payload, |b, p| {
+            let protocol = DriaP2PProtocol::default();
+            b.iter(|| black_box(DriaMessage::new(p, "bench", &protocol, &secret_key)))
+        });
+
+        let message = new_message(size, &secret_key);
+        group.bench_with_input(BenchmarkId::new("verify", size), &message, |b, m| {
+            b.iter(|| assert!(m.is_signed(&authorized).unwrap()))
+        });
+
+        group.bench_with_input(BenchmarkId::new("encrypt", size), &payload, |b, p| {
+            b.iter(|| {
+                black_box(
+                    TaskResponsePayload::new(
+                        p,
+                        "task-id",
+                        &public_key,
+                        "model".to_string(),
+                        TaskStats::new(),
+                    )
+                    .unwrap(),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_channel(c: &mut Criterion) {
+    let secret_key = SecretKey::random(&mut rand::thread_rng());
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("channel");
+    group.throughput(Throughput::Elements(CHANNEL_MESSAGES as u64));
+    for size in PAYLOAD_SIZES {
+        let bytes = new_message(size, &secret_key).to_bytes().unwrap();
+
+        // mimics the p2p client handing over raw messages to the node
+        group.bench_with_input(BenchmarkId::new("mpsc", size), &bytes, |b, data| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(1024);
+                    let data = data.clone();
+                    let sender = tokio::spawn(async move {
+                        for _ in 0..CHANNEL_MESSAGES {
+                            tx.send(data.clone()).await.unwrap();
+                        }
+                    });
+                    while let Some(data) = rx.recv().await {
+                        black_box(serde_json::from_slice::<DriaMessage>(&data).unwrap());
+                    }
+                    sender.await.unwrap();
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_serialization, bench_signing, bench_channel);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+#![no_main]
+
+use dkn_compute::utils::DriaMessage;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = serde_json::from_slice::<DriaMessage>(data) {
+        let _ = message.parse_payload::<serde_json::Value>();
+        let _ = message.to_string();
+    }
+});
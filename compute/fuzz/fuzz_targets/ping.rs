@@ -0,0 +1,8 @@
+#![no_main]
+
+use dkn_compute::gossipsub::PingpongPayload;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = serde_json::from_slice::<PingpongPayload>(data);
+});
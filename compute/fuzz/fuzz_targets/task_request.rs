@@ -0,0 +1,16 @@
+#![no_main]
+
+use dkn_compute::{
+    payloads::TaskRequestPayload,
+    reqres::{IsResponder, SpecResponder, TaskPayload, TaskResponder},
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = SpecResponder::try_parse_request(data);
+    if let Ok(message) = TaskResponder::try_parse_request(data) {
+        if let Ok(task) = message.parse_payload::<TaskRequestPayload<TaskPayload>>() {
+            let _ = task.filter.contains(b"address");
+        }
+    }
+});
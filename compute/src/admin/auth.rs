@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::AdminCommand;
+
+/// Domain separator for the token derivation, so that tokens are not reused elsewhere.
+const TOKEN_DOMAIN: &[u8] = b"dkn-admin-token";
+
+/// Scope of an admin token, a `control` token can also be used for `read` commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminScope {
+    /// Commands that only query the node.
+    Read,
+    /// Commands that change the state of the node.
+    Control,
+}
+
+impl AdminScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminScope::Read => "read",
+            AdminScope::Control => "control",
+        }
+    }
+}
+
+impl AdminCommand {
+    /// Returns the scope required to run this command.
+    pub fn scope(&self) -> AdminScope {
+        match self {
+            AdminCommand::Status
+            | AdminCommand::DeadLetters
+            | AdminCommand::StatsQuery
+            | AdminCommand::BlockedPeers
+            | AdminCommand::Metrics => AdminScope::Read,
+            AdminCommand::Pause
+            | AdminCommand::Resume
+            | AdminCommand::Reload
+            | AdminCommand::Shutdown => AdminScope::Control,
+        }
+    }
+}
+
+/// Scoped admin tokens, derived from a secret such as the wallet key.
+#[derive(Clone)]
+pub struct AdminAuth {
+    read_token: String,
+    control_token: String,
+}
+
+impl AdminAuth {
+    pub fn new(secret: &[u8]) -> Self {
+        let derive = |scope: AdminScope| {
+            hex::encode(
+                Sha256::new()
+                    .chain_update(TOKEN_DOMAIN)
+                    .chain_update(secret)
+                    .chain_update(scope.as_str())
+                    .finalize(),
+            )
+        };
+
+        Self {
+            read_token: derive(AdminScope::Read),
+            control_token: derive(AdminScope::Control),
+        }
+    }
+
+    /// Returns the token for the given scope.
+    pub fn token(&self, scope: AdminScope) -> &str {
+        match scope {
+            AdminScope::Read => &self.read_token,
+            AdminScope::Control => &self.control_token,
+        }
+    }
+
+    /// Checks that the given token is valid for the required scope.
+    pub fn authorize(&self, token: Option<&str>, required: AdminScope) -> Result<(), String> {
+        let token = token.ok_or_else(|| "missing token".to_string())?;
+
+        let scope = if constant_time_eq(token, &self.control_token) {
+            AdminScope::Control
+        } else if constant_time_eq(token, &self.read_token) {
+            AdminScope::Read
+        } else {
+            return Err("invalid token".to_string());
+        };
+
+        if scope < required {
+            return Err(format!("token does not have {} scope", required.as_str()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares two strings without returning early, so that the tokens can not be guessed by timing.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_auth() {
+        let auth = AdminAuth::new(b"secret");
+        let read = auth.token(AdminScope::Read).to_string();
+        let control = auth.token(AdminScope::Control).to_string();
+        assert_ne!(read, control);
+
+        assert!(auth.authorize(Some(&read), AdminScope::Read).is_ok());
+        assert!(auth.authorize(Some(&read), AdminScope::Control).is_err());
+        assert!(auth.authorize(Some(&control), AdminScope::Control).is_ok());
+        assert!(auth.authorize(Some(&control), AdminScope::Read).is_ok());
+        assert!(auth.authorize(Some("invalid"), AdminScope::Read).is_err());
+        assert!(auth.authorize(None, AdminScope::Read).is_err());
+
+        // tokens depend on the secret
+        let other = AdminAuth::new(b"other");
+        assert!(other.authorize(Some(&read), AdminScope::Read).is_err());
+    }
+}
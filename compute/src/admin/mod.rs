@@ -0,0 +1,176 @@
+//! Local administration channel, used to query & control a running node.
+//!
+//! Requests are newline-delimited JSON objects such as `{"command":"status"}`,
+//! and each request is answered with a single JSON line.
+//!
+//! If authentication is enabled, requests must carry a `token` with the scope of the command.
+
+use dkn_p2p::ChannelStats;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write;
+use tokio::sync::oneshot;
+
+mod auth;
+pub use auth::{AdminAuth, AdminScope};
+
+#[cfg(unix)]
+mod server;
+#[cfg(unix)]
+pub use server::AdminServer;
+
+/// A command that can be sent to the node over the admin channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminCommand {
+    /// Returns the current status of the node.
+    Status,
+    /// Stops accepting new tasks & responding to pings, pending tasks are still completed.
+    Pause,
+    /// Resumes accepting tasks after a `pause`.
+    Resume,
+    /// Refreshes the available Dria nodes and re-dials the RPCs.
+    Reload,
+    /// Returns the recent messages & requests that could not be processed.
+    #[serde(rename = "dead-letters")]
+    DeadLetters,
+    /// Runs a read-only SQL query over the task statistics, given within `sql`.
+    #[serde(rename = "stats-query")]
+    StatsQuery,
+    /// Returns the peers that are blocked due to their unauthorized requests.
+    #[serde(rename = "blocked-peers")]
+    BlockedPeers,
+    /// Returns the metrics of the node in the Prometheus text format, as a string.
+    Metrics,
+    /// Gracefully shuts down the node, same as receiving a termination signal.
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminRequest {
+    pub command: AdminCommand,
+    /// Admin token, required if authentication is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// SQL query for `stats-query`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sql: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminResponse {
+    /// Whether the command was handled successfully.
+    pub ok: bool,
+    /// Command-specific data, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    /// Error message, if the command has failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AdminResponse {
+    /// A successful response without any data.
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            data: None,
+            error: None,
+        }
+    }
+
+    /// A successful response with the given data.
+    pub fn with_data(data: Value) -> Self {
+        Self {
+            ok: true,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    /// A failed response with the given error message.
+    pub fn error(error: impl ToString) -> Self {
+        Self {
+            ok: false,
+            data: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// An admin command forwarded to the node, along with the channel to respond to.
+pub type AdminMessage = (AdminCommand, oneshot::Sender<AdminResponse>);
+
+/// Renders the channel stats in the Prometheus text format, so that their saturation can be scraped.
+pub fn channel_metrics(stats: &[(&str, ChannelStats)]) -> String {
+    let mut output = String::new();
+    let mut write_metric = |name: &str, kind: &str, help: &str, value: fn(&ChannelStats) -> u64| {
+        let _ = writeln!(output, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        for (channel, channel_stats) in stats {
+            let _ = writeln!(
+                output,
+                "{}{{channel=\"{}\"}} {}",
+                name,
+                channel,
+                value(channel_stats)
+            );
+        }
+    };
+
+    write_metric(
+        "dkn_channel_depth",
+        "gauge",
+        "Number of items waiting within the channel.",
+        |s| s.depth as u64,
+    );
+    write_metric(
+        "dkn_channel_capacity",
+        "gauge",
+        "Maximum number of items that the channel can hold.",
+        |s| s.capacity as u64,
+    );
+    write_metric(
+        "dkn_channel_dropped_total",
+        "counter",
+        "Number of items that could not be sent to the channel.",
+        |s| s.dropped,
+    );
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_request_parsing() {
+        let request: AdminRequest = serde_json::from_str(r#"{"command":"status"}"#).unwrap();
+        assert_eq!(request.command, AdminCommand::Status);
+        let request: AdminRequest = serde_json::from_str(r#"{"command":"dead-letters"}"#).unwrap();
+        assert_eq!(request.command, AdminCommand::DeadLetters);
+        let request: AdminRequest =
+            serde_json::from_str(r#"{"command":"stats-query","sql":"SELECT 1"}"#).unwrap();
+        assert_eq!(request.command, AdminCommand::StatsQuery);
+        assert_eq!(request.sql.as_deref(), Some("SELECT 1"));
+
+        assert!(serde_json::from_str::<AdminRequest>(r#"{"command":"explode"}"#).is_err());
+
+        let response = serde_json::to_string(&AdminResponse::error("nope")).unwrap();
+        assert_eq!(response, r#"{"ok":false,"error":"nope"}"#);
+    }
+
+    #[test]
+    fn test_channel_metrics() {
+        let stats = ChannelStats {
+            depth: 3,
+            capacity: 64,
+            dropped: 1,
+        };
+        let metrics = channel_metrics(&[("publish", stats)]);
+        assert!(metrics.contains("# TYPE dkn_channel_depth gauge\n"));
+        assert!(metrics.contains("dkn_channel_depth{channel=\"publish\"} 3\n"));
+        assert!(metrics.contains("dkn_channel_capacity{channel=\"publish\"} 64\n"));
+        assert!(metrics.contains("dkn_channel_dropped_total{channel=\"publish\"} 1\n"));
+    }
+}
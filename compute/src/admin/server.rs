@@ -0,0 +1,187 @@
+use eyre::{Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, oneshot},
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{AdminAuth, AdminCommand, AdminMessage, AdminRequest, AdminResponse};
+use crate::utils::StatsDb;
+
+/// Admin server listening on a unix socket, forwards commands to the node.
+pub struct AdminServer {
+    /// Path to the socket file, removed on exit.
+    path: PathBuf,
+    /// Listener for incoming connections.
+    listener: UnixListener,
+    /// Admin command sender, the receiver is the compute node itself.
+    node_tx: mpsc::Sender<AdminMessage>,
+    /// Cancellation token, used for `shutdown` and to stop the server.
+    cancellation: CancellationToken,
+    /// Token authentication, if enabled.
+    auth: Option<AdminAuth>,
+    /// Path to the task statistics database, if enabled.
+    stats_db: Option<PathBuf>,
+}
+
+impl AdminServer {
+    /// Binds to the socket at the given path, removing a stale socket file if there is one.
+    ///
+    /// The socket is only accessible by the owner of the process.
+    pub fn new(
+        path: impl AsRef<Path>,
+        node_tx: mpsc::Sender<AdminMessage>,
+        cancellation: CancellationToken,
+    ) -> Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            std::fs::remove_file(&path).wrap_err("could not remove stale admin socket")?;
+        }
+
+        let listener = UnixListener::bind(&path).wrap_err("could not bind admin socket")?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .wrap_err("could not set admin socket permissions")?;
+
+        Ok(Self {
+            path,
+            listener,
+            node_tx,
+            cancellation,
+            auth: None,
+            stats_db: None,
+        })
+    }
+
+    /// Requires each request to carry a token with the scope of its command.
+    pub fn with_auth(mut self, auth: AdminAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Answers `stats-query` commands from the task statistics database at the given path.
+    pub fn with_stats_db(mut self, path: impl AsRef<Path>) -> Self {
+        self.stats_db = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Accepts connections until cancellation, each connection is handled in its own task.
+    pub async fn run(self) {
+        log::info!(
+            "Admin socket listening at {} (authentication: {})",
+            self.path.display(),
+            if self.auth.is_some() { "on" } else { "off" }
+        );
+
+        loop {
+            tokio::select! {
+                conn = self.listener.accept() => match conn {
+                    Ok((stream, _)) => {
+                        let node_tx = self.node_tx.clone();
+                        let cancellation = self.cancellation.clone();
+                        let auth = self.auth.clone();
+                        let stats_db = self.stats_db.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::handle_connection(stream, node_tx, cancellation, auth, stats_db).await {
+                                log::warn!("Admin connection error: {:?}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::error!("Could not accept admin connection: {:?}", e),
+                },
+                _ = self.cancellation.cancelled() => break,
+            }
+        }
+
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Could not remove admin socket: {:?}", e);
+        }
+        log::info!("Closing admin socket.");
+    }
+
+    /// Reads requests line by line and writes a response line for each.
+    async fn handle_connection(
+        stream: UnixStream,
+        node_tx: mpsc::Sender<AdminMessage>,
+        cancellation: CancellationToken,
+        auth: Option<AdminAuth>,
+        stats_db: Option<PathBuf>,
+    ) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<AdminRequest>(&line) {
+                Ok(request) => {
+                    log::info!("Received admin command: {:?}", request.command);
+                    let authorized = auth.as_ref().map_or(Ok(()), |auth| {
+                        auth.authorize(request.token.as_deref(), request.command.scope())
+                    });
+                    match authorized {
+                        Ok(()) if request.command == AdminCommand::StatsQuery => {
+                            Self::handle_stats_query(request.sql, stats_db.clone()).await
+                        }
+                        Ok(()) => {
+                            Self::handle_command(request.command, &node_tx, &cancellation).await
+                        }
+                        Err(e) => {
+                            log::warn!("Unauthorized admin command {:?}: {}", request.command, e);
+                            AdminResponse::error(format!("unauthorized: {}", e))
+                        }
+                    }
+                }
+                Err(e) => AdminResponse::error(format!("invalid request: {}", e)),
+            };
+
+            let mut response_data = serde_json::to_vec(&response)?;
+            response_data.push(b'\n');
+            writer.write_all(&response_data).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles `shutdown` here by cancelling, and forwards the rest to the node.
+    async fn handle_command(
+        command: AdminCommand,
+        node_tx: &mpsc::Sender<AdminMessage>,
+        cancellation: &CancellationToken,
+    ) -> AdminResponse {
+        if command == AdminCommand::Shutdown {
+            cancellation.cancel();
+            return AdminResponse::ok();
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        if node_tx.send((command, sender)).await.is_err() {
+            return AdminResponse::error("node is not running");
+        }
+
+        receiver
+            .await
+            .unwrap_or_else(|_| AdminResponse::error("node did not respond"))
+    }
+
+    /// Runs a read-only query over the task statistics, in a blocking task as SQLite is synchronous.
+    async fn handle_stats_query(sql: Option<String>, stats_db: Option<PathBuf>) -> AdminResponse {
+        let Some(path) = stats_db else {
+            return AdminResponse::error("task statistics are not enabled");
+        };
+        let Some(sql) = sql else {
+            return AdminResponse::error("missing sql");
+        };
+
+        match tokio::task::spawn_blocking(move || StatsDb::query(path, &sql)).await {
+            Ok(Ok(data)) => AdminResponse::with_data(data),
+            Ok(Err(e)) => AdminResponse::error(format!("{:#}", e)),
+            Err(e) => AdminResponse::error(format!("query failed: {}", e)),
+        }
+    }
+}
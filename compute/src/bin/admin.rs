@@ -0,0 +1,52 @@
+//! Prints the tokens for the admin socket, when authentication is enabled with `DKN_ADMIN_AUTH=true`.
+//!
+//! ```sh
+//! # print a token for read-only commands, e.g. `status`
+//! cargo run --bin admin -- token read
+//!
+//! # print a token for all commands, e.g. `pause` and `shutdown`
+//! cargo run --bin admin -- token control
+//! ```
+//!
+//! Reads `DKN_ADMIN_SECRET` from the environment, or `DKN_WALLET_SECRET_KEY` if it is not set.
+
+use dkn_compute::admin::{AdminAuth, AdminScope};
+use dkn_utils::safe_read_env;
+use eyre::{eyre, Context, Result};
+use libsecp256k1::SecretKey;
+use std::env;
+
+const USAGE: &str = "usage: admin token <read | control>";
+
+fn main() -> Result<()> {
+    let _ = dotenvy::dotenv();
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let scope = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["token", "read"] => AdminScope::Read,
+        ["token", "control"] => AdminScope::Control,
+        _ => return Err(eyre!(USAGE)),
+    };
+
+    let auth = match safe_read_env(env::var("DKN_ADMIN_SECRET")) {
+        Some(secret) => AdminAuth::new(secret.as_bytes()),
+        None => {
+            let secret_env = env::var("DKN_WALLET_SECRET_KEY")
+                .wrap_err("neither DKN_ADMIN_SECRET nor DKN_WALLET_SECRET_KEY is set")?;
+            let secret_key = SecretKey::parse_slice(
+                &hex::decode(secret_env.trim_start_matches("0x"))
+                    .wrap_err("could not decode secret key")?,
+            )
+            .map_err(|e| eyre!("could not parse secret key: {}", e))?;
+            AdminAuth::new(&secret_key.serialize())
+        }
+    };
+
+    println!("{}", auth.token(scope));
+    Ok(())
+}
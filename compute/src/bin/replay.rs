@@ -0,0 +1,67 @@
+//! Replays a recorded task locally and compares the output with the recorded one.
+//!
+//! ```sh
+//! cargo run --bin replay -- ./task-dump.json
+//! ```
+
+use dkn_compute::{payloads::TaskDump, workers::replay_task};
+use dkn_workflows::{DriaWorkflowsConfig, WorkflowExecutor};
+use eyre::{Context, Result};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenvy::dotenv();
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Off)
+        .filter_module("replay", log::LevelFilter::Info)
+        .filter_module("dkn_compute", log::LevelFilter::Info)
+        .filter_module("dkn_workflows", log::LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    let path = std::env::args()
+        .nth(1)
+        .ok_or(eyre::eyre!("usage: replay <task-dump.json>"))?;
+    let dump: TaskDump = serde_json::from_slice(
+        &std::fs::read(&path).wrap_err_with(|| format!("could not read {}", path))?,
+    )
+    .wrap_err("could not parse task dump")?;
+    log::info!("Replaying task {} with model {}", dump.task_id, dump.model);
+
+    // only the recorded model is configured, and checked as usual
+    let mut workflows = DriaWorkflowsConfig::new_from_csv(&dump.model);
+    workflows.check_services().await?;
+
+    let output = replay_task(&dump, &workflows, &WorkflowExecutor::factory()).await?;
+    let replayed = output.result.map_err(|e| format!("{:#}", e));
+    let recorded = match (dump.result, dump.error) {
+        (Some(result), _) => Ok(result),
+        (None, Some(error)) => Err(error),
+        (None, None) => Err("nothing recorded".to_string()),
+    };
+
+    println!("Recorded: {:?}", recorded);
+    println!("Replayed: {:?}", replayed);
+    println!(
+        "Outputs {}.",
+        if recorded == replayed {
+            "match"
+        } else {
+            "differ"
+        }
+    );
+
+    // compare execution times as well, if recorded
+    let secs = |stats: &dkn_compute::payloads::TaskStats| {
+        (stats.execution_ended_at - stats.execution_started_at) as f64 / 1_000_000_000f64
+    };
+    if let Some(stats) = dump.stats.as_ref() {
+        println!(
+            "Execution time (recorded/replayed): {:.3}s / {:.3}s",
+            secs(stats),
+            secs(&output.stats)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Dumps the JSON schemas of the payloads, for the implementations of the protocol in other languages.
+//!
+//! ```sh
+//! # print all schemas as a single JSON object, by their names
+//! cargo run --features schema --bin schema -- dump
+//!
+//! # write each schema to its own file within the given directory, e.g. `./schemas/TaskStats.json`
+//! cargo run --features schema --bin schema -- dump ./schemas
+//! ```
+
+use dkn_compute::payloads::payload_schemas;
+use eyre::{eyre, Context, Result};
+use std::{env, path::Path};
+
+const USAGE: &str = "usage: schema dump [directory]";
+
+fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["dump"] => {
+            let schemas = payload_schemas()
+                .into_iter()
+                .map(|(name, schema)| Ok((name.to_string(), serde_json::to_value(schema)?)))
+                .collect::<Result<serde_json::Map<_, _>>>()?;
+            println!("{}", serde_json::to_string_pretty(&schemas)?);
+        }
+        ["dump", dir] => {
+            let dir = Path::new(dir);
+            std::fs::create_dir_all(dir).wrap_err("could not create directory")?;
+            for (name, schema) in payload_schemas() {
+                let path = dir.join(format!("{}.json", name));
+                std::fs::write(&path, serde_json::to_string_pretty(&schema)?)
+                    .wrap_err_with(|| format!("could not write {}", path.display()))?;
+                println!("{}", path.display());
+            }
+        }
+        _ => return Err(eyre!(USAGE)),
+    }
+
+    Ok(())
+}
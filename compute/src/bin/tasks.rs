@@ -0,0 +1,68 @@
+//! Queries the local task archive of the node.
+//!
+//! ```sh
+//! # list archived tasks, oldest first
+//! cargo run --bin tasks -- list
+//!
+//! # print an archived task as JSON, which can be given to the `replay` binary
+//! cargo run --bin tasks -- show <task-id>
+//!
+//! # remove all archived tasks
+//! cargo run --bin tasks -- purge
+//! ```
+//!
+//! Reads `DKN_TASK_ARCHIVE_DIR` and `DKN_WALLET_SECRET_KEY` from the environment.
+//! The node must be stopped first, as the archive can only be opened by one process.
+
+use dkn_compute::utils::TaskArchive;
+use dkn_utils::storage::SledStorage;
+use eyre::{eyre, Context, Result};
+use libsecp256k1::SecretKey;
+use std::{env, sync::Arc, time::UNIX_EPOCH};
+
+const USAGE: &str = "usage: tasks <list | show <task-id> | purge>";
+
+fn main() -> Result<()> {
+    let _ = dotenvy::dotenv();
+
+    let dir = env::var("DKN_TASK_ARCHIVE_DIR").wrap_err("DKN_TASK_ARCHIVE_DIR is not set")?;
+    // the storage is locked by the node while it is running
+    let storage =
+        SledStorage::open(dir).wrap_err("could not open the archive, is the node running?")?;
+    // the size cap is only applied when storing, so it does not matter here
+    let archive = TaskArchive::new(Arc::new(storage), u64::MAX);
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["list"] => {
+            for (task_id, modified) in archive.list()? {
+                let secs = modified.duration_since(UNIX_EPOCH)?.as_secs();
+                println!("{}\t{}", secs, task_id);
+            }
+        }
+        ["show", task_id] => {
+            let secret_env =
+                env::var("DKN_WALLET_SECRET_KEY").wrap_err("DKN_WALLET_SECRET_KEY is not set")?;
+            let secret_key = SecretKey::parse_slice(
+                &hex::decode(secret_env.trim_start_matches("0x"))
+                    .wrap_err("could not decode secret key")?,
+            )
+            .map_err(|e| eyre!("could not parse secret key: {}", e))?;
+
+            let dump = archive.load(task_id, &secret_key)?;
+            println!("{}", serde_json::to_string_pretty(&dump)?);
+        }
+        ["purge"] => {
+            let count = archive.purge_all()?;
+            println!("Removed {} archived tasks.", count);
+        }
+        _ => return Err(eyre!(USAGE)),
+    }
+
+    Ok(())
+}
@@ -1,16 +1,38 @@
 use dkn_p2p::{
-    libp2p::{Multiaddr, PeerId},
-    DriaNetworkType,
+    libp2p::{gossipsub::PeerScoreThresholds, PeerId},
+    parse_score_thresholds, ConnectionCaps, DriaNetworkType, ListenAddr, P2PTransport,
+    ResourceUsage,
 };
+use dkn_utils::{safe_read_env, split_csv_line};
 use dkn_workflows::DriaWorkflowsConfig;
 use eyre::{eyre, Result};
 use libsecp256k1::{PublicKey, SecretKey};
-use std::{env, str::FromStr};
+use std::{collections::BTreeMap, env, path::PathBuf, str::FromStr};
 
-use crate::utils::crypto::{public_key_to_address, secret_to_keypair};
+use crate::{
+    admin::AdminAuth,
+    utils::{
+        crypto::{public_key_to_address, secret_to_keypair},
+        NodeProfile,
+    },
+    workers::publish::{OverflowPolicy, PublishQuotas},
+};
 
-const DEFAULT_TASK_BATCH_SIZE: usize = 5;
-const DEFAULT_P2P_LISTEN_ADDR: &str = "/ip4/0.0.0.0/tcp/4001";
+/// Listens on both IPv4 & IPv6, an address that is not available on the host is skipped.
+const DEFAULT_P2P_LISTEN_ADDRS: &str = "/ip4/0.0.0.0/tcp/4001,/ip6/::/tcp/4001";
+const DEFAULT_P2P_IDLE_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_P2P_BOOTSTRAP_CONNECTION_SECS: u64 = 30;
+const DEFAULT_P2P_MAX_REDIALS: u32 = 10;
+const DEFAULT_P2P_MAX_REQUEST_MB: u64 = 1;
+const DEFAULT_P2P_MAX_RESPONSE_MB: u64 = 10;
+const DEFAULT_MAX_UNAUTHORIZED_REQUESTS: u32 = 10;
+const DEFAULT_MAX_REQUESTS_PER_PEER: u32 = 1200;
+const DEFAULT_RPC_KEEPALIVE_SECS: u64 = 45;
+const DEFAULT_TASK_STALL_SECS: u64 = 10 * 60;
+const DEFAULT_MAX_RESTARTS: usize = 5;
+const DEFAULT_TASK_ARCHIVE_MAX_MB: u64 = 100;
+const DEFAULT_STATS_DB_MAX_ROWS: usize = 1_000_000;
+const DEFAULT_SESSION_GRACE_SECS: u64 = 2 * 60;
 
 #[derive(Debug, Clone)]
 pub struct DriaComputeNodeConfig {
@@ -22,17 +44,108 @@ pub struct DriaComputeNodeConfig {
     pub address: String,
     /// Peer ID of the node.
     pub peer_id: PeerId,
-    /// P2P listen address, e.g. `/ip4/0.0.0.0/tcp/4001`.
-    pub p2p_listen_addr: Multiaddr,
+    /// P2P listen addresses along with their policies, e.g. `/ip4/0.0.0.0/tcp/4001`.
+    pub p2p_listen_addrs: Vec<ListenAddr>,
+    /// Number of seconds before an idle connection is closed, connections to RPCs are never closed due to idleness.
+    pub p2p_idle_timeout_secs: u64,
+    /// Number of seconds before a connection to a bootstrap node is closed, as it is only needed for the DHT.
+    pub p2p_bootstrap_connection_secs: u64,
+    /// Number of attempts to redial a disconnected RPC before giving up on it, RPCs are not redialled if zero.
+    pub p2p_max_redials: u32,
+    /// Maximum size of a request-response request in megabytes.
+    pub p2p_max_request_mb: u64,
+    /// Maximum size of a request-response response in megabytes, chunked ones included.
+    pub p2p_max_response_mb: u64,
+    /// Transport that the Dria nodes are dialled over, e.g. WebSockets behind restrictive firewalls.
+    pub p2p_transport: P2PTransport,
+    /// Whether the other nodes within the local network are discovered over mDNS.
+    pub p2p_mdns: bool,
     /// Workflow configurations, e.g. models and providers.
     pub workflows: DriaWorkflowsConfig,
     /// Network type of the node.
     pub network_type: DriaNetworkType,
+    /// Resource profile of the node, e.g. `low-power` for single-board computers.
+    pub profile: NodeProfile,
+    /// Resources of the P2P client w.r.t the profile, where the gossip mesh size & heartbeat can be overridden.
+    pub p2p_resources: ResourceUsage,
+    /// Thresholds of the Gossipsub peer scores, peers are not scored unless they are given.
+    pub p2p_score_thresholds: Option<PeerScoreThresholds>,
+    /// Limits of the pending & established connections of the P2P client.
+    pub p2p_limits: ConnectionCaps,
     /// Batch size for batchable tasks (e.g. API-based ones).
     ///
     /// A higher value will help execute more tasks concurrently,
-    /// at the risk of hitting rate-limits.
+    /// at the risk of hitting rate-limits. Defaults w.r.t the profile.
     pub batch_size: usize,
+    /// Buffer size of the channels between the node & its workers, defaults w.r.t the profile.
+    pub channel_bufsize: usize,
+    /// What the publish channel of the workers does with new outputs while it is full.
+    pub publish_overflow: OverflowPolicy,
+    /// Consecutive outputs that the single & batch pipelines publish while the other one is waiting.
+    pub publish_quotas: PublishQuotas,
+    /// Directory of the outputs spilled by the publish channel, within the temporary directory if `None`.
+    pub publish_spill_dir: Option<PathBuf>,
+    /// Maximum number of task requests accepted per minute regardless of the queue depth, unlimited if `None`.
+    pub max_tasks_per_minute: Option<u32>,
+    /// Number of unauthorized requests after which their peer is blocked, peers are not blocked if zero.
+    pub max_unauthorized_requests: u32,
+    /// Maximum number of requests handled per minute from a single peer, unlimited if `None`.
+    pub max_requests_per_peer: Option<u32>,
+    /// Number of seconds between keep-alive requests sent to RPC nodes.
+    ///
+    /// Some routers drop idle connections after a minute or so, a value
+    /// within 30-60 seconds keeps the connection warm; `0` disables it.
+    pub rpc_keepalive_secs: u64,
+    /// Number of seconds without any task progress, while there are pending tasks,
+    /// after which the workers are considered to be stalled.
+    pub task_stall_secs: u64,
+    /// Whether a task running longer than `task_stall_secs` should be aborted.
+    ///
+    /// An aborted task is responded to with an error, so that it can be re-assigned.
+    pub abort_stalled_tasks: bool,
+    /// Path to the admin socket, if enabled.
+    pub admin_socket: Option<PathBuf>,
+    /// Whether admin requests must carry a token.
+    pub admin_auth: bool,
+    /// Secret to derive admin tokens from, the wallet secret key is used if not given.
+    pub admin_secret: Option<String>,
+    /// Maximum number of times crashed components are restarted, before the node shuts down.
+    pub max_restarts: usize,
+    /// Directory of the local task archive, if enabled.
+    pub task_archive_dir: Option<PathBuf>,
+    /// Maximum size of the local task archive, in megabytes.
+    pub task_archive_max_mb: u64,
+    /// Number of hours to keep archived tasks for, kept until the size cap if `None`.
+    pub task_archive_retention_hours: Option<u64>,
+    /// Path to the local database of task statistics, if enabled.
+    pub stats_db: Option<PathBuf>,
+    /// Maximum number of rows kept within the task statistics database, oldest are removed first; all kept if `0`.
+    pub stats_db_max_rows: usize,
+    /// Directory to persist the reputations of RPC nodes in, kept in memory only if `None`.
+    pub reputation_dir: Option<PathBuf>,
+    /// File to save the RPC session in at shutdown, resumed by a restart within the grace period.
+    pub session_file: Option<PathBuf>,
+    /// Number of seconds within which a saved RPC session is resumed.
+    pub session_grace_secs: u64,
+    /// URL to fetch the rewarded models of the network from, models are not synced if `None`.
+    pub rewarded_models_url: Option<String>,
+    /// Whether rewarded models of the network that are missing in the node are enabled automatically.
+    pub auto_enable_models: bool,
+    /// Whether the node exits when it is below the minimum version of the network, so that it can be updated.
+    pub exit_for_update: bool,
+    /// URL to check the registration of the node address at, running as an observer if it is not registered.
+    /// The registration is not checked if `None`.
+    pub registration_url: Option<String>,
+    /// Operator labels, e.g. `region=eu`, shared within the specs.
+    pub labels: BTreeMap<String, String>,
+    /// Operator note, shared within the specs.
+    pub note: Option<String>,
+    /// Name of the model that scores the results of tasks, if any.
+    ///
+    /// It must be one of the configured models.
+    pub judge_model: Option<String>,
+    /// Whether the node only observes the network, without accepting tasks.
+    pub observe: bool,
 }
 
 #[allow(clippy::new_without_default)]
@@ -77,22 +190,249 @@ impl DriaComputeNodeConfig {
         let peer_id = secret_to_keypair(&secret_key).public().to_peer_id();
         log::info!("Node PeerID:      {}", peer_id);
 
-        // parse listen address
-        let p2p_listen_addr_str = env::var("DKN_P2P_LISTEN_ADDR")
-            .map(|addr| addr.trim_matches('"').to_string())
-            .unwrap_or(DEFAULT_P2P_LISTEN_ADDR.to_string());
-        let p2p_listen_addr = Multiaddr::from_str(&p2p_listen_addr_str)
-            .expect("could not parse the given P2P listen address.");
+        // parse comma-separated listen addresses, the singular variable is still read for older configs
+        let p2p_listen_addr_str = ["DKN_P2P_LISTEN_ADDRS", "DKN_P2P_LISTEN_ADDR"]
+            .into_iter()
+            .find_map(|key| env::var(key).ok().filter(|s| !s.trim().is_empty()))
+            .unwrap_or(DEFAULT_P2P_LISTEN_ADDRS.to_string());
+        let p2p_listen_addrs = split_csv_line(&p2p_listen_addr_str)
+            .iter()
+            .map(|addr| ListenAddr::from_str(addr))
+            .collect::<Result<Vec<_>>>()
+            .expect("could not parse the given P2P listen addresses.");
+
+        // parse connection timeouts
+        let p2p_idle_timeout_secs = env::var("DKN_P2P_IDLE_TIMEOUT_SECS")
+            .map(|s| s.parse::<u64>().unwrap_or(DEFAULT_P2P_IDLE_TIMEOUT_SECS))
+            .unwrap_or(DEFAULT_P2P_IDLE_TIMEOUT_SECS);
+        let p2p_bootstrap_connection_secs = env::var("DKN_P2P_BOOTSTRAP_CONNECTION_SECS")
+            .map(|s| {
+                s.parse::<u64>()
+                    .unwrap_or(DEFAULT_P2P_BOOTSTRAP_CONNECTION_SECS)
+            })
+            .unwrap_or(DEFAULT_P2P_BOOTSTRAP_CONNECTION_SECS);
+        let p2p_max_redials = env::var("DKN_P2P_MAX_REDIALS")
+            .map(|s| s.parse::<u32>().unwrap_or(DEFAULT_P2P_MAX_REDIALS))
+            .unwrap_or(DEFAULT_P2P_MAX_REDIALS);
+        let p2p_max_request_mb = env::var("DKN_P2P_MAX_REQUEST_MB")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|mb| *mb != 0)
+            .unwrap_or(DEFAULT_P2P_MAX_REQUEST_MB);
+        let p2p_max_response_mb = env::var("DKN_P2P_MAX_RESPONSE_MB")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|mb| *mb != 0)
+            .unwrap_or(DEFAULT_P2P_MAX_RESPONSE_MB);
 
         // parse network type
+        // parse p2p transport, plain TCP is used if its invalid
+        let p2p_transport = safe_read_env(env::var("DKN_P2P_TRANSPORT"))
+            .and_then(|s| {
+                s.parse()
+                    .inspect_err(|e| log::warn!("{}, using tcp transport.", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        let p2p_mdns = env::var("DKN_P2P_MDNS")
+            .map(|s| s.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or_default();
+
         let network_type = env::var("DKN_NETWORK")
             .map(|s| DriaNetworkType::from(s.as_str()))
             .unwrap_or_default();
 
+        // parse resource profile, the standard one is used if its invalid
+        let profile: NodeProfile = safe_read_env(env::var("DKN_PROFILE"))
+            .and_then(|s| {
+                s.parse()
+                    .inspect_err(|e| log::warn!("{}, using standard profile.", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        // parse gossip overrides, the profile defaults are kept for invalid values
+        let mut p2p_resources = profile.p2p_resources();
+        if let Some(mesh_n) = env::var("DKN_P2P_MESH_N")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|n| *n != 0)
+        {
+            p2p_resources.mesh_n = mesh_n;
+        }
+        if let Some(secs) = env::var("DKN_P2P_HEARTBEAT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs != 0)
+        {
+            p2p_resources.heartbeat_interval = std::time::Duration::from_secs(secs);
+        }
+        let p2p_score_thresholds =
+            safe_read_env(env::var("DKN_P2P_SCORE_THRESHOLDS")).and_then(|s| {
+                parse_score_thresholds(&s)
+                    .inspect_err(|e| log::warn!("{}, peers are not scored.", e))
+                    .ok()
+            });
+
+        // parse connection limits, where an empty or invalid value keeps the default and zero removes the limit
+        let read_limit = |key: &str| {
+            safe_read_env(env::var(key)).and_then(|s| {
+                s.parse::<u32>()
+                    .inspect_err(|e| log::warn!("Invalid {}: {}", key, e))
+                    .ok()
+                    .map(|max| Some(max).filter(|max| *max != 0))
+            })
+        };
+        let mut p2p_limits = ConnectionCaps::default();
+        if let Some(max) = read_limit("DKN_P2P_MAX_CONNECTIONS") {
+            p2p_limits.max_established = max;
+        }
+        if let Some(max) = read_limit("DKN_P2P_MAX_CONNECTIONS_PER_PEER") {
+            p2p_limits.max_established_per_peer = max;
+        }
+        if let Some(max) = read_limit("DKN_P2P_MAX_PENDING_CONNECTIONS") {
+            p2p_limits.max_pending_incoming = max;
+            p2p_limits.max_pending_outgoing = max;
+        }
+
         // parse batch size
         let batch_size = env::var("DKN_BATCH_SIZE")
-            .map(|s| s.parse::<usize>().unwrap_or(DEFAULT_TASK_BATCH_SIZE))
-            .unwrap_or(DEFAULT_TASK_BATCH_SIZE);
+            .map(|s| s.parse::<usize>().unwrap_or(profile.default_batch_size()))
+            .unwrap_or(profile.default_batch_size());
+
+        // parse channel sizing, the publish channel blocks the workers when full by default
+        let channel_bufsize = env::var("DKN_CHANNEL_BUFSIZE")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|size| *size != 0)
+            .unwrap_or(profile.channel_bufsize());
+        let publish_overflow = safe_read_env(env::var("DKN_PUBLISH_OVERFLOW"))
+            .and_then(|s| {
+                s.parse()
+                    .inspect_err(|e| log::warn!("{}, using block policy.", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let publish_quotas = safe_read_env(env::var("DKN_PUBLISH_QUOTAS"))
+            .and_then(|s| {
+                s.parse()
+                    .inspect_err(|e| log::warn!("{}, using default quotas.", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let publish_spill_dir = safe_read_env(env::var("DKN_PUBLISH_SPILL_DIR")).map(PathBuf::from);
+
+        // parse task intake limit, `0` is unlimited as well
+        let max_tasks_per_minute = env::var("DKN_MAX_TASKS_PER_MINUTE")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .filter(|max| *max != 0);
+
+        // parse the unauthorized requests tolerated per peer
+        let max_unauthorized_requests = env::var("DKN_MAX_UNAUTHORIZED_REQUESTS")
+            .map(|s| {
+                s.parse::<u32>()
+                    .unwrap_or(DEFAULT_MAX_UNAUTHORIZED_REQUESTS)
+            })
+            .unwrap_or(DEFAULT_MAX_UNAUTHORIZED_REQUESTS);
+
+        // parse the requests per minute of each peer, `0` is unlimited
+        let max_requests_per_peer = Some(
+            env::var("DKN_MAX_REQUESTS_PER_PEER")
+                .map(|s| s.parse::<u32>().unwrap_or(DEFAULT_MAX_REQUESTS_PER_PEER))
+                .unwrap_or(DEFAULT_MAX_REQUESTS_PER_PEER),
+        )
+        .filter(|max| *max != 0);
+
+        // parse rpc keep-alive interval
+        let rpc_keepalive_secs = env::var("DKN_RPC_KEEPALIVE_SECS")
+            .map(|s| s.parse::<u64>().unwrap_or(DEFAULT_RPC_KEEPALIVE_SECS))
+            .unwrap_or(DEFAULT_RPC_KEEPALIVE_SECS);
+
+        // parse stall detection settings
+        let task_stall_secs = env::var("DKN_TASK_STALL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs != 0)
+            .unwrap_or(DEFAULT_TASK_STALL_SECS);
+        let abort_stalled_tasks = env::var("DKN_TASK_STALL_ABORT")
+            .map(|s| s.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or_default();
+
+        // parse admin socket path, disabled if not given
+        let admin_socket = env::var("DKN_ADMIN_SOCKET")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from);
+
+        // admin tokens are only required if opted in
+        let admin_auth = env::var("DKN_ADMIN_AUTH")
+            .map(|s| s.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or_default();
+        let admin_secret = safe_read_env(env::var("DKN_ADMIN_SECRET"));
+
+        // parse restart limit for crashed components
+        let max_restarts = env::var("DKN_MAX_RESTARTS")
+            .map(|s| s.parse::<usize>().unwrap_or(DEFAULT_MAX_RESTARTS))
+            .unwrap_or(DEFAULT_MAX_RESTARTS);
+
+        // parse task archive settings, disabled if no directory is given
+        let task_archive_dir = env::var("DKN_TASK_ARCHIVE_DIR")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from);
+        let task_archive_max_mb = env::var("DKN_TASK_ARCHIVE_MAX_MB")
+            .map(|s| s.parse::<u64>().unwrap_or(DEFAULT_TASK_ARCHIVE_MAX_MB))
+            .unwrap_or(DEFAULT_TASK_ARCHIVE_MAX_MB);
+        let task_archive_retention_hours = env::var("DKN_TASK_ARCHIVE_RETENTION_HOURS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok());
+
+        // parse task statistics database path, disabled if not given
+        let stats_db = env::var("DKN_STATS_DB")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from);
+        let stats_db_max_rows = env::var("DKN_STATS_DB_MAX_ROWS")
+            .map(|s| s.parse::<usize>().unwrap_or(DEFAULT_STATS_DB_MAX_ROWS))
+            .unwrap_or(DEFAULT_STATS_DB_MAX_ROWS);
+
+        // parse reputation directory, reputations are not persisted if not given
+        let reputation_dir = env::var("DKN_REPUTATION_DIR")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from);
+
+        // parse the session file, sessions are not resumed if not given
+        let session_file = safe_read_env(env::var("DKN_SESSION_FILE")).map(PathBuf::from);
+        let session_grace_secs = env::var("DKN_SESSION_GRACE_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SESSION_GRACE_SECS);
+
+        // rewarded models are only compared if a source is given
+        let rewarded_models_url = safe_read_env(env::var("DKN_REWARDED_MODELS_URL"));
+
+        // rewarded models are only enabled automatically if opted in
+        let auto_enable_models = env::var("DKN_AUTO_ENABLE_MODELS")
+            .map(|s| s.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or_default();
+
+        // outdated nodes only exit for an update if opted in
+        let exit_for_update = env::var("DKN_EXIT_FOR_UPDATE")
+            .map(|s| s.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or_default();
+
+        // registration is only checked if a source is given
+        let registration_url = safe_read_env(env::var("DKN_REGISTRATION_URL"));
+
+        // parse operator labels & note
+        let labels = parse_labels(&env::var("DKN_LABELS").unwrap_or_default());
+        let note = safe_read_env(env::var("DKN_NOTE"));
+
+        // results are only judged if a judge model is given
+        let judge_model = safe_read_env(env::var("DKN_JUDGE_MODEL"));
 
         Self {
             secret_key,
@@ -100,17 +440,58 @@ impl DriaComputeNodeConfig {
             address,
             peer_id,
             workflows,
-            p2p_listen_addr,
+            p2p_listen_addrs,
+            p2p_idle_timeout_secs,
+            p2p_bootstrap_connection_secs,
+            p2p_max_redials,
+            p2p_max_request_mb,
+            p2p_max_response_mb,
+            p2p_transport,
+            p2p_mdns,
             network_type,
+            profile,
+            p2p_resources,
+            p2p_score_thresholds,
+            p2p_limits,
             batch_size,
+            channel_bufsize,
+            publish_overflow,
+            publish_quotas,
+            publish_spill_dir,
+            max_tasks_per_minute,
+            max_unauthorized_requests,
+            max_requests_per_peer,
+            rpc_keepalive_secs,
+            task_stall_secs,
+            abort_stalled_tasks,
+            admin_socket,
+            admin_auth,
+            admin_secret,
+            max_restarts,
+            task_archive_dir,
+            task_archive_max_mb,
+            task_archive_retention_hours,
+            stats_db,
+            stats_db_max_rows,
+            reputation_dir,
+            session_file,
+            session_grace_secs,
+            rewarded_models_url,
+            auto_enable_models,
+            exit_for_update,
+            registration_url,
+            labels,
+            note,
+            judge_model,
+            observe: false,
         }
     }
 
-    /// Asserts that the configured listen address is free.
-    /// Throws an error if the address is already in use.
+    /// Asserts that the TCP ports of the enabled listen addresses are free.
+    /// Throws an error if an address is already in use.
     ///
     /// Uses `is_port_reachable` function internally, which makes a simple
-    /// TCP connection to the given address.
+    /// TCP connection to the given address. Addresses without a TCP port, e.g. QUIC, are not checked.
     ///
     /// Can be inlined because the function is small and called only once.
     #[inline]
@@ -119,29 +500,26 @@ impl DriaComputeNodeConfig {
         use port_check::is_port_reachable;
         use std::net::{Ipv4Addr, SocketAddrV4};
 
-        let address_in_use = self
-            .p2p_listen_addr
-            .iter()
-            // find the port within our multiaddr
-            .find_map(|protocol| match protocol {
-                Protocol::Tcp(port) => Some(port),
-                _ => None,
-            })
-            // check if its reachable or not
-            .map(|port| is_port_reachable(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port)))
-            .unwrap_or_else(|| {
-                log::error!(
-                    "could not find any TCP port in the given address: {:?}",
-                    self.p2p_listen_addr
-                );
-                false
-            });
+        for listen_addr in self.p2p_listen_addrs.iter().filter(|l| l.enabled) {
+            let address_in_use = listen_addr
+                .addr
+                .iter()
+                // find the port within our multiaddr
+                .find_map(|protocol| match protocol {
+                    Protocol::Tcp(port) => Some(port),
+                    _ => None,
+                })
+                // check if its reachable or not
+                .is_some_and(|port| {
+                    is_port_reachable(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port))
+                });
 
-        if address_in_use {
-            return Err(eyre!(
-                "Listen address {} is already in use.",
-                self.p2p_listen_addr
-            ));
+            if address_in_use {
+                return Err(eyre!(
+                    "Listen address {} is already in use.",
+                    listen_addr.addr
+                ));
+            }
         }
 
         Ok(())
@@ -151,16 +529,55 @@ impl DriaComputeNodeConfig {
     pub fn check_network_specific(&self) -> Result<()> {
         // if network is `pro`, we require Jina and Serper to be present.
         if self.network_type == DriaNetworkType::Pro {
-            if !self.workflows.jina.has_api_key() {
-                return Err(eyre!("Jina is required for the Pro network."));
-            }
-            if !self.workflows.serper.has_api_key() {
-                return Err(eyre!("Serper is required for the Pro network."));
+            #[cfg(feature = "search")]
+            {
+                if !self.workflows.jina.has_api_key() {
+                    return Err(eyre!("Jina is required for the Pro network."));
+                }
+                if !self.workflows.serper.has_api_key() {
+                    return Err(eyre!("Serper is required for the Pro network."));
+                }
             }
+            #[cfg(not(feature = "search"))]
+            return Err(eyre!(
+                "Pro network requires a build with the search feature, e.g. the full build."
+            ));
         }
 
         Ok(())
     }
+
+    /// Sets whether the node only observes the network, i.e. never accepts tasks nor responds to pings.
+    pub fn with_observe(mut self, observe: bool) -> Self {
+        self.observe = observe;
+        self
+    }
+
+    /// Returns the admin token authentication, if enabled.
+    ///
+    /// Tokens are derived from `DKN_ADMIN_SECRET` if given, otherwise from the wallet secret key.
+    pub fn get_admin_auth(&self) -> Option<AdminAuth> {
+        self.admin_auth.then(|| match &self.admin_secret {
+            Some(secret) => AdminAuth::new(secret.as_bytes()),
+            None => AdminAuth::new(&self.secret_key.serialize()),
+        })
+    }
+}
+
+/// Parses comma-separated `key=value` labels, ignoring the invalid ones.
+fn parse_labels(input: &str) -> BTreeMap<String, String> {
+    split_csv_line(input)
+        .into_iter()
+        .filter_map(|label| match label.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Some((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => {
+                log::warn!("Ignoring invalid label: {}", label);
+                None
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -178,3 +595,42 @@ impl Default for DriaComputeNodeConfig {
         Self::new(Default::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_labels() {
+        let labels = parse_labels("region = eu-west, gpu-type=a100,invalid,=empty");
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels["region"], "eu-west");
+        assert_eq!(labels["gpu-type"], "a100");
+        assert!(parse_labels("").is_empty());
+    }
+
+    #[test]
+    fn test_build_features() {
+        use dkn_workflows::{is_provider_enabled, ModelProvider};
+
+        // providers & tools of the node are enabled along with those of the workflows
+        assert_eq!(dkn_workflows::SEARCH_ENABLED, cfg!(feature = "search"));
+        for (provider, enabled) in [
+            (ModelProvider::Ollama, cfg!(feature = "ollama")),
+            (ModelProvider::OpenAI, cfg!(feature = "openai")),
+            (ModelProvider::Gemini, cfg!(feature = "gemini")),
+            (ModelProvider::OpenRouter, cfg!(feature = "openrouter")),
+        ] {
+            assert_eq!(is_provider_enabled(&provider), enabled);
+        }
+
+        // a build without search can not join the Pro network
+        if !cfg!(feature = "search") {
+            let config = DriaComputeNodeConfig {
+                network_type: DriaNetworkType::Pro,
+                ..Default::default()
+            };
+            assert!(config.check_network_specific().is_err());
+        }
+    }
+}
@@ -1,4 +1,7 @@
 //! Gossipsub message handlers.
 
+mod nodes;
+pub use nodes::*;
+
 mod pingpong;
 pub use pingpong::*;
@@ -0,0 +1,83 @@
+use dkn_p2p::libp2p::gossipsub::MessageAcceptance;
+use dkn_utils::payloads::NodesUpdatePayload;
+use eyre::{Context, Result};
+
+use crate::{
+    utils::{apply_nodes_update, DriaMessage},
+    DriaComputeNode,
+};
+
+/// Maximum number of seconds that the deadline of a nodes update can be ahead of the local time,
+/// so that a captured update can only be replayed within this window.
+const MAX_NODES_UPDATE_TTL_SECS: u64 = 10 * 60;
+
+pub struct NodesUpdateHandler;
+
+impl NodesUpdateHandler {
+    /// Handles a nodes update pushed by an RPC node, e.g. during an RPC rotation.
+    ///
+    /// The update is applied to the available nodes right away, and new RPC nodes are unblocked & dialled.
+    /// Updates past their deadline or with a deadline too far ahead are ignored, as well as replacing
+    /// updates older than the last applied one, so that captured updates can not undo a later rotation.
+    /// Returns `MessageAcceptance::Accept` so that the update reaches the other nodes as well.
+    pub(crate) async fn handle_nodes_update(
+        node: &mut DriaComputeNode,
+        message: &DriaMessage,
+    ) -> Result<MessageAcceptance> {
+        let update = message
+            .parse_payload::<NodesUpdatePayload>()
+            .wrap_err("could not parse nodes update")?;
+
+        // an old update may add back the RPCs that were removed since
+        let current_time = node.now_nanos();
+        if current_time >= update.deadline {
+            log::debug!(
+                "Nodes update is past the deadline, ignoring. (local: {}, deadline: {})",
+                current_time,
+                update.deadline
+            );
+            return Ok(MessageAcceptance::Ignore);
+        }
+        let max_deadline = current_time + u128::from(MAX_NODES_UPDATE_TTL_SECS) * 1_000_000_000;
+        if update.deadline > max_deadline {
+            log::warn!(
+                "Nodes update has a deadline too far ahead, ignoring. (local: {}, deadline: {})",
+                current_time,
+                update.deadline
+            );
+            return Ok(MessageAcceptance::Ignore);
+        }
+
+        // a replacing update that is older than the last one would bring back the RPCs rotated out since
+        if update.replace && update.deadline <= node.nodes_update_deadline {
+            log::debug!(
+                "Nodes update is older than the last replacing one, ignoring. (last: {}, deadline: {})",
+                node.nodes_update_deadline,
+                update.deadline
+            );
+            return Ok(MessageAcceptance::Ignore);
+        }
+        let deadline = update.deadline;
+        let replace = update.replace;
+
+        log::info!(
+            "Received nodes update with {} RPC nodes (replace: {}).",
+            update.rpcs.len(),
+            update.replace
+        );
+        apply_nodes_update(
+            &mut node.dria_nodes,
+            &node.static_dria_nodes,
+            &mut node.removed_rpc_nodes,
+            update,
+        )?;
+        if replace {
+            node.nodes_update_deadline = deadline;
+        }
+        node.sync_keep_alive_peers().await;
+        node.unblock_rpc_nodes().await;
+        node.dial_rpc_nodes().await;
+
+        Ok(MessageAcceptance::Accept)
+    }
+}
@@ -1,8 +1,9 @@
 use dkn_p2p::libp2p::gossipsub::MessageAcceptance;
-use dkn_utils::get_current_time_nanos;
+use dkn_utils::payloads::DriaTopic;
 use dkn_workflows::{Model, ModelProvider};
 use eyre::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tokio::time::Instant;
 
 use crate::{utils::DriaMessage, DriaComputeNode};
@@ -15,6 +16,12 @@ pub struct PingpongPayload {
     uuid: String,
     /// Deadline for the ping request.
     deadline: u128,
+    /// Minimum node version required by the network, if any.
+    #[serde(default)]
+    min_version: Option<String>,
+    /// Deadline for nodes to meet the minimum version, in nanoseconds.
+    #[serde(default)]
+    min_version_deadline: Option<u128>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -25,19 +32,30 @@ pub struct PingpongResponse {
     pub(crate) models: Vec<(ModelProvider, Model)>,
     /// Number of tasks in the channel currently, `single` and `batch`.
     pub(crate) pending_tasks: [usize; 2],
+    /// Current utilization of the node, for load-aware scheduling.
+    pub(crate) utilization: PingpongUtilization,
 }
 
-impl PingpongHandler {
-    pub const LISTEN_TOPIC: &'static str = "ping";
-    pub const RESPONSE_TOPIC: &'static str = "pong";
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PingpongUtilization {
+    /// Number of tasks being executed at the moment, `single` and `batch`.
+    pub(crate) busy_slots: [usize; 2],
+    /// Number of tasks that can be executed at once, `single` and `batch`.
+    pub(crate) total_slots: [usize; 2],
+    /// Number of tasks waiting for a free slot, `single` and `batch`.
+    pub(crate) queue_depth: [usize; 2],
+    /// Recent average execution time per model, in milliseconds.
+    pub(crate) avg_latency_ms: HashMap<String, u64>,
+}
 
+impl PingpongHandler {
     /// Handles the ping message and responds with a pong message.
     ///
     /// 1. Parses the payload of the incoming message into a `PingpongPayload`.
     /// 2. Checks if the current time is past the deadline specified in the ping request.
     /// 3. If the current time is past the deadline, logs a debug message and ignores the ping request.
     /// 4. If the current time is within the deadline, constructs a `PingpongResponse` with the UUID from the ping request, the models from the node's configuration, and the current timestamp.
-    /// 5. Creates a new signed `DKNMessage` with the response body and the `RESPONSE_TOPIC`.
+    /// 5. Creates a new signed `DKNMessage` with the response body and the `pong` topic.
     /// 6. Publishes the response message.
     /// 7. Returns `MessageAcceptance::Accept` so that ping is propagated to others as well.
     pub(crate) async fn handle_ping(
@@ -49,7 +67,7 @@ impl PingpongHandler {
             .wrap_err("could not parse ping request")?;
 
         // check deadline
-        let current_time = get_current_time_nanos();
+        let current_time = node.now_nanos();
         if current_time >= pingpong.deadline {
             log::debug!(
                 "Ping (uuid: {}) is past the deadline, ignoring. (local: {}, deadline: {})",
@@ -64,6 +82,27 @@ impl PingpongHandler {
 
         log::info!("Received a ping for: {}", pingpong.uuid);
 
+        // keep track of the version requirement of the network, which is cleared once it is gone
+        node.handle_version_requirement(
+            pingpong.min_version.as_deref(),
+            pingpong.min_version_deadline,
+        );
+
+        // a paused node does not respond, so that it is not assigned any tasks
+        if node.is_paused() {
+            log::info!("Node is paused, not responding to ping {}", pingpong.uuid);
+            return Ok(MessageAcceptance::Accept);
+        }
+
+        // an observer does not respond either, as its tasks would not be rewarded
+        if node.is_observer() {
+            log::info!(
+                "Node is an observer, not responding to ping {}",
+                pingpong.uuid
+            );
+            return Ok(MessageAcceptance::Accept);
+        }
+
         // record ping moment
         node.last_pinged_at = Instant::now();
 
@@ -72,15 +111,43 @@ impl PingpongHandler {
             uuid: pingpong.uuid.clone(),
             models: node.config.workflows.models.clone(),
             pending_tasks: node.get_pending_task_count(),
+            utilization: node.get_utilization(),
         };
 
         // publish message
         let message = node.new_message(
             serde_json::json!(response_body).to_string(),
-            Self::RESPONSE_TOPIC,
+            DriaTopic::Pong,
         );
         node.publish(message).await?;
 
         Ok(MessageAcceptance::Accept)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_min_version() {
+        // pings without a version requirement are still valid
+        let ping =
+            serde_json::from_str::<PingpongPayload>(r#"{"uuid":"abc","deadline":1}"#).unwrap();
+        assert!(ping.min_version.is_none() && ping.min_version_deadline.is_none());
+
+        let ping = serde_json::from_str::<PingpongPayload>(
+            r#"{"uuid":"abc","deadline":1,"min_version":"0.4.0","min_version_deadline":2}"#,
+        )
+        .unwrap();
+        assert_eq!(ping.min_version.as_deref(), Some("0.4.0"));
+        assert_eq!(ping.min_version_deadline, Some(2));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_ping_parse_never_panics(data: Vec<u8>) {
+            let _ = serde_json::from_slice::<PingpongPayload>(&data);
+        }
+    }
+}
@@ -1,16 +1,31 @@
 use dkn_compute::*;
 use dkn_workflows::DriaWorkflowsConfig;
 use eyre::Result;
-use std::env;
+use std::{env, io::Write};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use utils::{Redactor, RestartPolicy, Supervisor};
 use workers::task::TaskWorker;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let dotenv_result = dotenvy::dotenv();
+    // keys within the keychain take precedence, and the executors read them from the environment
+    dkn_workflows::vault::resolve_secrets();
 
+    // secrets are redacted from the logs, in case they are shared for support
+    let redactor = Redactor::from_env();
     env_logger::builder()
-        .format_timestamp(Some(env_logger::TimestampPrecision::Millis))
+        .format(move |buf, record| {
+            let level_style = buf.default_level_style(record.level());
+            writeln!(
+                buf,
+                "[{} {level_style}{:<5}{level_style:#} {}] {}",
+                buf.timestamp_millis(),
+                record.level(),
+                record.target(),
+                redactor.redact(&record.args().to_string())
+            )
+        })
         .filter(None, log::LevelFilter::Off)
         .filter_module("dkn_compute", log::LevelFilter::Info)
         .filter_module("dkn_p2p", log::LevelFilter::Info)
@@ -65,49 +80,92 @@ async fn main() -> Result<()> {
         task_tracker_to_close.close();
     });
 
+    // an observer never executes tasks, so models & services are not required for it
+    let observe = env::args().skip(1).any(|arg| arg == "--observe");
+    if observe {
+        log::warn!("Running as an observer, tasks will not be accepted.");
+    }
+
     // create configurations & check required services & address in use
     let workflows_config =
         DriaWorkflowsConfig::new_from_csv(&env::var("DKN_MODELS").unwrap_or_default());
-    if workflows_config.models.is_empty() {
+    if workflows_config.models.is_empty() && !observe {
         return Err(eyre::eyre!("No models were provided, make sure to restart with at least one model provided within DKN_MODELS."));
     }
 
     log::info!("Configured models: {:?}", workflows_config.models);
-    let mut config = DriaComputeNodeConfig::new(workflows_config);
+    let mut config = DriaComputeNodeConfig::new(workflows_config).with_observe(observe);
+    log::info!("Using {} profile.", config.profile);
     config.assert_address_not_in_use()?;
-    // check services & models, will exit if there is an error
-    // since service check can take time, we allow early-exit here as well
-    tokio::select! {
-        result = config.workflows.check_services() => result,
-        _ = cancellation.cancelled() => {
-            log::info!("Service check cancelled, exiting.");
-            return Ok(());
-        }
-    }?;
-    log::warn!(
-        "Using models: {}",
-        config
-            .workflows
-            .models
-            .iter()
-            .map(|(p, m)| format!("{}/{}", p, m))
-            .collect::<Vec<_>>()
-            .join(", ")
-    );
+    if !observe {
+        // check services & models, will exit if there is an error
+        // since service check can take time, we allow early-exit here as well
+        tokio::select! {
+            result = config.workflows.check_services() => result,
+            _ = cancellation.cancelled() => {
+                log::info!("Service check cancelled, exiting.");
+                return Ok(());
+            }
+        }?;
+        log::warn!(
+            "Using models: {}",
+            config
+                .workflows
+                .models
+                .iter()
+                .map(|(p, m)| format!("{}/{}", p, m))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
 
-    // check network-specific configurations
-    config.check_network_specific()?;
+        // check network-specific configurations
+        config.check_network_specific()?;
+    }
 
     // create the node
     let batch_size = config.batch_size;
-    let (mut node, p2p, worker_batch, worker_single) = DriaComputeNode::new(config).await?;
+    let admin_socket = config.admin_socket.clone();
+    let admin_auth = config.get_admin_auth();
+    let stats_db = config.stats_db.clone();
+    let supervisor = Supervisor::new(
+        RestartPolicy {
+            max_restarts: config.max_restarts,
+            ..Default::default()
+        },
+        cancellation.clone(),
+    );
+    let (node, p2p, worker_batch, worker_single) = DriaComputeNode::new(config).await?;
+    let node = node.with_supervisor(supervisor.clone());
+
+    // spawn admin server if a socket path is given
+    if let Some(path) = admin_socket {
+        #[cfg(unix)]
+        {
+            let mut admin =
+                admin::AdminServer::new(path, node.admin_sender(), cancellation.clone())?;
+            if let Some(auth) = admin_auth {
+                admin = admin.with_auth(auth);
+            }
+            if let Some(stats_db) = stats_db {
+                admin = admin.with_stats_db(stats_db);
+            }
+            task_tracker.spawn(async move { admin.run().await });
+        }
+
+        #[cfg(not(unix))]
+        log::warn!(
+            "Admin socket {} is not supported on this platform.",
+            path.display()
+        );
+    }
 
     // spawn p2p client first
+    // the client owns the swarm and can not be restarted, so it is not supervised
     log::info!("Spawning peer-to-peer client thread.");
     task_tracker.spawn(async move { p2p.run().await });
 
     // spawn batch worker thread if we are using such models (e.g. OpenAI, Gemini, OpenRouter)
-    if let Some(mut worker_batch) = worker_batch {
+    if let Some(worker_batch) = worker_batch {
         assert!(
             batch_size <= TaskWorker::MAX_BATCH_SIZE,
             "batch size too large"
@@ -116,24 +174,49 @@ async fn main() -> Result<()> {
             "Spawning workflows batch worker thread. (batch size {})",
             batch_size
         );
-        task_tracker.spawn(async move { worker_batch.run_batch(batch_size).await });
+        let supervisor = supervisor.clone();
+        task_tracker.spawn(async move {
+            supervisor
+                .supervise("batch worker", worker_batch, |worker| {
+                    Box::pin(async move {
+                        worker.run_batch(batch_size).await;
+                        Ok(())
+                    })
+                })
+                .await;
+        });
     }
 
     // spawn single worker thread if we are using such models (e.g. Ollama)
-    if let Some(mut worker_single) = worker_single {
+    if let Some(worker_single) = worker_single {
         log::info!("Spawning workflows single worker thread.");
-        task_tracker.spawn(async move { worker_single.run_series().await });
+        let supervisor = supervisor.clone();
+        task_tracker.spawn(async move {
+            supervisor
+                .supervise("single worker", worker_single, |worker| {
+                    Box::pin(async move {
+                        worker.run_series().await;
+                        Ok(())
+                    })
+                })
+                .await;
+        });
     }
 
     // spawn compute node thread
     log::info!("Spawning compute node thread.");
     let node_token = cancellation.clone();
     task_tracker.spawn(async move {
-        if let Err(err) = node.run(node_token).await {
-            log::error!("Error within main node loop: {}", err);
+        // the node loop is restarted on errors as well, and shut down here if the supervisor gives up on it
+        if let Some(mut node) = supervisor
+            .supervise("node", node, |node| Box::pin(node.run(node_token.clone())))
+            .await
+        {
             log::error!("Shutting down node.");
-            node.shutdown().await.expect("could not shutdown node");
-        };
+            if let Err(err) = node.shutdown().await {
+                log::error!("Could not shutdown node: {:?}", err);
+            }
+        }
         log::info!("Closing node.")
     });
 
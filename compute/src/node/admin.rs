@@ -0,0 +1,91 @@
+use tokio::sync::mpsc;
+
+use crate::{
+    admin::{channel_metrics, AdminCommand, AdminMessage, AdminResponse},
+    DriaComputeNode, DRIA_COMPUTE_NODE_VERSION,
+};
+
+impl DriaComputeNode {
+    /// Returns a sender for admin commands, to be given to an admin server.
+    #[inline]
+    pub fn admin_sender(&self) -> mpsc::Sender<AdminMessage> {
+        self.admin_tx.clone()
+    }
+
+    /// Returns `true` if the node is paused via the admin channel.
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Handles a command received from the admin channel.
+    pub(crate) async fn handle_admin_command(&mut self, command: AdminCommand) -> AdminResponse {
+        match command {
+            AdminCommand::Status => {
+                let (mesh, all) = self.p2p.peer_counts().await.unwrap_or_default();
+                let [pending_single, pending_batch] = self.get_pending_task_count();
+                let channels = self
+                    .get_channel_stats()
+                    .await
+                    .into_iter()
+                    .collect::<std::collections::BTreeMap<_, _>>();
+                AdminResponse::with_data(serde_json::json!({
+                    "version": DRIA_COMPUTE_NODE_VERSION,
+                    "peer_id": self.config.peer_id.to_string(),
+                    "address": format!("0x{}", self.config.address),
+                    "network": self.config.network_type.to_string(),
+                    "paused": self.paused,
+                    "observer": self.observer,
+                    "peers": { "mesh": mesh, "all": all },
+                    "pending_tasks": { "single": pending_single, "batch": pending_batch },
+                    "completed_tasks": { "single": self.completed_tasks_single, "batch": self.completed_tasks_batch },
+                    "last_pinged_secs": self.last_pinged_at.elapsed().as_secs(),
+                    "selected_rpc": self.selected_rpc.map(|p| p.to_string()),
+                    "dead_letters": self.dead_letters.len(),
+                    "relay": self.relay_stats,
+                    "channels": channels,
+                    "models": self.config.workflows.get_model_names(),
+                }))
+            }
+            AdminCommand::Pause => {
+                log::warn!("Pausing node, new tasks will not be accepted.");
+                self.paused = true;
+                self.update_paused = false;
+                AdminResponse::ok()
+            }
+            AdminCommand::Resume => {
+                log::warn!("Resuming node.");
+                self.paused = false;
+                self.update_paused = false;
+                AdminResponse::ok()
+            }
+            AdminCommand::Reload => {
+                self.handle_available_nodes_refresh().await;
+                AdminResponse::ok()
+            }
+            AdminCommand::DeadLetters => AdminResponse::with_data(serde_json::json!(self
+                .dead_letters
+                .letters()
+                .collect::<Vec<_>>())),
+            AdminCommand::BlockedPeers => match self.p2p.blocked_peers().await {
+                Ok(blocked_peers) => AdminResponse::with_data(serde_json::json!(blocked_peers
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>())),
+                Err(e) => AdminResponse::error(e),
+            },
+            AdminCommand::Metrics => {
+                let metrics = channel_metrics(&self.get_channel_stats().await);
+                AdminResponse::with_data(serde_json::Value::String(metrics))
+            }
+            AdminCommand::Shutdown => {
+                // shutdown is done via cancellation by the admin server
+                AdminResponse::error("shutdown must be handled by the admin server")
+            }
+            AdminCommand::StatsQuery => {
+                // queries are run by the admin server over their own connection
+                AdminResponse::error("stats queries must be handled by the admin server")
+            }
+        }
+    }
+}
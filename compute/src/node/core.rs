@@ -2,28 +2,60 @@ use eyre::{eyre, Result};
 use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
-use crate::{node::PingpongHandler, utils::DriaMessage, DriaComputeNode};
+use dkn_p2p::libp2p::gossipsub::MessageAcceptance;
+use dkn_utils::payloads::DriaTopic;
+
+use crate::{
+    utils::{DriaMessage, Scheduler},
+    DriaComputeNode,
+};
+
+use super::reqres::await_goodbyes;
+
+/// Number of seconds between refreshing for diagnostic prints.
+const DIAGNOSTIC_REFRESH_INTERVAL_SECS: u64 = 30;
+/// Number of seconds between refreshing for diagnostic prints while there are anomalies.
+const DIAGNOSTIC_ANOMALY_INTERVAL_SECS: u64 = 10;
+/// Number of seconds between checking whether the TPS of the local models is due to be measured,
+/// which also delays a measurement that was skipped or aborted as the node was busy.
+const TPS_RETRY_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Periodic jobs of the node, run by its scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeJob {
+    /// Selects the RPC with the lowest latency & prints diagnostics.
+    Diagnostic,
+    /// Refreshes the available nodes & dials the RPCs.
+    AvailableNodesRefresh,
+    /// Retries fetching the available nodes until it succeeds once, before joining the network.
+    AvailableNodesPrefetch,
+    /// Compares the models with the ones rewarded by the network.
+    ModelSync,
+    /// Checks the registration of the node address.
+    RegistrationCheck,
+    /// Removes the expired tasks from the archive.
+    ArchiveRetention,
+    /// Keeps the connections to the RPCs alive.
+    RpcKeepalive,
+    /// Removes the pending tasks that can no longer be responded to.
+    StaleTaskSweep,
+    /// Measures the TPS of the local models again.
+    TpsRefresh,
+}
 
 impl DriaComputeNode {
     /// Runs the main loop of the compute node.
     /// This method is not expected to return until cancellation occurs for the given token.
     pub async fn run(&mut self, cancellation: CancellationToken) -> Result<()> {
-        /// Number of seconds between refreshing for diagnostic prints.
-        const DIAGNOSTIC_REFRESH_INTERVAL_SECS: u64 = 30;
-        /// Number of seconds between refreshing the available nodes.
-        const AVAILABLE_NODES_REFRESH_INTERVAL_SECS: u64 = 10 * 60;
+        let mut scheduler = self.new_scheduler();
 
-        // prepare durations for sleeps
-        let mut diagnostic_refresh_interval =
-            tokio::time::interval(Duration::from_secs(DIAGNOSTIC_REFRESH_INTERVAL_SECS));
-        diagnostic_refresh_interval.tick().await; // move one tick
-        let mut available_node_refresh_interval =
-            tokio::time::interval(Duration::from_secs(AVAILABLE_NODES_REFRESH_INTERVAL_SECS));
-        available_node_refresh_interval.tick().await; // move one tick
-
-        // subscribe to topics
-        self.subscribe(PingpongHandler::LISTEN_TOPIC).await?;
-        self.subscribe(PingpongHandler::RESPONSE_TOPIC).await?;
+        // the node does not take part in the network until it knows which RPCs to accept tasks from,
+        // so the topics are subscribed to once the available nodes are fetched in the background
+        if scheduler.is_scheduled(&NodeJob::AvailableNodesPrefetch) {
+            log::warn!("Available nodes are not fetched yet, joining the network once they are.");
+        } else {
+            self.subscribe_topics().await?;
+        }
 
         loop {
             tokio::select! {
@@ -46,10 +78,15 @@ impl DriaComputeNode {
                     // handle the message, returning a message acceptance for the received one
                     let acceptance = self.handle_message((propagation_peer_id, &message_id, message)).await;
 
-                    // validate the message based on the acceptance
+                    // validate the message based on the acceptance, accepted ones are forwarded if still in cache
                     // cant do anything but log if this gives an error as well
-                    if let Err(e) = self.p2p.validate_message(&message_id, &propagation_peer_id, acceptance).await {
-                        log::error!("Error validating message {}: {:?}", message_id, e);
+                    self.relay_stats.record(&acceptance);
+                    let is_accepted = matches!(acceptance, MessageAcceptance::Accept);
+                    match self.p2p.validate_message(&message_id, &propagation_peer_id, acceptance).await {
+                        Ok(in_cache) => if is_accepted && in_cache {
+                            self.relay_stats.record_forwarded();
+                        },
+                        Err(e) => log::error!("Error validating message {}: {:?}", message_id, e),
                     }
 
                 },
@@ -62,11 +99,36 @@ impl DriaComputeNode {
                   }
                 },
 
-                // check peer count every now and then
-                _ = diagnostic_refresh_interval.tick() => self.handle_diagnostic_refresh().await,
+                // an admin command is received, sent by the admin server
+                admin_msg_opt = self.admin_rx.recv() => {
+                    // we hold a sender ourselves, so this channel is never closed
+                    if let Some((command, sender)) = admin_msg_opt {
+                        let response = self.handle_admin_command(command).await;
+                        let _ = sender.send(response);
+                    }
+                },
 
-                // available nodes are refreshed every now and then
-                _ = available_node_refresh_interval.tick() => self.handle_available_nodes_refresh().await,
+                // a TPS refresh is completed in the background
+                tps_refresh_opt = self.tps_refresh_rx.recv() => {
+                    // we hold a sender ourselves, so this channel is never closed
+                    if let Some((tps, slow_models)) = tps_refresh_opt {
+                        if let Err(e) = self.handle_tps_refreshed(tps, slow_models) {
+                            log::error!("{}", e);
+                            cancellation.cancel();
+                        }
+                    }
+                },
+
+                // a keep-alive to an RPC has failed after its retries
+                keepalive_failed_opt = self.keepalive_failed_rx.recv() => {
+                    // we hold a sender ourselves, so this channel is never closed
+                    if let Some(peer_id) = keepalive_failed_opt {
+                        self.handle_rpc_keepalive_failed(peer_id);
+                    }
+                },
+
+                // a periodic job is due
+                job = scheduler.next() => self.handle_job(job, &mut scheduler, &cancellation).await,
 
                 // check if the cancellation token is cancelled
                 // this is expected to be cancelled by the main thread with signal handling
@@ -75,11 +137,27 @@ impl DriaComputeNode {
         }
 
         // unsubscribe from topics
-        self.unsubscribe(PingpongHandler::LISTEN_TOPIC).await?;
-        self.unsubscribe(PingpongHandler::RESPONSE_TOPIC).await?;
+        for topic in DriaTopic::GOSSIP {
+            self.unsubscribe(topic.as_str()).await?;
+        }
 
         // print one final diagnostic as a summary
-        self.handle_diagnostic_refresh().await;
+        self.handle_diagnostic_refresh(true).await;
+
+        // let the RPCs know that we are leaving, or restarting if we exit for an update,
+        // and wait for them to be delivered
+        self.handle_session_save();
+        let reason = match self.config.exit_for_update && self.version_requirement.is_some() {
+            true => "restart",
+            false => "shutdown",
+        };
+        let rpc_peerids = self
+            .dria_nodes
+            .rpc_peerids
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        await_goodbyes(self.handle_goodbye(&rpc_peerids, reason).await).await;
 
         // shutdown channels
         self.shutdown().await?;
@@ -87,6 +165,162 @@ impl DriaComputeNode {
         Ok(())
     }
 
+    /// Creates the scheduler for the periodic jobs of the node, jobs that are disabled by the config are left out.
+    ///
+    /// The diagnostics, node refreshes & model syncs run less often with the low-power profile.
+    fn new_scheduler(&self) -> Scheduler<NodeJob> {
+        /// Number of seconds between refreshing the available nodes.
+        const AVAILABLE_NODES_REFRESH_INTERVAL_SECS: u64 = 10 * 60;
+        /// Number of seconds between the attempts to fetch the available nodes, until they are fetched once.
+        const AVAILABLE_NODES_PREFETCH_INTERVAL_SECS: u64 = 15;
+        /// Number of seconds between comparing the models to the rewarded models of the network.
+        const MODEL_SYNC_INTERVAL_SECS: u64 = 30 * 60;
+        /// Number of seconds between checking the registration of the node address.
+        const REGISTRATION_CHECK_INTERVAL_SECS: u64 = 60 * 60;
+        /// Number of seconds between removing expired tasks from the archive.
+        const ARCHIVE_RETENTION_INTERVAL_SECS: u64 = 60 * 60;
+        /// Number of seconds between removing the stale pending tasks.
+        const STALE_TASK_SWEEP_INTERVAL_SECS: u64 = 5 * 60;
+
+        let profile = self.config.profile;
+        let mut scheduler = Scheduler::default()
+            .with_job(NodeJob::Diagnostic, self.diagnostic_interval(false), false)
+            // the RPCs of a resumed session are checked against the API right away
+            .with_job(
+                NodeJob::AvailableNodesRefresh,
+                profile.interval(AVAILABLE_NODES_REFRESH_INTERVAL_SECS),
+                !self.resumed_rpc_peerids.is_empty(),
+            )
+            // keep-alive is disabled with zero seconds
+            .with_job(
+                NodeJob::RpcKeepalive,
+                Duration::from_secs(self.config.rpc_keepalive_secs),
+                false,
+            )
+            .with_job(
+                NodeJob::StaleTaskSweep,
+                Duration::from_secs(STALE_TASK_SWEEP_INTERVAL_SECS),
+                false,
+            );
+
+        // model differences are reported at the start, if the rewarded models can be fetched
+        if self.config.rewarded_models_url.is_some() {
+            scheduler.schedule(
+                NodeJob::ModelSync,
+                profile.interval(MODEL_SYNC_INTERVAL_SECS),
+                true,
+            );
+        }
+
+        // the registration is checked at the start as well
+        if self.config.registration_url.is_some() {
+            scheduler.schedule(
+                NodeJob::RegistrationCheck,
+                Duration::from_secs(REGISTRATION_CHECK_INTERVAL_SECS),
+                true,
+            );
+        }
+
+        // the available nodes are retried until fetched, unless RPCs are given statically
+        if !self.dria_nodes_fetched && self.dria_nodes.rpc_peerids.is_empty() {
+            scheduler.schedule(
+                NodeJob::AvailableNodesPrefetch,
+                Duration::from_secs(AVAILABLE_NODES_PREFETCH_INTERVAL_SECS),
+                false,
+            );
+        }
+
+        // the TPS of local models is measured during their checks, so not at the start
+        if self.task_request_single_tx.is_some() {
+            scheduler.schedule(
+                NodeJob::TpsRefresh,
+                Duration::from_secs(TPS_RETRY_INTERVAL_SECS),
+                false,
+            );
+        }
+
+        // expired tasks are removed at the start as well
+        if self.archive.is_some() {
+            scheduler.schedule(
+                NodeJob::ArchiveRetention,
+                Duration::from_secs(ARCHIVE_RETENTION_INTERVAL_SECS),
+                true,
+            );
+        }
+
+        scheduler
+    }
+
+    /// Returns the interval of the diagnostics w.r.t the profile, which is shorter while there are anomalies.
+    fn diagnostic_interval(&self, anomalies: bool) -> Duration {
+        self.config.profile.interval(match anomalies {
+            true => DIAGNOSTIC_ANOMALY_INTERVAL_SECS,
+            false => DIAGNOSTIC_REFRESH_INTERVAL_SECS,
+        })
+    }
+
+    /// Runs a periodic job that is due.
+    ///
+    /// The diagnostics are refreshed more often while there are anomalies, so that incidents are captured in detail.
+    async fn handle_job(
+        &mut self,
+        job: NodeJob,
+        scheduler: &mut Scheduler<NodeJob>,
+        cancellation: &CancellationToken,
+    ) {
+        log::trace!("Running job {:?}", job);
+        match job {
+            NodeJob::Diagnostic => {
+                self.handle_rpc_selection().await;
+                let anomalies = self.handle_diagnostic_refresh(false).await;
+                if anomalies != self.diagnostic_anomalies {
+                    scheduler.schedule(
+                        NodeJob::Diagnostic,
+                        self.diagnostic_interval(anomalies),
+                        false,
+                    );
+                    self.diagnostic_anomalies = anomalies;
+                }
+
+                // the whole node is shut down, so that the launcher can update & restart it
+                if self.is_ready_to_update() {
+                    log::warn!("Exiting for the update, please restart with the latest version.");
+                    cancellation.cancel();
+                }
+            }
+            NodeJob::AvailableNodesRefresh => self.handle_available_nodes_refresh().await,
+            NodeJob::AvailableNodesPrefetch => {
+                if self.handle_available_nodes_prefetch().await {
+                    scheduler.cancel(&NodeJob::AvailableNodesPrefetch);
+                    if let Err(e) = self.subscribe_topics().await {
+                        log::error!("Error subscribing to topics: {:?}", e);
+                    }
+                }
+            }
+            NodeJob::ModelSync => self.handle_model_sync().await,
+            NodeJob::RegistrationCheck => self.handle_registration_check().await,
+            NodeJob::ArchiveRetention => self.handle_archive_retention(),
+            NodeJob::RpcKeepalive => self.handle_rpc_keepalive().await,
+            NodeJob::StaleTaskSweep => self.handle_stale_task_sweep(),
+            NodeJob::TpsRefresh => {
+                self.handle_tps_refresh();
+                scheduler.schedule(
+                    NodeJob::TpsRefresh,
+                    Duration::from_secs(TPS_RETRY_INTERVAL_SECS),
+                    false,
+                );
+            }
+        }
+    }
+
+    /// Subscribes to the gossipsub topics of the node, i.e. joins the network.
+    async fn subscribe_topics(&mut self) -> Result<()> {
+        for topic in DriaTopic::GOSSIP {
+            self.subscribe(topic.as_str()).await?;
+        }
+        Ok(())
+    }
+
     /// Shorthand method to create a signed message with the given data and topic.
     #[inline(always)]
     pub fn new_message(&self, data: impl AsRef<[u8]>, topic: impl ToString) -> DriaMessage {
@@ -107,6 +341,10 @@ impl DriaComputeNode {
         log::debug!("Closing task response channel.");
         self.task_output_rx.close();
 
+        if let Err(e) = self.rpc_reputations.persist() {
+            log::error!("Error persisting RPC reputations: {:?}", e);
+        }
+
         Ok(())
     }
 }
@@ -1,11 +1,30 @@
-use dkn_p2p::libp2p::multiaddr::Protocol;
-use std::time::Duration;
+use dkn_p2p::{
+    libp2p::{multiaddr::Protocol, PeerId},
+    ChannelStats, IpFamily, RedialStatus,
+};
+use dkn_utils::{get_current_time_nanos, SemanticVersion};
+use dkn_workflows::{Model, ModelProvider};
+use eyre::{eyre, Result};
+use std::{collections::HashMap, time::Duration};
 use tokio::time::Instant;
 
-use crate::{refresh_dria_nodes, DriaComputeNode, DRIA_COMPUTE_NODE_VERSION};
+use crate::{
+    gossipsub::PingpongUtilization,
+    refresh_dria_nodes,
+    utils::{diff_models, fetch_registration, fetch_rewarded_models, select_rpc},
+    DriaComputeNode, DRIA_COMPUTE_NODE_VERSION,
+};
+
+use super::reqres::await_goodbyes;
 
 /// Number of seconds such that if the last ping is older than this, the node is considered unreachable.
 const PING_LIVENESS_SECS: u64 = 150;
+/// Number of seconds to wait for the checks of rewarded models, as they run within the node loop.
+const MODEL_SYNC_CHECK_TIMEOUT_SECS: u64 = 60;
+/// Ratio of a channel that is full, after which it is reported as an anomaly.
+const CHANNEL_SATURATION_THRESHOLD: f64 = 0.8;
+/// Number of seconds between measuring the TPS of the local models.
+const TPS_REFRESH_INTERVAL_SECS: u64 = 7 * 24 * 60 * 60;
 
 impl DriaComputeNode {
     /// Returns the task count within the channels, `single` and `batch`.
@@ -17,24 +36,263 @@ impl DriaComputeNode {
         ]
     }
 
-    /// Peer refresh simply reports the peer count to the user.
-    pub(crate) async fn handle_diagnostic_refresh(&self) {
+    /// Returns the current utilization of the workers, w.r.t pending tasks and worker capacities.
+    pub fn get_utilization(&self) -> PingpongUtilization {
+        let pending = self.get_pending_task_count();
+        let total_slots = [
+            usize::from(self.task_request_single_tx.is_some()),
+            if self.task_request_batch_tx.is_some() {
+                self.config.batch_size
+            } else {
+                0
+            },
+        ];
+        let busy_slots = [0, 1].map(|i| pending[i].min(total_slots[i]));
+        let queue_depth = [0, 1].map(|i| pending[i] - busy_slots[i]);
+
+        PingpongUtilization {
+            busy_slots,
+            total_slots,
+            queue_depth,
+            avg_latency_ms: self
+                .model_latencies
+                .iter()
+                .map(|(model, latency)| (model.clone(), latency.round() as u64))
+                .collect(),
+        }
+    }
+
+    /// Returns the stats of the channels of the node along with the ones of its p2p client.
+    ///
+    /// The message & request channels of the client are the ones that the node receives from.
+    pub(crate) async fn get_channel_stats(&self) -> Vec<(&'static str, ChannelStats)> {
+        let mut stats = vec![("publish", self.task_output_rx.stats())];
+        if let Some(tx) = &self.task_request_single_tx {
+            stats.push((
+                "task_single",
+                ChannelStats::of_sender(tx, self.dropped_tasks[0]),
+            ));
+        }
+        if let Some(tx) = &self.task_request_batch_tx {
+            stats.push((
+                "task_batch",
+                ChannelStats::of_sender(tx, self.dropped_tasks[1]),
+            ));
+        }
+        match self.p2p.channel_stats().await {
+            Ok(p2p_stats) => stats.extend(p2p_stats),
+            Err(e) => log::error!("Error getting channel stats: {:?}", e),
+        }
+
+        stats
+    }
+
+    /// Updates the moving average of execution time for the given model.
+    pub(crate) fn record_model_latency(&mut self, model: &str, latency_ms: f64) {
+        /// Weight of the latest measurement within the moving average.
+        const LATENCY_EMA_ALPHA: f64 = 0.2;
+
+        self.model_latencies
+            .entry(model.to_string())
+            .and_modify(|avg| *avg += LATENCY_EMA_ALPHA * (latency_ms - *avg))
+            .or_insert(latency_ms);
+    }
+
+    /// Reports the state of the node to the user, returns `true` if there are anomalies.
+    ///
+    /// A healthy node prints a one-line summary, while the detailed diagnostics are printed
+    /// if there are anomalies (or if `detailed` is set), so that incidents are captured without
+    /// flooding the logs otherwise.
+    pub(crate) async fn handle_diagnostic_refresh(&mut self, detailed: bool) -> bool {
         let mut diagnostics = vec![format!("Diagnostics (v{}):", DRIA_COMPUTE_NODE_VERSION)];
+        let mut anomalies = Vec::new();
 
         // print peer counts
-        match self.p2p.peer_counts().await {
+        let peer_count = match self.p2p.peer_counts().await {
             Ok((mesh, all)) => {
-                diagnostics.push(format!("Peer Count (mesh/all): {} / {}", mesh, all))
+                diagnostics.push(format!("Peer Count (mesh/all): {} / {}", mesh, all));
+                Some(all)
+            }
+            Err(e) => {
+                log::error!("Error getting peer counts: {:?}", e);
+                None
+            }
+        };
+
+        // print the connections w.r.t their limits
+        match self.p2p.network_info().await {
+            Ok((info, limits)) => {
+                let counters = info.connection_counters();
+                let established = counters.num_established();
+                diagnostics.push(format!(
+                    "Connections (established/pending): {} / {}",
+                    established,
+                    counters.num_pending()
+                ));
+                if limits.max_established.is_some_and(|max| established >= max) {
+                    anomalies.push("connection limit reached".to_string());
+                }
+            }
+            Err(e) => log::error!("Error getting network info: {:?}", e),
+        }
+
+        // print the ip families of the connections, to see if dual-stack listening is in use
+        match self.p2p.peer_families().await {
+            Ok(families) => {
+                let ip6 = families.values().filter(|f| **f == IpFamily::Ip6).count();
+                diagnostics.push(format!(
+                    "Connections (ip4/ip6): {} / {}",
+                    families.len() - ip6,
+                    ip6
+                ));
+            }
+            Err(e) => log::error!("Error getting connection families: {:?}", e),
+        }
+
+        // print the nodes within the local network, if they are discovered
+        if self.config.p2p_mdns {
+            match self.p2p.lan_peers().await {
+                Ok(lan_peers) => diagnostics.push(format!("LAN Peers: {}", lan_peers.len())),
+                Err(e) => log::error!("Error getting LAN peers: {:?}", e),
+            }
+        }
+
+        // print the health of the relays, if there are any
+        match self.p2p.relay_health().await {
+            Ok(relays) if !relays.is_empty() => diagnostics.push(format!(
+                "Relays (rtt/accepted/dropped): {}",
+                relays
+                    .iter()
+                    .map(|r| format!(
+                        "{}{} {}/{}/{}",
+                        r.address,
+                        if r.reserved { " (reserved)" } else { "" },
+                        r.rtt
+                            .map(|rtt| format!("{}ms", rtt.as_millis()))
+                            .unwrap_or("-".to_string()),
+                        r.accepted,
+                        r.dropped
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            Ok(_) => {}
+            Err(e) => log::error!("Error getting relay health: {:?}", e),
+        }
+
+        // print the redials of the disconnected RPCs, those that are given up on are anomalies
+        match self.p2p.redial_status().await {
+            Ok(redials) if !redials.is_empty() => {
+                diagnostics.push(format!(
+                    "RPC Redials: {}",
+                    redials
+                        .iter()
+                        .map(|(peer_id, status)| match status {
+                            RedialStatus::Backoff { attempts, next_in } => format!(
+                                "{} ({} failed, next in {}s)",
+                                peer_id,
+                                attempts,
+                                next_in.as_secs()
+                            ),
+                            RedialStatus::Dialling { attempts } => {
+                                format!("{} ({} failed, dialling)", peer_id, attempts)
+                            }
+                            RedialStatus::GaveUp { attempts } => {
+                                anomalies.push(format!("gave up redialling {}", peer_id));
+                                format!("{} (gave up after {})", peer_id, attempts)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Error getting redial status: {:?}", e),
+        }
+
+        // print mesh & subscribed peer counts of each topic, and warn if there are no mesh peers
+        match self.p2p.subscriptions().await {
+            Ok(topics) => {
+                diagnostics.push(format!(
+                    "Topics (mesh/subscribed): {}",
+                    topics
+                        .iter()
+                        .map(|t| format!(
+                            "{}: {} / {}",
+                            t.topic,
+                            t.mesh_peers.len(),
+                            t.subscribed_peers
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+                for topic in topics.iter().filter(|t| !t.is_healthy()) {
+                    log::warn!("Topic {} has no mesh peers yet.", topic.topic);
+                    anomalies.push(format!("no mesh peers for {}", topic.topic));
+                }
             }
-            Err(e) => log::error!("Error getting peer counts: {:?}", e),
+            Err(e) => log::error!("Error getting subscriptions: {:?}", e),
         }
 
-        // print tasks count
+        // print how long it took for the first peer to connect to us, which means we are reachable
+        match self.p2p.first_inbound_after().await {
+            Ok(Some(after)) => diagnostics.push(format!(
+                "First Inbound Connection: after {}s",
+                after.as_secs()
+            )),
+            Ok(None) => diagnostics.push("First Inbound Connection: none yet".to_string()),
+            Err(e) => log::error!("Error getting first inbound connection: {:?}", e),
+        }
+
+        // print gossip relay contribution
+        diagnostics.push(format!(
+            "Relayed Messages (forwarded/validated): {} / {}",
+            self.relay_stats.forwarded,
+            self.relay_stats.validated()
+        ));
+
+        // print tasks count, and check if the queue is growing
         let [single, batch] = self.get_pending_task_count();
         diagnostics.push(format!(
             "Pending Tasks (single/batch): {} / {}",
             single, batch
         ));
+        let queue_depth = self.get_utilization().queue_depth.iter().sum::<usize>();
+        if queue_depth > self.last_queue_depth {
+            anomalies.push(format!(
+                "task queue grew from {} to {}",
+                self.last_queue_depth, queue_depth
+            ));
+        }
+        self.last_queue_depth = queue_depth;
+
+        // print channel depths, and check if any of them is close to overflowing or has dropped items
+        let channel_stats = self.get_channel_stats().await;
+        diagnostics.push(format!(
+            "Channels (depth/capacity): {}",
+            channel_stats
+                .iter()
+                .map(|(name, stats)| format!("{}: {} / {}", name, stats.depth, stats.capacity))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        for (name, stats) in &channel_stats {
+            if stats.saturation() >= CHANNEL_SATURATION_THRESHOLD {
+                anomalies.push(format!(
+                    "{} channel is {}% full",
+                    name,
+                    (stats.saturation() * 100.0).round()
+                ));
+            }
+        }
+        let channel_drops = channel_stats.iter().map(|(_, s)| s.dropped).sum::<u64>();
+        if channel_drops > self.last_channel_drops {
+            anomalies.push(format!(
+                "{} items dropped by channels",
+                channel_drops - self.last_channel_drops
+            ));
+        }
+        self.last_channel_drops = channel_drops;
 
         // completed tasks count is printed as well in debug
         if log::log_enabled!(log::Level::Debug) {
@@ -44,6 +302,38 @@ impl DriaComputeNode {
             ));
         }
 
+        // print component restarts, if any
+        if let Some(restarts) = self
+            .supervisor
+            .as_ref()
+            .map(|s| s.restart_count())
+            .filter(|r| *r != 0)
+        {
+            diagnostics.push(format!("Restarts: {}", restarts));
+        }
+
+        // print rpc latencies, with the selected one marked
+        if !self.rpc_latencies.is_empty() {
+            diagnostics.push(format!(
+                "RPC Latencies: {}",
+                self.rpc_latencies
+                    .iter()
+                    .map(|(peer_id, latency)| format!(
+                        "{}: {}ms, score {:.2}{}",
+                        peer_id,
+                        latency.as_millis(),
+                        self.rpc_reputations.score(peer_id),
+                        if self.selected_rpc == Some(*peer_id) {
+                            " (selected)"
+                        } else {
+                            ""
+                        }
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
         // print peer id and address
         diagnostics.push(format!("Peer ID: {}", self.config.peer_id));
         diagnostics.push(format!("Address: 0x{}", self.config.address));
@@ -60,20 +350,298 @@ impl DriaComputeNode {
                 .join(", ")
         ));
 
-        log::info!("{}", diagnostics.join("\n  "));
-
         // check liveness of the node w.r.t last ping-pong time
-        if self.last_pinged_at < Instant::now() - Duration::from_secs(PING_LIVENESS_SECS) {
+        let unreachable =
+            self.last_pinged_at < Instant::now() - Duration::from_secs(PING_LIVENESS_SECS);
+        if unreachable {
+            anomalies.push("no pings".to_string());
+        }
+
+        // check if workers are stalled, i.e. there are pending tasks but no progress for a while
+        let stalled_for = self.last_task_progress_at.elapsed();
+        let stalled =
+            single + batch != 0 && stalled_for > Duration::from_secs(self.config.task_stall_secs);
+        if stalled {
+            anomalies.push("stalled workers".to_string());
+        }
+
+        if self.selected_rpc.is_none() {
+            anomalies.push("no healthy RPC".to_string());
+        }
+
+        if anomalies.is_empty() && !detailed && !log::log_enabled!(log::Level::Debug) {
+            log::info!(
+                "Healthy (v{}): {} peers, {} / {} pending tasks, RPC latency {}",
+                DRIA_COMPUTE_NODE_VERSION,
+                peer_count.map_or_else(|| "?".to_string(), |count| count.to_string()),
+                single,
+                batch,
+                self.selected_rpc
+                    .and_then(|peer_id| self.rpc_latencies.get(&peer_id))
+                    .map_or_else(|| "?".to_string(), |l| format!("{}ms", l.as_millis()))
+            );
+        } else {
+            if !anomalies.is_empty() {
+                diagnostics.insert(1, format!("Anomalies: {}", anomalies.join(", ")));
+            }
+            log::info!("{}", diagnostics.join("\n  "));
+        }
+
+        if unreachable {
             log::error!(
                 "Node has not received any pings for at least {} seconds & it may be unreachable!\nPlease restart your node!",
                 PING_LIVENESS_SECS
             );
         }
 
+        if stalled {
+            log::error!(
+                "Watchdog: no task progress for {} seconds (pending single/batch: {} / {}, abort: {})",
+                stalled_for.as_secs(),
+                single,
+                batch,
+                self.config.abort_stalled_tasks
+            );
+        }
+
+        // warn about the version requirement with a countdown
+        if let Some((min_version, deadline)) = &self.version_requirement {
+            let now = get_current_time_nanos();
+            match deadline {
+                Some(deadline) if *deadline > now => {
+                    let secs = ((deadline - now) / 1_000_000_000) as u64;
+                    log::warn!(
+                        "Node must be updated to v{} within {}h {}m, otherwise it will be disconnected!",
+                        min_version,
+                        secs / 3600,
+                        (secs % 3600) / 60
+                    );
+                }
+                Some(_) => log::error!(
+                    "Node is below the required v{} & the deadline has passed, please update your node!",
+                    min_version
+                ),
+                None => log::warn!(
+                    "Node must be updated to v{}, please update your node!",
+                    min_version
+                ),
+            }
+        }
+
+        // remind that an observer does not execute tasks, unless it is observing on purpose
+        if self.observer && !self.config.observe {
+            log::warn!(
+                "Node is an observer as address 0x{} is not registered, tasks will not be executed!",
+                self.config.address
+            );
+        }
+
         // added rpc nodes check, sometimes this happens when API is down / bugs for some reason
         if self.dria_nodes.rpc_peerids.is_empty() {
             log::error!("No RPC peerids were found to be available, please restart your node!",);
         }
+
+        !anomalies.is_empty()
+    }
+
+    /// Updates the latencies & reputations of the RPC nodes, and selects the one with the lowest
+    /// latency weighted by its reputation.
+    ///
+    /// RPCs without a measured latency are not connected or not responding to pings, so they are not considered.
+    pub(crate) async fn handle_rpc_selection(&mut self) {
+        let latencies = match self.p2p.peer_latencies().await {
+            Ok(latencies) => latencies,
+            Err(e) => {
+                log::error!("Error getting peer latencies: {:?}", e);
+                return;
+            }
+        };
+        self.rpc_latencies = latencies
+            .into_iter()
+            .filter(|(peer_id, _)| self.dria_nodes.rpc_peerids.contains(peer_id))
+            .collect();
+
+        // update the reputations with the latest latencies & request outcomes
+        for (peer_id, latency) in &self.rpc_latencies {
+            self.rpc_reputations.record_latency(*peer_id, *latency);
+        }
+        match self.p2p.take_request_outcomes().await {
+            Ok(outcomes) => outcomes
+                .iter()
+                .filter(|(peer_id, _)| self.dria_nodes.rpc_peerids.contains(peer_id))
+                .for_each(|(peer_id, outcomes)| {
+                    self.rpc_reputations.record_requests(*peer_id, outcomes)
+                }),
+            Err(e) => log::error!("Error getting request outcomes: {:?}", e),
+        }
+        if let Err(e) = self.rpc_reputations.persist() {
+            log::error!("Error persisting RPC reputations: {:?}", e);
+        }
+
+        let scores = self
+            .rpc_latencies
+            .keys()
+            .map(|peer_id| (*peer_id, self.rpc_reputations.score(peer_id)))
+            .collect();
+        let selected_rpc = select_rpc(self.selected_rpc, &self.rpc_latencies, &scores);
+        if selected_rpc != self.selected_rpc {
+            match selected_rpc {
+                Some(peer_id) => log::info!(
+                    "Selected RPC {} with latency {}ms.",
+                    peer_id,
+                    self.rpc_latencies[&peer_id].as_millis()
+                ),
+                None => log::warn!("No healthy RPC nodes to select."),
+            }
+
+            // let the previous RPC know that we are switching away from it, without blocking the loop
+            if let Some(previous_rpc) = self.selected_rpc {
+                let responses = self.handle_goodbye(&[previous_rpc], "rpc-switch").await;
+                tokio::spawn(await_goodbyes(responses));
+            }
+            self.selected_rpc = selected_rpc;
+        }
+    }
+
+    /// Returns the selected RPC node, i.e. the healthy one with the lowest latency.
+    #[inline]
+    pub fn selected_rpc(&self) -> Option<PeerId> {
+        self.selected_rpc
+    }
+
+    /// Updates the minimum version required by the network, as advertised within the pings.
+    ///
+    /// If the node is outdated and is to exit for an update, it is paused so that it can finish its pending tasks.
+    /// The pause is lifted if the requirement goes away before that, e.g. the network rolls back its minimum version.
+    pub(crate) fn handle_version_requirement(
+        &mut self,
+        min_version: Option<&str>,
+        deadline: Option<u128>,
+    ) {
+        let min_version = match min_version.map(str::parse::<SemanticVersion>) {
+            Some(Ok(min_version)) => min_version,
+            Some(Err(e)) => {
+                log::debug!("Ignoring minimum version: {}", e);
+                return;
+            }
+            None => {
+                self.clear_version_requirement();
+                return;
+            }
+        };
+        let Ok(version) = DRIA_COMPUTE_NODE_VERSION.parse::<SemanticVersion>() else {
+            return;
+        };
+
+        if version >= min_version {
+            self.clear_version_requirement();
+            return;
+        }
+
+        if self.version_requirement.is_none() {
+            log::warn!(
+                "Network requires at least v{}, but the node is v{}.",
+                min_version,
+                version
+            );
+            if self.config.exit_for_update {
+                log::warn!(
+                    "Pausing node, it will exit for the update once pending tasks are done."
+                );
+                // a pause from the admin channel is kept as is
+                self.update_paused = !self.paused;
+                self.paused = true;
+            }
+        }
+        self.version_requirement = Some((min_version, deadline));
+    }
+
+    /// Clears the version requirement, and resumes the node if it was paused for the update.
+    fn clear_version_requirement(&mut self) {
+        if self.version_requirement.take().is_none() {
+            return;
+        }
+
+        log::info!("Network no longer requires a newer version.");
+        if self.update_paused {
+            log::warn!("Resuming node, it will not exit for the update.");
+            self.paused = false;
+            self.update_paused = false;
+        }
+    }
+
+    /// Checks whether the node address is registered, and switches to observer mode if it is not.
+    ///
+    /// The mode is kept as is if the registration can not be fetched, so that API downtimes do not affect the node.
+    pub(crate) async fn handle_registration_check(&mut self) {
+        let Some(url) = &self.config.registration_url else {
+            return;
+        };
+        let registered =
+            match fetch_registration(&self.http_client, url, &self.config.address).await {
+                Ok(registered) => registered,
+                Err(e) => {
+                    log::warn!("Could not check the registration: {:?}", e);
+                    return;
+                }
+            };
+
+        match (registered, self.observer) {
+            (false, false) => {
+                log::warn!(
+                    "Address 0x{} is not registered, running as an observer until it is.",
+                    self.config.address
+                );
+                log::warn!("An observer does not execute tasks, as they would not be rewarded.");
+                self.observer = true;
+            }
+            (true, true) if !self.config.observe => {
+                log::info!(
+                    "Address 0x{} is registered now, accepting tasks.",
+                    self.config.address
+                );
+                self.observer = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if the node is an observer, i.e. its address is not registered.
+    #[inline]
+    pub fn is_observer(&self) -> bool {
+        self.observer
+    }
+
+    /// Returns `true` if the node should exit for an update, i.e. it is opted in, outdated and has no pending tasks.
+    pub fn is_ready_to_update(&self) -> bool {
+        self.config.exit_for_update
+            && self.version_requirement.is_some()
+            && self.get_pending_task_count() == [0, 0]
+    }
+
+    /// Removes the expired tasks from the local archive in the background, if there is one.
+    pub(crate) fn handle_archive_retention(&self) {
+        if let Some(archive) = self.archive.clone() {
+            tokio::task::spawn_blocking(move || match archive.purge_expired() {
+                Ok(0) => {}
+                Ok(count) => log::info!("Removed {} expired tasks from the archive.", count),
+                Err(e) => log::error!("Error removing expired tasks from the archive: {:?}", e),
+            });
+        }
+    }
+
+    /// Retries refreshing the available nodes, returns `true` once they are fetched.
+    ///
+    /// Requests are only accepted from the fetched RPC nodes, so without this all tasks would be
+    /// rejected as unauthorized until the next periodic refresh.
+    pub(crate) async fn handle_available_nodes_prefetch(&mut self) -> bool {
+        log::info!("Retrying to fetch the available nodes.");
+        self.handle_available_nodes_refresh().await;
+        if !self.dria_nodes_fetched {
+            log::warn!("Available nodes are not fetched yet, retrying later.");
+        }
+
+        self.dria_nodes_fetched
     }
 
     /// Updates the local list of available nodes by refreshing it.
@@ -81,20 +649,93 @@ impl DriaComputeNode {
     pub(crate) async fn handle_available_nodes_refresh(&mut self) {
         log::info!("Refreshing available Dria nodes.");
 
-        // refresh available nodes
-        if let Err(e) = refresh_dria_nodes(&mut self.dria_nodes).await {
-            log::error!("Error refreshing available nodes: {:?}", e);
+        // refresh available nodes, where the RPCs of a resumed session are kept only if the API still lists them
+        let mut dria_nodes = self.dria_nodes.clone();
+        dria_nodes
+            .rpc_peerids
+            .retain(|peer_id| !self.resumed_rpc_peerids.contains(peer_id));
+        dria_nodes.rpc_nodes.retain(|addr| {
+            !addr.iter().any(|p| match p {
+                Protocol::P2p(peer_id) => self.resumed_rpc_peerids.contains(&peer_id),
+                _ => false,
+            })
+        });
+        match refresh_dria_nodes(&self.http_client, &mut dria_nodes).await {
+            Ok(()) => {
+                // the API may still list the RPCs removed by a nodes update since
+                self.removed_rpc_nodes.retain_removed(&mut dria_nodes);
+                self.dria_nodes = dria_nodes;
+                self.dria_nodes_fetched = true;
+
+                for peer_id in std::mem::take(&mut self.resumed_rpc_peerids) {
+                    if !self.dria_nodes.rpc_peerids.contains(&peer_id) {
+                        log::warn!(
+                            "RPC {} of the resumed session is not listed anymore, dropping it.",
+                            peer_id
+                        );
+                        if self.selected_rpc == Some(peer_id) {
+                            self.selected_rpc = None;
+                        }
+                    }
+                }
+            }
+            Err(e) => log::error!("Error refreshing available nodes: {:?}", e),
         };
 
-        // dial all rpc nodes
+        self.sync_keep_alive_peers().await;
+        self.unblock_rpc_nodes().await;
+        self.dial_rpc_nodes().await;
+
+        log::info!("Finished refreshing!");
+    }
+
+    /// Unblocks the peers that were blocked before, but have become RPCs since.
+    pub(crate) async fn unblock_rpc_nodes(&self) {
+        match self.p2p.blocked_peers().await {
+            Ok(blocked_peers) => {
+                for peer_id in blocked_peers {
+                    if self.dria_nodes.rpc_peerids.contains(&peer_id) {
+                        log::info!("Unblocking {} as it is an RPC now.", peer_id);
+                        if let Err(e) = self.p2p.unblock_peer(peer_id).await {
+                            log::error!("Could not unblock {}: {:?}", peer_id, e);
+                        }
+                    }
+                }
+            }
+            Err(e) => log::error!("Error getting blocked peers: {:?}", e),
+        }
+    }
+
+    /// Keeps the connections to the current RPC nodes alive, and stops doing so for the removed ones.
+    pub(crate) async fn sync_keep_alive_peers(&self) {
+        let peers = self.dria_nodes.rpc_peerids.clone();
+        if let Err(e) = self.p2p.set_keep_alive_peers(peers).await {
+            log::error!("Error updating the kept-alive peers: {:?}", e);
+        }
+    }
+
+    /// Dials the RPC nodes, except the healthy ones that we are connected to already.
+    ///
+    /// Only the addresses over the configured transport are dialled.
+    pub(crate) async fn dial_rpc_nodes(&mut self) {
+        let transport = self.config.p2p_transport;
         for addr in self.dria_nodes.rpc_nodes.iter() {
-            log::info!("Dialling RPC node: {}", addr);
+            if !transport.supports(addr) {
+                log::debug!("Skipping RPC node {} for {} transport.", addr, transport);
+                continue;
+            }
 
             // get peer id from rpc address
             if let Some(peer_id) = addr.iter().find_map(|p| match p {
                 Protocol::P2p(peer_id) => Some(peer_id),
                 _ => None,
             }) {
+                if self.rpc_latencies.contains_key(&peer_id) {
+                    log::debug!("RPC node {} is healthy, not dialling.", addr);
+                    continue;
+                }
+
+                log::info!("Dialling RPC node: {}", addr);
                 let fut = self.p2p.dial(peer_id, addr.clone());
                 match tokio::time::timeout(Duration::from_secs(10), fut).await {
                     Err(timeout) => {
@@ -113,7 +754,172 @@ impl DriaComputeNode {
                 log::warn!("Missing peerID in address: {}", addr);
             }
         }
+    }
 
-        log::info!("Finished refreshing!");
+    /// Measures the TPS of the local models again in the background if a week has passed since the last
+    /// measurement, see [`Self::handle_tps_refreshed`]. The models that are not served due to a low TPS
+    /// are measured as well, so that they can be served again.
+    ///
+    /// Running tasks would slow down the measurement, so it is skipped while there are pending single tasks,
+    /// and aborted if a single task arrives meanwhile; it is tried again at the next job then.
+    pub(crate) fn handle_tps_refresh(&mut self) {
+        if self.tps_measured_at.elapsed() < Duration::from_secs(TPS_REFRESH_INTERVAL_SECS)
+            || self.tps_refresh.as_ref().is_some_and(|h| !h.is_finished())
+        {
+            return;
+        }
+        if !self.pending_tasks_single.is_empty() {
+            log::info!("Node is busy, measuring the TPS of local models later.");
+            return;
+        }
+
+        let mut workflows = self.config.workflows.clone();
+        workflows
+            .models
+            .extend(self.unserved_models.iter().cloned());
+        let tps_refresh_tx = self.tps_refresh_tx.clone();
+        self.tps_refresh = Some(tokio::spawn(async move {
+            let slow_models = workflows.refresh_tps().await;
+            let _ = tps_refresh_tx
+                .send((workflows.measured_tps(), slow_models))
+                .await;
+        }));
+    }
+
+    /// Aborts the TPS measurement of the local models if it is running, as a task would slow it down.
+    pub(crate) fn abort_tps_refresh(&mut self) {
+        if let Some(refresh) = self.tps_refresh.take().filter(|h| !h.is_finished()) {
+            log::info!("Aborting the TPS measurement of local models for a task, measuring later.");
+            refresh.abort();
+        }
+    }
+
+    /// Reports the refreshed TPS within the specs from now on, stops serving the models that fell below
+    /// the minimum TPS, and serves the ones that are above it again.
+    ///
+    /// Returns an error if no models are left to serve.
+    pub(crate) fn handle_tps_refreshed(
+        &mut self,
+        tps: HashMap<String, f64>,
+        slow_models: Vec<Model>,
+    ) -> Result<()> {
+        self.tps_measured_at = Instant::now();
+        let model_names = |models: &[(ModelProvider, Model)]| {
+            models
+                .iter()
+                .map(|(_, m)| m.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let (still_slow, recovered): (Vec<_>, Vec<_>) = std::mem::take(&mut self.unserved_models)
+            .into_iter()
+            .partition(|(_, model)| slow_models.contains(model));
+        if !recovered.is_empty() {
+            log::info!(
+                "Models above the minimum TPS are served again: {}",
+                model_names(&recovered)
+            );
+        }
+        let newly_slow = self.config.workflows.remove_models(&slow_models);
+        if !newly_slow.is_empty() {
+            log::warn!(
+                "Models below the minimum TPS are not served until they are measured above it again: {}",
+                model_names(&newly_slow)
+            );
+        }
+        self.config.workflows.models.extend(recovered);
+        self.unserved_models = still_slow.into_iter().chain(newly_slow).collect();
+
+        self.config.workflows.update_measured_tps(tps.clone());
+        self.spec_collector.set_tps(tps);
+        self.spec_collector
+            .set_models(self.config.workflows.get_model_names());
+
+        if self.config.workflows.models.is_empty() {
+            return Err(eyre!(
+                "No models are left to serve, please check your machine & restart"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Compares the configured models to the rewarded models of the network, and warns about the differences.
+    ///
+    /// If auto-enable is opted in, missing rewarded models are checked & enabled. Only the models that
+    /// can be run by the existing workers are considered, e.g. an Ollama model is not enabled if there is
+    /// no single worker.
+    pub(crate) async fn handle_model_sync(&mut self) {
+        let Some(url) = &self.config.rewarded_models_url else {
+            return;
+        };
+        let rewarded = match fetch_rewarded_models(&self.http_client, url).await {
+            Ok(models) => models,
+            Err(e) => {
+                log::warn!("Could not fetch rewarded models: {:?}", e);
+                return;
+            }
+        };
+
+        // an empty list is more likely to be an API issue than nothing being rewarded
+        if rewarded.is_empty() {
+            return;
+        }
+
+        let (unrewarded, missing) =
+            diff_models(&self.config.workflows.get_model_names(), &rewarded);
+        if !unrewarded.is_empty() {
+            log::warn!(
+                "Models not rewarded by the network anymore: {}",
+                unrewarded.join(", ")
+            );
+        }
+        if missing.is_empty() {
+            return;
+        }
+        if !self.config.auto_enable_models {
+            log::warn!(
+                "Models rewarded by the network but not enabled by your node: {}",
+                missing.join(", ")
+            );
+            return;
+        }
+
+        let candidates = missing
+            .into_iter()
+            .filter_map(|model| Model::try_from(model).ok())
+            .map(|model| (ModelProvider::from(model.clone()), model))
+            .filter(|(provider, _)| match provider {
+                ModelProvider::Ollama => self.task_request_single_tx.is_some(),
+                _ => self.task_request_batch_tx.is_some(),
+            })
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            log::warn!("Rewarded models can not be enabled without a restart.");
+            return;
+        }
+
+        let mut workflows = self.config.workflows.clone();
+        workflows.models = candidates;
+        let check = workflows.check_services();
+        match tokio::time::timeout(Duration::from_secs(MODEL_SYNC_CHECK_TIMEOUT_SECS), check).await
+        {
+            Ok(Ok(())) => {
+                log::info!(
+                    "Enabled rewarded models: {}",
+                    workflows.get_model_names().join(", ")
+                );
+                self.config
+                    .workflows
+                    .update_measured_tps(workflows.measured_tps());
+                self.spec_collector.set_tps(workflows.measured_tps());
+                self.config.workflows.models.extend(workflows.models);
+                self.spec_collector
+                    .set_models(self.config.workflows.get_model_names());
+            }
+            Ok(Err(e)) => log::warn!("Could not enable rewarded models: {:?}", e),
+            Err(_) => log::warn!("Checks for rewarded models timed out."),
+        }
     }
 }
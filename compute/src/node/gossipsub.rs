@@ -1,5 +1,6 @@
 use dkn_p2p::libp2p::gossipsub::{Message, MessageAcceptance, MessageId};
 use dkn_p2p::libp2p::PeerId;
+use dkn_utils::payloads::DriaTopic;
 use eyre::Result;
 
 use crate::utils::DriaMessage;
@@ -56,8 +57,8 @@ impl DriaComputeNode {
         (propagation_peer_id, message_id, gossipsub_message): (PeerId, &MessageId, Message),
     ) -> MessageAcceptance {
         // handle message with respect to its topic
-        match gossipsub_message.topic.as_str() {
-            PingpongHandler::LISTEN_TOPIC => {
+        match gossipsub_message.topic.as_str().parse::<DriaTopic>() {
+            Ok(topic @ (DriaTopic::Ping | DriaTopic::NodesUpdate)) => {
                 // ensure that the message is from a valid source (origin)
                 let Some(source_peer_id) = gossipsub_message.source else {
                     log::warn!(
@@ -89,6 +90,12 @@ impl DriaComputeNode {
                             "Message: {}",
                             String::from_utf8_lossy(&gossipsub_message.data)
                         );
+                        self.dead_letters.push(
+                            format!("gossipsub/{}", gossipsub_message.topic),
+                            propagation_peer_id,
+                            e,
+                            &gossipsub_message.data,
+                        );
                         return MessageAcceptance::Ignore;
                     }
                 };
@@ -115,33 +122,46 @@ impl DriaComputeNode {
                     }
                 }
 
-                // handle the DKN message with respect to the topic
-                let handler_result = match gossipsub_message.topic.as_str() {
-                    PingpongHandler::LISTEN_TOPIC => {
-                        PingpongHandler::handle_ping(self, &message).await
+                // validate the message based on the result of the handler
+                let result = match topic {
+                    DriaTopic::NodesUpdate => {
+                        NodesUpdateHandler::handle_nodes_update(self, &message).await
                     }
-                    _ => unreachable!("unreachable due to match expression"),
+                    _ => PingpongHandler::handle_ping(self, &message).await,
                 };
-
-                // validate the message based on the result
-                handler_result.unwrap_or_else(|err| {
+                result.unwrap_or_else(|err| {
                     log::error!(
                         "Error handling {} message: {:?}",
                         gossipsub_message.topic,
                         err
                     );
+                    self.dead_letters.push(
+                        format!("gossipsub/{}", gossipsub_message.topic),
+                        propagation_peer_id,
+                        format!("{:#}", err),
+                        &gossipsub_message.data,
+                    );
                     MessageAcceptance::Ignore
                 })
             }
-            PingpongHandler::RESPONSE_TOPIC => {
+            Ok(DriaTopic::Pong) => {
                 // since we are responding to these topics, we might receive messages from other compute nodes
                 // we can gracefully ignore them and propagate it to to others
                 log::trace!("Ignoring {} message", gossipsub_message.topic);
                 MessageAcceptance::Accept
             }
-            other => {
-                // reject this message as its from a foreign topic
-                log::warn!("Received message from unexpected topic: {}", other);
+            Ok(DriaTopic::Goodbye | DriaTopic::Eval) | Err(_) => {
+                // reject this message as its from a foreign topic, request-response topics are not gossiped either
+                log::warn!(
+                    "Received message from unexpected topic: {}",
+                    gossipsub_message.topic
+                );
+                self.dead_letters.push(
+                    format!("gossipsub/{}", gossipsub_message.topic),
+                    propagation_peer_id,
+                    "unexpected topic",
+                    &gossipsub_message.data,
+                );
                 MessageAcceptance::Reject
             }
         }
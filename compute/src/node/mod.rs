@@ -1,34 +1,70 @@
 use dkn_p2p::{
     libp2p::{
         gossipsub::{Message, MessageId},
-        request_response::ResponseChannel,
         PeerId,
     },
-    DriaNodes, DriaP2PClient, DriaP2PCommander, DriaP2PProtocol,
+    ConnectionPolicy, DriaNodes, DriaP2PClient, DriaP2PCommander, DriaP2PConfig, DriaP2PProtocol,
+    RequestChannel,
 };
-use eyre::Result;
-use std::collections::HashMap;
-use tokio::{sync::mpsc, time::Instant};
+use dkn_utils::{get_current_time_nanos, storage::SledStorage, SemanticVersion};
+use dkn_workflows::{
+    DriaWorkflowsConfig, ExecutorFactory, Judge, Model, ModelProvider, WorkflowExecutor,
+};
+use eyre::{Context, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle, time::Instant};
 
 use crate::{
+    admin::AdminMessage,
     config::*,
-    gossipsub::*,
-    utils::{crypto::secret_to_keypair, refresh_dria_nodes, SpecCollector},
-    workers::task::{TaskWorker, TaskWorkerInput, TaskWorkerMetadata, TaskWorkerOutput},
+    reqres::{EvalMetadata, SentSpecs, TaskResponder},
+    utils::{
+        crypto::secret_to_keypair, refresh_dria_nodes, DeadLetterQueue, PeerRateLimiter,
+        RateLimiter, RelayStats, RemovedRpcNodes, RpcReputations, RpcSession, SpecCollector,
+        StatsDb, Supervisor, TaskArchive,
+    },
+    workers::{
+        publish::{publish_channel, PublishReceiver},
+        task::{TaskWorker, TaskWorkerInput, TaskWorkerMetadata},
+    },
 };
 
+mod admin;
 mod core;
 mod diagnostic;
 mod gossipsub;
 mod reqres;
 
-/// Buffer size for message publishes.
-const PUBLISH_CHANNEL_BUFSIZE: usize = 1024;
+/// Buffer size for admin commands.
+const ADMIN_CHANNEL_BUFSIZE: usize = 32;
+/// Buffer size for the RPCs whose keep-alives have failed.
+const KEEPALIVE_CHANNEL_BUFSIZE: usize = 16;
+/// Number of unprocessable messages to keep for diagnostics.
+const DEAD_LETTER_CAPACITY: usize = 100;
+/// Zstd level for the archived tasks, JSON dumps shrink several times even at low levels.
+const TASK_ARCHIVE_COMPRESSION_LEVEL: i32 = 3;
+
+/// Result of a TPS refresh, the measured TPS by model name & the models below the minimum TPS.
+type TpsRefresh = (HashMap<String, f64>, Vec<Model>);
 
 pub struct DriaComputeNode {
     pub config: DriaComputeNodeConfig,
     /// Pre-defined nodes that belong to Dria, e.g. bootstraps, relays and RPCs.
     pub dria_nodes: DriaNodes,
+    /// Whether the available nodes were fetched from the API at least once.
+    dria_nodes_fetched: bool,
+    /// Nodes that are given statically or by the environment, which the nodes updates do not remove.
+    pub(crate) static_dria_nodes: DriaNodes,
+    /// RPC nodes removed by the last nodes update, which the refreshes do not add back.
+    pub(crate) removed_rpc_nodes: RemovedRpcNodes,
+    /// RPC nodes taken from a resumed session, which are dropped by the first refresh unless the API still lists them.
+    resumed_rpc_peerids: HashSet<PeerId>,
+    /// Deadline of the last nodes update that replaced the RPC nodes, older replacing updates are ignored.
+    pub(crate) nodes_update_deadline: u128,
     /// Peer-to-peer client commander to interact with the network.
     pub p2p: DriaP2PCommander,
     /// The last time the node was pinged by the network.
@@ -39,9 +75,9 @@ pub struct DriaComputeNode {
     /// It will publish messages sent to this channel to the network.
     gossip_message_rx: mpsc::Receiver<(PeerId, MessageId, Message)>,
     /// Request-response request receiver.
-    request_rx: mpsc::Receiver<(PeerId, Vec<u8>, ResponseChannel<Vec<u8>>)>,
+    request_rx: mpsc::Receiver<(PeerId, Vec<u8>, RequestChannel)>,
     /// Task response receiver, will respond to the request-response channel with the given result.
-    task_output_rx: mpsc::Receiver<TaskWorkerOutput>,
+    task_output_rx: PublishReceiver,
     /// Task worker transmitter to send batchable tasks.
     task_request_batch_tx: Option<mpsc::Sender<TaskWorkerInput>>,
     /// Task worker transmitter to send single tasks.
@@ -50,12 +86,86 @@ pub struct DriaComputeNode {
     pending_tasks_single: HashMap<String, TaskWorkerMetadata>,
     // Batchable tasks
     pending_tasks_batch: HashMap<String, TaskWorkerMetadata>,
+    /// Evaluation tasks, waiting for the outputs of both models.
+    pub(crate) pending_evals: HashMap<String, EvalMetadata>,
     /// Completed single tasks count
     completed_tasks_single: usize,
     /// Completed batch tasks count
     completed_tasks_batch: usize,
+    /// Recent average execution time per model, in milliseconds.
+    model_latencies: HashMap<String, f64>,
+    /// The last time a task was completed, or a task was received while there were none pending.
+    /// Used to detect stalled workers.
+    last_task_progress_at: Instant,
+    /// Number of tasks waiting for a free worker slot at the last diagnostics, to detect a growing queue.
+    last_queue_depth: usize,
+    /// Whether there were anomalies at the last diagnostics, which are then refreshed more often.
+    diagnostic_anomalies: bool,
+    /// Number of single & batchable tasks that could not be sent to the workers.
+    dropped_tasks: [u64; 2],
+    /// Total number of items dropped by the channels at the last diagnostics, to detect new drops.
+    last_channel_drops: u64,
     /// Specifications collector.
     spec_collector: SpecCollector,
+    /// The last specs sent to each RPC, which the delta responses are based on.
+    sent_specs: HashMap<PeerId, SentSpecs>,
+    /// Nanoseconds that the clock is shifted back by, so that it follows the replayed session; zero otherwise.
+    clock_offset_nanos: u128,
+    /// Admin command sender, cloned for the admin server.
+    admin_tx: mpsc::Sender<AdminMessage>,
+    /// Admin command receiver.
+    admin_rx: mpsc::Receiver<AdminMessage>,
+    /// Sender of the TPS refreshes that run in the background, cloned for each refresh.
+    tps_refresh_tx: mpsc::Sender<TpsRefresh>,
+    /// Receiver of the measured TPS & the models below the minimum TPS, for each refresh.
+    tps_refresh_rx: mpsc::Receiver<TpsRefresh>,
+    /// The TPS refresh running in the background, if any.
+    tps_refresh: Option<JoinHandle<()>>,
+    /// Sender of the RPCs whose keep-alives have failed, cloned for each keep-alive.
+    keepalive_failed_tx: mpsc::Sender<PeerId>,
+    /// Receiver of the RPCs whose keep-alives have failed, which are then marked as unhealthy.
+    keepalive_failed_rx: mpsc::Receiver<PeerId>,
+    /// Time of the latest TPS measurement, either during the checks or a refresh.
+    tps_measured_at: Instant,
+    /// Local models that are not served as they fell below the minimum TPS, measured again with each refresh.
+    unserved_models: Vec<(ModelProvider, Model)>,
+    /// Whether the node is paused, i.e. not accepting tasks.
+    paused: bool,
+    /// Whether the pause is due to an update that the node is to exit for, rather than the admin channel.
+    update_paused: bool,
+    /// Whether the node is an observer, i.e. not accepting tasks, either due to `--observe`
+    /// or its address not being registered.
+    observer: bool,
+    /// Supervisor of the node components, used to report restarts.
+    supervisor: Option<Supervisor>,
+    /// Local archive of completed tasks, if enabled.
+    pub(crate) archive: Option<TaskArchive>,
+    /// Local database of task statistics, if enabled.
+    pub(crate) stats_db: Option<StatsDb>,
+    /// Judge model that scores the results, if enabled.
+    pub(crate) judge: Option<Arc<Judge>>,
+    /// Creates the executors of the tasks.
+    pub(crate) executor_factory: ExecutorFactory,
+    /// Minimum version required by the network along with its deadline, if the node is below it.
+    version_requirement: Option<(SemanticVersion, Option<u128>)>,
+    /// Latest round-trip times to the connected RPC nodes.
+    rpc_latencies: HashMap<PeerId, Duration>,
+    /// The RPC node with the lowest latency, if any RPC is reachable.
+    selected_rpc: Option<PeerId>,
+    /// Local reputations of the RPC nodes, weighing their latencies within the selection.
+    rpc_reputations: RpcReputations,
+    /// Recent messages & requests that could not be processed.
+    dead_letters: DeadLetterQueue,
+    /// Limiter of the task intake, if the tasks per minute are limited.
+    task_rate_limiter: Option<RateLimiter>,
+    /// Limiter of the requests of each peer, if their requests per minute are limited.
+    request_rate_limiter: Option<PeerRateLimiter>,
+    /// Number of unauthorized requests received from each peer that is not blocked yet, along with the time of the last one.
+    unauthorized_requests: HashMap<PeerId, (u32, Instant)>,
+    /// Messages validated for the gossip mesh.
+    relay_stats: RelayStats,
+    /// HTTP client for the API calls, with connection pools w.r.t the profile.
+    http_client: reqwest::Client,
 }
 
 impl DriaComputeNode {
@@ -77,48 +187,183 @@ impl DriaComputeNode {
         let mut dria_nodes = DriaNodes::new(config.network_type)
             .with_statics()
             .with_envs();
-        if let Err(e) = refresh_dria_nodes(&mut dria_nodes).await {
-            log::error!("Error populating available nodes: {:?}", e);
+        let static_dria_nodes = dria_nodes.clone();
+        let http_client = config.profile.http_client();
+
+        // a session saved by a recent restart is resumed, without fetching the available nodes again
+        let session = config.session_file.as_ref().and_then(|path| {
+            RpcSession::load(path, Duration::from_secs(config.session_grace_secs))
+                .inspect_err(|e| log::warn!("Could not resume the previous session: {:?}", e))
+                .ok()
+                .flatten()
+        });
+        let mut resumed_rpc_peerids = HashSet::new();
+        let dria_nodes_fetched = match &session {
+            Some(session) => {
+                resumed_rpc_peerids = session
+                    .rpc_peerids()
+                    .into_iter()
+                    .filter(|peer_id| !dria_nodes.rpc_peerids.contains(peer_id))
+                    .collect();
+                dria_nodes.rpc_nodes.extend(session.rpc_nodes());
+                dria_nodes.rpc_peerids.extend(session.rpc_peerids());
+                log::info!(
+                    "Resuming the previous session with {} RPC nodes.",
+                    dria_nodes.rpc_peerids.len()
+                );
+                true
+            }
+            None => match refresh_dria_nodes(&http_client, &mut dria_nodes).await {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("Error populating available nodes: {:?}", e);
+                    false
+                }
+            },
         };
 
         // we are using the major.minor version as the P2P version
         // so that patch versions do not interfere with the protocol
-        let protocol = DriaP2PProtocol::new_major_minor(config.network_type.protocol_name());
+        let protocol = DriaP2PProtocol::new_major_minor(config.network_type.protocol_name())
+            .with_max_request_size(config.p2p_max_request_mb * 1024 * 1024)
+            .with_max_response_size(config.p2p_max_response_mb * 1024 * 1024);
         log::info!("Using identity: {}", protocol);
 
+        // RPCs are kept connected, while bootstraps are only needed for a while
+        let policy = ConnectionPolicy::for_nodes(&dria_nodes)
+            .with_idle_timeout(Duration::from_secs(config.p2p_idle_timeout_secs))
+            .with_short_lived_timeout(Duration::from_secs(config.p2p_bootstrap_connection_secs))
+            .with_max_redial_attempts(config.p2p_max_redials);
+
         // create p2p client
-        let (p2p_client, p2p_commander, message_rx, request_rx) = DriaP2PClient::new(
-            keypair,
-            config.p2p_listen_addr.clone(),
-            &dria_nodes,
-            protocol,
-        )?;
+        let mut p2p_config = DriaP2PConfig::new(config.p2p_listen_addrs.clone())
+            .with_policy(policy)
+            .with_resources(config.p2p_resources.clone())
+            .with_transport(config.p2p_transport)
+            .with_limits(config.p2p_limits);
+        if let Some(thresholds) = &config.p2p_score_thresholds {
+            p2p_config = p2p_config.with_score_thresholds(thresholds.clone());
+        }
+        let (p2p_client, p2p_commander, message_rx, request_rx) =
+            DriaP2PClient::new(keypair, &dria_nodes, protocol, p2p_config)?;
+        let p2p_client = match config.p2p_mdns {
+            true => p2p_client.with_mdns()?,
+            false => p2p_client,
+        };
+        #[cfg(feature = "recorder")]
+        let (p2p_client, clock_offset_nanos) = Self::with_session_recording(p2p_client)?;
+        #[cfg(not(feature = "recorder"))]
+        let clock_offset_nanos = 0;
 
         // create workflow workers, all workers use the same publish channel
-        let (publish_tx, publish_rx) = mpsc::channel(PUBLISH_CHANNEL_BUFSIZE);
+        let channel_bufsize = config.channel_bufsize;
+        let spill_dir = config.publish_spill_dir.clone().unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("dkn-publish-{}", config.peer_id))
+        });
+        let (publish_tx, publish_rx) =
+            publish_channel(channel_bufsize, config.publish_overflow, spill_dir)?;
+        let publish_rx = publish_rx.with_quotas(config.publish_quotas);
+
+        // stalled executions are aborted by the workers only if configured so
+        let new_worker = |publish_tx| {
+            let (worker, sender) = TaskWorker::new(publish_tx, channel_bufsize);
+            if config.abort_stalled_tasks {
+                let timeout = Duration::from_secs(config.task_stall_secs);
+                (Some(worker.with_execution_timeout(timeout)), Some(sender))
+            } else {
+                (Some(worker), Some(sender))
+            }
+        };
 
         // check if we should create a worker for batchable workflows
         let (task_batch_worker, task_batch_tx) = if config.workflows.has_batchable_models() {
-            let (worker, sender) = TaskWorker::new(publish_tx.clone());
-            (Some(worker), Some(sender))
+            new_worker(publish_tx.clone())
         } else {
             (None, None)
         };
 
         // check if we should create a worker for single workflows
         let (task_single_worker, task_single_tx) = if config.workflows.has_non_batchable_models() {
-            let (worker, sender) = TaskWorker::new(publish_tx);
-            (Some(worker), Some(sender))
+            new_worker(publish_tx)
         } else {
             (None, None)
         };
 
-        let model_names = config.workflows.get_model_names();
+        let (admin_tx, admin_rx) = mpsc::channel(ADMIN_CHANNEL_BUFSIZE);
+        let (tps_refresh_tx, tps_refresh_rx) = mpsc::channel(1);
+        let (keepalive_failed_tx, keepalive_failed_rx) = mpsc::channel(KEEPALIVE_CHANNEL_BUFSIZE);
+
+        // a storage directory is opened only once, so that the archive & reputations can share it
+        let archive_storage = match config.task_archive_dir {
+            Some(ref dir) => Some(Arc::new(SledStorage::open(dir)?)),
+            None => None,
+        };
+
+        // create the task archive if enabled
+        let archive = match (&config.task_archive_dir, &archive_storage) {
+            (Some(dir), Some(storage)) => {
+                let mut archive =
+                    TaskArchive::new(storage.clone(), config.task_archive_max_mb * 1024 * 1024)
+                        .with_compression(TASK_ARCHIVE_COMPRESSION_LEVEL);
+                if let Some(hours) = config.task_archive_retention_hours {
+                    archive = archive.with_retention(Duration::from_secs(hours * 60 * 60));
+                }
+                log::info!("Archiving tasks at {}", dir.display());
+                Some(archive)
+            }
+            _ => None,
+        };
+
+        // open the task statistics database if enabled
+        let stats_db = match config.stats_db {
+            Some(ref path) => {
+                log::info!("Recording task statistics at {}", path.display());
+                Some(StatsDb::open(path, config.stats_db_max_rows)?)
+            }
+            None => None,
+        };
+
+        // load the reputations of the RPCs, if they are persisted
+        let rpc_reputations = match config.reputation_dir {
+            Some(ref dir) => {
+                let storage = match archive_storage {
+                    Some(storage) if config.task_archive_dir.as_ref() == Some(dir) => storage,
+                    _ => Arc::new(SledStorage::open(dir)?),
+                };
+                log::info!("Persisting RPC reputations at {}", dir.display());
+                RpcReputations::default().with_storage(storage)?
+            }
+            None => RpcReputations::default(),
+        };
+
+        // create the judge if enabled, its model must be served by the node
+        let executor_factory = WorkflowExecutor::factory();
+        let judge = Self::new_judge(&config.judge_model, &config.workflows, &executor_factory)?;
+        if let Some(ref judge) = judge {
+            log::info!("Judging results with {}", judge.model());
+        }
+
+        let observer = config.observe;
+        let mut spec_collector = SpecCollector::new(config.workflows.get_model_names())
+            .with_labels(config.labels.clone(), config.note.clone());
+        spec_collector.set_tps(config.workflows.measured_tps());
+        let task_rate_limiter = config
+            .max_tasks_per_minute
+            .map(|max| RateLimiter::new(max, Duration::from_secs(60)));
+        let request_rate_limiter = config
+            .max_requests_per_peer
+            .map(|max| PeerRateLimiter::new(max, Duration::from_secs(60)));
+
         Ok((
             DriaComputeNode {
                 config,
                 p2p: p2p_commander,
                 dria_nodes,
+                dria_nodes_fetched,
+                static_dria_nodes,
+                removed_rpc_nodes: RemovedRpcNodes::default(),
+                resumed_rpc_peerids,
+                nodes_update_deadline: 0,
                 // receivers
                 task_output_rx: publish_rx,
                 gossip_message_rx: message_rx,
@@ -129,15 +374,350 @@ impl DriaComputeNode {
                 // task trackers
                 pending_tasks_single: HashMap::new(),
                 pending_tasks_batch: HashMap::new(),
+                pending_evals: HashMap::new(),
                 completed_tasks_single: 0,
                 completed_tasks_batch: 0,
+                model_latencies: HashMap::new(),
+                last_task_progress_at: Instant::now(),
+                last_queue_depth: 0,
+                diagnostic_anomalies: false,
+                dropped_tasks: [0, 0],
+                last_channel_drops: 0,
                 // others
-                spec_collector: SpecCollector::new(model_names),
+                spec_collector,
+                sent_specs: session
+                    .as_ref()
+                    .map(RpcSession::sent_specs)
+                    .unwrap_or_default(),
                 last_pinged_at: Instant::now(),
+                clock_offset_nanos,
+                admin_tx,
+                admin_rx,
+                tps_refresh_tx,
+                tps_refresh_rx,
+                tps_refresh: None,
+                keepalive_failed_tx,
+                keepalive_failed_rx,
+                tps_measured_at: Instant::now(),
+                unserved_models: Vec::new(),
+                paused: false,
+                update_paused: false,
+                observer,
+                supervisor: None,
+                archive,
+                stats_db,
+                judge,
+                executor_factory,
+                version_requirement: None,
+                rpc_latencies: HashMap::new(),
+                selected_rpc: session.as_ref().and_then(RpcSession::selected_rpc),
+                rpc_reputations,
+                dead_letters: DeadLetterQueue::new(DEAD_LETTER_CAPACITY),
+                task_rate_limiter,
+                request_rate_limiter,
+                unauthorized_requests: HashMap::new(),
+                relay_stats: RelayStats::default(),
+                http_client,
             },
             p2p_client,
             task_batch_worker,
             task_single_worker,
         ))
     }
+
+    /// Records the inbound events to `DKN_P2P_RECORD`, and replays the messages & requests
+    /// recorded within `DKN_P2P_REPLAY`, if given.
+    ///
+    /// Replayed requests are responded to locally instead of the network, and the requests that are
+    /// responded to differently than within the recorded session are logged.
+    ///
+    /// Returns the offset of the replay clock as well, see [`dkn_p2p::recorder::replay_clock_offset`].
+    #[cfg(feature = "recorder")]
+    fn with_session_recording(mut p2p_client: DriaP2PClient) -> Result<(DriaP2PClient, u128)> {
+        use dkn_p2p::recorder::{read_session, replay_clock_offset, replay_session};
+        use dkn_utils::safe_read_env;
+
+        if let Some(path) = safe_read_env(std::env::var("DKN_P2P_RECORD")) {
+            log::warn!("Recording the peer-to-peer session to {}", path);
+            p2p_client = p2p_client.with_recorder(path)?;
+        }
+
+        let mut clock_offset_nanos = 0;
+        if let Some(path) = safe_read_env(std::env::var("DKN_P2P_REPLAY")) {
+            let events = read_session(&path)?;
+            log::warn!("Replaying {} events from {}", events.len(), path);
+            clock_offset_nanos = replay_clock_offset(&events);
+            let (msg_tx, req_tx) = p2p_client.replay_senders();
+            tokio::spawn(async move {
+                let replayed = match replay_session(events, msg_tx, req_tx, true).await {
+                    Ok(replayed) => replayed,
+                    Err(e) => return log::error!("Could not replay the session: {:?}", e),
+                };
+                log::info!("Replayed the session with {} requests.", replayed.len());
+                for request in replayed {
+                    let responded = request.response.await.is_ok();
+                    if responded != request.recorded.is_some() {
+                        log::warn!(
+                            "Request {} of {} is {} within the replay, unlike the recorded session.",
+                            request.index,
+                            request.peer_id,
+                            if responded { "responded" } else { "dropped" }
+                        );
+                    }
+                }
+            });
+        }
+
+        Ok((p2p_client, clock_offset_nanos))
+    }
+
+    /// Returns the current time in nanoseconds, w.r.t the clock of the replayed session if any.
+    ///
+    /// The deadlines of the messages & tasks are checked against this, so that replayed ones are not past.
+    #[inline]
+    pub fn now_nanos(&self) -> u128 {
+        get_current_time_nanos().saturating_sub(self.clock_offset_nanos)
+    }
+
+    /// Creates the judge with the given model, which must be served by the node.
+    fn new_judge(
+        judge_model: &Option<String>,
+        workflows: &DriaWorkflowsConfig,
+        executor_factory: &ExecutorFactory,
+    ) -> Result<Option<Arc<Judge>>> {
+        let Some(judge_model) = judge_model else {
+            return Ok(None);
+        };
+
+        let (model_provider, model) = workflows
+            .get_matching_model(judge_model.clone())
+            .wrap_err("judge model must be one of the configured models")?;
+        let model_name = model.to_string();
+        let (executor, _) =
+            TaskResponder::new_executor(executor_factory, workflows, model_provider, model, None);
+        Ok(Some(Arc::new(Judge::new(model_name, executor))))
+    }
+
+    /// Sets the factory that creates the executors of the tasks & the judge, instead of the built-in providers.
+    pub fn with_executor_factory(mut self, executor_factory: ExecutorFactory) -> Result<Self> {
+        self.judge = Self::new_judge(
+            &self.config.judge_model,
+            &self.config.workflows,
+            &executor_factory,
+        )?;
+        self.executor_factory = executor_factory;
+        Ok(self)
+    }
+
+    /// Sets the supervisor of the node components, so that restarts are reported in diagnostics.
+    pub fn with_supervisor(mut self, supervisor: Supervisor) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use dkn_p2p::{
+        recorder::{read_session, replay_session, SessionRecorder},
+        DriaNetworkType,
+    };
+    use dkn_workflows::{
+        Entry, ExecutionError, ExecutorCapabilities, ProgramMemory, TaskExecutor, Workflow,
+    };
+    use libsecp256k1::{PublicKey, SecretKey};
+    use tokio_util::sync::CancellationToken;
+
+    use crate::payloads::{TaskResponsePayload, TaskStats};
+    use crate::utils::DriaMessage;
+
+    /// Executor that answers every task with its prompt, instead of calling a provider.
+    struct EchoExecutor;
+
+    #[async_trait]
+    impl TaskExecutor for EchoExecutor {
+        async fn execute(
+            &self,
+            entry: Option<&Entry>,
+            _: &Workflow,
+            _: &mut ProgramMemory,
+        ) -> Result<String, ExecutionError> {
+            match entry {
+                Some(Entry::String(prompt)) => Ok(format!("echo: {}", prompt)),
+                _ => Err(ExecutionError::InvalidInput),
+            }
+        }
+
+        async fn check(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn capabilities(&self) -> ExecutorCapabilities {
+            ExecutorCapabilities {
+                models: vec!["gpt-4o-mini".to_string()],
+                batchable: true,
+            }
+        }
+    }
+
+    /// Creates a signed task request with the given prompt, whose result is encrypted for `public_key`.
+    fn task_request(
+        task_id: &str,
+        prompt: &str,
+        public_key: &PublicKey,
+        protocol: &DriaP2PProtocol,
+    ) -> Vec<u8> {
+        let task = serde_json::json!({
+            "taskId": task_id,
+            "deadline": get_current_time_nanos() + Duration::from_secs(60).as_nanos(),
+            "input": {
+                "workflow": {
+                    "config": { "max_steps": 10, "max_time": 250, "tools": [""] },
+                    "tasks": [
+                        {
+                            "id": "A",
+                            "name": "",
+                            "description": "",
+                            "operator": "generation",
+                            "messages": [{ "role": "user", "content": "{{prompt}}" }],
+                            "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+                        },
+                        {
+                            "id": "__end",
+                            "name": "end",
+                            "description": "End of the task",
+                            "operator": "end",
+                            "messages": [{ "role": "user", "content": "End of the task" }]
+                        }
+                    ],
+                    "steps": [{ "source": "A", "target": "__end" }],
+                    "return_value": { "input": { "type": "read", "key": "result" } }
+                },
+                "model": ["gpt-4o-mini"],
+                "prompt": prompt
+            },
+            "filter": { "hex": "00", "hashes": 1 },
+            "publicKey": hex::encode(public_key.serialize_compressed()),
+        });
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        DriaMessage::new(task.to_string(), "task", protocol, &secret_key)
+            .to_bytes()
+            .unwrap()
+    }
+
+    /// Returns the task id & the decrypted result of a task response.
+    fn task_result(response: &[u8], secret_key: &SecretKey) -> (String, String) {
+        let payload = serde_json::from_slice::<DriaMessage>(response)
+            .unwrap()
+            .parse_payload::<TaskResponsePayload>()
+            .unwrap();
+        let ciphertext = hex::decode(payload.ciphertext).unwrap();
+        let result = ecies::decrypt(&secret_key.serialize(), &ciphertext).unwrap();
+        (payload.task_id, String::from_utf8(result).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_session() {
+        let (rpc, stranger) = (PeerId::random(), PeerId::random());
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let public_key = PublicKey::from_secret_key(&secret_key);
+        let protocol = DriaP2PProtocol::new_major_minor(DriaNetworkType::Test.protocol_name());
+
+        // the RPC is given by a resumed session, so that the available nodes are not fetched
+        let id = get_current_time_nanos();
+        let session_file = std::env::temp_dir().join(format!("dkn-test-replay-{}.json", id));
+        let mut nodes = DriaNodes::new(DriaNetworkType::Test);
+        nodes.rpc_peerids.insert(rpc);
+        RpcSession::new(&nodes, None, &HashMap::new())
+            .save(&session_file)
+            .unwrap();
+
+        // a session where the RPC sends two tasks, and a task from an unknown peer is dropped in between
+        let path = std::env::temp_dir().join(format!("dkn-test-replay-{}.jsonl", id));
+        let mut recorder = SessionRecorder::new(&path).unwrap();
+        for (index, (peer_id, task_id, prompt)) in [
+            (rpc, "task-1", "hi"),
+            (stranger, "task-2", "who"),
+            (rpc, "task-3", "there"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let request = task_request(task_id, prompt, &public_key, &protocol);
+            recorder
+                .record_request(index as u64, &peer_id, &request)
+                .unwrap();
+        }
+        for (index, task_id, result) in [(0, "task-1", "echo: hi"), (2, "task-3", "echo: there")] {
+            let payload = TaskResponsePayload::new(
+                result,
+                task_id,
+                &public_key,
+                "gpt-4o-mini".to_string(),
+                TaskStats::new(),
+            )
+            .unwrap();
+            let response = DriaMessage::new(
+                serde_json::json!(payload).to_string(),
+                "response",
+                &protocol,
+                &secret_key,
+            );
+            recorder
+                .record_respond(index, &rpc, &response.to_bytes().unwrap())
+                .unwrap();
+        }
+        drop(recorder);
+
+        // the node runs with a stub executor, and without any jobs that reach out to the API
+        let config = DriaComputeNodeConfig {
+            network_type: DriaNetworkType::Test,
+            workflows: DriaWorkflowsConfig::new_from_csv("gpt-4o-mini"),
+            p2p_listen_addrs: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+            session_file: Some(session_file),
+            rewarded_models_url: None,
+            registration_url: None,
+            ..Default::default()
+        };
+        let (node, p2p_client, batch_worker, _) = DriaComputeNode::new(config).await.unwrap();
+        let mut node = node
+            .with_executor_factory(Arc::new(|_, _, _| Box::new(EchoExecutor)))
+            .unwrap();
+        node.resumed_rpc_peerids.clear();
+
+        let (msg_tx, req_tx) = p2p_client.replay_senders();
+        let mut batch_worker = batch_worker.unwrap();
+        let cancellation = CancellationToken::new();
+        let handles = [
+            tokio::spawn(p2p_client.run()),
+            tokio::spawn(async move { batch_worker.run_batch(4).await }),
+            tokio::spawn({
+                let cancellation = cancellation.clone();
+                async move { node.run(cancellation).await.unwrap() }
+            }),
+        ];
+
+        // the responses of the replay must match the recorded ones, while the result is re-encrypted
+        let events = read_session(&path).unwrap();
+        let replayed = replay_session(events, msg_tx, req_tx, false).await.unwrap();
+        assert_eq!(replayed.len(), 3);
+        for request in replayed {
+            let response = tokio::time::timeout(Duration::from_secs(10), request.response)
+                .await
+                .expect("replay timed out");
+            match request.recorded {
+                Some(recorded) => assert_eq!(
+                    task_result(&response.unwrap(), &secret_key),
+                    task_result(&recorded, &secret_key)
+                ),
+                None => assert!(response.is_err()),
+            }
+        }
+
+        cancellation.cancel();
+        handles.iter().for_each(|handle| handle.abort());
+        std::fs::remove_file(path).unwrap();
+    }
 }
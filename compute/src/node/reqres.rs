@@ -1,33 +1,124 @@
-use dkn_p2p::libp2p::{request_response::ResponseChannel, PeerId};
+use dkn_p2p::{libp2p::PeerId, PendingResponse, RequestChannel};
+use dkn_utils::{
+    get_current_time_nanos,
+    payloads::{DriaTopic, GoodbyePayload, GoodbyeTask, TaskStats},
+};
 use eyre::{eyre, Result};
+use std::time::Duration;
+use tokio::time::Instant;
 
-use crate::{reqres::*, workers::task::TaskWorkerOutput};
+use crate::{
+    payloads::TaskRequestPayload,
+    reqres::*,
+    utils::{select_rpc, DriaMessage, RpcSession},
+    workers::task::{TaskError, TaskWorkerInput, TaskWorkerMetadata, TaskWorkerOutput},
+};
 
 use super::DriaComputeNode;
 
 impl DriaComputeNode {
+    /// Counts an unauthorized request of a peer, and blocks the peer at the swarm level
+    /// once it has sent too many of them.
+    async fn count_unauthorized_request(&mut self, peer_id: PeerId) {
+        let max_requests = self.config.max_unauthorized_requests;
+        if max_requests == 0 {
+            return;
+        }
+
+        // forget the peers that have been quiet for a while, and the quietest ones if there are still too many
+        let now = Instant::now();
+        if !self.unauthorized_requests.contains_key(&peer_id) {
+            self.unauthorized_requests
+                .retain(|_, (_, last)| now.duration_since(*last) < UNAUTHORIZED_REQUESTS_TTL);
+            if self.unauthorized_requests.len() >= MAX_UNAUTHORIZED_PEERS {
+                if let Some(quietest) = self
+                    .unauthorized_requests
+                    .iter()
+                    .min_by_key(|(_, (_, last))| *last)
+                    .map(|(peer_id, _)| *peer_id)
+                {
+                    self.unauthorized_requests.remove(&quietest);
+                }
+            }
+        }
+
+        let (count, last) = self
+            .unauthorized_requests
+            .entry(peer_id)
+            .or_insert((0, now));
+        *count += 1;
+        *last = now;
+        if *count < max_requests {
+            return;
+        }
+
+        log::warn!(
+            "Blocking {} after {} unauthorized requests.",
+            peer_id,
+            max_requests
+        );
+        match self.p2p.block_peer(peer_id).await {
+            Ok(()) => {
+                self.unauthorized_requests.remove(&peer_id);
+            }
+            Err(e) => log::error!("Could not block {}: {:?}", peer_id, e),
+        }
+    }
+
     /// Handles a request-response request received from the network.
     ///
     /// Internally, the data is expected to be some JSON serialized data that is expected to be parsed and handled.
     pub(crate) async fn handle_request(
         &mut self,
-        (peer_id, data, channel): (PeerId, Vec<u8>, ResponseChannel<Vec<u8>>),
+        (peer_id, data, channel): (PeerId, Vec<u8>, RequestChannel),
     ) -> Result<()> {
-        // ensure that message is from the known RPCs
+        // ensure that message is from the known RPCs, others are dropped without a response
         if !self.dria_nodes.rpc_peerids.contains(&peer_id) {
             log::warn!("Received request from unauthorized source: {}", peer_id);
             log::debug!("Allowed sources: {:#?}", self.dria_nodes.rpc_peerids);
+            // the RPCs may not be known yet, so their requests do not count until then
+            if self.dria_nodes_fetched {
+                self.count_unauthorized_request(peer_id).await;
+            }
             return Err(eyre!("Received unauthorized request from {}", peer_id));
         }
 
+        // throttle the RPCs that flood requests, with a budget that is generous enough for the tasks of many users
+        if let Some(Err((throttled, retry_after))) = self
+            .request_rate_limiter
+            .as_mut()
+            .map(|limiter| limiter.try_acquire(peer_id, Instant::now()))
+        {
+            if throttled == 1 {
+                log::warn!("Throttling the requests of {}.", peer_id);
+            } else {
+                log::debug!("Throttled {} requests of {} in a row.", throttled, peer_id);
+            }
+
+            // tasks are told when to retry, other requests are dropped
+            return match TaskResponder::try_parse_request(&data) {
+                Ok(message) => {
+                    let error = TaskError::RateLimited { retry_after };
+                    TaskResponder::respond_rejected(self, peer_id, &message, error, channel).await
+                }
+                Err(_) => Ok(()),
+            };
+        }
+
         // try and parse the request
         if let Ok(spec_request) = SpecResponder::try_parse_request(&data) {
             self.handle_spec_request(peer_id, channel, spec_request)
                 .await?;
+        } else if let Ok(eval_request) = EvalResponder::try_parse_request(&data) {
+            self.handle_eval_request(peer_id, channel, eval_request)
+                .await?;
         } else if let Ok(task_request) = TaskResponder::try_parse_request(&data) {
             self.handle_task_request(peer_id, channel, task_request)
                 .await?;
         } else {
+            self.rpc_reputations.record_task(peer_id, false);
+            self.dead_letters
+                .push("request", peer_id, "unknown request", &data);
             return Err(eyre::eyre!(
                 "Received unknown request from {}: {:?}",
                 peer_id,
@@ -42,7 +133,7 @@ impl DriaComputeNode {
     async fn handle_spec_request(
         &mut self,
         peer_id: PeerId,
-        channel: ResponseChannel<Vec<u8>>,
+        channel: RequestChannel,
         spec_request: <SpecResponder as IsResponder>::Request,
     ) -> Result<()> {
         log::info!(
@@ -51,15 +142,33 @@ impl DriaComputeNode {
             spec_request.request_id
         );
 
-        let response = SpecResponder::respond(spec_request, self.spec_collector.collect().await);
+        let specs = self.spec_collector.collect(self.relay_stats).await;
+        let (response, sent_specs) =
+            SpecResponder::respond(spec_request, specs, self.sent_specs.get(&peer_id))?;
         let response_data = serde_json::to_vec(&response)?;
+        // forget the specs sent to the RPCs that are no longer known, if there are too many
+        if !self.sent_specs.contains_key(&peer_id) && self.sent_specs.len() >= MAX_SENT_SPECS_PEERS
+        {
+            let rpcs = &self.dria_nodes.rpc_peerids;
+            self.sent_specs.retain(|peer_id, _| rpcs.contains(peer_id));
+            let other = self.sent_specs.keys().next().copied();
+            if let Some(other) = other.filter(|_| self.sent_specs.len() >= MAX_SENT_SPECS_PEERS) {
+                self.sent_specs.remove(&other);
+            }
+        }
+        self.sent_specs.insert(peer_id, sent_specs);
 
         log::info!(
-            "Responding to spec request from peer {} with id {}",
+            "Responding to spec request from peer {} with id {}{}",
             peer_id,
-            response.request_id
+            response.request_id,
+            if response.base_hash.is_some() {
+                " (delta)"
+            } else {
+                ""
+            }
         );
-        self.p2p.respond(response_data, channel).await?;
+        self.p2p.respond(peer_id, response_data, channel).await?;
 
         Ok(())
     }
@@ -72,13 +181,78 @@ impl DriaComputeNode {
     async fn handle_task_request(
         &mut self,
         peer_id: PeerId,
-        channel: ResponseChannel<Vec<u8>>,
+        channel: RequestChannel,
         task_request: <TaskResponder as IsResponder>::Request,
     ) -> Result<()> {
         log::info!("Received a task request from {}", peer_id);
+        if self.paused {
+            return Err(eyre!("Node is paused, ignoring task request."));
+        }
+        if self.observer {
+            return Err(eyre!("Node is an observer, ignoring task request."));
+        }
+        if let Some(error) = self.check_duplicate_task(&task_request) {
+            return TaskResponder::respond_rejected(self, peer_id, &task_request, error, channel)
+                .await;
+        }
+        if let Some(Err(retry_after)) = self
+            .task_rate_limiter
+            .as_mut()
+            .map(|limiter| limiter.try_acquire(Instant::now()))
+        {
+            let error = TaskError::RateLimited { retry_after };
+            return TaskResponder::respond_rejected(self, peer_id, &task_request, error, channel)
+                .await;
+        }
 
-        let (task_input, task_metadata) =
-            TaskResponder::prepare_worker_input(self, &task_request, channel).await?;
+        let prepared =
+            TaskResponder::prepare_worker_input(self, peer_id, &task_request, channel).await;
+        self.record_prepared_task(peer_id, &prepared);
+        let (task_input, task_metadata) = prepared?;
+        self.dispatch_task(task_input, task_metadata).await
+    }
+
+    /// Returns an error if a task or an evaluation with the same id as the given request is still pending,
+    /// so that the pending one is not replaced and its requester still gets a response.
+    fn check_duplicate_task(&self, message: &DriaMessage) -> Option<TaskError> {
+        let task_id = message
+            .parse_payload::<TaskRequestPayload<serde_json::Value>>()
+            .ok()?
+            .task_id;
+        let is_pending = self.pending_tasks_single.contains_key(&task_id)
+            || self.pending_tasks_batch.contains_key(&task_id)
+            || self.pending_evals.contains_key(&task_id);
+
+        is_pending.then_some(TaskError::Duplicate { task_id })
+    }
+
+    /// Records a prepared task to the reputation of the RPC that sent it.
+    ///
+    /// Tasks that fail due to the node itself, e.g. when the model is not served, do not count against the RPC.
+    fn record_prepared_task<T>(&mut self, peer_id: PeerId, prepared: &Result<T>) {
+        let valid = match prepared {
+            Ok(_) => true,
+            Err(e) => match e.downcast_ref::<TaskError>() {
+                Some(TaskError::ModelNotServed { .. }) => return,
+                _ => false,
+            },
+        };
+        self.rpc_reputations.record_task(peer_id, valid);
+    }
+
+    /// Sends the task to the appropriate worker, and keeps its metadata until it is responded.
+    async fn dispatch_task(
+        &mut self,
+        task_input: TaskWorkerInput,
+        task_metadata: TaskWorkerMetadata,
+    ) -> Result<()> {
+        // workers are not expected to make progress while idle, so the stall timer starts with the first task
+        if self.pending_tasks_single.is_empty() && self.pending_tasks_batch.is_empty() {
+            self.last_task_progress_at = Instant::now();
+        }
+        if !task_input.batchable {
+            self.abort_tps_refresh();
+        }
         if let Err(e) = match task_input.batchable {
             // this is a batchable task, send it to batch worker
             // and keep track of the task id in pending tasks
@@ -109,15 +283,282 @@ impl DriaComputeNode {
             },
         } {
             log::error!("Error sending workflow message: {:?}", e);
+            self.dropped_tasks[usize::from(e.0.batchable)] += 1;
         };
 
         Ok(())
     }
 
+    /// Handles an evaluation request received from the network.
+    ///
+    /// The execution of each model is sent to the appropriate worker as a separate task,
+    /// and the response is sent once both of them are done.
+    async fn handle_eval_request(
+        &mut self,
+        peer_id: PeerId,
+        channel: RequestChannel,
+        eval_request: <EvalResponder as IsResponder>::Request,
+    ) -> Result<()> {
+        log::info!("Received an evaluation request from {}", peer_id);
+        if self.paused {
+            return Err(eyre!("Node is paused, ignoring evaluation request."));
+        }
+        if self.observer {
+            return Err(eyre!("Node is an observer, ignoring evaluation request."));
+        }
+        if let Some(error) = self.check_duplicate_task(&eval_request) {
+            return TaskResponder::respond_rejected(self, peer_id, &eval_request, error, channel)
+                .await;
+        }
+
+        let prepared =
+            EvalResponder::prepare_worker_inputs(self, peer_id, &eval_request, channel).await;
+        self.record_prepared_task(peer_id, &prepared);
+        let (task_inputs, eval_metadata) = prepared?;
+
+        // check the workers beforehand, so that an evaluation is not left with a single output
+        for task_input in &task_inputs {
+            let has_worker = match task_input.batchable {
+                true => self.task_request_batch_tx.is_some(),
+                false => self.task_request_single_tx.is_some(),
+            };
+            if !has_worker {
+                return Err(eyre!("Evaluation received but no worker available."));
+            }
+        }
+
+        let task_id = EvalResponder::parse_execution_id(&task_inputs[0].task_id)
+            .map(|(task_id, _)| task_id.to_string())
+            .ok_or_else(|| eyre!("invalid execution id"))?;
+        self.pending_evals.insert(task_id, eval_metadata);
+        for task_input in task_inputs {
+            let tx = match task_input.batchable {
+                true => self.task_request_batch_tx.as_mut(),
+                false => self.task_request_single_tx.as_mut(),
+            };
+            if let Some(tx) = tx {
+                if let Err(e) = tx.send(task_input).await {
+                    log::error!("Error sending evaluation message: {:?}", e);
+                    self.dropped_tasks[usize::from(e.0.batchable)] += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends a keep-alive request to each known RPC node, retried as they are safe to repeat.
+    ///
+    /// These are sent more frequently than the heartbeats, so that the connection
+    /// does not become idle in the eyes of a NAT in between. RPCs that do not respond
+    /// are handled by [`Self::handle_rpc_keepalive_failed`].
+    pub(crate) async fn handle_rpc_keepalive(&mut self) {
+        for peer_id in self.dria_nodes.rpc_peerids.iter() {
+            let request = KeepAliveRequest::new();
+            let request_data = match serde_json::to_vec(&request) {
+                Ok(data) => data,
+                Err(e) => {
+                    log::error!("Could not serialize keep-alive request: {:?}", e);
+                    return;
+                }
+            };
+
+            match self.p2p.request_with_retry(*peer_id, request_data).await {
+                Ok(pending) => {
+                    log::debug!(
+                        "Sent keep-alive {} to RPC {} with correlation id {}",
+                        request.keepalive_id,
+                        peer_id,
+                        pending.correlation_id
+                    );
+
+                    // the response is awaited aside, so that a lost RPC is noticed without blocking the node
+                    let (peer_id, keepalive_failed_tx) =
+                        (*peer_id, self.keepalive_failed_tx.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = pending.response().await {
+                            log::warn!("Keep-alive to RPC {} is not responded: {}", peer_id, e);
+                            let _ = keepalive_failed_tx.send(peer_id).await;
+                        }
+                    });
+                }
+                Err(e) => log::warn!("Could not send keep-alive to RPC {}: {:?}", peer_id, e),
+            }
+        }
+    }
+
+    /// Marks an RPC whose keep-alive has failed as unhealthy until its latency is measured again,
+    /// and selects another RPC if it was the selected one.
+    pub(crate) fn handle_rpc_keepalive_failed(&mut self, peer_id: PeerId) {
+        self.rpc_latencies.remove(&peer_id);
+        if self.selected_rpc != Some(peer_id) {
+            return;
+        }
+
+        let scores = self
+            .rpc_latencies
+            .keys()
+            .map(|peer_id| (*peer_id, self.rpc_reputations.score(peer_id)))
+            .collect();
+        self.selected_rpc = select_rpc(None, &self.rpc_latencies, &scores);
+        match self.selected_rpc {
+            Some(selected) => log::info!(
+                "Selected RPC {} as {} does not respond to keep-alives.",
+                selected,
+                peer_id
+            ),
+            None => log::warn!(
+                "No healthy RPC nodes to select, as {} does not respond to keep-alives.",
+                peer_id
+            ),
+        }
+    }
+
+    /// Saves the RPC session to the session file if there is one, so that a restart can resume it.
+    pub(crate) fn handle_session_save(&self) {
+        let Some(path) = &self.config.session_file else {
+            return;
+        };
+
+        let session = RpcSession::new(&self.dria_nodes, self.selected_rpc, &self.sent_specs);
+        match session.save(path) {
+            Ok(()) => log::info!("Saved the RPC session to {}", path.display()),
+            Err(e) => log::error!("Error saving the RPC session: {:?}", e),
+        }
+    }
+
+    /// Sends a signed goodbye message to each of the given RPC nodes, listing the in-flight tasks received from them.
+    ///
+    /// Returns the responses of the sent goodbyes, to be awaited with [`await_goodbyes`].
+    pub(crate) async fn handle_goodbye(
+        &mut self,
+        peer_ids: &[PeerId],
+        reason: &str,
+    ) -> Vec<(PeerId, PendingResponse)> {
+        let mut responses = Vec::new();
+        for peer_id in peer_ids {
+            let pending_tasks = self
+                .pending_tasks_single
+                .iter()
+                .map(|t| (t, false))
+                .chain(self.pending_tasks_batch.iter().map(|t| (t, true)))
+                .filter(|((_, metadata), _)| metadata.peer_id == *peer_id)
+                .map(|((task_id, metadata), batchable)| GoodbyeTask {
+                    task_id: task_id.clone(),
+                    model: metadata.model_name.clone(),
+                    batchable,
+                })
+                .collect::<Vec<_>>();
+            log::info!(
+                "Saying goodbye ({}) to RPC {} with {} pending tasks.",
+                reason,
+                peer_id,
+                pending_tasks.len()
+            );
+
+            let payload = GoodbyePayload {
+                reason: reason.to_string(),
+                pending_tasks,
+            };
+            let message =
+                self.new_message(serde_json::json!(payload).to_string(), DriaTopic::Goodbye);
+            let message_data = match message.to_bytes() {
+                Ok(data) => data,
+                Err(e) => {
+                    log::error!("Could not serialize goodbye message: {:?}", e);
+                    continue;
+                }
+            };
+
+            match self.p2p.request(*peer_id, message_data).await {
+                Ok(response) => responses.push((*peer_id, response)),
+                Err(e) => log::warn!("Could not send goodbye to RPC {}: {:?}", peer_id, e),
+            }
+        }
+
+        responses
+    }
+
+    /// Removes the pending tasks & evaluations that are long past their deadline and can no longer be responded to,
+    /// so that the pending task counts do not keep the tasks lost to network hiccups.
+    ///
+    /// Each removed task is reported as expired within the logs & the statistics.
+    pub(crate) fn handle_stale_task_sweep(&mut self) {
+        let now = get_current_time_nanos();
+        let stale_task_ids = self
+            .pending_tasks_single
+            .iter()
+            .chain(self.pending_tasks_batch.iter())
+            .filter(|(_, metadata)| metadata.is_stale(now))
+            .map(|(task_id, _)| task_id.clone())
+            .collect::<Vec<_>>();
+
+        for task_id in stale_task_ids {
+            let Some(metadata) = self
+                .pending_tasks_single
+                .remove(&task_id)
+                .or_else(|| self.pending_tasks_batch.remove(&task_id))
+            else {
+                continue;
+            };
+
+            let error = TaskError::Expired {
+                deadline: metadata.deadline,
+            };
+            log::warn!(
+                "Task {} (trace {}) {}, removing it.",
+                task_id,
+                metadata.trace_id,
+                error
+            );
+            if let Some(stats_db) = &self.stats_db {
+                stats_db.record(
+                    task_id.clone(),
+                    metadata.model_name.clone(),
+                    Some(error.to_string()),
+                    TaskStats::default(),
+                );
+            }
+        }
+
+        // evaluations wait for both of their outputs, which may never arrive as well
+        let stale_eval_ids = self
+            .pending_evals
+            .iter()
+            .filter(|(_, metadata)| metadata.is_stale(now))
+            .map(|(task_id, _)| task_id.clone())
+            .collect::<Vec<_>>();
+        for task_id in stale_eval_ids {
+            let Some(metadata) = self.pending_evals.remove(&task_id) else {
+                continue;
+            };
+
+            let error = TaskError::Expired {
+                deadline: metadata.deadline,
+            };
+            log::warn!(
+                "Evaluation {} (trace {}) {}, removing it.",
+                task_id,
+                metadata.trace_id,
+                error
+            );
+        }
+    }
+
     pub(crate) async fn handle_task_response(
         &mut self,
         task_response: TaskWorkerOutput,
     ) -> Result<()> {
+        self.last_task_progress_at = Instant::now();
+
+        // the output may belong to one of the models of an evaluation
+        if let Some((task_id, idx)) = EvalResponder::parse_execution_id(&task_response.task_id) {
+            if self.pending_evals.contains_key(task_id) {
+                let task_id = task_id.to_string();
+                return EvalResponder::handle_output(self, task_id, idx, task_response).await;
+            }
+        }
+
         // remove the task from pending tasks, and get its metadata
         let task_metadata = match task_response.batchable {
             true => {
@@ -133,6 +574,15 @@ impl DriaComputeNode {
         // respond to the response channel with the result
         match task_metadata {
             Some(channel) => {
+                // only successful executions are counted towards the latency
+                if task_response.result.is_ok() {
+                    let stats = &task_response.stats;
+                    let latency_ns = stats
+                        .execution_ended_at
+                        .saturating_sub(stats.execution_started_at);
+                    self.record_model_latency(&channel.model_name, latency_ns as f64 / 1e6);
+                }
+
                 TaskResponder::handle_respond(self, task_response, channel).await?;
             }
             None => {
@@ -146,3 +596,31 @@ impl DriaComputeNode {
         Ok(())
     }
 }
+
+/// Duration after which the unauthorized requests of a quiet peer are forgotten.
+const UNAUTHORIZED_REQUESTS_TTL: Duration = Duration::from_secs(60 * 60);
+/// Maximum number of peers whose unauthorized requests are counted at a time.
+const MAX_UNAUTHORIZED_PEERS: usize = 1000;
+/// Maximum number of RPCs whose last sent specs are kept for the delta responses.
+const MAX_SENT_SPECS_PEERS: usize = 256;
+
+/// Number of seconds to wait for the RPCs to acknowledge the goodbye messages.
+const GOODBYE_TIMEOUT_SECS: u64 = 5;
+
+/// Waits for the RPCs to acknowledge the goodbye messages, for at most [`GOODBYE_TIMEOUT_SECS`] seconds.
+pub(crate) async fn await_goodbyes(responses: Vec<(PeerId, PendingResponse)>) {
+    let acknowledgements = async {
+        for (peer_id, response) in responses {
+            match response.response().await {
+                Ok(_) => log::debug!("RPC {} acknowledged the goodbye.", peer_id),
+                Err(e) => log::warn!("Could not deliver goodbye to RPC {}: {:?}", peer_id, e),
+            }
+        }
+    };
+    if tokio::time::timeout(Duration::from_secs(GOODBYE_TIMEOUT_SECS), acknowledgements)
+        .await
+        .is_err()
+    {
+        log::warn!("Timed out waiting for the goodbyes to be acknowledged.");
+    }
+}
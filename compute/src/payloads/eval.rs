@@ -0,0 +1,84 @@
+use dkn_workflows::Judgement;
+use eyre::Result;
+use libsecp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+
+use super::{JudgementScore, TaskStats};
+
+/// Response to an evaluation task, where the same input is executed with two models.
+///
+/// Outputs are encrypted with the public key of the requester as in [`TaskResponsePayload`](super::TaskResponsePayload),
+/// while the scores are in plain so that they can be aggregated without decryption.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EvalResponsePayload {
+    /// The unique identifier of the task.
+    pub task_id: String,
+    /// Outputs of the two models, in the order they were requested.
+    pub outputs: [EvalOutput; 2],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EvalOutput {
+    /// Name of the model used for this output.
+    pub model: String,
+    /// Output encrypted with the public key of the task, hexadecimally encoded; `None` if the execution failed.
+    pub ciphertext: Option<String>,
+    /// Error of the execution, if it failed.
+    pub error: Option<String>,
+    /// Local scores of the output.
+    pub score: EvalScore,
+    /// Score of the output by the judge model of the node, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judgement: Option<JudgementScore>,
+    /// Stats about the execution.
+    pub stats: TaskStats,
+}
+
+/// Scores computed locally by the node for an output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EvalScore {
+    /// Length of the output in characters.
+    pub length: usize,
+    /// Whether the output matches each of the regular expressions of the task, in order.
+    pub checks: Vec<bool>,
+}
+
+impl EvalOutput {
+    /// Creates the output of a model, encrypting the result with the public key of the task.
+    pub fn new(
+        result: Result<String, String>,
+        score: EvalScore,
+        task_pk: &PublicKey,
+        model: String,
+        stats: TaskStats,
+    ) -> Result<Self> {
+        let (ciphertext, error) = match result {
+            Ok(result) => {
+                let ciphertext = ecies::encrypt(&task_pk.serialize(), result.as_bytes())?;
+                (Some(hex::encode(ciphertext)), None)
+            }
+            Err(error) => (None, Some(error)),
+        };
+
+        Ok(Self {
+            model,
+            ciphertext,
+            error,
+            score,
+            judgement: None,
+            stats,
+        })
+    }
+
+    /// Attaches the score of the output given by a judge model.
+    pub fn with_judgement(mut self, judgement: Option<Judgement>) -> Self {
+        self.judgement = judgement.map(JudgementScore::from);
+        self
+    }
+}
@@ -1,11 +1,32 @@
-mod error;
-pub use error::TaskErrorPayload;
+pub use dkn_utils::payloads::{TaskDump, TaskErrorClass, TaskErrorPayload, TaskStats};
+
+mod eval;
+pub use eval::{EvalOutput, EvalResponsePayload, EvalScore};
 
 mod request;
 pub use request::TaskRequestPayload;
 
 mod response;
-pub use response::TaskResponsePayload;
+pub use response::{JudgementScore, TaskResponsePayload};
+
+/// Returns the JSON schemas of the payloads along with their names, which are the ones of
+/// [`dkn_utils::payloads::payload_schemas`] and the request-response payloads of the node.
+#[cfg(feature = "schema")]
+pub fn payload_schemas() -> Vec<(&'static str, schemars::schema::RootSchema)> {
+    use crate::reqres::{SpecRequest, SpecResponse};
+    use schemars::schema_for;
 
-mod stats;
-pub use stats::TaskStats;
+    let mut schemas = dkn_utils::payloads::payload_schemas();
+    schemas.extend([
+        // the input of a task is parsed depending on its topic, so it is left as any value here
+        (
+            "TaskRequestPayload",
+            schema_for!(TaskRequestPayload<serde_json::Value>),
+        ),
+        ("TaskResponsePayload", schema_for!(TaskResponsePayload)),
+        ("EvalResponsePayload", schema_for!(EvalResponsePayload)),
+        ("SpecRequest", schema_for!(SpecRequest)),
+        ("SpecResponse", schema_for!(SpecResponse)),
+    ]);
+    schemas
+}
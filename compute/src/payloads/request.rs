@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 
 /// A generic task request, given by Dria.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct TaskRequestPayload<T> {
     /// The unique identifier of the task.
     pub task_id: String,
@@ -1,3 +1,4 @@
+use dkn_workflows::Judgement;
 use eyre::Result;
 use libsecp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,7 @@ use super::TaskStats;
 /// To check the commitment, one must decrypt the ciphertext and parse plaintext from it,
 /// and compute the digest using SHA256. That digest will then be used for the signature check.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct TaskResponsePayload {
     /// The unique identifier of the task.
@@ -20,6 +22,31 @@ pub struct TaskResponsePayload {
     pub model: String,
     /// Stats about the task execution.
     pub stats: TaskStats,
+    /// Score of the result by the judge model of the node, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judgement: Option<JudgementScore>,
+}
+
+/// Score of a result by a judge model, sent in plain next to the encrypted result.
+///
+/// The rationale of the judge is not included, as it often quotes the result itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct JudgementScore {
+    /// Name of the judge model.
+    pub model: String,
+    /// Score from 0 to 10, higher is better.
+    pub score: u8,
+}
+
+impl From<Judgement> for JudgementScore {
+    fn from(judgement: Judgement) -> Self {
+        Self {
+            model: judgement.model,
+            score: judgement.score,
+        }
+    }
 }
 
 impl TaskResponsePayload {
@@ -41,8 +68,15 @@ impl TaskResponsePayload {
             ciphertext: hex::encode(ciphertext),
             model,
             stats,
+            judgement: None,
         })
     }
+
+    /// Attaches the score of the result given by a judge model.
+    pub fn with_judgement(mut self, judgement: Option<Judgement>) -> Self {
+        self.judgement = judgement.map(JudgementScore::from);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -73,6 +107,17 @@ mod tests {
         .expect("to create payload");
 
         // decrypt result and compare it to plaintext
+        // only the model & score of the judgement are sent in plain
+        let payload = payload.with_judgement(Some(Judgement {
+            model: "judge".to_string(),
+            score: 7,
+            rationale: RESULT.escape_ascii().to_string(),
+        }));
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap()["judgement"],
+            serde_json::json!({ "model": "judge", "score": 7 })
+        );
+
         let ciphertext_bytes = hex::decode(payload.ciphertext).unwrap();
         let result = decrypt(&task_sk.serialize(), &ciphertext_bytes).expect("to decrypt");
         assert_eq!(result, RESULT, "Result mismatch");
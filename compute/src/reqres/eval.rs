@@ -0,0 +1,292 @@
+use dkn_p2p::{libp2p::PeerId, RequestChannel};
+use dkn_utils::payloads::DriaTopic;
+use dkn_workflows::{Entry, Workflow};
+use eyre::{eyre, Context, Result};
+use libsecp256k1::PublicKey;
+use regex::Regex;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::payloads::*;
+use crate::utils::DriaMessage;
+use crate::workers::task::*;
+use crate::DriaComputeNode;
+
+use super::{IsResponder, TaskResponder};
+
+pub struct EvalResponder;
+
+impl IsResponder for EvalResponder {
+    type Request = DriaMessage;
+    type Response = DriaMessage;
+
+    /// Evaluation requests are signed messages just like tasks, and are told apart by their topic.
+    fn try_parse_request(data: &[u8]) -> Result<Self::Request> {
+        let message: DriaMessage =
+            serde_json::from_slice(data).wrap_err("could not parse request")?;
+        if message.topic != DriaTopic::Eval.as_str() {
+            return Err(eyre!("not an evaluation request"));
+        }
+
+        Ok(message)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct EvalPayload {
+    /// [Workflow](https://github.com/andthattoo/ollama-workflows/blob/main/src/program/workflow.rs) object to be parsed.
+    pub(crate) workflow: Workflow,
+    /// The two models to compare, each one can be a model or a provider name as in a task.
+    pub(crate) models: [String; 2],
+    /// Prompts can be provided within the workflow itself, in which case this is `None`.
+    pub(crate) prompt: Option<String>,
+    /// Regular expressions to check against each output.
+    #[serde(default)]
+    pub(crate) checks: Vec<String>,
+}
+
+/// An evaluation task that is waiting for the outputs of its models.
+pub struct EvalMetadata {
+    /// The RPC that the evaluation is responded to.
+    pub peer_id: PeerId,
+    pub public_key: PublicKey,
+    pub model_names: [String; 2],
+    /// Trace id of the request, attached to the response.
+    pub trace_id: String,
+    pub deadline: u128,
+    pub checks: Vec<Regex>,
+    pub outputs: [Option<TaskWorkerOutput>; 2],
+    pub channel: RequestChannel,
+}
+
+impl EvalMetadata {
+    /// Returns `true` if the evaluation is long past its deadline and can never be responded to,
+    /// same as [`TaskWorkerMetadata::is_stale`].
+    pub fn is_stale(&self, now: u128) -> bool {
+        now > self.deadline.saturating_add(STALE_TASK_GRACE_NANOS) && !self.channel.is_open()
+    }
+}
+
+impl EvalResponder {
+    /// Returns the id of the execution for the model at `idx` within an evaluation task.
+    #[inline]
+    pub(crate) fn execution_id(task_id: &str, idx: usize) -> String {
+        format!("{}#{}", task_id, idx)
+    }
+
+    /// Parses an execution id back into the task id and the model index.
+    pub(crate) fn parse_execution_id(execution_id: &str) -> Option<(&str, usize)> {
+        let (task_id, idx) = execution_id.rsplit_once('#')?;
+        let idx = idx.parse().ok().filter(|idx| *idx < 2)?;
+        Some((task_id, idx))
+    }
+
+    /// Handles the evaluation message, returning a worker input for each model.
+    pub(crate) async fn prepare_worker_inputs(
+        node: &mut DriaComputeNode,
+        peer_id: PeerId,
+        eval_message: &DriaMessage,
+        channel: RequestChannel,
+    ) -> Result<([TaskWorkerInput; 2], EvalMetadata)> {
+        let task = eval_message
+            .parse_payload::<TaskRequestPayload<EvalPayload>>()
+            .wrap_err("could not parse evaluation task")?;
+        let trace_id = eval_message.get_trace_id();
+        log::info!(
+            "Handling evaluation task {} (trace {})",
+            task.task_id,
+            trace_id
+        );
+
+        let stats = TaskStats::new()
+            .record_received_at()
+            .record_received_bytes(eval_message.payload.len());
+
+        if node.now_nanos() >= task.deadline {
+            return Err(eyre!(
+                "Evaluation task {} is past the deadline, ignoring",
+                task.task_id
+            ));
+        }
+
+        let task_public_key_bytes =
+            hex::decode(&task.public_key).wrap_err("could not decode public key")?;
+        let task_public_key = PublicKey::parse_slice(&task_public_key_bytes, None)?;
+
+        let checks = task
+            .input
+            .checks
+            .iter()
+            .map(|check| Regex::new(check))
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("could not parse checks")?;
+
+        // both models must be served by this node, otherwise the comparison is not possible
+        let [model_a, model_b] = task.input.models.clone().map(|model_or_provider| {
+            node.config
+                .workflows
+                .get_matching_model(model_or_provider)
+                .map_err(|e| TaskError::ModelNotServed {
+                    reason: e.to_string(),
+                })
+        });
+        let models = [model_a?, model_b?];
+        let model_names = models.clone().map(|(_, model)| model.to_string());
+        log::info!(
+            "Using models {} and {} for evaluation task {} (trace {})",
+            model_names[0],
+            model_names[1],
+            task.task_id,
+            trace_id
+        );
+
+        let judge = TaskResponder::judge_input(node, eval_message);
+        let entry: Option<Entry> = task
+            .input
+            .prompt
+            .map(|prompt| Entry::try_value_or_str(&prompt));
+        let remaining = Duration::from_nanos(
+            u64::try_from(task.deadline.saturating_sub(node.now_nanos())).unwrap_or(u64::MAX),
+        );
+        let mut idx = 0;
+        let task_inputs = models.map(|(model_provider, model)| {
+            let (executor, batchable) = TaskResponder::new_executor(
+                &node.executor_factory,
+                &node.config.workflows,
+                model_provider,
+                model,
+                Some(remaining),
+            );
+            let task_id = Self::execution_id(&task.task_id, idx);
+            idx += 1;
+
+            TaskWorkerInput {
+                entry: entry.clone(),
+                executor,
+                workflow: task.input.workflow.clone(),
+                task_id,
+                trace_id: trace_id.clone(),
+                stats: stats.clone(),
+                batchable,
+                judge: judge.clone(),
+                constraints: None,
+            }
+        });
+
+        let eval_metadata = EvalMetadata {
+            peer_id,
+            public_key: task_public_key,
+            model_names,
+            trace_id,
+            deadline: task.deadline,
+            checks,
+            outputs: [None, None],
+            channel,
+        };
+
+        Ok((task_inputs, eval_metadata))
+    }
+
+    /// Stores the output of a model, and responds once the outputs of both models are ready.
+    pub(crate) async fn handle_output(
+        node: &mut DriaComputeNode,
+        task_id: String,
+        idx: usize,
+        task_output: TaskWorkerOutput,
+    ) -> Result<()> {
+        let eval_metadata = node
+            .pending_evals
+            .get_mut(&task_id)
+            .ok_or_else(|| eyre!("Evaluation not found for task id: {}", task_id))?;
+        eval_metadata.outputs[idx] = Some(task_output);
+        if eval_metadata.outputs.iter().any(Option::is_none) {
+            return Ok(());
+        }
+
+        let eval_metadata = node
+            .pending_evals
+            .remove(&task_id)
+            .expect("evaluation exists");
+        let mut outputs = Vec::with_capacity(2);
+        for (task_output, model_name) in eval_metadata
+            .outputs
+            .into_iter()
+            .flatten()
+            .zip(eval_metadata.model_names)
+        {
+            let result = task_output.result.map_err(|err| format!("{:#}", err));
+            let score = match &result {
+                Ok(result) => Self::score(result, &eval_metadata.checks),
+                Err(_) => EvalScore::default(),
+            };
+            outputs.push(
+                EvalOutput::new(
+                    result,
+                    score,
+                    &eval_metadata.public_key,
+                    model_name,
+                    task_output.stats.record_published_at(),
+                )?
+                .with_judgement(task_output.judgement),
+            );
+        }
+
+        log::info!(
+            "Publishing result for evaluation task {} (trace {})",
+            task_id,
+            eval_metadata.trace_id
+        );
+        let payload = EvalResponsePayload {
+            task_id,
+            outputs: outputs
+                .try_into()
+                .map_err(|_| eyre!("expected two outputs"))?,
+        };
+        let response = node
+            .new_message(serde_json::json!(payload).to_string(), "response")
+            .with_trace_id(eval_metadata.trace_id);
+        node.p2p
+            .respond(
+                eval_metadata.peer_id,
+                response.to_bytes()?,
+                eval_metadata.channel,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Scores an output w.r.t its length and the given checks.
+    fn score(output: &str, checks: &[Regex]) -> EvalScore {
+        EvalScore {
+            length: output.chars().count(),
+            checks: checks.iter().map(|check| check.is_match(output)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_execution_ids() {
+        let execution_id = EvalResponder::execution_id("task#1", 1);
+        assert_eq!(
+            EvalResponder::parse_execution_id(&execution_id),
+            Some(("task#1", 1))
+        );
+        assert_eq!(EvalResponder::parse_execution_id("task#2"), None);
+        assert_eq!(EvalResponder::parse_execution_id("task"), None);
+
+        let checks = [Regex::new(r"^\d+$").unwrap(), Regex::new("ok").unwrap()];
+        assert_eq!(
+            EvalResponder::score("1234", &checks),
+            EvalScore {
+                length: 4,
+                checks: vec![true, false]
+            }
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A keep-alive request sent to RPC nodes, distinct from the ping-pong heartbeats.
+///
+/// Its only purpose is to keep the underlying connection from being dropped by
+/// NATs with aggressive idle timeouts; the response is not used.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeepAliveRequest {
+    /// UUID of the keep-alive request.
+    pub keepalive_id: String,
+}
+
+impl KeepAliveRequest {
+    /// Creates a new keep-alive request with a random id.
+    pub fn new() -> Self {
+        Self {
+            keepalive_id: Uuid::new_v4().to_string(),
+        }
+    }
+}
+
+impl Default for KeepAliveRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keepalive_serialization() {
+        let request = KeepAliveRequest::new();
+        let data = serde_json::to_string(&request).unwrap();
+        assert!(data.starts_with(r#"{"keepalive_id":""#));
+    }
+}
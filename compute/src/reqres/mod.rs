@@ -3,11 +3,17 @@
 use eyre::Context;
 use serde::{de::DeserializeOwned, Serialize};
 
+mod eval;
+pub use eval::{EvalMetadata, EvalPayload, EvalResponder};
+
 mod specs;
-pub use specs::SpecResponder;
+pub use specs::{SentSpecs, SpecRequest, SpecResponder, SpecResponse};
 
 mod task;
-pub use task::TaskResponder;
+pub use task::{TaskPayload, TaskResponder};
+
+mod keepalive;
+pub use keepalive::KeepAliveRequest;
 
 /// A responder should implement a request & response type, both serializable.
 ///
@@ -70,4 +76,59 @@ mod tests {
         assert_eq!(a_variant, a_deserialized);
         assert_eq!(b_variant, b_deserialized);
     }
+
+    #[test]
+    fn test_strict_unknown_fields() {
+        use crate::payloads::TaskRequestPayload;
+        use serde_json::{json, Value};
+
+        let spec_request = json!({ "request_id": "id" });
+        let task_request = json!({
+            "taskId": "id",
+            "deadline": 0,
+            "input": {},
+            "filter": { "hex": "00", "hashes": 1 },
+            "publicKey": "00"
+        });
+        let parse_spec =
+            |value: &Value| serde_json::from_value::<SpecRequest>(value.clone()).map(|_| ());
+        let parse_task = |value: &Value| {
+            serde_json::from_value::<TaskRequestPayload<Value>>(value.clone()).map(|_| ())
+        };
+
+        // known fields are accepted in either mode
+        assert!(parse_spec(&spec_request).is_ok());
+        assert!(parse_task(&task_request).is_ok());
+
+        // unknown fields, including the nested ones, are rejected only in strict mode
+        let mut spec_unknown = spec_request.clone();
+        spec_unknown["extra"] = json!(1);
+        let mut task_unknown = task_request.clone();
+        task_unknown["extra"] = json!(1);
+        let mut filter_unknown = task_request.clone();
+        filter_unknown["filter"]["extra"] = json!(1);
+        for result in [
+            parse_spec(&spec_unknown),
+            parse_task(&task_unknown),
+            parse_task(&filter_unknown),
+        ] {
+            assert_eq!(result.is_err(), cfg!(feature = "strict"));
+            if let Err(e) = result {
+                assert!(e.to_string().contains("unknown field `extra`"));
+            }
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_request_parse_never_panics(data: Vec<u8>) {
+            let _ = SpecResponder::try_parse_request(&data);
+            if let Ok(message) = EvalResponder::try_parse_request(&data) {
+                let _ = message.parse_payload::<crate::payloads::TaskRequestPayload<EvalPayload>>();
+            }
+            if let Ok(message) = TaskResponder::try_parse_request(&data) {
+                let _ = message.parse_payload::<crate::payloads::TaskRequestPayload<TaskPayload>>();
+            }
+        }
+    }
 }
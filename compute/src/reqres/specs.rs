@@ -1,21 +1,41 @@
-use crate::utils::Specs;
+use crate::utils::{crypto::sha256hash, Specs};
 
 use super::IsResponder;
+use eyre::{eyre, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct SpecRequest {
     /// UUID of the specs request, prevents replay attacks.
     pub request_id: String,
+    /// Hash of the specs that the RPC has from this node, if it supports delta responses.
+    #[serde(default)]
+    pub base_hash: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SpecResponse {
     /// UUID of the specs request, prevents replay attacks.
     pub request_id: String,
-    /// Node specs, will be flattened during serialization.
+    /// Hash of the full specs, to be given as `base_hash` within the next request.
+    pub hash: String,
+    /// Hash of the specs that this response is a delta of, `None` if the specs are full.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_hash: Option<String>,
+    /// Node specs, or only the changed fields if this is a delta; will be flattened during serialization.
     #[serde(flatten)]
-    specs: Specs,
+    specs: Map<String, Value>,
+}
+
+/// The last specs sent to an RPC, which its delta responses are based on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentSpecs {
+    hash: String,
+    specs: Map<String, Value>,
 }
 
 pub struct SpecResponder;
@@ -26,10 +46,95 @@ impl IsResponder for SpecResponder {
 }
 
 impl SpecResponder {
-    pub fn respond(request: SpecRequest, specs: Specs) -> SpecResponse {
-        SpecResponse {
-            request_id: request.request_id,
+    /// Responds with the full specs, or only the changed fields if the RPC asks for a delta
+    /// based on the specs that were last sent to it.
+    ///
+    /// Returns the response along with the specs to be kept as the base of the next delta.
+    pub fn respond(
+        request: SpecRequest,
+        specs: Specs,
+        last: Option<&SentSpecs>,
+    ) -> Result<(SpecResponse, SentSpecs)> {
+        let Value::Object(specs) = serde_json::to_value(specs)? else {
+            return Err(eyre!("specs must be an object"));
+        };
+
+        Ok(Self::respond_with(request, specs, last))
+    }
+
+    fn respond_with(
+        request: SpecRequest,
+        specs: Map<String, Value>,
+        last: Option<&SentSpecs>,
+    ) -> (SpecResponse, SentSpecs) {
+        // keys are sorted within the map, so the hash does not depend on the field order
+        let hash = hex::encode(sha256hash(Value::Object(specs.clone()).to_string()));
+        let sent = SentSpecs {
+            hash: hash.clone(),
             specs,
+        };
+
+        let response = match last.filter(|last| request.base_hash.as_ref() == Some(&last.hash)) {
+            Some(last) => SpecResponse {
+                request_id: request.request_id,
+                hash,
+                base_hash: Some(last.hash.clone()),
+                specs: sent
+                    .specs
+                    .iter()
+                    .filter(|(key, value)| last.specs.get(*key) != Some(*value))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            },
+            None => SpecResponse {
+                request_id: request.request_id,
+                hash,
+                base_hash: None,
+                specs: sent.specs.clone(),
+            },
+        };
+
+        (response, sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_spec_deltas() {
+        let request = |base_hash: Option<&str>| SpecRequest {
+            request_id: "id".to_string(),
+            base_hash: base_hash.map(String::from),
+        };
+        let specs = |free_mem: u64| match json!({"os": "linux", "free_mem": free_mem}) {
+            Value::Object(specs) => specs,
+            _ => unreachable!(),
+        };
+
+        // first response is full, even if a delta is asked for
+        let (full, sent) = SpecResponder::respond_with(request(Some("unknown")), specs(10), None);
+        assert!(full.base_hash.is_none());
+        assert_eq!(full.specs.len(), 2);
+
+        // the next one only has the changed fields
+        let (delta, _) =
+            SpecResponder::respond_with(request(Some(&full.hash)), specs(20), Some(&sent));
+        assert_eq!(delta.base_hash, Some(full.hash.clone()));
+        assert_eq!(
+            serde_json::to_value(&delta).unwrap(),
+            json!({"request_id": "id", "hash": delta.hash, "base_hash": full.hash, "free_mem": 20})
+        );
+
+        // an RPC without delta support, or with another base, gets the full specs
+        for base_hash in [None, Some("other")] {
+            let (response, _) =
+                SpecResponder::respond_with(request(base_hash), specs(20), Some(&sent));
+            assert!(response.base_hash.is_none());
+            assert_eq!(response.specs.len(), 2);
+            assert_eq!(response.hash, delta.hash);
         }
     }
 }
@@ -1,11 +1,14 @@
 #![allow(unused)]
 
-use dkn_p2p::libp2p::request_response::ResponseChannel;
-use dkn_utils::get_current_time_nanos;
-use dkn_workflows::{Entry, Executor, ModelProvider, Workflow};
+use dkn_p2p::{libp2p::PeerId, RequestChannel};
+use dkn_workflows::{
+    DriaWorkflowsConfig, Entry, ExecutorFactory, Judge, Model, ModelProvider, TaskConstraints,
+    TaskExecutor, Workflow,
+};
 use eyre::{eyre, Context, Result};
 use libsecp256k1::PublicKey;
 use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
 
 use crate::payloads::*;
 use crate::utils::DriaMessage;
@@ -22,6 +25,7 @@ impl IsResponder for TaskResponder {
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct TaskPayload {
     /// [Workflow](https://github.com/andthattoo/ollama-workflows/blob/main/src/program/workflow.rs) object to be parsed.
     pub(crate) workflow: Workflow,
@@ -32,26 +36,85 @@ pub struct TaskPayload {
     /// Prompts can be provided within the workflow itself, in which case this is `None`.
     /// Otherwise, the prompt is expected to be `Some` here.
     pub(crate) prompt: Option<String>,
+    /// Constraints on the output, enforced after the generation with a single corrective re-prompt.
+    #[serde(default)]
+    pub(crate) constraints: Option<TaskConstraints>,
 }
 
 impl TaskResponder {
+    /// Creates an executor for the given model, and returns whether it is batchable or not.
+    ///
+    /// If the time left until the deadline of the task is given, pulling a missing Ollama model is limited by it.
+    pub(crate) fn new_executor(
+        executor_factory: &ExecutorFactory,
+        workflows: &DriaWorkflowsConfig,
+        model_provider: ModelProvider,
+        model: Model,
+        remaining: Option<Duration>,
+    ) -> (Box<dyn TaskExecutor>, bool) {
+        let executor = match remaining {
+            Some(remaining) if model_provider == ModelProvider::Ollama => executor_factory(
+                &workflows.clone().with_pull_timeout_limit(remaining),
+                model_provider,
+                model,
+            ),
+            _ => executor_factory(workflows, model_provider, model),
+        };
+        let batchable = executor.capabilities().batchable;
+        (executor, batchable)
+    }
+
+    /// Returns the judge of the node along with the instruction of the task, if the node has a judge.
+    ///
+    /// The instruction is the prompt of the task if given, otherwise its workflow where the prompts are embedded.
+    pub(crate) fn judge_input(
+        node: &DriaComputeNode,
+        compute_message: &DriaMessage,
+    ) -> Option<(Arc<Judge>, String)> {
+        let judge = node.judge.clone()?;
+        let task = compute_message
+            .parse_payload::<TaskRequestPayload<serde_json::Value>>()
+            .ok()?;
+        let instruction = match task.input.get("prompt").and_then(|p| p.as_str()) {
+            Some(prompt) => prompt.to_string(),
+            None => task.input.get("workflow")?.to_string(),
+        };
+
+        Some((judge, instruction))
+    }
+
     /// Handles the compute message for workflows.
     pub(crate) async fn prepare_worker_input(
         node: &mut DriaComputeNode,
+        peer_id: PeerId,
         compute_message: &DriaMessage,
-        channel: ResponseChannel<Vec<u8>>,
+        channel: RequestChannel,
     ) -> Result<(TaskWorkerInput, TaskWorkerMetadata)> {
         // parse payload
         let task = compute_message
             .parse_payload::<TaskRequestPayload<TaskPayload>>()
             .wrap_err("could not parse workflow task")?;
-        log::info!("Handling task {}", task.task_id);
+        Self::prepare_parsed_worker_input(node, peer_id, task, compute_message, channel).await
+    }
+
+    /// Handles a task that is already parsed from the given compute message.
+    pub(crate) async fn prepare_parsed_worker_input(
+        node: &mut DriaComputeNode,
+        peer_id: PeerId,
+        task: TaskRequestPayload<TaskPayload>,
+        compute_message: &DriaMessage,
+        channel: RequestChannel,
+    ) -> Result<(TaskWorkerInput, TaskWorkerMetadata)> {
+        let trace_id = compute_message.get_trace_id();
+        log::info!("Handling task {} (trace {})", task.task_id, trace_id);
 
-        let stats = TaskStats::new().record_received_at();
+        let stats = TaskStats::new()
+            .record_received_at()
+            .record_received_bytes(compute_message.payload.len());
 
         // check if deadline is past or not
         // FIXME: with request-response, we dont expect this to happen much
-        if get_current_time_nanos() >= task.deadline {
+        if node.now_nanos() >= task.deadline {
             return Err(eyre!(
                 "Task {} is past the deadline, ignoring",
                 task.task_id
@@ -68,23 +131,30 @@ impl TaskResponder {
         let (model_provider, model) = node
             .config
             .workflows
-            .get_any_matching_model(task.input.model)?;
+            .get_any_matching_model(task.input.model)
+            .map_err(|e| TaskError::ModelNotServed {
+                reason: e.to_string(),
+            })?;
         let model_name = model.to_string(); // get model name, we will pass it in payload
-        log::info!("Using model {} for task {}", model_name, task.task_id);
+        log::info!(
+            "Using model {} via {} for task {} (trace {})",
+            model_name,
+            model_provider,
+            task.task_id,
+            trace_id
+        );
 
         // prepare workflow executor
-        let (executor, batchable) = if model_provider == ModelProvider::Ollama {
-            (
-                Executor::new_at(
-                    model,
-                    &node.config.workflows.ollama.host,
-                    node.config.workflows.ollama.port,
-                ),
-                false,
-            )
-        } else {
-            (Executor::new(model), true)
-        };
+        let remaining = Duration::from_nanos(
+            u64::try_from(task.deadline.saturating_sub(node.now_nanos())).unwrap_or(u64::MAX),
+        );
+        let (executor, batchable) = Self::new_executor(
+            &node.executor_factory,
+            &node.config.workflows,
+            model_provider,
+            model,
+            Some(remaining),
+        );
 
         // prepare entry from prompt
         let entry: Option<Entry> = task
@@ -100,12 +170,28 @@ impl TaskResponder {
             executor,
             workflow,
             task_id: task.task_id,
+            trace_id: trace_id.clone(),
             stats,
             batchable,
+            judge: Self::judge_input(node, compute_message),
+            constraints: task.input.constraints,
+        };
+
+        // keep the raw input only if it is going to be archived
+        let input = match node.archive {
+            Some(_) => compute_message
+                .parse_payload::<TaskRequestPayload<serde_json::Value>>()
+                .map(|task| task.input)
+                .ok(),
+            None => None,
         };
 
         let task_metadata = TaskWorkerMetadata {
+            peer_id,
             model_name,
+            trace_id,
+            deadline: task.deadline,
+            input,
             public_key: task_public_key,
             channel,
         };
@@ -113,23 +199,112 @@ impl TaskResponder {
         Ok((task_input, task_metadata))
     }
 
+    /// Responds with an error to a task that is rejected before it is prepared, e.g. when it is rate limited.
+    pub(crate) async fn respond_rejected(
+        node: &mut DriaComputeNode,
+        peer_id: PeerId,
+        compute_message: &DriaMessage,
+        error: TaskError,
+        channel: RequestChannel,
+    ) -> Result<()> {
+        let task = compute_message
+            .parse_payload::<TaskRequestPayload<serde_json::Value>>()
+            .wrap_err("could not parse task")?;
+        let trace_id = compute_message.get_trace_id();
+        log::warn!(
+            "Task {} (trace {}) is rejected: {}",
+            task.task_id,
+            trace_id,
+            error
+        );
+
+        let error_payload = TaskErrorPayload {
+            task_id: task.task_id,
+            error: error.to_string(),
+            model: String::new(),
+            stats: TaskStats::new().record_received_at(),
+            retry_after_secs: error.retry_after_secs(),
+            error_class: Some(error.class()),
+        };
+        let response = node
+            .new_message(serde_json::json!(error_payload).to_string(), "response")
+            .with_trace_id(trace_id);
+        node.p2p
+            .respond(peer_id, response.to_bytes()?, channel)
+            .await?;
+
+        Ok(())
+    }
+
     /// Handles the result of a workflow task.
     pub(crate) async fn handle_respond(
         node: &mut DriaComputeNode,
         task_output: TaskWorkerOutput,
         task_metadata: TaskWorkerMetadata,
     ) -> Result<()> {
+        let stats = task_output.stats.record_published_at();
+
+        // store a copy of the task in the local archive, if enabled
+        if let (Some(archive), Some(input)) = (&node.archive, task_metadata.input) {
+            let (result, error) = match &task_output.result {
+                Ok(result) => (Some(result.clone()), None),
+                Err(err) => (None, Some(format!("{:#}", err))),
+            };
+            let dump = TaskDump {
+                task_id: task_output.task_id.clone(),
+                input,
+                model: task_metadata.model_name.clone(),
+                result,
+                error,
+                stats: Some(stats.clone()),
+            };
+
+            // the encryption & the disk writes are done aside, so that they do not block the node
+            let (archive, public_key) = (archive.clone(), node.config.public_key);
+            let trace_id = task_metadata.trace_id.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = archive.store(&dump, &public_key) {
+                    log::warn!(
+                        "Could not archive task {} (trace {}): {:?}",
+                        dump.task_id,
+                        trace_id,
+                        e
+                    );
+                }
+            });
+        }
+
+        // record the statistics of the task, if enabled
+        if let Some(stats_db) = &node.stats_db {
+            let error = task_output
+                .result
+                .as_ref()
+                .err()
+                .map(|e| format!("{:#}", e));
+            stats_db.record(
+                task_output.task_id.clone(),
+                task_metadata.model_name.clone(),
+                error,
+                stats.clone(),
+            );
+        }
+
         let response = match task_output.result {
             Ok(result) => {
                 // prepare signed and encrypted payload
-                log::info!("Publishing result for task {}", task_output.task_id);
+                log::info!(
+                    "Publishing result for task {} (trace {})",
+                    task_output.task_id,
+                    task_metadata.trace_id
+                );
                 let payload = TaskResponsePayload::new(
                     result,
                     &task_output.task_id,
                     &task_metadata.public_key,
                     task_metadata.model_name,
-                    task_output.stats.record_published_at(),
-                )?;
+                    stats,
+                )?
+                .with_judgement(task_output.judgement);
 
                 // convert payload to message
                 let payload_str = serde_json::json!(payload).to_string();
@@ -139,14 +314,22 @@ impl TaskResponder {
             Err(err) => {
                 // use pretty display string for error logging with causes
                 let err_string = format!("{:#}", err);
-                log::error!("Task {} failed: {}", task_output.task_id, err_string);
+                log::error!(
+                    "Task {} (trace {}) failed ({:?}): {}",
+                    task_output.task_id,
+                    task_metadata.trace_id,
+                    task_output.error_class,
+                    err_string
+                );
 
                 // prepare error payload
                 let error_payload = TaskErrorPayload {
                     task_id: task_output.task_id,
                     error: err_string,
                     model: task_metadata.model_name,
-                    stats: task_output.stats.record_published_at(),
+                    stats,
+                    retry_after_secs: None,
+                    error_class: task_output.error_class,
                 };
                 let error_payload_str = serde_json::json!(error_payload).to_string();
 
@@ -154,9 +337,11 @@ impl TaskResponder {
             }
         };
 
-        // respond through the channel
-        let data = response.to_bytes()?;
-        node.p2p.respond(data, task_metadata.channel).await?;
+        // respond through the channel, with the trace id of the request
+        let data = response.with_trace_id(task_metadata.trace_id).to_bytes()?;
+        node.p2p
+            .respond(task_metadata.peer_id, data, task_metadata.channel)
+            .await?;
 
         Ok(())
     }
@@ -0,0 +1,302 @@
+use dkn_utils::{
+    get_current_time_nanos,
+    storage::{compress, decompress, is_compressed, Storage},
+};
+use eyre::{eyre, Context, Result};
+use libsecp256k1::{PublicKey, SecretKey};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::payloads::TaskDump;
+
+/// Storage namespace for archived tasks.
+const ARCHIVE_NAMESPACE: &str = "archive";
+/// Number of bytes for the archival time, prefixed to each stored task.
+const ARCHIVED_AT_LEN: usize = 16;
+/// Percentage of the size cap that the archive is pruned down to, so that it is not pruned on every store.
+const PRUNE_TARGET_PERCENT: u64 = 90;
+
+/// A local archive of completed tasks, each stored as a task dump under its task id.
+///
+/// Tasks are encrypted with the public key of the node, so only the owner of the
+/// node's secret key can read them; optionally, they are compressed before the encryption.
+/// The archive is size-capped, oldest tasks are
+/// removed first when the cap is exceeded. The total size is kept as tasks are stored, so that
+/// the archive is only read as a whole when it has to be pruned. Optionally, tasks older than a retention
+/// period are removed with `purge_expired`.
+#[derive(Debug, Clone)]
+pub struct TaskArchive {
+    /// Storage of the archive.
+    storage: Arc<dyn Storage>,
+    /// Maximum total size of the archive in bytes.
+    max_bytes: u64,
+    /// Maximum age of an archived task, if any.
+    retention: Option<Duration>,
+    /// Zstd compression level of the archived tasks, if they are compressed.
+    compression: Option<i32>,
+    /// Total size of the archive in bytes, read from the storage on the first store.
+    total_bytes: Arc<Mutex<Option<u64>>>,
+}
+
+impl TaskArchive {
+    /// Creates the archive within the given storage.
+    pub fn new(storage: Arc<dyn Storage>, max_bytes: u64) -> Self {
+        Self {
+            storage,
+            max_bytes,
+            retention: None,
+            compression: None,
+            total_bytes: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sets the maximum age of archived tasks.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Sets the compression level of the archived tasks, which are compressed before the encryption.
+    ///
+    /// Tasks that were archived without compression can still be loaded.
+    pub fn with_compression(mut self, level: i32) -> Self {
+        self.compression = Some(level);
+        self
+    }
+
+    /// Encrypts & stores the given task, then prunes the archive w.r.t the size cap.
+    ///
+    /// The stored value is the archival time in nanoseconds (big-endian) followed by the ciphertext.
+    pub fn store(&self, dump: &TaskDump, public_key: &PublicKey) -> Result<()> {
+        let mut data = serde_json::to_vec(dump)?;
+        if let Some(level) = self.compression {
+            data = compress(&data, level)?;
+        }
+        let ciphertext = ecies::encrypt(&public_key.serialize(), &data)
+            .map_err(|e| eyre!("could not encrypt task: {}", e))?;
+
+        let mut value = get_current_time_nanos().to_be_bytes().to_vec();
+        value.extend(ciphertext);
+
+        let mut total_bytes = self.total_bytes.lock().expect("archive lock is poisoned");
+        let mut total = match *total_bytes {
+            Some(total) => total,
+            None => self.entries()?.iter().map(|(_, _, len)| len).sum(),
+        };
+        // a task that is archived again replaces its previous value
+        if let Some(previous) = self
+            .storage
+            .get(ARCHIVE_NAMESPACE, &dump.task_id)
+            .wrap_err("could not read archived task")?
+        {
+            total = total.saturating_sub(previous.len() as u64);
+        }
+        total += value.len() as u64;
+        self.storage
+            .put(ARCHIVE_NAMESPACE, &dump.task_id, value)
+            .wrap_err("could not write archived task")?;
+
+        if total > self.max_bytes {
+            total = self.prune()?;
+        }
+        *total_bytes = Some(total);
+
+        Ok(())
+    }
+
+    /// Reads & decrypts the task with the given id.
+    pub fn load(&self, task_id: &str, secret_key: &SecretKey) -> Result<TaskDump> {
+        let value = self
+            .storage
+            .get(ARCHIVE_NAMESPACE, task_id)
+            .wrap_err("could not read archived task")?
+            .ok_or_else(|| eyre!("task {} is not archived", task_id))?;
+        let (_, ciphertext) = split_archived(&value)?;
+        let mut data = ecies::decrypt(&secret_key.serialize(), ciphertext)
+            .map_err(|e| eyre!("could not decrypt task: {}", e))?;
+        if is_compressed(&data) {
+            data = decompress(&data)?;
+        }
+        serde_json::from_slice(&data).wrap_err("could not parse archived task")
+    }
+
+    /// Returns the archived task ids along with their archival times, oldest first.
+    pub fn list(&self) -> Result<Vec<(String, SystemTime)>> {
+        let mut entries = self
+            .entries()?
+            .into_iter()
+            .map(|(task_id, archived_at, _)| (task_id, archived_at))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, archived_at)| *archived_at);
+
+        Ok(entries)
+    }
+
+    /// Removes the archived tasks older than the retention period, returns the number of removed tasks.
+    ///
+    /// Does nothing if there is no retention period.
+    pub fn purge_expired(&self) -> Result<usize> {
+        let Some(retention) = self.retention else {
+            return Ok(0);
+        };
+
+        let mut total_bytes = self.total_bytes.lock().expect("archive lock is poisoned");
+        let now = SystemTime::now();
+        let mut count = 0;
+        for (task_id, archived_at, len) in self.entries()? {
+            if now.duration_since(archived_at).unwrap_or_default() > retention {
+                self.remove(&task_id)?;
+                if let Some(total) = total_bytes.as_mut() {
+                    *total = total.saturating_sub(len);
+                }
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Removes all archived tasks, returns the number of removed tasks.
+    pub fn purge_all(&self) -> Result<usize> {
+        let mut total_bytes = self.total_bytes.lock().expect("archive lock is poisoned");
+        let entries = self.entries()?;
+        for (task_id, _, _) in &entries {
+            self.remove(task_id)?;
+        }
+        *total_bytes = Some(0);
+
+        Ok(entries.len())
+    }
+
+    /// Removes the oldest tasks until the archive fits within a portion of the size cap,
+    /// returns the total size of the archive afterwards.
+    fn prune(&self) -> Result<u64> {
+        let mut entries = self.entries()?;
+        let mut total_bytes = entries.iter().map(|(_, _, len)| len).sum::<u64>();
+        if total_bytes <= self.max_bytes {
+            return Ok(total_bytes);
+        }
+
+        let target_bytes = self.max_bytes / 100 * PRUNE_TARGET_PERCENT;
+        entries.sort_by_key(|(_, archived_at, _)| *archived_at);
+        for (task_id, _, len) in entries {
+            if total_bytes <= target_bytes {
+                break;
+            }
+            self.remove(&task_id)?;
+            total_bytes -= len;
+        }
+
+        Ok(total_bytes)
+    }
+
+    /// Removes an archived task.
+    #[inline]
+    fn remove(&self, task_id: &str) -> Result<()> {
+        self.storage
+            .remove(ARCHIVE_NAMESPACE, task_id)
+            .wrap_err("could not remove archived task")?;
+        Ok(())
+    }
+
+    /// Returns the task id, archival time and size of each archived task.
+    fn entries(&self) -> Result<Vec<(String, SystemTime, u64)>> {
+        let mut entries = Vec::new();
+        for entry in self.storage.iter(ARCHIVE_NAMESPACE) {
+            let (task_id, value) = entry?;
+            let (archived_at, _) = split_archived(&value)?;
+            entries.push((task_id, archived_at, value.len() as u64));
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Splits a stored value into its archival time and ciphertext.
+fn split_archived(value: &[u8]) -> Result<(SystemTime, &[u8])> {
+    if value.len() < ARCHIVED_AT_LEN {
+        return Err(eyre!("archived task is too short"));
+    }
+    let (archived_at, ciphertext) = value.split_at(ARCHIVED_AT_LEN);
+    let nanos = u128::from_be_bytes(archived_at.try_into()?);
+    let archived_at = UNIX_EPOCH + Duration::from_nanos(nanos as u64);
+
+    Ok((archived_at, ciphertext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::storage::MemoryStorage;
+
+    #[test]
+    fn test_archive_store_load_prune() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let public_key = PublicKey::from_secret_key(&secret_key);
+
+        let dump = |task_id: &str| TaskDump {
+            task_id: task_id.to_string(),
+            input: serde_json::json!({ "prompt": "hi" }),
+            model: "gpt-4o-mini".to_string(),
+            result: Some("hello".repeat(100)),
+            error: None,
+            stats: None,
+        };
+
+        // a single task is ~600 bytes, so only one fits
+        let archive = TaskArchive::new(Arc::new(MemoryStorage::default()), 1000);
+        archive.store(&dump("task-1"), &public_key).unwrap();
+        let loaded = archive.load("task-1", &secret_key).unwrap();
+        assert_eq!(loaded.result, dump("task-1").result);
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        archive.store(&dump("task-2"), &public_key).unwrap();
+        let ids = archive
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["task-2".to_string()]);
+        let stored_bytes = archive.entries().unwrap()[0].2;
+        assert_eq!(*archive.total_bytes.lock().unwrap(), Some(stored_bytes));
+
+        // nothing is expired without a retention, everything is expired with a zero retention
+        assert_eq!(archive.purge_expired().unwrap(), 0);
+        let archive = archive.with_retention(Duration::ZERO);
+        assert_eq!(archive.purge_expired().unwrap(), 1);
+        assert!(archive.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_archive_compression() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let public_key = PublicKey::from_secret_key(&secret_key);
+        let dump = TaskDump {
+            task_id: "task-1".to_string(),
+            input: serde_json::json!({ "prompt": "hi" }),
+            model: "gpt-4o-mini".to_string(),
+            result: Some("hello".repeat(100)),
+            error: None,
+            stats: None,
+        };
+
+        // tasks archived before the compression was enabled are still readable
+        let storage = Arc::new(MemoryStorage::default());
+        let archive = TaskArchive::new(storage.clone(), 1024 * 1024);
+        archive.store(&dump, &public_key).unwrap();
+        let archive = TaskArchive::new(storage, 1024 * 1024).with_compression(3);
+        assert_eq!(
+            archive.load("task-1", &secret_key).unwrap().result,
+            dump.result
+        );
+
+        archive.store(&dump, &public_key).unwrap();
+        assert_eq!(
+            archive.load("task-1", &secret_key).unwrap().result,
+            dump.result
+        );
+    }
+}
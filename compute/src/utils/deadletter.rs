@@ -0,0 +1,109 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use dkn_utils::get_current_time_nanos;
+use serde::{Serialize, Serializer};
+use std::collections::VecDeque;
+
+/// Maximum number of bytes kept from the raw data of a dead letter.
+const MAX_DEAD_LETTER_BYTES: usize = 16 * 1024;
+
+/// An unprocessable message, kept for diagnosing protocol mismatches.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    /// Where the message was received from, e.g. `gossipsub/ping` or `request`.
+    pub source: String,
+    /// Peer that has sent the message.
+    pub peer_id: String,
+    /// Error while processing the message.
+    pub error: String,
+    /// Raw data of the message, truncated if too large; serialized as `base64`.
+    #[serde(serialize_with = "serialize_base64")]
+    pub data: Vec<u8>,
+    /// Time of receipt, in nanoseconds.
+    pub received_at: u128,
+}
+
+/// A bounded buffer of dead letters, oldest ones are dropped first.
+#[derive(Debug, Clone)]
+pub struct DeadLetterQueue {
+    letters: VecDeque<DeadLetter>,
+    capacity: usize,
+}
+
+impl DeadLetterQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            letters: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds a dead letter, dropping the oldest one if the buffer is full.
+    pub fn push(
+        &mut self,
+        source: impl ToString,
+        peer_id: impl ToString,
+        error: impl ToString,
+        data: &[u8],
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.letters.len() == self.capacity {
+            self.letters.pop_front();
+        }
+
+        self.letters.push_back(DeadLetter {
+            source: source.to_string(),
+            peer_id: peer_id.to_string(),
+            error: error.to_string(),
+            data: data[..data.len().min(MAX_DEAD_LETTER_BYTES)].to_vec(),
+            received_at: get_current_time_nanos(),
+        });
+    }
+
+    /// Returns the dead letters, oldest first.
+    #[inline]
+    pub fn letters(&self) -> impl Iterator<Item = &DeadLetter> {
+        self.letters.iter()
+    }
+
+    /// Returns the number of dead letters.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    /// Returns `true` if there are no dead letters.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+}
+
+fn serialize_base64<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&BASE64_STANDARD.encode(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_letter_queue() {
+        let mut queue = DeadLetterQueue::new(2);
+        queue.push("request", "peer", "could not parse", b"first");
+        queue.push("request", "peer", "could not parse", b"second");
+        queue.push("gossipsub/ping", "peer", "could not parse", b"third");
+
+        // oldest one is dropped
+        assert_eq!(queue.len(), 2);
+        let data = queue.letters().map(|l| l.data.clone()).collect::<Vec<_>>();
+        assert_eq!(data, vec![b"second".to_vec(), b"third".to_vec()]);
+
+        // data is base64 encoded
+        let letter = serde_json::to_value(queue.letters().last().unwrap()).unwrap();
+        assert_eq!(letter["data"], BASE64_STANDARD.encode(b"third"));
+        assert_eq!(letter["source"], "gossipsub/ping");
+    }
+}
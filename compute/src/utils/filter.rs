@@ -7,6 +7,8 @@ use serde_json::{json, to_string};
 ///
 /// The filter is a Bloom Filter with a set of items and a false positive rate, it is serialized as a hex string.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct TaskFilter {
     pub(crate) hex: String,
     pub(crate) hashes: u32,
@@ -40,10 +42,16 @@ impl TryFrom<String> for TaskFilter {
 }
 
 impl TryFrom<&TaskFilter> for BloomFilter {
-    type Error = hex::FromHexError;
+    type Error = eyre::Report;
 
     fn try_from(value: &TaskFilter) -> Result<Self, Self::Error> {
         let filter = hex::decode(value.hex.as_str())?;
+
+        // the filter library panics for empty filters, so we check them here
+        if filter.is_empty() || value.hashes == 0 {
+            return Err(eyre::eyre!("filter is empty"));
+        }
+
         Ok(BloomFilter::from_u8_array(&filter, value.hashes))
     }
 }
@@ -99,13 +107,25 @@ mod tests {
     }
 
     #[test]
-    #[ignore = "this panics, its a bug within the filter library"]
     fn test_filter_empty() {
+        // this panics within the filter library, so it must be rejected beforehand
         let filter_payload = TaskFilter {
             hex: "".to_string(),
             hashes: 0,
         };
 
-        BloomFilter::try_from(&filter_payload).expect("Should parse filter");
+        assert!(BloomFilter::try_from(&filter_payload).is_err());
+        assert!(filter_payload.contains(b"helloworld").is_err());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_filter_never_panics(bytes: Vec<u8>, hashes in 0u32..16) {
+            let filter_payload = TaskFilter {
+                hex: hex::encode(bytes),
+                hashes,
+            };
+            let _ = filter_payload.contains(b"helloworld");
+        }
     }
 }
@@ -31,6 +31,10 @@ pub struct DriaMessage {
     pub signature: String,
     /// The recovery id for the signature.
     pub recovery_id: u8,
+    /// Identifier that correlates the logs of a task across the RPC & the nodes,
+    /// it is carried from a request to its response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
 }
 
 impl DriaMessage {
@@ -40,7 +44,7 @@ impl DriaMessage {
     /// - `topic` is the name of the [gossipsub topic](https://docs.libp2p.io/concepts/pubsub/overview/).
     /// - `protocol` is the protocol name, e.g. `dria`.
     /// - `signing_key` is the secret key to sign the message.
-    pub(crate) fn new(
+    pub fn new(
         data: impl AsRef<[u8]>,
         topic: impl ToString,
         protocol: &DriaP2PProtocol,
@@ -61,9 +65,23 @@ impl DriaMessage {
             version: DRIA_COMPUTE_NODE_VERSION.to_string(),
             signature: hex::encode(signature.serialize()),
             recovery_id: recovery_id.serialize(),
+            trace_id: None,
         }
     }
 
+    /// Attaches the trace id of the request that this message responds to.
+    pub fn with_trace_id(mut self, trace_id: impl ToString) -> Self {
+        self.trace_id = Some(trace_id.to_string());
+        self
+    }
+
+    /// Returns the trace id of the message, or a new one if the sender did not give any.
+    pub fn get_trace_id(&self) -> String {
+        self.trace_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    }
+
     /// Decodes the base64 payload into bytes.
     #[inline(always)]
     pub(crate) fn decode_payload(&self) -> Result<Vec<u8>, base64::DecodeError> {
@@ -77,7 +95,7 @@ impl DriaMessage {
     }
 
     /// Checks if the payload is signed by the owner of one of the given peer ids.
-    pub(crate) fn is_signed(&self, authorized_peerids: &HashSet<PeerId>) -> Result<bool> {
+    pub fn is_signed(&self, authorized_peerids: &HashSet<PeerId>) -> Result<bool> {
         let recovered_public_key = self.get_origin()?;
         let recovered_peer_id = public_key_to_peer_id(&recovered_public_key);
 
@@ -85,7 +103,7 @@ impl DriaMessage {
     }
 
     /// Recovers the public key of the message origin, i.e. the owner of the signature.
-    pub(crate) fn get_origin(&self) -> Result<libsecp256k1::PublicKey> {
+    pub fn get_origin(&self) -> Result<libsecp256k1::PublicKey> {
         let signature_bytes =
             hex::decode(&self.signature).wrap_err("could not decode signature hex")?;
         let signature = Signature::parse_standard_slice(&signature_bytes)
@@ -192,4 +210,182 @@ mod tests {
         let parsed_body = message.parse_payload().expect("Should decode");
         assert_eq!(body, parsed_body);
     }
+
+    #[test]
+    fn test_message_trace_id() {
+        let sk = SecretKey::random(&mut thread_rng());
+        let protocol = DriaP2PProtocol::new_major_minor("test");
+
+        // messages without a trace id are serialized as before
+        let message = DriaMessage::new("{}", TOPIC, &protocol, &sk);
+        assert!(!String::from_utf8(message.to_bytes().unwrap())
+            .unwrap()
+            .contains("trace_id"));
+        assert_ne!(message.get_trace_id(), message.get_trace_id());
+
+        let message = message.with_trace_id("trace-1");
+        let parsed: DriaMessage = serde_json::from_slice(&message.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.get_trace_id(), "trace-1");
+    }
+
+    /// Signed message test vectors, shared with other implementations.
+    const MESSAGE_VECTORS_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../utils/vectors/messages.json"
+    );
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct MessageVector {
+        /// What this vector is about.
+        description: String,
+        /// Secret key of the signer, in hexadecimals.
+        secret_key: String,
+        /// Compressed public key of the signer, in hexadecimals.
+        public_key: String,
+        /// Ethereum address of the signer, in hexadecimals.
+        address: String,
+        /// Peer id of the signer.
+        peer_id: String,
+        /// Data within the payload, before it is encoded.
+        data: String,
+        /// The signed message, as it is sent over the network.
+        message: DriaMessage,
+    }
+
+    /// Returns the test vectors as created by this implementation.
+    fn create_message_vectors() -> Vec<MessageVector> {
+        const CASES: [(&str, &str, &str, &str); 5] = [
+            (
+                "ping from an RPC",
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                "ping",
+                r#"{"uuid":"59b93cb2-5738-4da4-992d-89a1835738d6","deadline":1700000060000000000}"#,
+            ),
+            (
+                "pong from a node",
+                "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                "pong",
+                r#"{"uuid":"59b93cb2-5738-4da4-992d-89a1835738d6","models":[["openai","gpt-4o"]],"pending_tasks":[0,1]}"#,
+            ),
+            (
+                "goodbye from a node",
+                "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+                "goodbye",
+                r#"{"reason":"shutdown","pendingTasks":[{"taskId":"task-1","model":"gpt-4o","batchable":true}]}"#,
+            ),
+            (
+                "empty data",
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                "ping",
+                "",
+            ),
+            (
+                "non-ascii data",
+                "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a",
+                "pong",
+                "Dria ✓ 🚀",
+            ),
+        ];
+
+        CASES
+            .into_iter()
+            .map(|(description, secret_key, topic, data)| {
+                let sk = SecretKey::parse_slice(&hex::decode(secret_key).unwrap()).unwrap();
+                let pk = PublicKey::from_secret_key(&sk);
+                let mut message =
+                    DriaMessage::new(data, topic, &DriaP2PProtocol::new("dria", "0.3"), &sk);
+
+                // these are not signed, so they are fixed for the vectors to stay the same across versions
+                message.timestamp = 1700000000000000000;
+                message.version = "0.3.5".to_string();
+
+                MessageVector {
+                    description: description.to_string(),
+                    secret_key: secret_key.to_string(),
+                    public_key: hex::encode(pk.serialize_compressed()),
+                    address: hex::encode(crate::utils::crypto::public_key_to_address(&pk)),
+                    peer_id: public_key_to_peer_id(&pk).to_string(),
+                    data: data.to_string(),
+                    message,
+                }
+            })
+            .collect()
+    }
+
+    /// Checks that the messages within the vectors are parsed, recovered & signed the same.
+    #[test]
+    fn test_message_vectors() {
+        let vectors: Vec<MessageVector> =
+            serde_json::from_str(&std::fs::read_to_string(MESSAGE_VECTORS_PATH).unwrap()).unwrap();
+
+        let expected_vectors = create_message_vectors();
+        assert_eq!(
+            vectors.len(),
+            expected_vectors.len(),
+            "vectors are outdated, regenerate them"
+        );
+
+        for (vector, expected) in vectors.iter().zip(expected_vectors) {
+            let message = &vector.message;
+            assert_eq!(message.decode_payload().unwrap(), vector.data.as_bytes());
+            assert_eq!(
+                hex::encode(message.get_origin().unwrap().serialize_compressed()),
+                vector.public_key,
+                "{}",
+                vector.description
+            );
+            assert_eq!(
+                serde_json::to_value(vector).unwrap(),
+                serde_json::to_value(expected).unwrap(),
+                "{} is outdated",
+                vector.description
+            );
+        }
+    }
+
+    /// Writes the message vectors, to be run after the message format is changed on purpose.
+    ///
+    /// ## Run command
+    ///
+    /// ```sh
+    /// cargo test --package dkn-compute --lib -- generate_message_vectors --ignored
+    /// ```
+    #[test]
+    #[ignore = "run this manually"]
+    fn generate_message_vectors() {
+        let mut data = serde_json::to_string_pretty(&create_message_vectors()).unwrap();
+        data.push('\n');
+        std::fs::write(MESSAGE_VECTORS_PATH, data).unwrap();
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_message_parse_never_panics(data: Vec<u8>) {
+            let _ = serde_json::from_slice::<DriaMessage>(&data);
+        }
+
+        #[test]
+        fn test_malformed_message_never_panics(
+            payload: String,
+            signature in "[0-9a-f]{0,140}",
+            recovery_id: u8,
+        ) {
+            let message = DriaMessage {
+                payload,
+                topic: TOPIC.to_string(),
+                version: DRIA_COMPUTE_NODE_VERSION.to_string(),
+                protocol: "dria".to_string(),
+                timestamp: 0,
+                signature,
+                recovery_id,
+                trace_id: None,
+            };
+
+            // these must return errors instead of panicking
+            let _ = message.parse_payload::<TestStruct>();
+            let _ = message.is_signed(&HashSet::new());
+            let _ = message.to_string();
+        }
+    }
 }
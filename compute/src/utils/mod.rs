@@ -1,11 +1,55 @@
 pub mod crypto;
+
+mod archive;
+pub use archive::TaskArchive;
+
+mod deadletter;
+pub use deadletter::{DeadLetter, DeadLetterQueue};
+
 pub mod filter;
 
 mod message;
 pub use message::DriaMessage;
 
+mod models;
+pub use models::*;
+
 mod nodes;
 pub use nodes::*;
 
+mod profile;
+pub use profile::NodeProfile;
+
+mod ratelimit;
+pub use dkn_utils::RateLimiter;
+pub use ratelimit::PeerRateLimiter;
+
+mod redact;
+pub use redact::Redactor;
+
+mod registration;
+pub use registration::fetch_registration;
+
+mod relay;
+pub use relay::RelayStats;
+
+mod reputation;
+pub use reputation::{RpcReputation, RpcReputations};
+
+mod rpc;
+pub use rpc::select_rpc;
+
+mod scheduler;
+pub use scheduler::Scheduler;
+
+mod session;
+pub use session::RpcSession;
+
 mod specs;
 pub use specs::*;
+
+mod statsdb;
+pub use statsdb::StatsDb;
+
+mod supervisor;
+pub use supervisor::*;
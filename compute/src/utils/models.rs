@@ -0,0 +1,46 @@
+use eyre::Result;
+
+/// Fetches the names of the models that are currently rewarded by the network from the given URL,
+/// which is expected to respond with a JSON array of model names.
+pub async fn fetch_rewarded_models(client: &reqwest::Client, url: &str) -> Result<Vec<String>> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    let models = response.json::<Vec<String>>().await?;
+
+    Ok(models)
+}
+
+/// Compares the configured models to the rewarded ones.
+///
+/// Returns the configured models that are not rewarded, and the rewarded models that are not configured.
+pub fn diff_models(configured: &[String], rewarded: &[String]) -> (Vec<String>, Vec<String>) {
+    let unrewarded = configured
+        .iter()
+        .filter(|m| !rewarded.contains(m))
+        .cloned()
+        .collect();
+    let missing = rewarded
+        .iter()
+        .filter(|m| !configured.contains(m))
+        .cloned()
+        .collect();
+
+    (unrewarded, missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_models() {
+        let configured = ["gpt-4o".to_string(), "phi3:3.8b".to_string()];
+        let rewarded = ["gpt-4o".to_string(), "llama3.1:latest".to_string()];
+
+        let (unrewarded, missing) = diff_models(&configured, &rewarded);
+        assert_eq!(unrewarded, vec!["phi3:3.8b".to_string()]);
+        assert_eq!(missing, vec!["llama3.1:latest".to_string()]);
+
+        let (unrewarded, missing) = diff_models(&configured, &configured);
+        assert!(unrewarded.is_empty() && missing.is_empty());
+    }
+}
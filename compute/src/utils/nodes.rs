@@ -1,9 +1,16 @@
-use dkn_p2p::{libp2p::PeerId, DriaNetworkType, DriaNodes};
-use dkn_utils::parse_vec;
-use eyre::Result;
+use dkn_p2p::{
+    libp2p::{Multiaddr, PeerId},
+    DriaNetworkType, DriaNodes,
+};
+use dkn_utils::{parse_vec, payloads::NodesUpdatePayload};
+use eyre::{eyre, Result};
+use std::{collections::HashSet, time::Duration};
+
+/// Number of seconds to wait for the available nodes from the API.
+const AVAILABLE_NODES_TIMEOUT_SECS: u64 = 10;
 
 /// Refresh available nodes using the API.
-pub async fn refresh_dria_nodes(nodes: &mut DriaNodes) -> Result<()> {
+pub async fn refresh_dria_nodes(client: &reqwest::Client, nodes: &mut DriaNodes) -> Result<()> {
     #[derive(serde::Deserialize, Debug)]
     struct DriaNodesApiResponse {
         pub bootstraps: Vec<String>,
@@ -21,7 +28,11 @@ pub async fn refresh_dria_nodes(nodes: &mut DriaNodes) -> Result<()> {
     };
 
     // make the request
-    let response = reqwest::get(url).await?;
+    let response = client
+        .get(url)
+        .timeout(Duration::from_secs(AVAILABLE_NODES_TIMEOUT_SECS))
+        .send()
+        .await?;
     let response_body = response.json::<DriaNodesApiResponse>().await?;
     nodes
         .bootstrap_nodes
@@ -51,21 +62,133 @@ pub async fn refresh_dria_nodes(nodes: &mut DriaNodes) -> Result<()> {
     Ok(())
 }
 
+/// RPC nodes that were removed by a nodes update replacing them, which the refreshes must not add back
+/// while the API still lists them, e.g. until it catches up with an RPC rotation.
+#[derive(Debug, Default)]
+pub struct RemovedRpcNodes {
+    peer_ids: HashSet<PeerId>,
+    addrs: HashSet<Multiaddr>,
+}
+
+impl RemovedRpcNodes {
+    /// Removes the RPC nodes that a refresh has added back, and forgets the ones that the API no longer lists.
+    pub fn retain_removed(&mut self, nodes: &mut DriaNodes) {
+        self.peer_ids
+            .retain(|peer_id| nodes.rpc_peerids.remove(peer_id));
+        self.addrs.retain(|addr| nodes.rpc_nodes.remove(addr));
+    }
+}
+
+/// Applies an update pushed by an RPC node to the available nodes.
+///
+/// The RPC nodes removed by a replacing update are kept within `removed`, while the ones
+/// given by any update are no longer considered removed. The static RPC nodes, e.g. the ones
+/// given with `DKN_RPC_NODES`, are never removed.
+///
+/// Unlike the API response, an update with an unparsable address is rejected as a whole.
+pub fn apply_nodes_update(
+    nodes: &mut DriaNodes,
+    static_nodes: &DriaNodes,
+    removed: &mut RemovedRpcNodes,
+    update: NodesUpdatePayload,
+) -> Result<()> {
+    let bootstraps = parse_vec::<Multiaddr>(update.bootstraps)?;
+    let relays = parse_vec::<Multiaddr>(update.relays)?;
+    let rpc_addrs = parse_vec::<Multiaddr>(update.rpc_addrs)?;
+    let rpcs = parse_vec::<PeerId>(update.rpcs)?;
+
+    if update.replace {
+        // otherwise, the node would not accept anything until the next refresh
+        if rpcs.is_empty() {
+            return Err(eyre!("Nodes update can not remove all RPC nodes."));
+        }
+        let (kept, dropped) = std::mem::take(&mut nodes.rpc_nodes)
+            .into_iter()
+            .partition(|addr| static_nodes.rpc_nodes.contains(addr));
+        nodes.rpc_nodes = kept;
+        removed.addrs.extend::<HashSet<_>>(dropped);
+        let (kept, dropped) = std::mem::take(&mut nodes.rpc_peerids)
+            .into_iter()
+            .partition(|peer_id| static_nodes.rpc_peerids.contains(peer_id));
+        nodes.rpc_peerids = kept;
+        removed.peer_ids.extend::<HashSet<_>>(dropped);
+    }
+    for addr in &rpc_addrs {
+        removed.addrs.remove(addr);
+    }
+    for peer_id in &rpcs {
+        removed.peer_ids.remove(peer_id);
+    }
+
+    nodes.bootstrap_nodes.extend(bootstraps);
+    nodes.relay_nodes.extend(relays);
+    nodes.rpc_nodes.extend(rpc_addrs);
+    nodes.rpc_peerids.extend(rpcs);
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_apply_nodes_update() {
+        let old_rpc = PeerId::random();
+        let new_rpc = PeerId::random();
+        let static_rpc = PeerId::random();
+        let statics = DriaNodes::new(DriaNetworkType::Community).with_rpc_peer_ids([static_rpc]);
+        let mut nodes = statics.clone().with_rpc_peer_ids([old_rpc]);
+        let mut removed = RemovedRpcNodes::default();
+
+        let mut update = NodesUpdatePayload {
+            rpcs: vec![new_rpc.to_string()],
+            rpc_addrs: vec![format!("/ip4/127.0.0.1/tcp/4001/p2p/{}", new_rpc)],
+            ..Default::default()
+        };
+        apply_nodes_update(&mut nodes, &statics, &mut removed, update.clone()).unwrap();
+        assert_eq!(nodes.rpc_peerids.len(), 3);
+
+        // replacing removes the old RPC, but keeps the static one
+        update.replace = true;
+        apply_nodes_update(&mut nodes, &statics, &mut removed, update.clone()).unwrap();
+        assert_eq!(nodes.rpc_peerids, HashSet::from([new_rpc, static_rpc]));
+        assert_eq!(nodes.rpc_nodes.len(), 1);
+
+        // a refresh that still lists the old RPC does not add it back
+        nodes.rpc_peerids.extend([old_rpc, new_rpc]);
+        removed.retain_removed(&mut nodes);
+        assert_eq!(nodes.rpc_peerids, HashSet::from([new_rpc, static_rpc]));
+
+        // once the API no longer lists it, it can be added back by a later refresh
+        removed.retain_removed(&mut nodes);
+        nodes.rpc_peerids.insert(old_rpc);
+        removed.retain_removed(&mut nodes);
+        assert_eq!(nodes.rpc_peerids.len(), 3);
+        nodes.rpc_peerids.remove(&old_rpc);
+
+        // invalid updates, or ones that remove all RPCs, are not applied at all
+        update.relays = vec!["not-an-address".to_string()];
+        assert!(apply_nodes_update(&mut nodes, &statics, &mut removed, update.clone()).is_err());
+        assert!(nodes.relay_nodes.is_empty());
+        update.relays = vec![];
+        update.rpcs = vec![];
+        assert!(apply_nodes_update(&mut nodes, &statics, &mut removed, update).is_err());
+        assert_eq!(nodes.rpc_peerids.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_refresh_dria_nodes() {
+        let client = reqwest::Client::new();
         let mut nodes = DriaNodes::new(DriaNetworkType::Community);
-        refresh_dria_nodes(&mut nodes).await.unwrap();
+        refresh_dria_nodes(&client, &mut nodes).await.unwrap();
         assert!(!nodes.bootstrap_nodes.is_empty());
         assert!(!nodes.relay_nodes.is_empty());
         assert!(!nodes.rpc_nodes.is_empty());
         assert!(!nodes.rpc_peerids.is_empty());
 
         let mut nodes = DriaNodes::new(DriaNetworkType::Pro);
-        refresh_dria_nodes(&mut nodes).await.unwrap();
+        refresh_dria_nodes(&client, &mut nodes).await.unwrap();
         assert!(!nodes.bootstrap_nodes.is_empty());
         assert!(!nodes.relay_nodes.is_empty());
         assert!(!nodes.rpc_nodes.is_empty());
@@ -0,0 +1,118 @@
+use dkn_p2p::ResourceUsage;
+use std::{str::FromStr, time::Duration};
+
+/// Resource profile of the node, read from `DKN_PROFILE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeProfile {
+    /// For desktops & servers.
+    #[default]
+    Standard,
+    /// For constrained devices such as single-board computers, e.g. a Raspberry Pi serving a tiny model.
+    ///
+    /// The gossip mesh is smaller, periodic jobs run less often, tasks are executed one at a time
+    /// and the channel buffers & HTTP connection pools are kept small.
+    LowPower,
+}
+
+impl NodeProfile {
+    /// Returns the resources of the peer-to-peer client.
+    pub fn p2p_resources(&self) -> ResourceUsage {
+        match self {
+            Self::Standard => ResourceUsage::default(),
+            Self::LowPower => ResourceUsage {
+                mesh_n: 3,
+                heartbeat_interval: Duration::from_secs(20),
+                ping_interval: Duration::from_secs(60),
+                channel_bufsize: self.channel_bufsize(),
+            },
+        }
+    }
+
+    /// Returns the interval of a periodic job, which is doubled for the low-power profile.
+    #[inline]
+    pub fn interval(&self, secs: u64) -> Duration {
+        match self {
+            Self::Standard => Duration::from_secs(secs),
+            Self::LowPower => Duration::from_secs(secs * 2),
+        }
+    }
+
+    /// Returns the number of batchable tasks executed at once, if not configured otherwise.
+    #[inline]
+    pub fn default_batch_size(&self) -> usize {
+        match self {
+            Self::Standard => 5,
+            Self::LowPower => 1,
+        }
+    }
+
+    /// Returns the buffer size of the channels between the node & its workers.
+    #[inline]
+    pub fn channel_bufsize(&self) -> usize {
+        match self {
+            Self::Standard => 1024,
+            Self::LowPower => 64,
+        }
+    }
+
+    /// Returns the HTTP client for the API calls of the node.
+    ///
+    /// The low-power profile keeps at most one idle connection per host, and closes it soon.
+    pub fn http_client(&self) -> reqwest::Client {
+        match self {
+            Self::Standard => reqwest::Client::new(),
+            Self::LowPower => reqwest::Client::builder()
+                .pool_max_idle_per_host(1)
+                .pool_idle_timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_else(|e| {
+                    log::warn!("Could not build HTTP client: {}, using the default.", e);
+                    reqwest::Client::new()
+                }),
+        }
+    }
+}
+
+impl FromStr for NodeProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Self::Standard),
+            "low-power" => Ok(Self::LowPower),
+            _ => Err(format!("unknown profile: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for NodeProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Standard => write!(f, "standard"),
+            Self::LowPower => write!(f, "low-power"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        assert_eq!("low-power".parse(), Ok(NodeProfile::LowPower));
+        assert_eq!("standard".parse(), Ok(NodeProfile::Standard));
+        assert!("turbo".parse::<NodeProfile>().is_err());
+        for profile in [NodeProfile::Standard, NodeProfile::LowPower] {
+            assert_eq!(profile.to_string().parse(), Ok(profile));
+        }
+
+        // low-power uses less of everything
+        let (standard, low_power) = (NodeProfile::Standard, NodeProfile::LowPower);
+        assert_eq!(standard.p2p_resources(), ResourceUsage::default());
+        assert!(low_power.p2p_resources().mesh_n < standard.p2p_resources().mesh_n);
+        assert!(low_power.interval(30) > standard.interval(30));
+        assert_eq!(low_power.default_batch_size(), 1);
+        assert!(low_power.channel_bufsize() < standard.channel_bufsize());
+    }
+}
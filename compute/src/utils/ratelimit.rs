@@ -0,0 +1,96 @@
+use dkn_p2p::libp2p::PeerId;
+use dkn_utils::RateLimiter;
+use std::{collections::HashMap, time::Duration};
+use tokio::time::Instant;
+
+/// Token buckets of each peer, so that a peer flooding its events is limited on its own.
+#[derive(Debug, Clone)]
+pub struct PeerRateLimiter {
+    /// Events allowed per `period` for each peer.
+    capacity: u32,
+    period: Duration,
+    /// Buckets of the recent peers, full buckets are dropped as new peers arrive.
+    buckets: HashMap<PeerId, RateLimiter>,
+    /// Number of events throttled in a row for each peer.
+    throttled: HashMap<PeerId, u64>,
+}
+
+impl PeerRateLimiter {
+    /// Creates a limiter that allows `capacity` events per `period` for each peer.
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        Self {
+            capacity,
+            period,
+            buckets: HashMap::new(),
+            throttled: HashMap::new(),
+        }
+    }
+
+    /// Takes a token of the peer for an event at `now`.
+    ///
+    /// If the peer has no tokens left, returns the number of its events throttled in a row including this one,
+    /// along with the duration after which a token will be available.
+    pub fn try_acquire(&mut self, peer_id: PeerId, now: Instant) -> Result<(), (u64, Duration)> {
+        if !self.buckets.contains_key(&peer_id) {
+            self.buckets.retain(|_, bucket| !bucket.is_full(now));
+            self.throttled.retain(|p, _| self.buckets.contains_key(p));
+        }
+
+        let (capacity, period) = (self.capacity, self.period);
+        let bucket = self
+            .buckets
+            .entry(peer_id)
+            .or_insert_with(|| RateLimiter::new(capacity, period));
+        match bucket.try_acquire(now) {
+            Ok(()) => {
+                self.throttled.remove(&peer_id);
+                Ok(())
+            }
+            Err(retry_after) => {
+                let throttled = self.throttled.entry(peer_id).or_default();
+                *throttled += 1;
+                Err((*throttled, retry_after))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_rate_limiter() {
+        let mut limiter = PeerRateLimiter::new(1, Duration::from_secs(10));
+        let (flooder, other) = (PeerId::random(), PeerId::random());
+        let now = Instant::now();
+
+        // each peer has its own bucket
+        assert!(limiter.try_acquire(flooder, now).is_ok());
+        assert_eq!(
+            limiter.try_acquire(flooder, now),
+            Err((1, Duration::from_secs(10)))
+        );
+        assert_eq!(
+            limiter.try_acquire(flooder, now),
+            Err((2, Duration::from_secs(10)))
+        );
+        assert!(limiter.try_acquire(other, now).is_ok());
+
+        // the throttled count starts over once a token is refilled
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.try_acquire(flooder, later).is_ok());
+        assert_eq!(
+            limiter.try_acquire(flooder, later),
+            Err((1, Duration::from_secs(10)))
+        );
+
+        // full buckets are dropped as new peers arrive, e.g. that of the other peer by now
+        limiter.try_acquire(PeerId::random(), later).unwrap();
+        assert_eq!(limiter.buckets.len(), 2);
+        limiter
+            .try_acquire(PeerId::random(), now + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(limiter.buckets.len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+use dkn_utils::{safe_read_env, split_csv_line};
+use dkn_workflows::vault::read_secret;
+use std::{borrow::Cow, env};
+
+/// Placeholder that replaces the secrets.
+const REDACTED: &str = "[REDACTED]";
+/// Secrets shorter than this are not redacted, as they would match unrelated text.
+const MIN_SECRET_LEN: usize = 8;
+/// Environment variables with secret values, redacted by default.
+const SECRET_ENV_VARS: [&str; 7] = [
+    "DKN_WALLET_SECRET_KEY",
+    "DKN_ADMIN_SECRET",
+    "OPENAI_API_KEY",
+    "GEMINI_API_KEY",
+    "OPENROUTER_API_KEY",
+    "SERPER_API_KEY",
+    "JINA_API_KEY",
+];
+
+/// Replaces known secret values within a text, such as a log record.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    /// Secrets to be redacted, longest first so that overlapping ones are fully covered.
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets = secrets
+            .into_iter()
+            .flat_map(|secret| {
+                // hexadecimals may be logged without the prefix as well
+                let secret = secret.trim().to_string();
+                let unprefixed = secret.strip_prefix("0x").map(str::to_string);
+                std::iter::once(secret).chain(unprefixed)
+            })
+            .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+            .collect::<Vec<_>>();
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets.dedup();
+
+        Self { secrets }
+    }
+
+    /// Creates a redactor for the secret environment variables, along with the ones
+    /// named within `DKN_LOG_REDACT_VARS`.
+    ///
+    /// API keys are read from the keychain as well, if it is enabled.
+    pub fn from_env() -> Self {
+        let extra_vars = safe_read_env(env::var("DKN_LOG_REDACT_VARS"))
+            .map(|vars| split_csv_line(&vars))
+            .unwrap_or_default();
+
+        Self::new(
+            SECRET_ENV_VARS
+                .iter()
+                .map(|var| var.to_string())
+                .chain(extra_vars)
+                .filter_map(|var| read_secret(&var)),
+        )
+    }
+
+    /// Returns the text with the secrets replaced, borrowing it if there are none.
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let redactor = Redactor::new([
+            "0xac0974bec39a17e36ba4a6b4d238ff94".to_string(),
+            "sk-abcdefgh".to_string(),
+            "short".to_string(),
+        ]);
+
+        assert_eq!(
+            redactor.redact("key sk-abcdefgh and 0xac0974bec39a17e36ba4a6b4d238ff94"),
+            "key [REDACTED] and [REDACTED]"
+        );
+        assert_eq!(
+            redactor.redact("secret: ac0974bec39a17e36ba4a6b4d238ff94"),
+            "secret: [REDACTED]"
+        );
+
+        // short secrets are ignored, and texts without secrets are borrowed
+        assert!(matches!(redactor.redact("short"), Cow::Borrowed("short")));
+    }
+}
@@ -0,0 +1,25 @@
+use eyre::Result;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct RegistrationResponse {
+    /// Whether the address is registered & staked, i.e. eligible for rewards.
+    registered: bool,
+}
+
+/// Fetches whether the given address is registered within the network from the given URL,
+/// where `{address}` is replaced with the address.
+///
+/// The address is expected to be in hexadecimals, without the `0x` prefix.
+pub async fn fetch_registration(
+    client: &reqwest::Client,
+    url: &str,
+    address: &str,
+) -> Result<bool> {
+    let url = url.replace("{address}", &format!("0x{}", address));
+
+    let response = client.get(url).send().await?.error_for_status()?;
+    let registration = response.json::<RegistrationResponse>().await?;
+
+    Ok(registration.registered)
+}
@@ -0,0 +1,59 @@
+use dkn_p2p::libp2p::gossipsub::MessageAcceptance;
+use serde::{Deserialize, Serialize};
+
+/// Contribution of the node to the gossip mesh, i.e. the messages it has validated for others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayStats {
+    /// Number of messages accepted, which are propagated to the mesh.
+    pub accepted: u64,
+    /// Number of messages rejected, penalizing their sender.
+    pub rejected: u64,
+    /// Number of messages ignored, neither propagated nor penalized.
+    pub ignored: u64,
+    /// Number of accepted messages that were still in the cache, and thus forwarded.
+    pub forwarded: u64,
+}
+
+impl RelayStats {
+    /// Records the validation result of a message.
+    pub fn record(&mut self, acceptance: &MessageAcceptance) {
+        match acceptance {
+            MessageAcceptance::Accept => self.accepted += 1,
+            MessageAcceptance::Reject => self.rejected += 1,
+            MessageAcceptance::Ignore => self.ignored += 1,
+        }
+    }
+
+    /// Records that an accepted message was still in the cache, and thus forwarded.
+    #[inline]
+    pub fn record_forwarded(&mut self) {
+        self.forwarded += 1;
+    }
+
+    /// Total number of messages validated.
+    #[inline]
+    pub fn validated(&self) -> u64 {
+        self.accepted + self.rejected + self.ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_stats() {
+        let mut stats = RelayStats::default();
+        stats.record(&MessageAcceptance::Accept);
+        stats.record_forwarded();
+        stats.record(&MessageAcceptance::Accept);
+        stats.record(&MessageAcceptance::Reject);
+        stats.record(&MessageAcceptance::Ignore);
+
+        assert_eq!(stats.accepted, 2);
+        assert_eq!(stats.forwarded, 1);
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.ignored, 1);
+        assert_eq!(stats.validated(), 4);
+    }
+}
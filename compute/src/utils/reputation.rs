@@ -0,0 +1,208 @@
+use dkn_p2p::{libp2p::PeerId, RequestOutcomes};
+use dkn_utils::storage::Storage;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// Storage namespace for the reputations of RPC nodes.
+const REPUTATION_NAMESPACE: &str = "reputation";
+/// Weight of the latest measurement within the moving average of latency.
+const LATENCY_EMA_ALPHA: f64 = 0.2;
+/// Maximum number of RPCs whose reputations are kept, the least observed ones are forgotten first.
+const MAX_REPUTATION_PEERS: usize = 256;
+
+/// Local reputation of an RPC node, built from its behavior towards this node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcReputation {
+    /// Number of requests to the RPC that were responded to.
+    pub acked: u64,
+    /// Number of requests to the RPC that failed.
+    pub missed: u64,
+    /// Number of tasks from the RPC that could be handled.
+    pub valid_tasks: u64,
+    /// Number of tasks from the RPC that were invalid, e.g. could not be parsed.
+    pub invalid_tasks: u64,
+    /// Moving average of the round-trip time to the RPC, in milliseconds.
+    pub latency_ms: Option<f64>,
+}
+
+impl RpcReputation {
+    /// Returns the score of the RPC within `(0, 1]`, the product of its ack & task validity ratios.
+    ///
+    /// An RPC starts with a perfect score, which only decreases with failures.
+    pub fn score(&self) -> f64 {
+        let ratio = |good: u64, bad: u64| (good + 1) as f64 / (good + bad + 1) as f64;
+        ratio(self.acked, self.missed) * ratio(self.valid_tasks, self.invalid_tasks)
+    }
+
+    /// Returns the number of requests & tasks that the reputation is built from.
+    #[inline]
+    fn observations(&self) -> u64 {
+        self.acked + self.missed + self.valid_tasks + self.invalid_tasks
+    }
+}
+
+/// Reputations of the RPC nodes, optionally persisted so that they survive restarts.
+#[derive(Debug, Default)]
+pub struct RpcReputations {
+    peers: HashMap<PeerId, RpcReputation>,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl RpcReputations {
+    /// Loads the reputations from the given storage, and persists them there from now on.
+    ///
+    /// Entries that can not be parsed are skipped.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Result<Self> {
+        for entry in storage.iter(REPUTATION_NAMESPACE) {
+            let (key, value) = entry.wrap_err("could not read reputations")?;
+            match (
+                key.parse::<PeerId>(),
+                serde_json::from_slice::<RpcReputation>(&value),
+            ) {
+                (Ok(peer_id), Ok(reputation)) => {
+                    self.peers.insert(peer_id, reputation);
+                }
+                _ => log::warn!("Skipping invalid reputation of {}", key),
+            }
+        }
+
+        self.storage = Some(storage);
+        self.forget_least_observed(MAX_REPUTATION_PEERS);
+        Ok(self)
+    }
+
+    /// Returns the reputation of the RPC, creating it if it is not known yet.
+    fn entry(&mut self, peer_id: PeerId) -> &mut RpcReputation {
+        if !self.peers.contains_key(&peer_id) {
+            self.forget_least_observed(MAX_REPUTATION_PEERS - 1);
+        }
+        self.peers.entry(peer_id).or_default()
+    }
+
+    /// Forgets the least observed RPCs until at most `max_peers` of them are left, within the storage as well.
+    fn forget_least_observed(&mut self, max_peers: usize) {
+        while self.peers.len() > max_peers {
+            let Some(peer_id) = self
+                .peers
+                .iter()
+                .min_by_key(|(_, reputation)| reputation.observations())
+                .map(|(peer_id, _)| *peer_id)
+            else {
+                break;
+            };
+            self.peers.remove(&peer_id);
+            if let Some(storage) = &self.storage {
+                if let Err(e) = storage.remove(REPUTATION_NAMESPACE, &peer_id.to_string()) {
+                    log::warn!("Could not remove the reputation of {}: {:?}", peer_id, e);
+                }
+            }
+        }
+    }
+
+    /// Records the outcomes of the requests made to the RPC.
+    pub fn record_requests(&mut self, peer_id: PeerId, outcomes: &RequestOutcomes) {
+        let reputation = self.entry(peer_id);
+        reputation.acked += outcomes.responded;
+        reputation.missed += outcomes.failed;
+    }
+
+    /// Records whether a task received from the RPC was valid.
+    pub fn record_task(&mut self, peer_id: PeerId, valid: bool) {
+        let reputation = self.entry(peer_id);
+        match valid {
+            true => reputation.valid_tasks += 1,
+            false => reputation.invalid_tasks += 1,
+        }
+    }
+
+    /// Updates the moving average of the round-trip time to the RPC.
+    pub fn record_latency(&mut self, peer_id: PeerId, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let reputation = self.entry(peer_id);
+        reputation.latency_ms = Some(match reputation.latency_ms {
+            Some(avg) => avg + LATENCY_EMA_ALPHA * (latency_ms - avg),
+            None => latency_ms,
+        });
+    }
+
+    /// Returns the score of the RPC, a perfect score if it is not known yet.
+    #[inline]
+    pub fn score(&self, peer_id: &PeerId) -> f64 {
+        self.peers.get(peer_id).map_or(1.0, RpcReputation::score)
+    }
+
+    /// Returns the reputation of the RPC, if it is known.
+    #[inline]
+    pub fn get(&self, peer_id: &PeerId) -> Option<&RpcReputation> {
+        self.peers.get(peer_id)
+    }
+
+    /// Writes the reputations to the storage, if there is one.
+    pub fn persist(&self) -> Result<()> {
+        let Some(storage) = &self.storage else {
+            return Ok(());
+        };
+
+        for (peer_id, reputation) in &self.peers {
+            storage
+                .put(
+                    REPUTATION_NAMESPACE,
+                    &peer_id.to_string(),
+                    serde_json::to_vec(reputation)?,
+                )
+                .wrap_err("could not write reputation")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::storage::MemoryStorage;
+
+    #[test]
+    fn test_reputations() {
+        let storage = Arc::new(MemoryStorage::default());
+        let mut reputations = RpcReputations::default()
+            .with_storage(storage.clone())
+            .unwrap();
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        // unknown RPCs have a perfect score, which decreases with failures
+        assert_eq!(reputations.score(&a), 1.0);
+        reputations.record_requests(
+            a,
+            &RequestOutcomes {
+                responded: 3,
+                failed: 4,
+            },
+        );
+        reputations.record_task(a, true);
+        reputations.record_task(a, false);
+        assert_eq!(reputations.score(&a), 0.5 * (2.0 / 3.0));
+        reputations.record_task(b, true);
+        assert_eq!(reputations.score(&b), 1.0);
+
+        reputations.record_latency(a, Duration::from_millis(100));
+        reputations.record_latency(a, Duration::from_millis(200));
+        assert_eq!(reputations.get(&a).unwrap().latency_ms, Some(120.0));
+
+        // reputations are loaded back from the storage
+        reputations.persist().unwrap();
+        let loaded = RpcReputations::default().with_storage(storage).unwrap();
+        assert_eq!(loaded.get(&a), reputations.get(&a));
+        assert_eq!(loaded.get(&b), reputations.get(&b));
+
+        // the least observed RPCs are forgotten once there are too many of them
+        for _ in 0..MAX_REPUTATION_PEERS {
+            reputations.record_latency(PeerId::random(), Duration::from_millis(100));
+        }
+        assert_eq!(reputations.peers.len(), MAX_REPUTATION_PEERS);
+        assert!(reputations.get(&a).is_some());
+        assert!(reputations.get(&b).is_some());
+    }
+}
@@ -0,0 +1,76 @@
+use dkn_p2p::libp2p::PeerId;
+use std::{collections::HashMap, time::Duration};
+
+/// A candidate must be faster than the selected RPC by this ratio to replace it,
+/// so that the selection does not flap between RPCs with similar latencies.
+const RPC_SWITCH_RATIO: f64 = 0.8;
+
+/// Selects the RPC with the lowest latency, among the given healthy RPCs.
+///
+/// Latencies are weighted by the reputation scores within `(0, 1]`, e.g. an RPC with a
+/// score of `0.5` is considered twice as slow; RPCs without a score are not penalized.
+///
+/// The current RPC is kept unless it is not healthy anymore, or the best candidate is
+/// faster by a margin. Returns `None` if there are no healthy RPCs.
+pub fn select_rpc(
+    current: Option<PeerId>,
+    latencies: &HashMap<PeerId, Duration>,
+    scores: &HashMap<PeerId, f64>,
+) -> Option<PeerId> {
+    let weighted = |peer_id: &PeerId, latency: &Duration| {
+        let score = scores
+            .get(peer_id)
+            .copied()
+            .unwrap_or(1.0)
+            .max(f64::EPSILON);
+        latency.as_secs_f64() / score
+    };
+    let (best, best_latency) = latencies
+        .iter()
+        .map(|(peer_id, latency)| (*peer_id, weighted(peer_id, latency)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    match current.and_then(|peer_id| {
+        latencies
+            .get(&peer_id)
+            .map(|l| (peer_id, weighted(&peer_id, l)))
+    }) {
+        Some((current, current_latency)) if best_latency >= current_latency * RPC_SWITCH_RATIO => {
+            Some(current)
+        }
+        _ => Some(best),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_rpc() {
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let mut latencies = HashMap::from([
+            (a, Duration::from_millis(100)),
+            (b, Duration::from_millis(90)),
+        ]);
+
+        let scores = HashMap::new();
+
+        // picks the fastest one initially
+        assert_eq!(select_rpc(None, &latencies, &scores), Some(b));
+        assert_eq!(select_rpc(None, &HashMap::new(), &scores), None);
+
+        // keeps the current one if the best one is not faster by a margin
+        assert_eq!(select_rpc(Some(a), &latencies, &scores), Some(a));
+        latencies.insert(b, Duration::from_millis(50));
+        assert_eq!(select_rpc(Some(a), &latencies, &scores), Some(b));
+
+        // a faster one with a poor reputation is not preferred
+        let poor = HashMap::from([(b, 0.25)]);
+        assert_eq!(select_rpc(None, &latencies, &poor), Some(a));
+
+        // switches if the current one is not healthy anymore
+        latencies.remove(&b);
+        assert_eq!(select_rpc(Some(b), &latencies, &scores), Some(a));
+    }
+}
@@ -0,0 +1,118 @@
+use rand::Rng;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Fraction of the period that is added as a random delay to each run, so that the
+/// nodes started at the same time do not hit the same services at the same time.
+const JITTER_RATIO: f64 = 0.1;
+
+/// A job that is run periodically by the scheduler.
+#[derive(Debug, Clone)]
+struct ScheduledJob<J> {
+    job: J,
+    period: Duration,
+    next_at: Instant,
+}
+
+/// Schedules periodic jobs of type `J`, which are returned by [`Scheduler::next`] when they are due.
+///
+/// The scheduler does not run the jobs itself, so the caller can run them with mutable access to its state.
+/// Each run is delayed by a random jitter of up to a tenth of its period.
+#[derive(Debug, Clone)]
+pub struct Scheduler<J> {
+    jobs: Vec<ScheduledJob<J>>,
+}
+
+impl<J> Default for Scheduler<J> {
+    fn default() -> Self {
+        Self { jobs: Vec::new() }
+    }
+}
+
+impl<J: Clone + PartialEq> Scheduler<J> {
+    /// Adds a job that is run every `period`, where the first run is immediate if `immediate` is set.
+    ///
+    /// A job with a zero period is not scheduled at all.
+    pub fn with_job(mut self, job: J, period: Duration, immediate: bool) -> Self {
+        self.schedule(job, period, immediate);
+        self
+    }
+
+    /// Adds a job, replacing the existing one with the same value; see [`Scheduler::with_job`].
+    pub fn schedule(&mut self, job: J, period: Duration, immediate: bool) {
+        self.cancel(&job);
+        if period.is_zero() {
+            return;
+        }
+
+        let next_at = if immediate {
+            Instant::now()
+        } else {
+            Instant::now() + period + jitter(period)
+        };
+        self.jobs.push(ScheduledJob {
+            job,
+            period,
+            next_at,
+        });
+    }
+
+    /// Removes a job, returns `true` if it was scheduled.
+    pub fn cancel(&mut self, job: &J) -> bool {
+        let len = self.jobs.len();
+        self.jobs.retain(|j| j.job != *job);
+        self.jobs.len() != len
+    }
+
+    /// Returns `true` if the job is scheduled.
+    #[inline]
+    pub fn is_scheduled(&self, job: &J) -> bool {
+        self.jobs.iter().any(|j| j.job == *job)
+    }
+
+    /// Waits until the earliest job is due, then returns it and schedules its next run.
+    ///
+    /// Waits forever if there are no jobs. This is cancellation-safe, so it can be used within `tokio::select!`.
+    pub async fn next(&mut self) -> J {
+        let Some(due) = self.jobs.iter_mut().min_by_key(|j| j.next_at) else {
+            return std::future::pending().await;
+        };
+
+        tokio::time::sleep_until(due.next_at).await;
+        due.next_at = Instant::now() + due.period + jitter(due.period);
+        due.job.clone()
+    }
+}
+
+/// Returns a random delay up to `JITTER_RATIO` of the period.
+#[inline]
+fn jitter(period: Duration) -> Duration {
+    period.mul_f64(rand::thread_rng().gen_range(0.0..JITTER_RATIO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scheduler() {
+        let mut scheduler = Scheduler::default()
+            .with_job("slow", Duration::from_secs(60), false)
+            .with_job("fast", Duration::from_millis(20), true)
+            .with_job("never", Duration::ZERO, true);
+        assert!(!scheduler.is_scheduled(&"never"));
+
+        // the immediate job is returned first, then it is repeated before the slow one
+        let started_at = Instant::now();
+        assert_eq!(scheduler.next().await, "fast");
+        assert_eq!(scheduler.next().await, "fast");
+        assert!(started_at.elapsed() >= Duration::from_millis(20));
+
+        // cancelled jobs are not returned anymore
+        assert!(scheduler.cancel(&"slow"));
+        assert!(!scheduler.cancel(&"slow"));
+        assert!(scheduler.cancel(&"fast"));
+        let next = tokio::time::timeout(Duration::from_millis(50), scheduler.next()).await;
+        assert!(next.is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use dkn_p2p::{
+    libp2p::{Multiaddr, PeerId},
+    DriaNodes,
+};
+use dkn_utils::get_current_time_nanos;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use crate::reqres::SentSpecs;
+
+/// Session with the RPC nodes, saved at shutdown so that a quick restart can resume it
+/// instead of fetching the available nodes & sending the full specs again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSession {
+    /// Time that the session was saved at, in nanoseconds.
+    saved_at: u128,
+    /// Addresses of the RPC nodes.
+    rpc_nodes: Vec<String>,
+    /// Peer ids of the RPC nodes that tasks are accepted from.
+    rpc_peerids: Vec<String>,
+    /// The selected RPC node, if any.
+    selected_rpc: Option<String>,
+    /// The last specs sent to each RPC, which their delta requests are based on.
+    sent_specs: HashMap<String, SentSpecs>,
+}
+
+impl RpcSession {
+    /// Creates a session with the given state, saved at the current time.
+    pub fn new(
+        nodes: &DriaNodes,
+        selected_rpc: Option<PeerId>,
+        sent_specs: &HashMap<PeerId, SentSpecs>,
+    ) -> Self {
+        Self {
+            saved_at: get_current_time_nanos(),
+            rpc_nodes: nodes.rpc_nodes.iter().map(|a| a.to_string()).collect(),
+            rpc_peerids: nodes.rpc_peerids.iter().map(|p| p.to_string()).collect(),
+            selected_rpc: selected_rpc.map(|p| p.to_string()),
+            sent_specs: sent_specs
+                .iter()
+                .map(|(peer_id, specs)| (peer_id.to_string(), specs.clone()))
+                .collect(),
+        }
+    }
+
+    /// Writes the session to the given file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_vec(self)?;
+        std::fs::write(path, data).wrap_err("could not write session")
+    }
+
+    /// Reads the session from the given file if it was saved within the grace period.
+    ///
+    /// The file is removed in any case, so that a session is resumed at most once.
+    pub fn load(path: &Path, grace_period: Duration) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = std::fs::read(path).wrap_err("could not read session");
+        let _ = std::fs::remove_file(path);
+        let session = serde_json::from_slice::<Self>(&data?).wrap_err("could not parse session")?;
+
+        let age = get_current_time_nanos().saturating_sub(session.saved_at);
+        if age > grace_period.as_nanos() {
+            log::info!(
+                "Previous session is {} seconds old, not resuming it.",
+                age / 1_000_000_000
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(session))
+    }
+
+    /// Returns the addresses of the RPC nodes, invalid ones are skipped.
+    pub fn rpc_nodes(&self) -> Vec<Multiaddr> {
+        self.rpc_nodes
+            .iter()
+            .filter_map(|a| a.parse().ok())
+            .collect()
+    }
+
+    /// Returns the peer ids of the RPC nodes, invalid ones are skipped.
+    pub fn rpc_peerids(&self) -> Vec<PeerId> {
+        self.rpc_peerids
+            .iter()
+            .filter_map(|p| p.parse().ok())
+            .collect()
+    }
+
+    /// Returns the selected RPC node, if it was valid.
+    pub fn selected_rpc(&self) -> Option<PeerId> {
+        self.selected_rpc.as_ref().and_then(|p| p.parse().ok())
+    }
+
+    /// Returns the last specs sent to each RPC, invalid peer ids are skipped.
+    pub fn sent_specs(&self) -> HashMap<PeerId, SentSpecs> {
+        self.sent_specs
+            .iter()
+            .filter_map(|(peer_id, specs)| Some((peer_id.parse().ok()?, specs.clone())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_resumption() {
+        let path = std::env::temp_dir().join(format!(
+            "dkn-test-session-{}.json",
+            get_current_time_nanos()
+        ));
+        let peer_id = PeerId::random();
+        let addr = format!("/ip4/10.0.0.1/tcp/4001/p2p/{}", peer_id)
+            .parse::<Multiaddr>()
+            .unwrap();
+
+        let nodes = DriaNodes::new(dkn_p2p::DriaNetworkType::Community)
+            .with_rpc_nodes([addr.clone()])
+            .with_rpc_peer_ids([peer_id]);
+        let session = RpcSession::new(&nodes, Some(peer_id), &HashMap::new());
+        session.save(&path).unwrap();
+        let resumed = RpcSession::load(&path, Duration::from_secs(60))
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.rpc_nodes(), vec![addr]);
+        assert_eq!(resumed.rpc_peerids(), vec![peer_id]);
+        assert_eq!(resumed.selected_rpc(), Some(peer_id));
+
+        // the session is resumed once
+        assert!(RpcSession::load(&path, Duration::from_secs(60))
+            .unwrap()
+            .is_none());
+
+        // a stale session is not resumed
+        session.save(&path).unwrap();
+        assert!(RpcSession::load(&path, Duration::ZERO).unwrap().is_none());
+    }
+}
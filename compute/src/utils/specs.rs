@@ -1,7 +1,17 @@
 use public_ip_address::response::LookupResponse;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind};
 
+use super::RelayStats;
+
+/// Measured performance of a model on this machine.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpecModelPerformance {
+    /// Tokens per second of a sample generation.
+    pub tps: f64,
+}
+
 /// Machine info & location.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Specs {
@@ -21,6 +31,14 @@ pub struct Specs {
     lookup: Option<LookupResponse>,
     /// Used models.
     models: Vec<String>,
+    /// Measured performance of the local models, by model name.
+    performance: BTreeMap<String, SpecModelPerformance>,
+    /// Operator labels, e.g. `region`, `owner`.
+    labels: BTreeMap<String, String>,
+    /// Operator note.
+    note: Option<String>,
+    /// Gossip relay contribution since the node has started.
+    relay: RelayStats,
     // GPU adapter infos, showing information about the available GPUs.
     // gpus: Vec<wgpu::AdapterInfo>,
 }
@@ -31,6 +49,12 @@ pub struct SpecCollector {
     system: sysinfo::System,
     /// Used models.
     models: Vec<String>,
+    /// Measured performance of the local models.
+    performance: BTreeMap<String, SpecModelPerformance>,
+    /// Operator labels.
+    labels: BTreeMap<String, String>,
+    /// Operator note.
+    note: Option<String>,
     // GPU adapter infos, showing information about the available GPUs.
     // gpus: Vec<wgpu::AdapterInfo>,
 }
@@ -46,6 +70,9 @@ impl SpecCollector {
         SpecCollector {
             system: sysinfo::System::new_with_specifics(Self::get_refresh_specifics()),
             models,
+            performance: BTreeMap::new(),
+            labels: BTreeMap::new(),
+            note: None,
             // gpus: wgpu::Instance::default()
             //     .enumerate_adapters(wgpu::Backends::all())
             //     .into_iter()
@@ -54,6 +81,27 @@ impl SpecCollector {
         }
     }
 
+    /// Sets the operator labels & note to be included in the specs.
+    pub fn with_labels(mut self, labels: BTreeMap<String, String>, note: Option<String>) -> Self {
+        self.labels = labels;
+        self.note = note;
+        self
+    }
+
+    /// Sets the used models, dropping the measurements of the models that are not used anymore.
+    pub fn set_models(&mut self, models: Vec<String>) {
+        self.performance.retain(|model, _| models.contains(model));
+        self.models = models;
+    }
+
+    /// Sets the measured tokens per second of the models, keeping the measurements of other models.
+    pub fn set_tps(&mut self, tps: impl IntoIterator<Item = (String, f64)>) {
+        self.performance.extend(
+            tps.into_iter()
+                .map(|(model, tps)| (model, SpecModelPerformance { tps })),
+        );
+    }
+
     /// Returns the selected refresh kinds. It is important to ignore
     /// process values here because it will consume a lot of file-descriptors.
     #[inline(always)]
@@ -63,7 +111,8 @@ impl SpecCollector {
             .with_memory(MemoryRefreshKind::everything())
     }
 
-    pub async fn collect(&mut self) -> Specs {
+    /// Collects the specs, along with the given relay contribution of the node.
+    pub async fn collect(&mut self, relay: RelayStats) -> Specs {
         self.system.refresh_specifics(Self::get_refresh_specifics());
 
         Specs {
@@ -75,6 +124,10 @@ impl SpecCollector {
             arch: std::env::consts::ARCH.to_string(),
             lookup: public_ip_address::perform_lookup(None).await.ok(),
             models: self.models.clone(),
+            performance: self.performance.clone(),
+            labels: self.labels.clone(),
+            note: self.note.clone(),
+            relay,
             // gpus: self.gpus.clone(),
         }
     }
@@ -86,7 +139,7 @@ mod tests {
     #[tokio::test]
     async fn test_print_specs() {
         let mut spec_collector = SpecCollector::new(vec!["gpt-4o".to_string()]);
-        let specs = spec_collector.collect().await;
+        let specs = spec_collector.collect(RelayStats::default()).await;
         assert!(specs.total_mem > 0);
         assert!(specs.free_mem > 0);
         assert!(specs.num_cpus.is_some());
@@ -0,0 +1,230 @@
+use dkn_utils::payloads::TaskStats;
+use eyre::{eyre, Context, Result};
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+use serde_json::{json, Value};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Maximum number of rows returned by a query, the rest are left out.
+const MAX_QUERY_ROWS: usize = 1000;
+/// Maximum duration of a query, after which it is interrupted.
+const MAX_QUERY_DURATION: Duration = Duration::from_secs(5);
+/// Number of virtual machine instructions between the checks of the query duration.
+const QUERY_PROGRESS_OPS: i32 = 10_000;
+/// Duration to wait for the other connection to release its lock, e.g. a long query.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema of the database, timestamps are in nanoseconds as within [`TaskStats`].
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tasks (
+    task_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    error TEXT,
+    received_at INTEGER NOT NULL,
+    execution_started_at INTEGER NOT NULL,
+    execution_ended_at INTEGER NOT NULL,
+    published_at INTEGER NOT NULL,
+    execution_ms INTEGER NOT NULL,
+    received_bytes INTEGER NOT NULL,
+    result_bytes INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS tasks_by_model ON tasks (model, published_at);
+";
+
+/// A local SQLite database with a row for each completed task, to be queried by the operator.
+///
+/// The database is in WAL mode, so that the queries over their own connections do not block the writes.
+#[derive(Debug, Clone)]
+pub struct StatsDb {
+    conn: Arc<Mutex<Connection>>,
+    /// Maximum number of rows to keep, oldest are removed first; all are kept if `0`.
+    max_rows: usize,
+}
+
+impl StatsDb {
+    /// Opens the database at the given path, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>, max_rows: usize) -> Result<Self> {
+        let conn = Connection::open(path).wrap_err("could not open stats database")?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .wrap_err("could not enable WAL mode")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)
+            .wrap_err("could not create stats tables")?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            max_rows,
+        })
+    }
+
+    /// Records the statistics of a completed task, `error` is given if the task has failed.
+    ///
+    /// The write happens on a blocking thread, so that it does not stall the node; errors are only logged.
+    pub fn record(&self, task_id: String, model: String, error: Option<String>, stats: TaskStats) {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = db.insert(&task_id, &model, error.as_deref(), &stats) {
+                log::warn!("Could not record stats of task {}: {:?}", task_id, e);
+            }
+        });
+    }
+
+    /// Inserts the statistics of a task, and removes the oldest rows above the limit.
+    fn insert(
+        &self,
+        task_id: &str,
+        model: &str,
+        error: Option<&str>,
+        stats: &TaskStats,
+    ) -> Result<()> {
+        let execution_ns = stats
+            .execution_ended_at
+            .saturating_sub(stats.execution_started_at);
+        let conn = self.conn.lock().map_err(|_| eyre!("poisoned lock"))?;
+        conn.execute(
+            "INSERT INTO tasks VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                task_id,
+                model,
+                error,
+                stats.received_at as i64,
+                stats.execution_started_at as i64,
+                stats.execution_ended_at as i64,
+                stats.published_at as i64,
+                (execution_ns / 1_000_000) as i64,
+                stats.received_bytes as i64,
+                stats.result_bytes as i64,
+            ],
+        )
+        .wrap_err("could not record task stats")?;
+
+        // rows are only appended, so the row ids are increasing & the oldest rows have the lowest ids
+        if self.max_rows != 0 {
+            conn.execute(
+                "DELETE FROM tasks WHERE rowid <= (SELECT MAX(rowid) FROM tasks) - ?1",
+                rusqlite::params![self.max_rows as i64],
+            )
+            .wrap_err("could not prune task stats")?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs a single read-only query over the database at the given path.
+    ///
+    /// The query runs on its own read-only connection, so it can not modify the database
+    /// nor block the node. Only the first statement of `sql` is prepared, so any statements
+    /// after it are never run. At most `MAX_QUERY_ROWS` rows are returned, and a query that
+    /// takes longer than `MAX_QUERY_DURATION` is interrupted.
+    ///
+    /// Returns the column names and the rows, along with whether the rows were truncated.
+    pub fn query(path: impl AsRef<Path>, sql: &str) -> Result<Value> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .wrap_err("could not open stats database")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let deadline = Instant::now() + MAX_QUERY_DURATION;
+        conn.progress_handler(QUERY_PROGRESS_OPS, Some(move || Instant::now() > deadline));
+
+        let mut stmt = conn.prepare(sql).wrap_err("invalid query")?;
+        if !stmt.readonly() {
+            return Err(eyre!("only read-only queries are allowed"));
+        }
+        let columns = stmt
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        let mut rows = stmt.query([]).wrap_err("could not run query")?;
+        let mut values = Vec::new();
+        let mut truncated = false;
+        while let Some(row) = rows.next().wrap_err("could not read query results")? {
+            if values.len() == MAX_QUERY_ROWS {
+                truncated = true;
+                break;
+            }
+
+            let mut value = Vec::with_capacity(columns.len());
+            for idx in 0..columns.len() {
+                value.push(match row.get_ref(idx)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(i) => json!(i),
+                    ValueRef::Real(f) => json!(f),
+                    ValueRef::Text(text) => json!(String::from_utf8_lossy(text)),
+                    ValueRef::Blob(blob) => json!(hex::encode(blob)),
+                });
+            }
+            values.push(Value::Array(value));
+        }
+
+        Ok(json!({
+            "columns": columns,
+            "rows": values,
+            "truncated": truncated,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_db() {
+        let path = std::env::temp_dir().join(format!(
+            "dkn-stats-{}.db",
+            dkn_utils::get_current_time_nanos()
+        ));
+        let db = StatsDb::open(&path, 3).unwrap();
+
+        let stats = TaskStats {
+            received_at: 1700000000000000000,
+            published_at: 1700000005000000000,
+            execution_started_at: 1700000001000000000,
+            execution_ended_at: 1700000004000000000,
+            received_bytes: 512,
+            result_bytes: 128,
+        };
+        db.insert("task-0", "gpt-4o", None, &stats).unwrap();
+        db.insert("task-1", "gpt-4o", None, &stats).unwrap();
+        db.insert("task-2", "gpt-4o", Some("timeout"), &stats)
+            .unwrap();
+        db.insert("task-3", "llama3.1:latest", None, &stats)
+            .unwrap();
+
+        let result = StatsDb::query(
+            &path,
+            "SELECT model, COUNT(*) AS tasks, AVG(execution_ms) AS avg_ms FROM tasks \
+            WHERE error IS NULL GROUP BY model ORDER BY model",
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "columns": ["model", "tasks", "avg_ms"],
+                "rows": [["gpt-4o", 1, 3000.0], ["llama3.1:latest", 1, 3000.0]],
+                "truncated": false,
+            })
+        );
+
+        // only the oldest row is removed due to the limit
+        let result = StatsDb::query(&path, "SELECT task_id FROM tasks ORDER BY task_id").unwrap();
+        assert_eq!(result["rows"], json!([["task-1"], ["task-2"], ["task-3"]]));
+
+        // only a single read-only statement is run
+        assert!(StatsDb::query(&path, "DELETE FROM tasks").is_err());
+        let _ = StatsDb::query(&path, "SELECT 1; DELETE FROM tasks");
+        assert!(StatsDb::query(&path, "SELECT * FROM missing").is_err());
+        let result = StatsDb::query(&path, "SELECT COUNT(*) FROM tasks").unwrap();
+        assert_eq!(result["rows"], json!([[3]]));
+
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+}
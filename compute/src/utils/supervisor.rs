@@ -0,0 +1,163 @@
+use dkn_p2p::libp2p::futures::{future::BoxFuture, FutureExt};
+use eyre::Result;
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+/// Restart policy for supervised components.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Maximum number of restarts in total, after which the node is shut down.
+    pub max_restarts: usize,
+    /// Initial backoff before a restart, doubled after each restart.
+    pub initial_backoff: Duration,
+    /// Maximum backoff before a restart.
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A small supervisor that restarts a component when it panics or returns an error.
+///
+/// Components that return `Ok` are not restarted, as that is how they exit on shutdown.
+/// If the restart limit is reached, the cancellation token is cancelled to shut down the node.
+#[derive(Debug, Clone)]
+pub struct Supervisor {
+    policy: RestartPolicy,
+    /// Number of restarts so far, shared among all clones.
+    restarts: Arc<AtomicUsize>,
+    cancellation: CancellationToken,
+}
+
+impl Supervisor {
+    pub fn new(policy: RestartPolicy, cancellation: CancellationToken) -> Self {
+        Self {
+            policy,
+            restarts: Arc::new(AtomicUsize::new(0)),
+            cancellation,
+        }
+    }
+
+    /// Returns the total number of restarts so far.
+    #[inline]
+    pub fn restart_count(&self) -> usize {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Runs the given component until it returns `Ok`, restarting it if it panics or returns an error.
+    ///
+    /// The component is kept across restarts, so its channels remain intact.
+    /// If the supervisor gives up on the component, it is returned back so that the caller can clean it up.
+    pub async fn supervise<T: Send>(
+        &self,
+        name: &'static str,
+        mut component: T,
+        mut run: impl for<'a> FnMut(&'a mut T) -> BoxFuture<'a, Result<()>> + Send,
+    ) -> Option<T> {
+        let mut backoff = self.policy.initial_backoff;
+        loop {
+            match AssertUnwindSafe(run(&mut component)).catch_unwind().await {
+                Ok(Ok(())) => return None,
+                Ok(Err(err)) => log::error!("Supervisor: {} returned an error: {:?}", name, err),
+                Err(_) => log::error!("Supervisor: {} panicked.", name),
+            }
+
+            let restarts = self.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+            if restarts > self.policy.max_restarts || self.cancellation.is_cancelled() {
+                log::error!(
+                    "Supervisor: {} crashed & restart limit ({}) is reached, shutting down.",
+                    name,
+                    self.policy.max_restarts
+                );
+                self.cancellation.cancel();
+                return Some(component);
+            }
+
+            log::error!(
+                "Supervisor: {} crashed, restarting in {} seconds ({}/{}).",
+                name,
+                backoff.as_secs(),
+                restarts,
+                self.policy.max_restarts
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {},
+                _ = self.cancellation.cancelled() => return Some(component),
+            }
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_supervisor_restarts() {
+        let policy = RestartPolicy {
+            max_restarts: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+
+        // panics twice, then returns
+        let supervisor = Supervisor::new(policy, CancellationToken::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        supervisor
+            .supervise("test", runs.clone(), |runs| {
+                Box::pin(async move {
+                    let run = runs.fetch_add(1, Ordering::Relaxed) + 1;
+                    if run <= 2 {
+                        panic!("crash {}", run);
+                    }
+                    Ok(())
+                })
+            })
+            .await;
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+        assert_eq!(supervisor.restart_count(), 2);
+        assert!(!supervisor.cancellation.is_cancelled());
+
+        // errors twice, then returns
+        let supervisor = Supervisor::new(policy, CancellationToken::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let component = supervisor
+            .supervise("test", runs.clone(), |runs| {
+                Box::pin(async move {
+                    let run = runs.fetch_add(1, Ordering::Relaxed) + 1;
+                    if run <= 2 {
+                        eyre::bail!("error {}", run);
+                    }
+                    Ok(())
+                })
+            })
+            .await;
+        assert!(component.is_none());
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+        assert_eq!(supervisor.restart_count(), 2);
+        assert!(!supervisor.cancellation.is_cancelled());
+
+        // always panics, should cancel after the limit
+        let supervisor = Supervisor::new(policy, CancellationToken::new());
+        let component = supervisor
+            .supervise("test", (), |_| Box::pin(async { panic!("crash") }))
+            .await;
+        assert!(component.is_some());
+        assert!(supervisor.cancellation.is_cancelled());
+    }
+}
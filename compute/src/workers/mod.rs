@@ -1 +1,5 @@
+pub mod publish;
 pub mod task;
+
+mod replay;
+pub use replay::replay_task;
@@ -0,0 +1,607 @@
+use dkn_p2p::ChannelStats;
+use dkn_workflows::{ExecutionError, Judgement};
+use eyre::{eyre, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::Notify;
+
+use super::task::TaskWorkerOutput;
+use crate::payloads::{TaskErrorClass, TaskStats};
+
+/// What the publish channel does with a new output while it is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The worker waits until there is room, as with a regular channel.
+    #[default]
+    Block,
+    /// The oldest output is dropped with an error, its task is then swept as expired.
+    DropOldest,
+    /// The outputs are written to disk until there is room, and read back in order.
+    Spill,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            "spill" => Ok(Self::Spill),
+            _ => Err(format!("unknown overflow policy: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Block => write!(f, "block"),
+            Self::DropOldest => write!(f, "drop-oldest"),
+            Self::Spill => write!(f, "spill"),
+        }
+    }
+}
+
+/// Number of consecutive outputs that each pipeline can publish while the other one has outputs waiting.
+///
+/// Batch outputs are completed together, so without quotas they would hold back the single outputs behind them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishQuotas {
+    /// Quota of the single (non-batchable) tasks.
+    pub single: usize,
+    /// Quota of the batchable tasks.
+    pub batch: usize,
+}
+
+impl Default for PublishQuotas {
+    fn default() -> Self {
+        Self {
+            single: 1,
+            batch: 4,
+        }
+    }
+}
+
+impl FromStr for PublishQuotas {
+    type Err = String;
+
+    /// Parses the `single,batch` quotas, e.g. `1,4`; quotas must be positive.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let quota = |q: &str| match q.trim().parse::<usize>() {
+            Ok(0) | Err(_) => Err(format!("invalid publish quota: {}", q)),
+            Ok(quota) => Ok(quota),
+        };
+        match s.split_once(',') {
+            Some((single, batch)) => Ok(Self {
+                single: quota(single)?,
+                batch: quota(batch)?,
+            }),
+            None => Err(format!("expected single,batch publish quotas: {}", s)),
+        }
+    }
+}
+
+/// A task output written to disk, the error of a failed execution is kept as its message.
+#[derive(Serialize, Deserialize)]
+struct SpilledOutput {
+    task_id: String,
+    result: Result<String, String>,
+    #[serde(default)]
+    error_class: Option<TaskErrorClass>,
+    stats: TaskStats,
+    batchable: bool,
+    judgement: Option<Judgement>,
+}
+
+impl From<TaskWorkerOutput> for SpilledOutput {
+    fn from(output: TaskWorkerOutput) -> Self {
+        Self {
+            task_id: output.task_id,
+            result: output.result.map_err(|e| format!("{:#}", e)),
+            error_class: output.error_class,
+            stats: output.stats,
+            batchable: output.batchable,
+            judgement: output.judgement,
+        }
+    }
+}
+
+impl From<SpilledOutput> for TaskWorkerOutput {
+    fn from(output: SpilledOutput) -> Self {
+        Self {
+            task_id: output.task_id,
+            result: output.result.map_err(ExecutionError::WorkflowFailed),
+            error_class: output.error_class,
+            stats: output.stats,
+            batchable: output.batchable,
+            judgement: output.judgement,
+        }
+    }
+}
+
+struct State {
+    /// Outputs waiting to be received.
+    queue: VecDeque<TaskWorkerOutput>,
+    /// Files of the outputs spilled to disk by their sequence numbers, `None` for the ones lost while spilling.
+    spilled: BTreeMap<u64, Option<PathBuf>>,
+    /// Sequence number of the next spilled output.
+    spill_seq: u64,
+    /// Sequence number of the next spilled output to read back.
+    refill_seq: u64,
+    /// Number of outputs that are being written to disk.
+    spilling: usize,
+    /// Whether the spilled outputs are being read back.
+    refilling: bool,
+    /// Number of senders alive.
+    senders: usize,
+    /// Whether the receiver is closed.
+    closed: bool,
+    /// Number of outputs dropped due to overflows, or lost while spilling.
+    dropped: u64,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    spill_dir: PathBuf,
+    /// Notified when an output is sent or all senders are dropped.
+    sent: Notify,
+    /// Notified when an output is received or the receiver is closed.
+    received: Notify,
+}
+
+impl Shared {
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns `true` if an output can be queued directly, i.e. nothing is spilled before it.
+    #[inline]
+    fn has_room(&self, state: &State) -> bool {
+        state.queue.len() < self.capacity
+            && state.spilled.is_empty()
+            && state.spilling == 0
+            && !state.refilling
+    }
+
+    /// Returns `true` if some outputs are on disk, or on their way to or from it.
+    #[inline]
+    fn has_spilled(&self, state: &State) -> bool {
+        !state.spilled.is_empty() || state.spilling != 0 || state.refilling
+    }
+
+    /// Returns `true` if the next spilled output in order has landed on disk, or was lost while spilling.
+    #[inline]
+    fn has_next_spilled(&self, state: &State) -> bool {
+        state
+            .spilled
+            .first_key_value()
+            .is_some_and(|(seq, _)| *seq == state.refill_seq)
+    }
+
+    /// Starts reading the spilled outputs back in the background, if there is room, no refill is running,
+    /// and the next output in order has landed.
+    ///
+    /// The file I/O runs on a blocking thread without the lock, and completes even if the receiver stops waiting.
+    fn start_refill(self: &Arc<Self>, state: &mut State) {
+        if state.refilling || !self.has_next_spilled(state) || state.queue.len() >= self.capacity {
+            return;
+        }
+
+        state.refilling = true;
+        let shared = self.clone();
+        tokio::task::spawn_blocking(move || shared.refill());
+    }
+
+    /// Moves the spilled outputs back to the queue in order while there is room,
+    /// stopping at an output that is still being written.
+    fn refill(&self) {
+        loop {
+            let path = {
+                let mut state = self.lock();
+                if state.queue.len() >= self.capacity || !self.has_next_spilled(&state) {
+                    state.refilling = false;
+                    break;
+                }
+                state.refill_seq += 1;
+                match state.spilled.pop_first() {
+                    Some((_, Some(path))) => path,
+                    // the lost output is already counted as dropped
+                    _ => continue,
+                }
+            };
+
+            let output = std::fs::read(&path)
+                .wrap_err("could not read")
+                .and_then(|data| {
+                    serde_json::from_slice::<SpilledOutput>(&data).wrap_err("could not parse")
+                });
+            let _ = std::fs::remove_file(&path);
+
+            let mut state = self.lock();
+            match output {
+                Ok(output) => state.queue.push_back(output.into()),
+                Err(e) => {
+                    log::error!("Lost spilled output {}: {:?}", path.display(), e);
+                    state.dropped += 1;
+                }
+            }
+        }
+
+        self.sent.notify_one();
+    }
+
+    /// Writes the output to the given file without the lock, then adds it to the spilled outputs with its sequence number.
+    fn spill(self: &Arc<Self>, seq: u64, path: PathBuf, output: TaskWorkerOutput) -> Result<()> {
+        let written = serde_json::to_vec(&SpilledOutput::from(output))
+            .wrap_err("could not serialize spilled output")
+            .and_then(|data| {
+                std::fs::write(&path, data).wrap_err("could not write spilled output")
+            });
+
+        let mut state = self.lock();
+        state.spilling -= 1;
+        match written {
+            Ok(()) => state.spilled.insert(seq, Some(path)),
+            Err(_) => {
+                state.dropped += 1;
+                state.spilled.insert(seq, None)
+            }
+        };
+        self.start_refill(&mut state);
+        drop(state);
+        self.sent.notify_one();
+
+        written
+    }
+}
+
+/// Creates the channel that the workers publish their outputs to, holding `capacity` outputs in memory.
+///
+/// With the `spill` policy, the outputs beyond the capacity are kept within `spill_dir`,
+/// which is cleared first as the outputs of an earlier run can not be responded to anymore,
+/// and is only accessible by the owner of the process.
+pub fn publish_channel(
+    capacity: usize,
+    policy: OverflowPolicy,
+    spill_dir: PathBuf,
+) -> Result<(PublishSender, PublishReceiver)> {
+    if policy == OverflowPolicy::Spill {
+        if spill_dir.exists() {
+            std::fs::remove_dir_all(&spill_dir).wrap_err("could not clear spill directory")?;
+        }
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&spill_dir)
+            .wrap_err("could not create spill directory")?;
+    }
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            spilled: BTreeMap::new(),
+            spill_seq: 0,
+            refill_seq: 0,
+            spilling: 0,
+            refilling: false,
+            senders: 1,
+            closed: false,
+            dropped: 0,
+        }),
+        capacity: capacity.max(1),
+        policy,
+        spill_dir,
+        sent: Notify::new(),
+        received: Notify::new(),
+    });
+
+    Ok((
+        PublishSender {
+            shared: shared.clone(),
+        },
+        PublishReceiver {
+            shared,
+            turns: Turns::default(),
+        },
+    ))
+}
+
+/// Sender of the publish channel, used by the workers.
+pub struct PublishSender {
+    shared: Arc<Shared>,
+}
+
+impl PublishSender {
+    /// Sends the output w.r.t the overflow policy, returns an error if the receiver is closed
+    /// or if the output could not be spilled.
+    pub async fn send(&self, output: TaskWorkerOutput) -> Result<()> {
+        let mut output = Some(output);
+        loop {
+            // registered before checking the state, so that a receipt in between is not missed
+            let received = self.shared.received.notified();
+            tokio::pin!(received);
+            received.as_mut().enable();
+
+            let spill = {
+                let mut state = self.shared.lock();
+                if state.closed {
+                    return Err(eyre!("publish channel is closed"));
+                }
+
+                // once an output is spilled, the following ones are spilled as well to keep the order
+                if self.shared.has_room(&state) {
+                    state
+                        .queue
+                        .push_back(output.take().expect("output is sent once"));
+                    self.shared.sent.notify_one();
+                    return Ok(());
+                }
+                match self.shared.policy {
+                    OverflowPolicy::Block => None,
+                    OverflowPolicy::DropOldest => {
+                        if let Some(oldest) = state.queue.pop_front() {
+                            log::error!(
+                                "Publish channel is full, dropped the output of task {}",
+                                oldest.task_id
+                            );
+                            state.dropped += 1;
+                        }
+                        state
+                            .queue
+                            .push_back(output.take().expect("output is sent once"));
+                        self.shared.sent.notify_one();
+                        return Ok(());
+                    }
+                    OverflowPolicy::Spill => {
+                        // the file is reserved under the lock, but written on a blocking thread without it
+                        let seq = state.spill_seq;
+                        let path = self.shared.spill_dir.join(format!("{:020}.json", seq));
+                        state.spill_seq += 1;
+                        state.spilling += 1;
+                        let shared = self.shared.clone();
+                        let output = output.take().expect("output is sent once");
+                        Some(tokio::task::spawn_blocking(move || {
+                            shared.spill(seq, path, output)
+                        }))
+                    }
+                }
+            };
+
+            if let Some(spill) = spill {
+                return spill.await.wrap_err("spill task failed")?;
+            }
+
+            received.await;
+        }
+    }
+}
+
+impl Clone for PublishSender {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for PublishSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.shared.sent.notify_one();
+        }
+    }
+}
+
+/// Receiver of the publish channel, used by the node.
+pub struct PublishReceiver {
+    shared: Arc<Shared>,
+    turns: Turns,
+}
+
+/// Turns of the pipelines w.r.t their quotas.
+#[derive(Default)]
+struct Turns {
+    quotas: PublishQuotas,
+    /// Pipeline of the last received outputs (whether they are batchable), and their count.
+    streak: (bool, usize),
+}
+
+impl Turns {
+    /// Takes the next output in order, unless its pipeline used up its quota
+    /// and the other pipeline has an output waiting, which is taken instead.
+    fn take_next(&mut self, state: &mut State) -> Option<TaskWorkerOutput> {
+        let batchable = state.queue.front()?.batchable;
+        let quota = match batchable {
+            true => self.quotas.batch,
+            false => self.quotas.single,
+        };
+        let index = match self.streak {
+            (streak, count) if streak == batchable && count >= quota => state
+                .queue
+                .iter()
+                .position(|o| o.batchable != batchable)
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        let output = state.queue.remove(index)?;
+        self.streak = match self.streak {
+            (streak, count) if streak == output.batchable => (streak, count + 1),
+            _ => (output.batchable, 1),
+        };
+        Some(output)
+    }
+}
+
+impl PublishReceiver {
+    /// Sets the quotas of the pipelines.
+    pub fn with_quotas(mut self, quotas: PublishQuotas) -> Self {
+        self.turns.quotas = quotas;
+        self
+    }
+
+    /// Receives the next output, in order within each pipeline, returns `None` once it is closed or all senders are dropped.
+    pub async fn recv(&mut self) -> Option<TaskWorkerOutput> {
+        loop {
+            let sent = self.shared.sent.notified();
+            {
+                let mut state = self.shared.lock();
+                if let Some(output) = self.turns.take_next(&mut state) {
+                    self.shared.start_refill(&mut state);
+                    self.shared.received.notify_waiters();
+                    return Some(output);
+                }
+                if (state.closed || state.senders == 0) && !self.shared.has_spilled(&state) {
+                    return None;
+                }
+                self.shared.start_refill(&mut state);
+            }
+
+            sent.await;
+        }
+    }
+
+    /// Closes the channel, the outputs within can still be received.
+    pub fn close(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.received.notify_waiters();
+    }
+
+    /// Returns the stats of the channel, where the spilled outputs are counted within its depth.
+    pub fn stats(&self) -> ChannelStats {
+        let state = self.shared.lock();
+        ChannelStats {
+            depth: state.queue.len()
+                + state.spilled.values().flatten().count()
+                + state.spilling
+                + usize::from(state.refilling),
+            capacity: self.shared.capacity,
+            dropped: state.dropped,
+        }
+    }
+}
+
+impl Drop for PublishReceiver {
+    fn drop(&mut self) {
+        self.close();
+        if self.shared.policy == OverflowPolicy::Spill {
+            let _ = std::fs::remove_dir_all(&self.shared.spill_dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(task_id: &str) -> TaskWorkerOutput {
+        TaskWorkerOutput {
+            result: Ok(format!("result of {}", task_id)),
+            error_class: None,
+            task_id: task_id.to_string(),
+            stats: TaskStats::default(),
+            batchable: true,
+            judgement: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_overflow() {
+        // the oldest output is dropped
+        let (tx, mut rx) =
+            publish_channel(2, OverflowPolicy::DropOldest, PathBuf::default()).unwrap();
+        for task_id in ["a", "b", "c"] {
+            tx.send(output(task_id)).await.unwrap();
+        }
+        assert_eq!(rx.stats().dropped, 1);
+        assert_eq!(rx.recv().await.unwrap().task_id, "b");
+        assert_eq!(rx.recv().await.unwrap().task_id, "c");
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+
+        // outputs beyond the capacity are spilled & received in order
+        let spill_dir = std::env::temp_dir().join(format!(
+            "dkn-test-publish-spill-{}",
+            dkn_utils::get_current_time_nanos()
+        ));
+        let (tx, mut rx) = publish_channel(1, OverflowPolicy::Spill, spill_dir.clone()).unwrap();
+        for task_id in ["a", "b", "c"] {
+            tx.send(output(task_id)).await.unwrap();
+        }
+        assert_eq!(rx.stats().depth, 3);
+        for task_id in ["a", "b", "c"] {
+            let received = rx.recv().await.unwrap();
+            assert_eq!(received.task_id, task_id);
+            assert_eq!(received.result.unwrap(), format!("result of {}", task_id));
+        }
+        drop(rx);
+        assert!(!spill_dir.exists());
+
+        // a spilled output that lands early waits for the ones before it
+        let (tx, mut rx) = publish_channel(1, OverflowPolicy::Spill, spill_dir.clone()).unwrap();
+        {
+            let mut state = tx.shared.lock();
+            state.spill_seq = 2;
+            state.spilling = 2;
+        }
+        let path = |seq: u64| spill_dir.join(format!("{:020}.json", seq));
+        tx.shared.spill(1, path(1), output("b")).unwrap();
+        assert!(!tx.shared.lock().refilling);
+        tx.shared.spill(0, path(0), output("a")).unwrap();
+        assert_eq!(rx.recv().await.unwrap().task_id, "a");
+        assert_eq!(rx.recv().await.unwrap().task_id, "b");
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+        drop(rx);
+
+        // a blocked sender waits for the receiver
+        let (tx, mut rx) = publish_channel(1, OverflowPolicy::Block, PathBuf::default()).unwrap();
+        tx.send(output("a")).await.unwrap();
+        let handle = tokio::spawn(async move { tx.send(output("b")).await });
+        assert_eq!(rx.recv().await.unwrap().task_id, "a");
+        handle.await.unwrap().unwrap();
+        assert_eq!(rx.recv().await.unwrap().task_id, "b");
+    }
+
+    #[tokio::test]
+    async fn test_publish_quotas() {
+        let (tx, rx) = publish_channel(16, OverflowPolicy::Block, PathBuf::default()).unwrap();
+        let mut rx = rx.with_quotas("1,2".parse().unwrap());
+        for task_id in ["b1", "b2", "b3", "b4"] {
+            tx.send(output(task_id)).await.unwrap();
+        }
+        for task_id in ["s1", "s2"] {
+            tx.send(TaskWorkerOutput {
+                batchable: false,
+                ..output(task_id)
+            })
+            .await
+            .unwrap();
+        }
+
+        // the single outputs are not held back behind all batch outputs
+        let mut received = Vec::new();
+        for _ in 0..6 {
+            received.push(rx.recv().await.unwrap().task_id);
+        }
+        assert_eq!(received, ["b1", "b2", "s1", "b3", "b4", "s2"]);
+
+        assert!("1,4".parse::<PublishQuotas>().is_ok());
+        assert!("0,4".parse::<PublishQuotas>().is_err());
+        assert!("4".parse::<PublishQuotas>().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use dkn_workflows::{DriaWorkflowsConfig, Entry, ExecutorFactory};
+use eyre::{Context, Result};
+
+use crate::payloads::{TaskDump, TaskStats};
+use crate::reqres::{TaskPayload, TaskResponder};
+
+use super::publish::{publish_channel, OverflowPolicy};
+use super::task::{TaskWorker, TaskWorkerInput, TaskWorkerOutput};
+
+/// Re-executes a recorded task locally, using the same execution path as the workers.
+///
+/// The recorded model is used, so it must be available w.r.t the given configuration.
+/// The executor is created with the given factory, which is [`WorkflowExecutor::factory`](dkn_workflows::WorkflowExecutor::factory) for the actual providers.
+pub async fn replay_task(
+    dump: &TaskDump,
+    workflows: &DriaWorkflowsConfig,
+    executor_factory: &ExecutorFactory,
+) -> Result<TaskWorkerOutput> {
+    let task = serde_json::from_value::<TaskPayload>(dump.input.clone())
+        .wrap_err("could not parse task input")?;
+    let (model_provider, model) = workflows
+        .get_any_matching_model(vec![dump.model.clone()])
+        .wrap_err("recorded model is not available")?;
+    let (executor, batchable) =
+        TaskResponder::new_executor(executor_factory, workflows, model_provider, model, None);
+
+    let input = TaskWorkerInput {
+        entry: task.prompt.map(|prompt| Entry::try_value_or_str(&prompt)),
+        executor,
+        workflow: task.workflow,
+        task_id: dump.task_id.clone(),
+        trace_id: uuid::Uuid::new_v4().to_string(),
+        stats: TaskStats::new().record_received_at(),
+        batchable,
+        judge: None,
+        constraints: task.constraints,
+    };
+
+    let (publish_tx, mut publish_rx) =
+        publish_channel(1, OverflowPolicy::Block, Default::default())?;
+    TaskWorker::execute((input, &publish_tx), None).await;
+    publish_rx
+        .recv()
+        .await
+        .ok_or(eyre::eyre!("no output for the replayed task"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use dkn_utils::storage::MemoryStorage;
+    use dkn_workflows::{
+        ExecutionError, ExecutorCapabilities, ProgramMemory, TaskExecutor, Workflow,
+    };
+    use libsecp256k1::{PublicKey, SecretKey};
+    use std::sync::Arc;
+
+    use crate::utils::TaskArchive;
+
+    /// Executor that answers every task with the same output, instead of calling a provider.
+    struct EchoExecutor;
+
+    #[async_trait]
+    impl TaskExecutor for EchoExecutor {
+        async fn execute(
+            &self,
+            entry: Option<&Entry>,
+            _: &Workflow,
+            _: &mut ProgramMemory,
+        ) -> Result<String, ExecutionError> {
+            match entry {
+                Some(Entry::String(prompt)) => Ok(format!("echo: {}", prompt)),
+                _ => Err(ExecutionError::InvalidInput),
+            }
+        }
+
+        async fn check(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn capabilities(&self) -> ExecutorCapabilities {
+            ExecutorCapabilities {
+                models: vec!["gpt-4o-mini".to_string()],
+                batchable: true,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_archived_task() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let public_key = PublicKey::from_secret_key(&secret_key);
+
+        let dump = TaskDump {
+            task_id: "task-1".to_string(),
+            input: serde_json::json!({
+                "workflow": {
+                    "config": { "max_steps": 10, "max_time": 250, "tools": [""] },
+                    "tasks": [
+                        {
+                            "id": "A",
+                            "name": "",
+                            "description": "",
+                            "operator": "generation",
+                            "messages": [{ "role": "user", "content": "{{prompt}}" }],
+                            "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+                        },
+                        {
+                            "id": "__end",
+                            "name": "end",
+                            "description": "End of the task",
+                            "operator": "end",
+                            "messages": [{ "role": "user", "content": "End of the task" }]
+                        }
+                    ],
+                    "steps": [{ "source": "A", "target": "__end" }],
+                    "return_value": { "input": { "type": "read", "key": "result" } }
+                },
+                "model": ["gpt-4o-mini"],
+                "prompt": "hi"
+            }),
+            model: "gpt-4o-mini".to_string(),
+            result: Some("echo: hi".to_string()),
+            error: None,
+            stats: None,
+        };
+
+        let archive = TaskArchive::new(Arc::new(MemoryStorage::default()), 1024 * 1024);
+        archive.store(&dump, &public_key).unwrap();
+        let archived = archive.load("task-1", &secret_key).unwrap();
+
+        let workflows = DriaWorkflowsConfig::new_from_csv(&archived.model);
+        let factory: ExecutorFactory = Arc::new(|_, _, _| Box::new(EchoExecutor));
+        let output = replay_task(&archived, &workflows, &factory).await.unwrap();
+        assert_eq!(output.task_id, "task-1");
+        assert_eq!(output.result.ok(), dump.result);
+    }
+}
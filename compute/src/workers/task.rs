@@ -1,30 +1,112 @@
-use dkn_p2p::libp2p::request_response::ResponseChannel;
-use dkn_workflows::{Entry, ExecutionError, Executor, Workflow};
+use dkn_p2p::{libp2p::PeerId, RequestChannel};
+use dkn_workflows::{
+    Entry, ExecutionError, Judge, Judgement, TaskConstraints, TaskExecutor, Workflow,
+};
 use libsecp256k1::PublicKey;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::mpsc;
 
-use crate::payloads::TaskStats;
+use super::publish::PublishSender;
+use crate::payloads::{TaskErrorClass, TaskStats};
+
+/// Number of nanoseconds past its deadline after which a pending task is considered stale.
+pub(crate) const STALE_TASK_GRACE_NANOS: u128 = 5 * 60 * 1_000_000_000;
+
+/// An error of a task that occurs outside of its execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    /// The task is long past its deadline and its requester no longer waits for a response.
+    Expired { deadline: u128 },
+    /// The task is rejected as the node takes no more tasks for a while.
+    RateLimited { retry_after: Duration },
+    /// The task asks for models that are not served by this node.
+    ModelNotServed { reason: String },
+    /// A task with the same id is still pending, which is responded to instead.
+    Duplicate { task_id: String },
+}
+
+impl TaskError {
+    /// Returns the number of seconds after which the task can be sent again, if it was rate limited.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            Self::RateLimited { retry_after } => Some(retry_after.as_secs_f64().ceil() as u64),
+            Self::Expired { .. } | Self::ModelNotServed { .. } | Self::Duplicate { .. } => None,
+        }
+    }
+
+    /// Returns the class of the error, an expired task fails on any node.
+    pub fn class(&self) -> TaskErrorClass {
+        match self {
+            Self::RateLimited { .. } => TaskErrorClass::RateLimited,
+            Self::Expired { .. } | Self::Duplicate { .. } => TaskErrorClass::BadRequest,
+            Self::ModelNotServed { .. } => TaskErrorClass::ModelUnavailable,
+        }
+    }
+}
+
+impl std::fmt::Display for TaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expired { deadline } => write!(f, "expired after its deadline {}", deadline),
+            Self::RateLimited { retry_after } => write!(
+                f,
+                "rate limited, retry after {} seconds",
+                retry_after.as_secs_f64().ceil()
+            ),
+            Self::ModelNotServed { reason } => write!(f, "model not served: {}", reason),
+            Self::Duplicate { task_id } => write!(f, "task {} is already pending", task_id),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
 
 pub struct TaskWorkerMetadata {
+    /// The RPC that the task is responded to.
+    pub peer_id: PeerId,
     pub public_key: PublicKey,
     pub model_name: String,
-    pub channel: ResponseChannel<Vec<u8>>,
+    /// Trace id of the request, attached to the response.
+    pub trace_id: String,
+    /// Deadline of the task in nanoseconds.
+    pub deadline: u128,
+    /// Raw input of the task, only kept if the task is to be archived.
+    pub input: Option<serde_json::Value>,
+    pub channel: RequestChannel,
+}
+
+impl TaskWorkerMetadata {
+    /// Returns `true` if the task is long past its deadline and its response channel is dropped,
+    /// e.g. the connection to the requester was lost, so that it can never be responded to.
+    pub fn is_stale(&self, now: u128) -> bool {
+        now > self.deadline.saturating_add(STALE_TASK_GRACE_NANOS) && !self.channel.is_open()
+    }
 }
 
 pub struct TaskWorkerInput {
     pub entry: Option<Entry>,
-    pub executor: Executor,
+    pub executor: Box<dyn TaskExecutor>,
     pub workflow: Workflow,
     pub task_id: String,
+    /// Trace id of the request, included within the logs of the task.
+    pub trace_id: String,
     pub stats: TaskStats,
     pub batchable: bool,
+    /// Judge to score the result with, along with the instruction of the task.
+    pub judge: Option<(Arc<Judge>, String)>,
+    /// Constraints on the output, if the task has any.
+    pub constraints: Option<TaskConstraints>,
 }
 
 pub struct TaskWorkerOutput {
     pub result: Result<String, ExecutionError>,
+    /// Class of the error given by the executor, if the execution failed.
+    pub error_class: Option<TaskErrorClass>,
     pub task_id: String,
     pub stats: TaskStats,
     pub batchable: bool,
+    /// Score of the result, if it was judged.
+    pub judgement: Option<Judgement>,
 }
 
 /// Workflows worker is a task executor that can process workflows in parallel / series.
@@ -34,12 +116,13 @@ pub struct TaskWorker {
     /// Workflow message channel receiver, the sender is most likely the compute node itself.
     task_rx: mpsc::Receiver<TaskWorkerInput>,
     /// Publish message channel sender, the receiver is most likely the compute node itself.
-    publish_tx: mpsc::Sender<TaskWorkerOutput>,
+    publish_tx: PublishSender,
+    /// Maximum duration of a single task execution, if any.
+    ///
+    /// A stuck execution (e.g. a hanging provider call) is aborted after this duration.
+    execution_timeout: Option<Duration>,
 }
 
-/// Buffer size for workflow tasks (per worker).
-const TASK_RX_CHANNEL_BUFSIZE: usize = 1024;
-
 impl TaskWorker {
     /// Batch size that defines how many tasks can be executed concurrently at once.
     ///
@@ -48,19 +131,29 @@ impl TaskWorker {
     pub const MAX_BATCH_SIZE: usize = 8;
 
     /// Creates a worker and returns the sender and receiver for the worker.
+    ///
+    /// The task channel of the worker is buffered with `channel_bufsize` tasks.
     pub fn new(
-        publish_tx: mpsc::Sender<TaskWorkerOutput>,
+        publish_tx: PublishSender,
+        channel_bufsize: usize,
     ) -> (TaskWorker, mpsc::Sender<TaskWorkerInput>) {
-        let (task_tx, task_rx) = mpsc::channel(TASK_RX_CHANNEL_BUFSIZE);
+        let (task_tx, task_rx) = mpsc::channel(channel_bufsize);
 
         let worker = TaskWorker {
             task_rx,
             publish_tx,
+            execution_timeout: None,
         };
 
         (worker, task_tx)
     }
 
+    /// Sets the maximum duration of a single task execution.
+    pub fn with_execution_timeout(mut self, timeout: Duration) -> Self {
+        self.execution_timeout = Some(timeout);
+        self
+    }
+
     /// Closes the workflow receiver channel.
     fn shutdown(&mut self) {
         log::info!("Closing workflows worker.");
@@ -76,8 +169,12 @@ impl TaskWorker {
             let task = self.task_rx.recv().await;
 
             if let Some(task) = task {
-                log::info!("Processing task {} (single)", task.task_id);
-                TaskWorker::execute((task, &self.publish_tx)).await
+                log::info!(
+                    "Processing task {} (single, trace {})",
+                    task.task_id,
+                    task.trace_id
+                );
+                TaskWorker::execute((task, &self.publish_tx), self.execution_timeout).await
             } else {
                 return self.shutdown();
             };
@@ -130,72 +227,73 @@ impl TaskWorker {
             debug_assert!(num_tasks != 0, "number of tasks cant be zero");
 
             log::info!("Processing {} tasks in batch", num_tasks);
+            let timeout = self.execution_timeout;
             let mut batch = tasks.into_iter().map(|b| (b, &self.publish_tx));
             match num_tasks {
                 1 => {
-                    TaskWorker::execute(batch.next().unwrap()).await;
+                    TaskWorker::execute(batch.next().unwrap(), timeout).await;
                 }
                 2 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 3 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 4 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 5 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 6 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 7 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 8 => {
                     tokio::join!(
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap()),
-                        TaskWorker::execute(batch.next().unwrap())
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout),
+                        TaskWorker::execute(batch.next().unwrap(), timeout)
                     );
                 }
                 _ => {
@@ -210,39 +308,105 @@ impl TaskWorker {
     }
 
     /// Executes a single task, and publishes the output.
+    ///
+    /// If a timeout is given and the execution takes longer, it is aborted and an error is published instead.
     pub async fn execute(
-        (mut input, publish_tx): (TaskWorkerInput, &mpsc::Sender<TaskWorkerOutput>),
+        (mut input, publish_tx): (TaskWorkerInput, &PublishSender),
+        timeout: Option<Duration>,
     ) {
         input.stats = input.stats.record_execution_started_at();
-        let result = input
-            .executor
-            .execute(
-                input.entry.as_ref(),
-                &input.workflow,
-                &mut Default::default(),
-            )
-            .await;
+        let mut memory = Default::default();
+        let (executor, constraints) = (input.executor.as_ref(), input.constraints.as_ref());
+        // a corrective re-prompt for the constraints is a part of the execution
+        let execution = async {
+            let result = executor
+                .execute(input.entry.as_ref(), &input.workflow, &mut memory)
+                .await?;
+            match constraints {
+                Some(constraints) => constraints.enforce(executor, result).await,
+                None => Ok(result),
+            }
+        };
+        let result = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, execution).await {
+                Ok(result) => result,
+                Err(_) => {
+                    log::error!(
+                        "Watchdog: aborted stalled task {} (trace {}) after {} seconds",
+                        input.task_id,
+                        input.trace_id,
+                        timeout.as_secs()
+                    );
+                    Err(ExecutionError::WorkflowFailed(format!(
+                        "execution aborted after {} seconds",
+                        timeout.as_secs()
+                    )))
+                }
+            },
+            None => execution.await,
+        };
         input.stats = input.stats.record_execution_ended_at();
+        let error_class = result.as_ref().err().map(|e| executor.classify_error(e));
+        if let Ok(ref result) = result {
+            input.stats = input.stats.record_result_bytes(result.len());
+        }
+
+        // the judge is not counted within the execution time, but it is bounded by the same timeout
+        let judgement = match (&result, input.judge) {
+            (Ok(result), Some((judge, instruction))) => {
+                let judgement = judge.judge(&instruction, result);
+                let judgement = match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, judgement)
+                        .await
+                        .unwrap_or_else(|_| Err(eyre::eyre!("judge timed out"))),
+                    None => judgement.await,
+                };
+                judgement
+                    .inspect(|judgement| {
+                        // the rationale is not sent within the response, so it is only logged locally
+                        log::debug!(
+                            "Judged task {} (trace {}) with score {}: {}",
+                            input.task_id,
+                            input.trace_id,
+                            judgement.score,
+                            judgement.rationale
+                        )
+                    })
+                    .inspect_err(|e| {
+                        log::warn!(
+                            "Could not judge task {} (trace {}): {:?}",
+                            input.task_id,
+                            input.trace_id,
+                            e
+                        )
+                    })
+                    .ok()
+            }
+            _ => None,
+        };
 
         let output = TaskWorkerOutput {
             result,
+            error_class,
             task_id: input.task_id,
             batchable: input.batchable,
             stats: input.stats,
+            judgement,
         };
 
         if let Err(e) = publish_tx.send(output).await {
-            log::error!("Error sending workflow result: {}", e);
+            log::error!("Error sending workflow result: {:?}", e);
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use dkn_workflows::{Executor, Model};
+    use dkn_workflows::{DriaWorkflowsConfig, Model, ModelProvider, WorkflowExecutor};
 
     use super::*;
     use crate::payloads::TaskStats;
+    use crate::workers::publish::{publish_channel, OverflowPolicy};
 
     /// Tests the workflows worker with a single task sent within a batch.
     ///
@@ -260,8 +424,9 @@ mod tests {
             .is_test(true)
             .try_init();
 
-        let (publish_tx, mut publish_rx) = mpsc::channel(1024);
-        let (mut worker, task_tx) = TaskWorker::new(publish_tx);
+        let (publish_tx, mut publish_rx) =
+            publish_channel(1024, OverflowPolicy::Block, Default::default()).unwrap();
+        let (mut worker, task_tx) = TaskWorker::new(publish_tx, 1024);
 
         // create batch workflow worker
         let worker_handle = tokio::spawn(async move {
@@ -303,14 +468,21 @@ mod tests {
 
             let workflow = serde_json::from_value(workflow.clone()).unwrap();
 
-            let executor = Executor::new(model.clone());
+            let executor = Box::new(WorkflowExecutor::new(
+                &DriaWorkflowsConfig::default(),
+                ModelProvider::OpenAI,
+                model.clone(),
+            ));
             let task_input = TaskWorkerInput {
                 entry: None,
                 executor,
                 workflow,
                 task_id: format!("task-{}", i + 1),
+                trace_id: format!("trace-{}", i + 1),
                 stats: TaskStats::default(),
                 batchable: true,
+                judge: None,
+                constraints: None,
             };
 
             // send workflow to worker
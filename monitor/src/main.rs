@@ -1,6 +1,7 @@
 use dkn_compute::refresh_dria_nodes;
 use dkn_p2p::{
-    libp2p_identity::Keypair, DriaNetworkType, DriaNodes, DriaP2PClient, DriaP2PProtocol,
+    libp2p_identity::Keypair, ConnectionPolicy, DriaNetworkType, DriaNodes, DriaP2PClient,
+    DriaP2PConfig, DriaP2PProtocol, ListenAddr,
 };
 use tokio_util::sync::CancellationToken;
 
@@ -23,18 +24,18 @@ async fn main() -> eyre::Result<()> {
         .map(|s| DriaNetworkType::from(s.as_str()))
         .unwrap_or(DriaNetworkType::Pro);
     let mut nodes = DriaNodes::new(network);
-    refresh_dria_nodes(&mut nodes).await?;
+    refresh_dria_nodes(&reqwest::Client::new(), &mut nodes).await?;
 
     // setup p2p client
-    let listen_addr = "/ip4/0.0.0.0/tcp/4069".parse()?;
-    log::info!("Listen Address: {}", listen_addr);
+    let listen_addr: ListenAddr = "/ip4/0.0.0.0/tcp/4069".parse()?;
+    log::info!("Listen Address: {}", listen_addr.addr);
     let keypair = Keypair::generate_secp256k1();
     log::info!("PeerID: {}", keypair.public().to_peer_id());
     let (client, commander, msg_rx, _) = DriaP2PClient::new(
         keypair,
-        listen_addr,
         &nodes,
         DriaP2PProtocol::new_major_minor(network.protocol_name()),
+        DriaP2PConfig::new(vec![listen_addr]).with_policy(ConnectionPolicy::for_nodes(&nodes)),
     )?;
 
     // spawn p2p task
@@ -5,11 +5,17 @@ use std::time::Duration;
 use eyre::{eyre, Context, Result};
 use libp2p::identity::{Keypair, PeerId, PublicKey};
 use libp2p::kad::store::MemoryStore;
+use libp2p::swarm::behaviour::toggle::Toggle;
 use libp2p::StreamProtocol;
 use libp2p::{
-    autonat, connection_limits, dcutr, gossipsub, identify, kad, relay, request_response,
+    allow_block_list, autonat, dcutr, gossipsub, identify, kad, mdns, ping, relay, request_response,
 };
 
+use crate::chunk::{CHUNK_REQUEST_SIZE_MAXIMUM, CHUNK_RESPONSE_SIZE_MAXIMUM};
+use crate::limits::ExemptConnectionLimits;
+use crate::policy::KeepAliveBehaviour;
+use crate::{DriaP2PConfig, DriaP2PProtocol, ResourceUsage};
+
 #[derive(libp2p::swarm::NetworkBehaviour)]
 pub struct DriaBehaviour {
     pub relay: relay::client::Behaviour,
@@ -18,64 +24,109 @@ pub struct DriaBehaviour {
     pub identify: identify::Behaviour,
     pub autonat: autonat::Behaviour,
     pub dcutr: dcutr::Behaviour,
-    pub connection_limits: connection_limits::Behaviour,
+    pub connection_limits: ExemptConnectionLimits,
+    /// Peers whose connections are denied, e.g. due to misbehaviour.
+    pub blocked_peers: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
     pub request_response: request_response::cbor::Behaviour<Vec<u8>, Vec<u8>>,
+    /// Chunks of the large responses, on a protocol of their own.
+    pub chunks: request_response::cbor::Behaviour<Vec<u8>, Vec<u8>>,
+    pub ping: ping::Behaviour,
+    pub keep_alive: KeepAliveBehaviour,
+    /// Discovery of the peers within the local network, disabled unless enabled by the client.
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
 }
 
 impl DriaBehaviour {
     pub fn new(
         key: &Keypair,
         relay_behaviour: relay::client::Behaviour,
-        identity_protocol: String,
-        kademlia_protocol: StreamProtocol,
-        reqres_protocol: StreamProtocol,
+        protocol: &DriaP2PProtocol,
+        config: &DriaP2PConfig,
     ) -> Result<Self> {
         let public_key = key.public();
         let peer_id = public_key.to_peer_id();
 
         Ok(Self {
-            connection_limits: create_connection_limits_behaviour(),
+            connection_limits: ExemptConnectionLimits::new(
+                &config.limits,
+                config.policy.keep_alive_peers.clone(),
+                config.policy.short_lived_peers.clone(),
+            ),
+            blocked_peers: allow_block_list::Behaviour::default(),
             relay: relay_behaviour,
             dcutr: create_dcutr_behaviour(peer_id),
             autonat: create_autonat_behaviour(peer_id),
-            identify: create_identify_behaviour(public_key, identity_protocol),
-            kademlia: create_kademlia_behaviour(peer_id, kademlia_protocol),
-            gossipsub: create_gossipsub_behaviour(peer_id)?,
-            request_response: create_request_response_behaviour(reqres_protocol),
+            identify: create_identify_behaviour(public_key, protocol.identity()),
+            kademlia: create_kademlia_behaviour(peer_id, protocol.kademlia()),
+            gossipsub: create_gossipsub_behaviour(
+                peer_id,
+                &config.resources,
+                config.score_thresholds.clone(),
+            )?,
+            request_response: create_request_response_behaviour(protocol),
+            chunks: create_chunks_behaviour(protocol),
+            ping: create_ping_behaviour(config.resources.ping_interval),
+            keep_alive: KeepAliveBehaviour::new(config.policy.keep_alive_peers.clone()),
+            mdns: Toggle::from(None),
         })
     }
 }
 
+/// Configures the mDNS behaviour, to discover the nodes running within the same local network.
+#[inline]
+pub(crate) fn create_mdns_behaviour(local_peer_id: PeerId) -> Result<mdns::tokio::Behaviour> {
+    mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id)
+        .wrap_err("could not create mDNS behaviour")
+}
+
 /// Configures the request-response behaviour for the node.
 ///
-/// The protocol supports bytes only,
+/// The protocol supports bytes only, within the size limits of the given protocol.
 #[inline]
 fn create_request_response_behaviour(
-    protocol_name: StreamProtocol,
+    protocol: &DriaP2PProtocol,
 ) -> request_response::cbor::Behaviour<Vec<u8>, Vec<u8>> {
     use request_response::{Behaviour, Config, ProtocolSupport};
 
     const REQUEST_RESPONSE_TIMEOUT_SECS: u64 = 180;
 
-    Behaviour::new(
-        [(protocol_name, ProtocolSupport::Full)],
+    let codec = request_response::cbor::codec::Codec::default()
+        .set_request_size_maximum(protocol.max_request_size)
+        .set_response_size_maximum(protocol.max_response_size);
+
+    Behaviour::with_codec(
+        codec,
+        [(protocol.request_response(), ProtocolSupport::Full)],
         Config::default().with_request_timeout(Duration::from_secs(REQUEST_RESPONSE_TIMEOUT_SECS)),
     )
 }
 
-/// Configures the connection limits.
+/// Configures the request-response behaviour for the chunks of the large responses.
+///
+/// Its frames are limited to the size of a single chunk.
 #[inline]
-fn create_connection_limits_behaviour() -> connection_limits::Behaviour {
-    use connection_limits::{Behaviour, ConnectionLimits};
+fn create_chunks_behaviour(
+    protocol: &DriaP2PProtocol,
+) -> request_response::cbor::Behaviour<Vec<u8>, Vec<u8>> {
+    use request_response::{Behaviour, Config, ProtocolSupport};
 
-    /// Number of established outgoing connections limit, this is directly correlated to peer count
-    /// so limiting this will cause a limitation on peers as well.
-    const EST_OUTGOING_LIMIT: u32 = 300;
+    const CHUNK_REQUEST_TIMEOUT_SECS: u64 = 60;
 
-    let limits =
-        ConnectionLimits::default().with_max_established_outgoing(Some(EST_OUTGOING_LIMIT));
+    let codec = request_response::cbor::codec::Codec::default()
+        .set_request_size_maximum(CHUNK_REQUEST_SIZE_MAXIMUM)
+        .set_response_size_maximum(CHUNK_RESPONSE_SIZE_MAXIMUM);
 
-    Behaviour::new(limits)
+    Behaviour::with_codec(
+        codec,
+        [(protocol.request_response_chunks(), ProtocolSupport::Full)],
+        Config::default().with_request_timeout(Duration::from_secs(CHUNK_REQUEST_TIMEOUT_SECS)),
+    )
+}
+
+/// Configures the ping behaviour, used to measure round-trip times to connected peers.
+#[inline]
+fn create_ping_behaviour(interval: Duration) -> ping::Behaviour {
+    ping::Behaviour::new(ping::Config::new().with_interval(interval))
 }
 
 /// Configures the Kademlia DHT behavior for the node.
@@ -134,19 +185,23 @@ fn create_autonat_behaviour(local_peer_id: PeerId) -> autonat::Behaviour {
 }
 
 /// Configures the Gossipsub behavior for pub/sub messaging across peers.
+///
+/// Peers are scored with the default parameters if `score_thresholds` are given.
 #[inline]
-fn create_gossipsub_behaviour(author: PeerId) -> Result<gossipsub::Behaviour> {
+fn create_gossipsub_behaviour(
+    author: PeerId,
+    resources: &ResourceUsage,
+    score_thresholds: Option<gossipsub::PeerScoreThresholds>,
+) -> Result<gossipsub::Behaviour> {
     use gossipsub::{
-        Behaviour, ConfigBuilder, Message, MessageAuthenticity, MessageId, ValidationMode,
+        Behaviour, ConfigBuilder, Message, MessageAuthenticity, MessageId, PeerScoreParams,
+        ValidationMode,
     };
 
     /// We accept permissive validation mode, meaning that we accept all messages
     /// and check their fields based on whether they exist or not.
     const VALIDATION_MODE: ValidationMode = ValidationMode::Permissive;
 
-    /// Heartbeat interval in seconds
-    const HEARTBEAT_INTERVAL_SECS: u64 = 10;
-
     /// Duplicate cache time in seconds
     const DUPLICATE_CACHE_TIME_SECS: u64 = 120;
 
@@ -183,10 +238,16 @@ fn create_gossipsub_behaviour(author: PeerId) -> Result<gossipsub::Behaviour> {
         MessageId::from(digest.to_be_bytes())
     };
 
-    Behaviour::new(
+    let (mesh_n_low, mesh_n, mesh_n_high, mesh_outbound_min) = resources.mesh_params();
+
+    let mut behaviour = Behaviour::new(
         MessageAuthenticity::Author(author),
         ConfigBuilder::default()
-            .heartbeat_interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS))
+            .heartbeat_interval(resources.heartbeat_interval)
+            .mesh_n_low(mesh_n_low)
+            .mesh_n(mesh_n)
+            .mesh_n_high(mesh_n_high)
+            .mesh_outbound_min(mesh_outbound_min)
             .max_transmit_size(MAX_TRANSMIT_SIZE)
             .message_id_fn(message_id_fn)
             .duplicate_cache_time(Duration::from_secs(DUPLICATE_CACHE_TIME_SECS))
@@ -203,5 +264,13 @@ fn create_gossipsub_behaviour(author: PeerId) -> Result<gossipsub::Behaviour> {
             .build()
             .wrap_err(eyre!("could not create Gossipsub config"))?,
     )
-    .map_err(|e| eyre!(e))
+    .map_err(|e| eyre!(e))?;
+
+    if let Some(thresholds) = score_thresholds {
+        behaviour
+            .with_peer_score(PeerScoreParams::default(), thresholds)
+            .map_err(|e| eyre!("could not enable peer scoring: {}", e))?;
+    }
+
+    Ok(behaviour)
 }
@@ -0,0 +1,390 @@
+//! Chunked transfer of the large responses, to the peers that support it.
+//!
+//! The peers that support chunked transfer advertise the chunk protocol within their identify info,
+//! see [`DriaP2PProtocol::request_response_chunks`](crate::DriaP2PProtocol). A large response to such a
+//! peer is split into chunks, and the first one is sent as the response. Each chunk is framed as
+//! `DRIACHNK | transfer id (u64) | index (u32) | total (u32) | data`, with big-endian integers.
+//! The requester gets the rest of the chunks over the chunk protocol with requests framed as
+//! `DRIACHRQ | transfer id (u64) | index (u32)`, and reassembles the response in order.
+
+use libp2p::PeerId;
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::BuildHasher,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Prefix of a response frame that is a chunk of a larger response.
+const CHUNK_MAGIC: &[u8; 8] = b"DRIACHNK";
+/// Prefix of a request for a chunk of a larger response.
+const CHUNK_REQUEST_MAGIC: &[u8; 8] = b"DRIACHRQ";
+/// Length of the header of a chunk frame.
+const CHUNK_HEADER_LEN: usize = 8 + 8 + 4 + 4;
+/// Length of a chunk request frame.
+const CHUNK_REQUEST_LEN: usize = 8 + 8 + 4;
+/// Room left for the encoding of the codec within a frame.
+const CODEC_OVERHEAD: usize = 64;
+/// Duration that the chunks of a transfer are kept for, same as the request timeout.
+const TRANSFER_TTL: Duration = Duration::from_secs(180);
+/// Number of times that each chunk of a transfer can be served, to allow for a few retries.
+const MAX_SERVES_PER_CHUNK: u8 = 2;
+/// Number of the largest responses whose chunks can be kept at once, see [`ChunkStore::new`].
+pub(crate) const MAX_STORED_RESPONSES: u64 = 4;
+
+/// Maximum number of bytes of data within a chunk.
+pub(crate) const CHUNK_SIZE: usize = 256 * 1024;
+/// Maximum size of a chunk request on the wire.
+pub(crate) const CHUNK_REQUEST_SIZE_MAXIMUM: u64 = (2 * CHUNK_REQUEST_LEN + CODEC_OVERHEAD) as u64;
+/// Maximum size of a chunk on the wire, where the cbor codec may encode a byte within two.
+pub(crate) const CHUNK_RESPONSE_SIZE_MAXIMUM: u64 =
+    (2 * (CHUNK_HEADER_LEN + CHUNK_SIZE) + CODEC_OVERHEAD) as u64;
+
+/// A chunk of a response that is sent in multiple frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Identifier of the transfer that this chunk belongs to.
+    pub transfer_id: u64,
+    /// Index of the chunk, starting from 0.
+    pub index: u32,
+    /// Number of chunks of the transfer.
+    pub total: u32,
+    /// Data of the chunk.
+    pub data: Vec<u8>,
+}
+
+impl Chunk {
+    /// Encodes the chunk into a frame.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(CHUNK_HEADER_LEN + self.data.len());
+        frame.extend_from_slice(CHUNK_MAGIC);
+        frame.extend_from_slice(&self.transfer_id.to_be_bytes());
+        frame.extend_from_slice(&self.index.to_be_bytes());
+        frame.extend_from_slice(&self.total.to_be_bytes());
+        frame.extend_from_slice(&self.data);
+        frame
+    }
+
+    /// Decodes a chunk from a frame, returns `None` if the frame is not a chunk.
+    pub fn decode(frame: &[u8]) -> Option<Self> {
+        if frame.len() < CHUNK_HEADER_LEN || !frame.starts_with(CHUNK_MAGIC) {
+            return None;
+        }
+
+        Some(Self {
+            transfer_id: u64::from_be_bytes(frame[8..16].try_into().ok()?),
+            index: u32::from_be_bytes(frame[16..20].try_into().ok()?),
+            total: u32::from_be_bytes(frame[20..24].try_into().ok()?),
+            data: frame[CHUNK_HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// Encodes a request for the chunk at `index` of a transfer.
+    pub fn encode_request(transfer_id: u64, index: u32) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(CHUNK_REQUEST_LEN);
+        frame.extend_from_slice(CHUNK_REQUEST_MAGIC);
+        frame.extend_from_slice(&transfer_id.to_be_bytes());
+        frame.extend_from_slice(&index.to_be_bytes());
+        frame
+    }
+
+    /// Decodes a chunk request into its transfer id & index, returns `None` if the request is not for a chunk.
+    pub fn decode_request(frame: &[u8]) -> Option<(u64, u32)> {
+        if frame.len() != CHUNK_REQUEST_LEN || !frame.starts_with(CHUNK_REQUEST_MAGIC) {
+            return None;
+        }
+
+        Some((
+            u64::from_be_bytes(frame[8..16].try_into().ok()?),
+            u32::from_be_bytes(frame[16..20].try_into().ok()?),
+        ))
+    }
+}
+
+/// Chunks of a response that is being sent.
+#[derive(Debug)]
+struct Transfer {
+    /// The peer that the response is sent to, the only one that can request its chunks.
+    peer_id: PeerId,
+    created_at: Instant,
+    chunks: Vec<Vec<u8>>,
+    /// Number of times that each chunk was served so far.
+    served: Vec<u8>,
+    /// Number of bytes of data within the chunks.
+    size: usize,
+}
+
+/// Chunks of the large responses, kept until the requester gets them or they expire.
+#[derive(Debug)]
+pub(crate) struct ChunkStore {
+    /// Maximum number of bytes of data within a chunk.
+    chunk_size: usize,
+    /// Maximum number of bytes of data kept within the chunks of all transfers.
+    max_stored_bytes: usize,
+    /// Number of bytes of data kept within the chunks of all transfers.
+    stored_bytes: usize,
+    /// Chunks of each transfer.
+    transfers: HashMap<u64, Transfer>,
+    /// Source of the transfer ids, so that they can not be guessed by other peers.
+    random: RandomState,
+    /// Number of transfers so far.
+    count: u64,
+}
+
+impl ChunkStore {
+    /// Creates a store that splits the responses into chunks of at most `chunk_size` bytes,
+    /// and keeps at most `max_stored_bytes` bytes of chunks by dropping the oldest transfers.
+    pub fn new(chunk_size: usize, max_stored_bytes: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            max_stored_bytes,
+            stored_bytes: 0,
+            transfers: HashMap::new(),
+            random: RandomState::new(),
+            count: 0,
+        }
+    }
+
+    /// Returns the response to the given peer as is if it fits into a chunk, otherwise splits it
+    /// into chunks and returns the frame of the first one.
+    pub fn split(&mut self, peer_id: PeerId, data: Vec<u8>, now: Instant) -> Vec<u8> {
+        if data.len() <= self.chunk_size {
+            return data;
+        }
+        self.transfers
+            .retain(|_, transfer| now.duration_since(transfer.created_at) < TRANSFER_TTL);
+        self.stored_bytes = self.transfers.values().map(|t| t.size).sum();
+        // make room for the new transfer, the newest one is always kept
+        while self.stored_bytes + data.len() > self.max_stored_bytes {
+            let Some((&oldest_id, oldest)) =
+                self.transfers.iter().min_by_key(|(_, t)| t.created_at)
+            else {
+                break;
+            };
+            log::warn!(
+                "Dropping the chunks of a response to {} to keep at most {} bytes of chunks.",
+                oldest.peer_id,
+                self.max_stored_bytes
+            );
+            self.stored_bytes -= oldest.size;
+            self.transfers.remove(&oldest_id);
+        }
+
+        self.count += 1;
+        let transfer_id = self.random.hash_one(self.count);
+        let chunks = data
+            .chunks(self.chunk_size)
+            .map(|c| c.to_vec())
+            .collect::<Vec<_>>();
+        log::info!(
+            "Splitting a response of {} bytes to {} into {} chunks.",
+            data.len(),
+            peer_id,
+            chunks.len()
+        );
+
+        let first = Chunk {
+            transfer_id,
+            index: 0,
+            total: chunks.len() as u32,
+            data: chunks[0].clone(),
+        };
+        let mut served = vec![0; chunks.len()];
+        served[0] = 1;
+        self.stored_bytes += data.len();
+        self.transfers.insert(
+            transfer_id,
+            Transfer {
+                peer_id,
+                created_at: now,
+                chunks,
+                served,
+                size: data.len(),
+            },
+        );
+        first.encode()
+    }
+
+    /// Returns the frame of a chunk of an ongoing transfer to the given peer.
+    ///
+    /// Chunks of the transfers to other peers are not served, and neither are the chunks that were
+    /// served too many times already.
+    pub fn get(&mut self, peer_id: &PeerId, transfer_id: u64, index: u32) -> Option<Vec<u8>> {
+        let transfer = self
+            .transfers
+            .get_mut(&transfer_id)
+            .filter(|transfer| transfer.peer_id == *peer_id)?;
+        let served = transfer.served.get_mut(index as usize)?;
+        if *served >= MAX_SERVES_PER_CHUNK {
+            return None;
+        }
+        *served += 1;
+        let data = &transfer.chunks[index as usize];
+
+        Some(
+            Chunk {
+                transfer_id,
+                index,
+                total: transfer.chunks.len() as u32,
+                data: data.clone(),
+            }
+            .encode(),
+        )
+    }
+}
+
+/// Time of the first chunk of a transfer, along with the chunks received so far.
+type PartialTransfer = (Instant, Vec<Option<Vec<u8>>>);
+
+/// Reassembles the chunked responses from the chunks received so far.
+#[derive(Debug)]
+pub(crate) struct ChunkAssembler {
+    /// Maximum number of bytes of data within a chunk.
+    chunk_size: usize,
+    /// Maximum number of chunks of a transfer, so that a response can not be larger than the limit.
+    max_chunks: u32,
+    /// Chunks of each transfer that is not complete yet, w.r.t the peer that sends it.
+    transfers: HashMap<(PeerId, u64), PartialTransfer>,
+}
+
+impl ChunkAssembler {
+    /// Creates an assembler for responses of at most `max_response_size` bytes, in chunks of at most `chunk_size` bytes.
+    pub fn new(max_response_size: u64, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            chunk_size,
+            max_chunks: u32::try_from(max_response_size.div_ceil(chunk_size as u64))
+                .unwrap_or(u32::MAX),
+            transfers: HashMap::new(),
+        }
+    }
+
+    /// Starts reassembling a transfer of the given peer with its first chunk.
+    ///
+    /// Returns `false` if the chunk is invalid, e.g. the transfer would be larger than the limit,
+    /// in which case the rest of its chunks are not to be requested.
+    pub fn start(&mut self, peer_id: PeerId, chunk: Chunk, now: Instant) -> bool {
+        self.transfers
+            .retain(|_, (started_at, _)| now.duration_since(*started_at) < TRANSFER_TTL);
+        if chunk.index != 0
+            || chunk.total < 2
+            || chunk.total > self.max_chunks
+            || chunk.data.len() > self.chunk_size
+        {
+            log::warn!(
+                "Invalid first chunk from {}: {} of {} chunks with {} bytes",
+                peer_id,
+                chunk.index,
+                chunk.total,
+                chunk.data.len()
+            );
+            return false;
+        }
+
+        let mut chunks = vec![None; chunk.total as usize];
+        chunks[0] = Some(chunk.data);
+        self.transfers
+            .insert((peer_id, chunk.transfer_id), (now, chunks));
+        true
+    }
+
+    /// Adds a chunk to its transfer, returns the whole response once all of its chunks are received.
+    ///
+    /// Chunks of the transfers that are not started, or that do not match their transfer, are ignored.
+    pub fn add(&mut self, peer_id: PeerId, chunk: Chunk, now: Instant) -> Option<Vec<u8>> {
+        let key = (peer_id, chunk.transfer_id);
+        let (started_at, chunks) = self.transfers.get_mut(&key)?;
+        if now.duration_since(*started_at) >= TRANSFER_TTL
+            || chunk.total as usize != chunks.len()
+            || chunk.data.len() > self.chunk_size
+        {
+            return None;
+        }
+        *chunks.get_mut(chunk.index as usize)? = Some(chunk.data);
+        if chunks.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let (_, chunks) = self.transfers.remove(&key)?;
+        Some(chunks.into_iter().flatten().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked_transfer() {
+        let peer_id = PeerId::random();
+        let mut store = ChunkStore::new(60, 2000);
+        let mut assembler = ChunkAssembler::new(1200, 60);
+        let now = Instant::now();
+
+        // small responses are sent as they are
+        assert_eq!(store.split(peer_id, b"{}".to_vec(), now), b"{}".to_vec());
+        assert!(Chunk::decode(b"{}").is_none());
+
+        // large ones are split & reassembled
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        let first = Chunk::decode(&store.split(peer_id, data.clone(), now)).unwrap();
+        assert_eq!(first.index, 0);
+        assert_eq!(first.total, 17);
+        let transfer_id = first.transfer_id;
+        assert!(assembler.start(peer_id, first, now));
+
+        // the chunks are served to the requester only
+        assert!(store.get(&PeerId::random(), transfer_id, 1).is_none());
+        let mut reassembled = None;
+        for index in (1..17).rev() {
+            let request = Chunk::encode_request(transfer_id, index);
+            assert_eq!(Chunk::decode_request(&request), Some((transfer_id, index)));
+            let chunk = Chunk::decode(&store.get(&peer_id, transfer_id, index).unwrap()).unwrap();
+            assert!(assembler
+                .add(PeerId::random(), chunk.clone(), now)
+                .is_none());
+            reassembled = assembler.add(peer_id, chunk, now);
+        }
+        assert_eq!(reassembled.as_ref(), Some(&data));
+
+        // unknown chunks are not found, and each chunk is served at most twice
+        assert!(store.get(&peer_id, transfer_id, 17).is_none());
+        assert!(store.get(&peer_id, transfer_id + 1, 0).is_none());
+        assert!(store.get(&peer_id, transfer_id, 0).is_some());
+        assert!(store.get(&peer_id, transfer_id, 0).is_none());
+        assert!(store.get(&peer_id, transfer_id, 1).is_some());
+        assert!(store.get(&peer_id, transfer_id, 1).is_none());
+        assert!(store.get(&peer_id, transfer_id, 2).is_some());
+
+        // the oldest transfers are dropped to keep the stored bytes within the limit
+        let later = now + Duration::from_secs(1);
+        let second = Chunk::decode(&store.split(peer_id, data.clone(), later)).unwrap();
+        assert_eq!(store.stored_bytes, 2000);
+        let third = Chunk::decode(&store.split(peer_id, data.clone(), later)).unwrap();
+        assert_eq!(store.stored_bytes, 2000);
+        assert!(store.get(&peer_id, transfer_id, 3).is_none());
+        assert!(store.get(&peer_id, second.transfer_id, 1).is_some());
+        assert!(store.get(&peer_id, third.transfer_id, 1).is_some());
+
+        // transfers larger than the limit are rejected
+        let chunk = Chunk {
+            transfer_id,
+            index: 0,
+            total: u32::MAX,
+            data: vec![0; 60],
+        };
+        assert!(!assembler.start(peer_id, chunk, now));
+        assert!(assembler
+            .add(
+                peer_id,
+                Chunk {
+                    transfer_id,
+                    index: 1,
+                    total: u32::MAX,
+                    data: vec![]
+                },
+                now
+            )
+            .is_none());
+    }
+}
@@ -1,20 +1,31 @@
-use eyre::Result;
+use eyre::{eyre, Result};
 use libp2p::futures::StreamExt;
 use libp2p::gossipsub::{Message, MessageId};
 use libp2p::kad::{GetClosestPeersError, GetClosestPeersOk, QueryResult};
-use libp2p::request_response::{self, ResponseChannel};
+use libp2p::request_response;
 use libp2p::swarm::dial_opts::{DialOpts, PeerCondition};
-use libp2p::swarm::SwarmEvent;
-use libp2p::{autonat, gossipsub, identify, kad, multiaddr::Protocol, noise, tcp, yamux};
+use libp2p::swarm::{DialError, SwarmEvent};
+use libp2p::{
+    autonat, gossipsub, identify, kad, mdns, multiaddr::Protocol, noise, ping, relay, tcp, yamux,
+};
 use libp2p::{Multiaddr, PeerId, Swarm, SwarmBuilder};
 use libp2p_identity::Keypair;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 
-use crate::behaviour::{DriaBehaviour, DriaBehaviourEvent};
-use crate::{DriaNodes, DriaP2PProtocol};
+use crate::behaviour::{create_mdns_behaviour, DriaBehaviour, DriaBehaviourEvent};
+use crate::chunk::{ChunkAssembler, ChunkStore, CHUNK_SIZE, MAX_STORED_RESPONSES};
+use crate::listen::is_public_addr;
+use crate::redial::RedialScheduler;
+use crate::relay::RelaySelector;
+use crate::retry::{PendingRequest, PendingRequests};
+use crate::{
+    Chunk, ConnectionCaps, DriaNodes, DriaP2PConfig, DriaP2PProtocol, IpFamily, ListenAddr,
+};
 
-use super::commands::DriaP2PCommand;
+use super::commands::{ChannelStats, DriaP2PCommand, RequestChannel, RequestOutcomes, TopicInfo};
 use super::DriaP2PCommander;
 
 /// Peer-to-peer client for Dria Knowledge Network.
@@ -28,65 +39,160 @@ pub struct DriaP2PClient {
     /// Gossipsub protoocol, gossip message sender.
     msg_tx: mpsc::Sender<(PeerId, MessageId, Message)>,
     /// Request-response protocol, request sender.
-    req_tx: mpsc::Sender<(PeerId, Vec<u8>, ResponseChannel<Vec<u8>>)>,
+    req_tx: mpsc::Sender<(PeerId, Vec<u8>, RequestChannel)>,
+    /// Number of requests received, used to index them.
+    inbound_requests: u64,
     /// Command receiver.
     cmd_rx: mpsc::Receiver<DriaP2PCommand>,
+    /// Latest round-trip times of connected peers, measured with pings.
+    peer_rtts: HashMap<PeerId, Duration>,
+    /// Listen addresses whose external addresses are not advertised.
+    unadvertised_addrs: Vec<ListenAddr>,
+    /// Peers whose connections are closed soon after they are established.
+    short_lived_peers: HashSet<PeerId>,
+    /// Duration after which the connections to short-lived peers are closed.
+    short_lived_timeout: Duration,
+    /// Connection times of the connected short-lived peers.
+    short_lived_connections: HashMap<PeerId, Instant>,
+    /// RPC nodes to be dialled once a listen address is established.
+    pending_rpc_dials: Vec<Multiaddr>,
+    /// Creation time of the client.
+    started_at: Instant,
+    /// Time from the creation of the client to its first inbound connection, if any.
+    first_inbound_after: Option<Duration>,
+    /// Outcomes of the outbound requests since they were last taken.
+    request_outcomes: HashMap<PeerId, RequestOutcomes>,
+    /// Number of messages & requests that could not be sent to the node.
+    dropped: (u64, u64),
+    /// IP families of the latest connections to the connected peers.
+    peer_families: HashMap<PeerId, IpFamily>,
+    /// Peers discovered within the local network over mDNS, if enabled.
+    lan_peers: HashSet<PeerId>,
+    /// Relays that reservations are held with, selected by their health.
+    relays: RelaySelector,
+    /// Limits of the connections, as configured.
+    limits: ConnectionCaps,
+    /// Redials of the disconnected RPC nodes.
+    redials: RedialScheduler,
+    /// Outbound requests that await their responses.
+    pending_requests: PendingRequests,
+    /// Chunks of the large responses that are being sent.
+    chunks: ChunkStore,
+    /// Chunks of the received responses that are being reassembled.
+    assembler: ChunkAssembler,
+    /// Requests whose responses are being reassembled, w.r.t the peers & transfer ids of the responses.
+    chunked_requests: HashMap<(PeerId, u64), request_response::OutboundRequestId>,
+    /// Peers that support chunked responses, as per their identify info.
+    chunk_peers: HashSet<PeerId>,
+    /// Peers blocked within the behaviour, kept here as well so that they can be listed.
+    blocked_peers: HashSet<PeerId>,
+    /// Recorder of the inbound events, if enabled.
+    #[cfg(feature = "recorder")]
+    recorder: Option<crate::recorder::SessionRecorder>,
 }
 
-// TODO: make all these configurable
-/// Number of seconds before an idle connection is closed.
-const IDLE_CONNECTION_TIMEOUT_SECS: u64 = 60;
-/// Buffer size for command channel.
-const COMMAND_CHANNEL_BUFSIZE: usize = 1024;
-/// Buffer size for events channel.
-const MSG_CHANNEL_BUFSIZE: usize = 1024;
+/// Number of seconds between the checks of short-lived connections.
+const SHORT_LIVED_CHECK_INTERVAL_SECS: u64 = 10;
+/// Number of seconds to wait for a listen address before dialling the RPC nodes anyways.
+const RPC_DIAL_FALLBACK_SECS: u64 = 5;
+/// Number of seconds to measure the latencies of the relays before selecting them.
+const RELAY_PROBE_SECS: u64 = 5;
+/// Number of seconds between the checks of relay reservations.
+const RELAY_CHECK_INTERVAL_SECS: u64 = 30;
+/// Number of seconds between the checks of due redials.
+const REDIAL_CHECK_INTERVAL_SECS: u64 = 1;
+/// Number of seconds between the checks of timed out requests.
+const REQUEST_CHECK_INTERVAL_SECS: u64 = 1;
 
 impl DriaP2PClient {
-    /// Creates a new P2P client with the given keypair and listen address.
+    /// Creates a new P2P client with the given keypair, listening on the addresses within `config`.
     ///
     /// Can provide a list of bootstrap and relay nodes to connect to as well at the start, and RPC addresses to dial preemptively.
+    /// The client listens before dialing anyone, and RPCs are dialled once the client is listening, so that they can dial back.
+    ///
+    /// Connections are kept or closed w.r.t the policy of the `config`, see [`ConnectionPolicy::for_nodes`].
+    /// The gossip mesh, ping interval & channel buffers are sized w.r.t its resources, and peers are scored if it has score thresholds.
+    /// Only the Dria nodes with an address over its transport are dialled.
     ///
     /// The `version` is used to create the protocol strings for the client, and its very important that
     /// they match with the clients existing within the network.
     #[allow(clippy::type_complexity)]
     pub fn new(
         keypair: Keypair,
-        listen_addr: Multiaddr,
         nodes: &DriaNodes,
         protocol: DriaP2PProtocol,
+        config: DriaP2PConfig,
     ) -> Result<(
         DriaP2PClient,
         DriaP2PCommander,
         mpsc::Receiver<(PeerId, MessageId, Message)>,
-        mpsc::Receiver<(PeerId, Vec<u8>, ResponseChannel<Vec<u8>>)>,
+        mpsc::Receiver<(PeerId, Vec<u8>, RequestChannel)>,
     )> {
         // this is our peerId
         let peer_id = keypair.public().to_peer_id();
         log::info!("Compute node peer address: {}", peer_id);
+        if !config.transport.is_enabled() {
+            return Err(eyre!(
+                "{} transport is not enabled within this build",
+                config.transport
+            ));
+        }
 
-        let mut swarm = SwarmBuilder::with_existing_identity(keypair)
+        let builder = SwarmBuilder::with_existing_identity(keypair)
             .with_tokio()
             .with_tcp(
                 tcp::Config::default(),
                 noise::Config::new,
                 yamux::Config::default,
-            )?
-            .with_quic()
+            )?;
+        // without QUIC, its listen addresses are skipped as unsupported
+        #[cfg(feature = "quic")]
+        let builder = builder.with_quic();
+        // websockets are resolved over DNS, as the nodes behind a proxy are mostly given as `/dns/.../wss`
+        #[cfg(feature = "websocket")]
+        let builder = builder.with_other_transport(|key| {
+            use libp2p::Transport;
+
+            let tcp = libp2p::dns::tokio::Transport::system(tcp::tokio::Transport::new(
+                tcp::Config::default(),
+            ))?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(
+                libp2p::websocket::WsConfig::new(tcp)
+                    .upgrade(libp2p::core::upgrade::Version::V1)
+                    .authenticate(noise::Config::new(key)?)
+                    .multiplex(yamux::Config::default()),
+            )
+        })?;
+        // TCP addresses given with `/dns4`, `/dns6` or `/dnsaddr` are resolved before they are dialled;
+        // this comes after the websockets, which must see the domain name for their TLS handshake
+        let builder = builder.with_other_transport(|key| {
+            use libp2p::Transport;
+
+            let tcp = libp2p::dns::tokio::Transport::system(tcp::tokio::Transport::new(
+                tcp::Config::default(),
+            ))?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(
+                tcp.upgrade(libp2p::core::upgrade::Version::V1)
+                    .authenticate(noise::Config::new(key)?)
+                    .multiplex(yamux::Config::default()),
+            )
+        })?;
+        let mut swarm = builder
             .with_relay_client(noise::Config::new, yamux::Config::default)?
             .with_behaviour(|key, relay_behaviour| {
-                DriaBehaviour::new(
-                    key,
-                    relay_behaviour,
-                    protocol.identity(),
-                    protocol.kademlia(),
-                    protocol.request_response(),
-                )
-                .map_err(Into::into)
+                DriaBehaviour::new(key, relay_behaviour, &protocol, &config).map_err(Into::into)
             })?
-            .with_swarm_config(|c| {
-                c.with_idle_connection_timeout(Duration::from_secs(IDLE_CONNECTION_TIMEOUT_SECS))
-            })
+            .with_swarm_config(|c| c.with_idle_connection_timeout(config.policy.idle_timeout))
             .build();
+        let DriaP2PConfig {
+            listen_addrs,
+            policy,
+            resources,
+            transport,
+            limits,
+            request_retry,
+            ..
+        } = config;
 
         // set mode to server so that RPC nodes add us to the DHT
         swarm
@@ -94,8 +200,35 @@ impl DriaP2PClient {
             .kademlia
             .set_mode(Some(libp2p::kad::Mode::Server));
 
+        // listen before dialing anyone, so that our addresses are known to the peers we connect to
+        // an address that is not supported is skipped
+        let mut listening = false;
+        for listen_addr in listen_addrs.iter().filter(|l| l.enabled) {
+            log::info!("Listening p2p network on: {}", listen_addr.addr);
+            match swarm.listen_on(listen_addr.addr.clone()) {
+                Ok(_) => listening = true,
+                Err(e) => log::error!("Could not listen on {}: {:?}", listen_addr.addr, e),
+            }
+        }
+        if !listening {
+            return Err(eyre!("could not listen on any of the given addresses"));
+        }
+
+        // dial the relays so that their latencies are measured, they are listened on once selected
+        let relays = RelaySelector::new(nodes.relay_nodes.iter().filter(|a| transport.supports(a)));
+        for addr in relays.addresses() {
+            log::info!("Dialling relay: {}", addr);
+            if let Err(e) = swarm.dial(addr.clone()) {
+                log::warn!("Could not dial relay {}: {:?}", addr, e);
+            }
+        }
+
         // initiate bootstrap
-        for addr in &nodes.bootstrap_nodes {
+        for addr in nodes
+            .bootstrap_nodes
+            .iter()
+            .filter(|a| transport.supports(a))
+        {
             log::info!("Dialling bootstrap: {:#?}", addr);
             if let Some(peer_id) = addr.iter().find_map(|p| match p {
                 Protocol::P2p(peer_id) => Some(peer_id),
@@ -119,49 +252,112 @@ impl DriaP2PClient {
             .behaviour_mut()
             .kademlia
             .get_closest_peers(random_peer);
-        swarm.behaviour_mut().kademlia.bootstrap()?;
-
-        // listen on all interfaces for incoming connections
-        log::info!("Listening p2p network on: {}", listen_addr);
-        swarm.listen_on(listen_addr)?;
-
-        // listen on relay addresses with p2p circuit
-        for addr in &nodes.relay_nodes {
-            log::info!("Listening to relay: {}", addr);
-            swarm.listen_on(addr.clone().with(Protocol::P2pCircuit))?;
-        }
-
-        // dial rpc nodes
-        for rpc_addr in &nodes.rpc_nodes {
-            log::info!("Dialing RPC node: {}", rpc_addr);
-            if let Err(e) = swarm.dial(rpc_addr.clone()) {
-                log::error!("Error dialing RPC node: {:?}", e);
-            };
+        // nodes without bootstraps, e.g. within a local network, are found by the other means later
+        if let Err(e) = swarm.behaviour_mut().kademlia.bootstrap() {
+            log::warn!("Could not bootstrap Kademlia DHT: {:?}", e);
         }
 
         // create commander
-        let (cmd_tx, cmd_rx) = mpsc::channel(COMMAND_CHANNEL_BUFSIZE);
+        let (cmd_tx, cmd_rx) = mpsc::channel(resources.channel_bufsize);
         let commander = DriaP2PCommander::new(cmd_tx, protocol.clone());
 
         // create p2p client itself
-        let (msg_tx, msg_rx) = mpsc::channel(MSG_CHANNEL_BUFSIZE);
-        let (req_tx, req_rx) = mpsc::channel(MSG_CHANNEL_BUFSIZE);
+        let (msg_tx, msg_rx) = mpsc::channel(resources.channel_bufsize);
+        let (req_tx, req_rx) = mpsc::channel(resources.channel_bufsize);
+        // rpc nodes are dialled once we are listening, see `dial_pending_rpcs`, and redialled once disconnected
+        let pending_rpc_dials = nodes
+            .rpc_nodes
+            .iter()
+            .filter(|a| transport.supports(a))
+            .cloned()
+            .collect::<Vec<_>>();
+        let redials = RedialScheduler::new(&pending_rpc_dials, policy.max_redial_attempts);
+        let assembler = ChunkAssembler::new(protocol.max_response_size, CHUNK_SIZE);
+        let chunks = ChunkStore::new(
+            CHUNK_SIZE,
+            usize::try_from(MAX_STORED_RESPONSES.saturating_mul(protocol.max_response_size))
+                .unwrap_or(usize::MAX),
+        );
         let client = Self {
             peer_id,
             swarm,
             protocol,
             msg_tx,
             req_tx,
+            inbound_requests: 0,
             cmd_rx,
+            peer_rtts: HashMap::new(),
+            unadvertised_addrs: listen_addrs.into_iter().filter(|l| !l.advertise).collect(),
+            short_lived_peers: policy.short_lived_peers,
+            short_lived_timeout: policy.short_lived_timeout,
+            short_lived_connections: HashMap::new(),
+            pending_rpc_dials,
+            started_at: Instant::now(),
+            first_inbound_after: None,
+            request_outcomes: HashMap::new(),
+            dropped: (0, 0),
+            peer_families: HashMap::new(),
+            lan_peers: HashSet::new(),
+            relays,
+            limits,
+            redials,
+            pending_requests: PendingRequests::new(request_retry),
+            chunks,
+            assembler,
+            chunked_requests: HashMap::new(),
+            chunk_peers: HashSet::new(),
+            blocked_peers: HashSet::new(),
+            #[cfg(feature = "recorder")]
+            recorder: None,
         };
 
         Ok((client, commander, msg_rx, req_rx))
     }
 
+    /// Enables the discovery of the nodes within the local network over mDNS.
+    ///
+    /// Discovered nodes are dialled, so that the nodes running on the same LAN gossip with each other directly.
+    pub fn with_mdns(mut self) -> Result<Self> {
+        let mdns = create_mdns_behaviour(self.peer_id)?;
+        self.swarm.behaviour_mut().mdns = Some(mdns).into();
+        Ok(self)
+    }
+
+    /// Records the inbound messages, requests & responses to the given file, along with the responses of the node to the requests.
+    #[cfg(feature = "recorder")]
+    pub fn with_recorder(mut self, path: impl AsRef<std::path::Path>) -> Result<Self> {
+        self.recorder = Some(crate::recorder::SessionRecorder::new(path)?);
+        Ok(self)
+    }
+
+    /// Returns senders for the gossipsub message & request channels, so that recorded sessions can be replayed.
+    #[cfg(feature = "recorder")]
+    #[allow(clippy::type_complexity)]
+    pub fn replay_senders(
+        &self,
+    ) -> (
+        mpsc::Sender<(PeerId, MessageId, Message)>,
+        mpsc::Sender<(PeerId, Vec<u8>, RequestChannel)>,
+    ) {
+        (self.msg_tx.clone(), self.req_tx.clone())
+    }
+
     /// Waits for swarm events and Node commands at the same time.
     ///
     /// To terminate, the command channel must be closed.
     pub async fn run(mut self) {
+        let mut short_lived_check =
+            tokio::time::interval(Duration::from_secs(SHORT_LIVED_CHECK_INTERVAL_SECS));
+        let rpc_dial_fallback = tokio::time::sleep(Duration::from_secs(RPC_DIAL_FALLBACK_SECS));
+        tokio::pin!(rpc_dial_fallback);
+        let mut redial_check =
+            tokio::time::interval(Duration::from_secs(REDIAL_CHECK_INTERVAL_SECS));
+        let mut request_check =
+            tokio::time::interval(Duration::from_secs(REQUEST_CHECK_INTERVAL_SECS));
+        let mut relay_check = tokio::time::interval_at(
+            Instant::now() + Duration::from_secs(RELAY_PROBE_SECS),
+            Duration::from_secs(RELAY_CHECK_INTERVAL_SECS),
+        );
         loop {
             tokio::select! {
                 // this is a special keyword that changes the polling order from random to linear,
@@ -176,10 +372,107 @@ impl DriaP2PClient {
                     },
                 },
                 event = self.swarm.select_next_some() => self.handle_event(event).await,
+                _ = short_lived_check.tick() => self.close_short_lived_connections(),
+                _ = relay_check.tick() => self.listen_on_relays(),
+                _ = redial_check.tick() => self.redial_peers(),
+                _ = request_check.tick() => self.retry_expired_requests(),
+                _ = &mut rpc_dial_fallback, if !self.pending_rpc_dials.is_empty() => {
+                    log::warn!("No listen address yet, dialling RPC nodes anyways.");
+                    self.dial_pending_rpcs();
+                }
+            }
+        }
+    }
+
+    /// Dials the RPC nodes that were waiting for a listen address, so that they can dial us back.
+    fn dial_pending_rpcs(&mut self) {
+        for rpc_addr in std::mem::take(&mut self.pending_rpc_dials) {
+            log::info!("Dialing RPC node: {}", rpc_addr);
+            if let Err(e) = self.swarm.dial(rpc_addr) {
+                log::error!("Error dialing RPC node: {:?}", e);
+            };
+        }
+    }
+
+    /// Redials the disconnected peers whose backoff is over.
+    fn redial_peers(&mut self) {
+        for (peer_id, addrs) in self.redials.take_due(Instant::now()) {
+            log::info!("Redialling {}", peer_id);
+            let opts = DialOpts::peer_id(peer_id)
+                .addresses(addrs)
+                .condition(PeerCondition::DisconnectedAndNotDialing)
+                .build();
+            match self.swarm.dial(opts) {
+                // connected or being dialled already, the outcome of that is awaited instead
+                Ok(()) | Err(DialError::DialPeerConditionFalse(_)) => {}
+                Err(e) => {
+                    log::warn!("Could not redial {}: {:?}", peer_id, e);
+                    self.redials.dial_failed(peer_id, Instant::now());
+                }
             }
         }
     }
 
+    /// Sends an attempt of a request, which is tracked until it is responded or timed out.
+    fn send_request(&mut self, request: PendingRequest) {
+        let request_id = self
+            .swarm
+            .behaviour_mut()
+            .request_response
+            .send_request(&request.peer_id, request.data.clone());
+        log::debug!(
+            "Sent request {} to {} with request_id {}",
+            request.correlation_id,
+            request.peer_id,
+            request_id
+        );
+        self.pending_requests
+            .track(request_id, request, Instant::now());
+    }
+
+    /// Retries the requests whose latest attempt is not responded within the timeout.
+    fn retry_expired_requests(&mut self) {
+        for request in self.pending_requests.expire(Instant::now()) {
+            self.request_outcomes
+                .entry(request.peer_id)
+                .or_default()
+                .failed += 1;
+            self.send_request(request);
+        }
+
+        // responses of the dropped requests are not reassembled anymore
+        let pending_requests = &self.pending_requests;
+        self.chunked_requests
+            .retain(|_, request_id| pending_requests.contains(request_id));
+    }
+
+    /// Listens on the best relays that are not listened on yet, to hold a reservation with them.
+    fn listen_on_relays(&mut self) {
+        for (peer_id, addr) in self.relays.select() {
+            log::info!("Listening to relay: {}", addr);
+            match self.swarm.listen_on(addr.with(Protocol::P2pCircuit)) {
+                Ok(listener_id) => self.relays.listening(listener_id, peer_id),
+                Err(e) => log::error!("Could not listen to relay {}: {:?}", peer_id, e),
+            }
+        }
+    }
+
+    /// Closes the connections to short-lived peers that are open for longer than their timeout.
+    fn close_short_lived_connections(&mut self) {
+        let expired = self
+            .short_lived_connections
+            .iter()
+            .filter(|(_, connected_at)| connected_at.elapsed() >= self.short_lived_timeout)
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+
+        for peer_id in expired {
+            log::debug!("Closing short-lived connection to {}", peer_id);
+            self.short_lived_connections.remove(&peer_id);
+            let _ = self.swarm.disconnect_peer_id(peer_id);
+        }
+    }
+
     /// Handles a single command, which originates from `DriaP2PCommander`.
     pub async fn handle_command(&mut self, command: DriaP2PCommand) {
         match command {
@@ -188,6 +481,8 @@ impl DriaP2PClient {
                 address,
                 sender,
             } => {
+                // an explicit dial gives the peer a fresh set of redials
+                self.redials.track(peer_id, address.clone());
                 let opts = DialOpts::peer_id(peer_id)
                     .addresses(vec![address])
                     .condition(PeerCondition::Always)
@@ -195,7 +490,7 @@ impl DriaP2PClient {
                 let _ = sender.send(self.swarm.dial(opts));
             }
             DriaP2PCommand::NetworkInfo { sender } => {
-                let _ = sender.send(self.swarm.network_info());
+                let _ = sender.send((self.swarm.network_info(), self.limits));
             }
             DriaP2PCommand::Subscribe { topic, sender } => {
                 let _ = sender.send(
@@ -214,6 +509,43 @@ impl DriaP2PClient {
                         .unwrap_or_default(), // FIXME: due to v0.54 vs 0.55,
                 );
             }
+            DriaP2PCommand::SubscribeMany { topics, sender } => {
+                let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+                let _ = sender.send(
+                    topics
+                        .into_iter()
+                        .map(|topic| gossipsub.subscribe(&gossipsub::IdentTopic::new(topic)))
+                        .collect(),
+                );
+            }
+            DriaP2PCommand::UnsubscribeMany { topics, sender } => {
+                let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+                let _ = sender.send(
+                    topics
+                        .into_iter()
+                        .map(|topic| {
+                            gossipsub
+                                .unsubscribe(&gossipsub::IdentTopic::new(topic))
+                                .unwrap_or_default() // FIXME: due to v0.54 vs 0.55,
+                        })
+                        .collect(),
+                );
+            }
+            DriaP2PCommand::Subscriptions { sender } => {
+                let gossipsub = &self.swarm.behaviour().gossipsub;
+                let topics = gossipsub
+                    .topics()
+                    .map(|topic| TopicInfo {
+                        topic: topic.to_string(),
+                        mesh_peers: gossipsub.mesh_peers(topic).cloned().collect(),
+                        subscribed_peers: gossipsub
+                            .all_peers()
+                            .filter(|(_, topics)| topics.contains(&topic))
+                            .count(),
+                    })
+                    .collect();
+                let _ = sender.send(topics);
+            }
             DriaP2PCommand::Publish {
                 topic,
                 data,
@@ -227,10 +559,25 @@ impl DriaP2PClient {
                 );
             }
             DriaP2PCommand::Respond {
+                peer_id,
+                index,
                 data,
                 channel,
                 sender,
             } => {
+                log::debug!("Responding to request {} of {}", index, peer_id);
+                #[cfg(feature = "recorder")]
+                if let Some(recorder) = &mut self.recorder {
+                    if let Err(e) = recorder.record_respond(index, &peer_id, &data) {
+                        log::error!("Could not record respond: {:?}", e);
+                    }
+                }
+
+                // peers that do not support chunks get the response in a single frame
+                let data = match self.chunk_peers.contains(&peer_id) {
+                    true => self.chunks.split(peer_id, data, Instant::now()),
+                    false => data,
+                };
                 let _ = sender.send(
                     self.swarm
                         .behaviour_mut()
@@ -239,18 +586,7 @@ impl DriaP2PClient {
                         .map_err(|_| eyre::eyre!("could not send response, channel is closed?")),
                 );
             }
-            DriaP2PCommand::Request {
-                data,
-                peer_id,
-                sender,
-            } => {
-                let _ = sender.send(
-                    self.swarm
-                        .behaviour_mut()
-                        .request_response
-                        .send_request(&peer_id, data),
-                );
-            }
+            DriaP2PCommand::Request { request } => self.send_request(request),
             DriaP2PCommand::ValidateMessage {
                 msg_id,
                 propagation_source,
@@ -284,6 +620,66 @@ impl DriaP2PClient {
                 let all = self.swarm.behaviour().gossipsub.all_peers().count();
                 let _ = sender.send((mesh, all));
             }
+            DriaP2PCommand::PeerLatencies { sender } => {
+                let _ = sender.send(self.peer_rtts.clone());
+            }
+            DriaP2PCommand::FirstInbound { sender } => {
+                let _ = sender.send(self.first_inbound_after);
+            }
+            DriaP2PCommand::TakeRequestOutcomes { sender } => {
+                let _ = sender.send(std::mem::take(&mut self.request_outcomes));
+            }
+            DriaP2PCommand::ChannelStats { sender } => {
+                let _ = sender.send(vec![
+                    ("command", ChannelStats::of_receiver(&self.cmd_rx, 0)),
+                    (
+                        "message",
+                        ChannelStats::of_sender(&self.msg_tx, self.dropped.0),
+                    ),
+                    (
+                        "request",
+                        ChannelStats::of_sender(&self.req_tx, self.dropped.1),
+                    ),
+                ]);
+            }
+            DriaP2PCommand::PeerFamilies { sender } => {
+                let _ = sender.send(self.peer_families.clone());
+            }
+            DriaP2PCommand::LanPeers { sender } => {
+                let _ = sender.send(self.lan_peers.iter().cloned().collect());
+            }
+            DriaP2PCommand::RelayHealth { sender } => {
+                let _ = sender.send(self.relays.health());
+            }
+            DriaP2PCommand::RedialStatus { sender } => {
+                let _ = sender.send(self.redials.status(Instant::now()));
+            }
+            DriaP2PCommand::BlockPeer { peer_id, sender } => {
+                log::warn!("Blocking peer {}", peer_id);
+                self.swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+                self.blocked_peers.insert(peer_id);
+                let _ = sender.send(());
+            }
+            DriaP2PCommand::UnblockPeer { peer_id, sender } => {
+                self.swarm
+                    .behaviour_mut()
+                    .blocked_peers
+                    .unblock_peer(peer_id);
+                self.blocked_peers.remove(&peer_id);
+                let _ = sender.send(());
+            }
+            DriaP2PCommand::SetKeepAlivePeers { peers, sender } => {
+                self.redials.retain(&peers);
+                self.swarm
+                    .behaviour_mut()
+                    .connection_limits
+                    .set_keep_alive_peers(peers.clone());
+                self.swarm.behaviour_mut().keep_alive.set_peers(peers);
+                let _ = sender.send(());
+            }
+            DriaP2PCommand::BlockedPeers { sender } => {
+                let _ = sender.send(self.blocked_peers.iter().cloned().collect());
+            }
             DriaP2PCommand::Shutdown { sender } => {
                 // close the command channel
                 self.cmd_rx.close();
@@ -309,8 +705,16 @@ impl DriaP2PClient {
                 message_id,
                 message,
             })) => {
+                #[cfg(feature = "recorder")]
+                if let Some(recorder) = &mut self.recorder {
+                    if let Err(e) = recorder.record_message(&peer_id, &message_id, &message) {
+                        log::error!("Could not record message: {:?}", e);
+                    }
+                }
+
                 if let Err(e) = self.msg_tx.send((peer_id, message_id, message)).await {
                     log::error!("Could not send Gossipsub message: {:?}", e);
+                    self.dropped.0 += 1;
                 }
             }
 
@@ -325,12 +729,23 @@ impl DriaP2PClient {
                     channel,
                     request_id,
                 } => {
+                    let index = self.inbound_requests;
+                    self.inbound_requests += 1;
+                    #[cfg(feature = "recorder")]
+                    if let Some(recorder) = &mut self.recorder {
+                        if let Err(e) = recorder.record_request(index, &peer, &request) {
+                            log::error!("Could not record request: {:?}", e);
+                        }
+                    }
+
+                    let channel = RequestChannel::Network { index, channel };
                     if let Err(e) = self.req_tx.send((peer, request, channel)).await {
                         log::error!(
                             "Could not send response for request_id {}: {:?}",
                             request_id,
                             e,
                         );
+                        self.dropped.1 += 1;
                     }
                 }
 
@@ -338,14 +753,97 @@ impl DriaP2PClient {
                     request_id,
                     response,
                 } => {
-                    // while we support the protocol, we dont really make any requests
-                    log::warn!(
-                        "Unexpected response message with request_id {}: {:?}",
+                    self.request_outcomes.entry(peer).or_default().responded += 1;
+                    log::debug!(
+                        "Received response for request_id {}: {} bytes",
                         request_id,
-                        response
+                        response.len()
                     );
+
+                    // a chunked response is reassembled by requesting the rest of its chunks,
+                    // and it is the response of the request that its first chunk responds to
+                    let Some(chunk) = Chunk::decode(&response) else {
+                        #[cfg(feature = "recorder")]
+                        if let Some(recorder) = &mut self.recorder {
+                            if let Err(e) = recorder.record_response(&peer, &response) {
+                                log::error!("Could not record response: {:?}", e);
+                            }
+                        }
+
+                        self.pending_requests.respond(&request_id, response);
+                        return;
+                    };
+                    let (transfer_id, total) = (chunk.transfer_id, chunk.total);
+                    if !self.assembler.start(peer, chunk, Instant::now()) {
+                        if let Some(request) = self
+                            .pending_requests
+                            .fail(&request_id, "invalid chunked response".to_string())
+                        {
+                            self.send_request(request);
+                        }
+                        return;
+                    }
+                    self.chunked_requests
+                        .insert((peer, transfer_id), request_id);
+                    let chunks = &mut self.swarm.behaviour_mut().chunks;
+                    for index in 1..total {
+                        chunks.send_request(&peer, Chunk::encode_request(transfer_id, index));
+                    }
                 }
             },
+
+            // chunks of the large responses, served & reassembled here without the node
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Chunks(
+                request_response::Event::Message { message, peer, .. },
+            )) => match message {
+                // only the peer that a transfer is sent to can get its chunks, a limited number of times
+                request_response::Message::Request {
+                    request, channel, ..
+                } => {
+                    let Some(chunk) =
+                        Chunk::decode_request(&request).and_then(|(transfer_id, index)| {
+                            self.chunks.get(&peer, transfer_id, index)
+                        })
+                    else {
+                        log::debug!("Ignoring an invalid chunk request from {}", peer);
+                        return;
+                    };
+                    let chunks = &mut self.swarm.behaviour_mut().chunks;
+                    if chunks.send_response(channel, chunk).is_err() {
+                        log::warn!("Could not send a chunk to {}", peer);
+                    }
+                }
+                request_response::Message::Response { response, .. } => {
+                    let Some(chunk) = Chunk::decode(&response) else {
+                        log::debug!("Ignoring an invalid chunk from {}", peer);
+                        return;
+                    };
+                    let transfer_id = chunk.transfer_id;
+                    if let Some(response) = self.assembler.add(peer, chunk, Instant::now()) {
+                        log::debug!(
+                            "Reassembled a chunked response from {}: {} bytes",
+                            peer,
+                            response.len()
+                        );
+                        #[cfg(feature = "recorder")]
+                        if let Some(recorder) = &mut self.recorder {
+                            if let Err(e) = recorder.record_response(&peer, &response) {
+                                log::error!("Could not record response: {:?}", e);
+                            }
+                        }
+
+                        if let Some(request_id) = self.chunked_requests.remove(&(peer, transfer_id))
+                        {
+                            self.pending_requests.respond(&request_id, response);
+                        }
+                    }
+                }
+            },
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Chunks(
+                request_response::Event::OutboundFailure { peer, error, .. },
+            )) => {
+                log::warn!("Could not get a chunk from {}: {:?}", peer, error);
+            }
             SwarmEvent::Behaviour(DriaBehaviourEvent::RequestResponse(
                 request_response::Event::ResponseSent {
                     peer, request_id, ..
@@ -365,12 +863,16 @@ impl DriaP2PClient {
                     ..
                 },
             )) => {
+                self.request_outcomes.entry(peer).or_default().failed += 1;
                 log::error!(
                     "Request-Response: Outbound failure to peer {} with request_id {}: {:?}",
                     peer,
                     request_id,
                     error
                 );
+                if let Some(request) = self.pending_requests.fail(&request_id, error.to_string()) {
+                    self.send_request(request);
+                }
             }
             SwarmEvent::Behaviour(DriaBehaviourEvent::RequestResponse(
                 request_response::Event::InboundFailure {
@@ -411,9 +913,114 @@ impl DriaP2PClient {
                 log::warn!("AutoNAT status changed from {:?} to {:?}", old, new);
             }
 
+            // mdns events, dial the nodes within the local network
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Mdns(mdns::Event::Discovered(peers))) => {
+                for (peer_id, addr) in peers {
+                    if self.lan_peers.insert(peer_id) {
+                        log::info!(
+                            "Discovered {} within the local network at {}",
+                            peer_id,
+                            addr
+                        );
+                    }
+                    self.swarm
+                        .behaviour_mut()
+                        .kademlia
+                        .add_address(&peer_id, addr.clone());
+                    let opts = DialOpts::peer_id(peer_id)
+                        .condition(PeerCondition::DisconnectedAndNotDialing)
+                        .addresses(vec![addr])
+                        .build();
+                    if let Err(e) = self.swarm.dial(opts) {
+                        log::debug!("Could not dial local peer {}: {:?}", peer_id, e);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Mdns(mdns::Event::Expired(peers))) => {
+                for (peer_id, _) in peers {
+                    if self.lan_peers.remove(&peer_id) {
+                        log::info!("Local peer {} has expired", peer_id);
+                    }
+                }
+            }
+
+            // ping events, keep track of the round-trip times
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Ping(ping::Event {
+                peer, result, ..
+            })) => match result {
+                Ok(rtt) => {
+                    self.peer_rtts.insert(peer, rtt);
+                    self.relays.record_rtt(&peer, rtt);
+                }
+                Err(e) => {
+                    log::debug!("Ping to {} failed: {:?}", peer, e);
+                    self.peer_rtts.remove(&peer);
+                }
+            },
+            // relay events, keep track of the reservations & rotate away from the dropped ones
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Relay(
+                relay::client::Event::ReservationReqAccepted {
+                    relay_peer_id,
+                    renewal,
+                    ..
+                },
+            )) => {
+                if !renewal {
+                    log::info!("Reservation accepted by relay {}", relay_peer_id);
+                }
+                self.relays.record_accepted(&relay_peer_id);
+            }
+            SwarmEvent::ListenerClosed {
+                listener_id,
+                reason,
+                ..
+            } => {
+                if let Some(peer_id) = self.relays.record_closed(&listener_id) {
+                    log::warn!(
+                        "Reservation with relay {} is dropped: {:?}",
+                        peer_id,
+                        reason
+                    );
+                }
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                self.peer_rtts.remove(&peer_id);
+                self.short_lived_connections.remove(&peer_id);
+                self.peer_families.remove(&peer_id);
+                self.chunk_peers.remove(&peer_id);
+                self.redials.disconnected(peer_id, Instant::now());
+            }
+            SwarmEvent::ConnectionEstablished {
+                peer_id, endpoint, ..
+            } => {
+                if endpoint.is_listener() && self.first_inbound_after.is_none() {
+                    let elapsed = self.started_at.elapsed();
+                    log::info!(
+                        "First inbound connection from {} after {}s",
+                        peer_id,
+                        elapsed.as_secs()
+                    );
+                    self.first_inbound_after = Some(elapsed);
+                }
+                if let Some(family) = IpFamily::of(endpoint.get_remote_address()) {
+                    self.peer_families.insert(peer_id, family);
+                }
+                self.redials.connected(&peer_id, Instant::now());
+                if self.short_lived_peers.contains(&peer_id) {
+                    self.short_lived_connections
+                        .entry(peer_id)
+                        .or_insert_with(Instant::now);
+                }
+            }
+
             // log listen addreses
             SwarmEvent::NewListenAddr { address, .. } => {
                 log::warn!("Local node is listening on {}", address);
+                self.dial_pending_rpcs();
             }
 
             // add external address of peers to Kademlia routing table
@@ -425,13 +1032,23 @@ impl DriaP2PClient {
             }
             // add your own peer_id to kademlia as well
             SwarmEvent::ExternalAddrConfirmed { address } => {
-                // this is usually the external address via relay
-                log::info!("External address confirmed: {}", address);
-                let peer_id = *self.swarm.local_peer_id();
-                self.swarm
-                    .behaviour_mut()
-                    .kademlia
-                    .add_address(&peer_id, address);
+                // the address is withdrawn if its listen address is not to be advertised
+                if self
+                    .unadvertised_addrs
+                    .iter()
+                    .any(|l| l.matches_external(&address))
+                {
+                    log::info!("External address confirmed, not advertising: {}", address);
+                    self.swarm.remove_external_address(&address);
+                } else {
+                    // this is usually the external address via relay
+                    log::info!("External address confirmed: {}", address);
+                    let peer_id = *self.swarm.local_peer_id();
+                    self.swarm
+                        .behaviour_mut()
+                        .kademlia
+                        .add_address(&peer_id, address);
+                }
             }
 
             // SwarmEvent::IncomingConnectionError {
@@ -461,7 +1078,10 @@ impl DriaP2PClient {
             //         send_back_addr
             //     );
             // }
-
+            SwarmEvent::OutgoingConnectionError {
+                peer_id: Some(peer_id),
+                ..
+            } => self.redials.dial_failed(peer_id, Instant::now()),
             // SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
             //     if let Some(peer_id) = peer_id {
             //         log::warn!("Could not connect to peer {}: {:?}", peer_id, error);
@@ -479,6 +1099,16 @@ impl DriaP2PClient {
     ///
     /// - For Kademlia, we check the kademlia protocol and then add the address to the Kademlia routing table.
     fn handle_identify_event(&mut self, peer_id: PeerId, info: identify::Info) {
+        // large responses are sent in chunks only to the peers that can reassemble them
+        if info
+            .protocols
+            .contains(&self.protocol.request_response_chunks)
+        {
+            self.chunk_peers.insert(peer_id);
+        } else {
+            self.chunk_peers.remove(&peer_id);
+        }
+
         // check identify protocol string
         if info.protocol_version != self.protocol.identity {
             log::warn!(
@@ -503,16 +1133,8 @@ impl DriaP2PClient {
             {
                 // if it matches our protocol, add it to the Kademlia routing table
                 if *kad_protocol == self.protocol.kademlia {
-                    // filter listen addresses
-                    let addrs = info.listen_addrs.into_iter().filter(|listen_addr| {
-                        if let Some(Protocol::Ip4(ipv4_addr)) = listen_addr.iter().next() {
-                            // ignore private & localhost addresses
-                            !(ipv4_addr.is_private() || ipv4_addr.is_loopback())
-                        } else {
-                            // ignore non ipv4 addresses
-                            false
-                        }
-                    });
+                    // filter listen addresses, ignoring private & localhost ones
+                    let addrs = info.listen_addrs.into_iter().filter(is_public_addr);
 
                     // add them to kademlia
                     for addr in addrs {
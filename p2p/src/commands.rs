@@ -1,14 +1,22 @@
 use eyre::{Context, Result};
 use libp2p::{gossipsub, kad, request_response, swarm, Multiaddr, PeerId};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 use tokio::sync::{mpsc, oneshot};
 
-use crate::DriaP2PProtocol;
+use crate::retry::PendingRequest;
+use crate::{
+    ConnectionCaps, DriaP2PProtocol, IpFamily, PendingResponse, RedialStatus, RelayHealth,
+};
 
 #[derive(Debug)]
 pub enum DriaP2PCommand {
-    /// Returns the network information, such as the number of incoming and outgoing connections.
+    /// Returns the network information, such as the number of incoming and outgoing connections,
+    /// along with the limits of the connections.
     NetworkInfo {
-        sender: oneshot::Sender<swarm::NetworkInfo>,
+        sender: oneshot::Sender<(swarm::NetworkInfo, ConnectionCaps)>,
     },
     /// Make a Kademlia closest-peers query.
     Refresh {
@@ -22,6 +30,57 @@ pub enum DriaP2PCommand {
     PeerCounts {
         sender: oneshot::Sender<(usize, usize)>,
     },
+    /// Get the latest round-trip times of the connected peers.
+    PeerLatencies {
+        sender: oneshot::Sender<HashMap<PeerId, Duration>>,
+    },
+    /// Get the time from the start of the client to its first inbound connection.
+    FirstInbound {
+        sender: oneshot::Sender<Option<Duration>>,
+    },
+    /// Take the outcomes of the outbound requests since the last call.
+    TakeRequestOutcomes {
+        sender: oneshot::Sender<HashMap<PeerId, RequestOutcomes>>,
+    },
+    /// Get the stats of the channels of the client.
+    ChannelStats {
+        sender: oneshot::Sender<Vec<(&'static str, ChannelStats)>>,
+    },
+    /// Get the IP families of the connected peers.
+    PeerFamilies {
+        sender: oneshot::Sender<HashMap<PeerId, IpFamily>>,
+    },
+    /// Get the peers discovered within the local network.
+    LanPeers {
+        sender: oneshot::Sender<Vec<PeerId>>,
+    },
+    /// Get the health of the relays.
+    RelayHealth {
+        sender: oneshot::Sender<Vec<RelayHealth>>,
+    },
+    /// Get the redial states of the disconnected RPC nodes.
+    RedialStatus {
+        sender: oneshot::Sender<HashMap<PeerId, RedialStatus>>,
+    },
+    /// Block a peer, closing its connections & denying new ones.
+    BlockPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<()>,
+    },
+    /// Unblock a peer that was blocked before.
+    UnblockPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<()>,
+    },
+    /// Replace the peers whose connections are kept alive & redialled, e.g. the RPCs.
+    SetKeepAlivePeers {
+        peers: HashSet<PeerId>,
+        sender: oneshot::Sender<()>,
+    },
+    /// Get the blocked peers.
+    BlockedPeers {
+        sender: oneshot::Sender<Vec<PeerId>>,
+    },
     /// Dial a known peer.
     Dial {
         peer_id: PeerId,
@@ -38,26 +97,38 @@ pub enum DriaP2PCommand {
         topic: String,
         sender: oneshot::Sender<bool>,
     },
+    /// Subscribe to many topics at once, results are in the same order as the topics.
+    SubscribeMany {
+        topics: Vec<String>,
+        sender: oneshot::Sender<Vec<Result<bool, gossipsub::SubscriptionError>>>,
+    },
+    /// Unsubscribe from many topics at once, results are in the same order as the topics.
+    UnsubscribeMany {
+        topics: Vec<String>,
+        sender: oneshot::Sender<Vec<bool>>,
+    },
+    /// Get the subscribed topics along with their mesh peers.
+    Subscriptions {
+        sender: oneshot::Sender<Vec<TopicInfo>>,
+    },
     /// Publishes a message to a topic, returns the message ID.
     Publish {
         topic: String,
         data: Vec<u8>,
         sender: oneshot::Sender<Result<gossipsub::MessageId, gossipsub::PublishError>>,
     },
-    /// Respond to a request-response message.
+    /// Respond to a request-response message, with the index of the request among the inbound ones.
     Respond {
+        peer_id: PeerId,
+        index: u64,
         data: Vec<u8>,
         channel: request_response::ResponseChannel<Vec<u8>>,
         sender: oneshot::Sender<Result<()>>,
     },
-    /// Request a request-response message.
+    /// Request a request-response message, retried until it is responded or its attempts are exhausted.
     /// Note that you are likely to be caught by the RPC peer id check,
     /// and your messages will be ignored.
-    Request {
-        peer_id: PeerId,
-        data: Vec<u8>,
-        sender: oneshot::Sender<request_response::OutboundRequestId>,
-    },
+    Request { request: PendingRequest },
     /// Validates a GossipSub message for propagation, returns whether the message existed in cache.
     ///
     /// - `Accept`: Accept the message and propagate it.
@@ -76,14 +147,112 @@ pub enum DriaP2PCommand {
     Shutdown { sender: oneshot::Sender<()> },
 }
 
+/// Subscription state of a GossipSub topic.
+#[derive(Debug, Clone)]
+pub struct TopicInfo {
+    /// Topic name.
+    pub topic: String,
+    /// Peers within the mesh of this topic.
+    pub mesh_peers: Vec<PeerId>,
+    /// Number of known peers that are subscribed to this topic.
+    pub subscribed_peers: usize,
+}
+
+impl TopicInfo {
+    /// Returns `true` if the topic has at least one mesh peer, i.e. messages can actually be exchanged.
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        !self.mesh_peers.is_empty()
+    }
+}
+
+/// Channel to respond to an inbound request with.
+#[derive(Debug)]
+pub enum RequestChannel {
+    /// A request received from the network, along with its index among the inbound requests.
+    Network {
+        index: u64,
+        channel: request_response::ResponseChannel<Vec<u8>>,
+    },
+    /// A replayed request, whose response is given back to the replayer instead of the network.
+    #[cfg(feature = "recorder")]
+    Replay(oneshot::Sender<Vec<u8>>),
+}
+
+impl RequestChannel {
+    /// Returns `true` if the requester can still be responded to.
+    pub fn is_open(&self) -> bool {
+        match self {
+            RequestChannel::Network { channel, .. } => channel.is_open(),
+            #[cfg(feature = "recorder")]
+            RequestChannel::Replay(sender) => !sender.is_closed(),
+        }
+    }
+}
+
+/// Outcomes of the outbound requests to a peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestOutcomes {
+    /// Number of requests that were responded to.
+    pub responded: u64,
+    /// Number of requests that failed, e.g. timed out or the connection was closed.
+    pub failed: u64,
+}
+
+/// Saturation of a channel, so that its overflows can be anticipated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ChannelStats {
+    /// Number of items waiting within the channel.
+    pub depth: usize,
+    /// Maximum number of items that the channel can hold.
+    pub capacity: usize,
+    /// Number of items that could not be sent, as the channel was closed.
+    pub dropped: u64,
+}
+
+impl ChannelStats {
+    /// Returns the stats of a channel from its sender.
+    pub fn of_sender<T>(tx: &mpsc::Sender<T>, dropped: u64) -> Self {
+        Self {
+            depth: tx.max_capacity() - tx.capacity(),
+            capacity: tx.max_capacity(),
+            dropped,
+        }
+    }
+
+    /// Returns the stats of a channel from its receiver.
+    pub fn of_receiver<T>(rx: &mpsc::Receiver<T>, dropped: u64) -> Self {
+        Self {
+            depth: rx.len(),
+            capacity: rx.max_capacity(),
+            dropped,
+        }
+    }
+
+    /// Returns the ratio of the channel that is full, from 0 to 1.
+    #[inline]
+    pub fn saturation(&self) -> f64 {
+        match self.capacity {
+            0 => 0.0,
+            capacity => self.depth as f64 / capacity as f64,
+        }
+    }
+}
+
 pub struct DriaP2PCommander {
     sender: mpsc::Sender<DriaP2PCommand>,
     protocol: DriaP2PProtocol,
+    /// Correlation id of the latest request, shared by all of its attempts.
+    correlation_id: u64,
 }
 
 impl DriaP2PCommander {
     pub fn new(sender: mpsc::Sender<DriaP2PCommand>, protocol: DriaP2PProtocol) -> Self {
-        Self { sender, protocol }
+        Self {
+            sender,
+            protocol,
+            correlation_id: 0,
+        }
     }
 
     /// Returns a reference to the protocol.
@@ -92,8 +261,8 @@ impl DriaP2PCommander {
     }
 
     /// Returns the network information, such as the number of
-    /// incoming and outgoing connections.
-    pub async fn network_info(&self) -> Result<swarm::NetworkInfo> {
+    /// incoming and outgoing connections, along with their limits.
+    pub async fn network_info(&self) -> Result<(swarm::NetworkInfo, ConnectionCaps)> {
         let (sender, receiver) = oneshot::channel();
 
         self.sender
@@ -139,6 +308,61 @@ impl DriaP2PCommander {
         receiver.await.wrap_err("could not receive")
     }
 
+    /// Subscribe to many topics at once.
+    ///
+    /// Returns whether each topic was newly subscribed, in the same order as the topics.
+    pub async fn subscribe_many(&self, topic_names: &[&str]) -> Result<Vec<bool>> {
+        let (sender, receiver) = oneshot::channel();
+
+        log::debug!("Subscribing to {}", topic_names.join(", "));
+        self.sender
+            .send(DriaP2PCommand::SubscribeMany {
+                topics: topic_names.iter().map(|t| t.to_string()).collect(),
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver
+            .await
+            .wrap_err("could not receive")?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("could not subscribe")
+    }
+
+    /// Unsubscribe from many topics at once.
+    ///
+    /// Returns whether each topic was subscribed before, in the same order as the topics.
+    pub async fn unsubscribe_many(&self, topic_names: &[&str]) -> Result<Vec<bool>> {
+        let (sender, receiver) = oneshot::channel();
+
+        log::debug!("Unsubscribing from {}", topic_names.join(", "));
+        self.sender
+            .send(DriaP2PCommand::UnsubscribeMany {
+                topics: topic_names.iter().map(|t| t.to_string()).collect(),
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Get the subscribed topics, along with their mesh peers.
+    ///
+    /// Can be used to verify that a subscription has actually succeeded, i.e. the topic has mesh peers.
+    pub async fn subscriptions(&self) -> Result<Vec<TopicInfo>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Subscriptions { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
     /// Publish a message to a topic.
     ///
     /// Returns the message ID.
@@ -165,15 +389,28 @@ impl DriaP2PCommander {
             .wrap_err("could not publish")
     }
 
+    /// Responds to a request of the given peer, in chunks if the response is large and the peer supports them.
     pub async fn respond(
         &mut self,
+        peer_id: PeerId,
         data: Vec<u8>,
-        channel: request_response::ResponseChannel<Vec<u8>>,
+        channel: RequestChannel,
     ) -> Result<()> {
+        let (index, channel) = match channel {
+            RequestChannel::Network { index, channel } => (index, channel),
+            #[cfg(feature = "recorder")]
+            RequestChannel::Replay(sender) => {
+                return sender
+                    .send(data)
+                    .map_err(|_| eyre::eyre!("could not respond, replay is closed"));
+            }
+        };
         let (sender, receiver) = oneshot::channel();
 
         self.sender
             .send(DriaP2PCommand::Respond {
+                peer_id,
+                index,
                 data,
                 channel,
                 sender,
@@ -187,23 +424,40 @@ impl DriaP2PCommander {
             .wrap_err("could not respond")
     }
 
-    pub async fn request(
+    /// Makes a request to a peer with a single attempt, with the timeout configured within the client.
+    ///
+    /// Returns the pending response, which can be awaited for the response or a [`RequestError`](crate::RequestError)
+    /// once the attempt has failed; it can also be dropped if the response is not needed.
+    pub async fn request(&mut self, peer_id: PeerId, data: Vec<u8>) -> Result<PendingResponse> {
+        self.send_request(peer_id, data, false).await
+    }
+
+    /// Makes a request to a peer, with a timeout & retries as configured within the client.
+    ///
+    /// Only requests that are safe to repeat should be retried, as a lost response does not mean a lost request.
+    pub async fn request_with_retry(
         &mut self,
         peer_id: PeerId,
         data: Vec<u8>,
-    ) -> Result<request_response::OutboundRequestId> {
-        let (sender, receiver) = oneshot::channel();
+    ) -> Result<PendingResponse> {
+        self.send_request(peer_id, data, true).await
+    }
+
+    async fn send_request(
+        &mut self,
+        peer_id: PeerId,
+        data: Vec<u8>,
+        retry: bool,
+    ) -> Result<PendingResponse> {
+        self.correlation_id += 1;
+        let (request, response) = PendingRequest::new(self.correlation_id, peer_id, data, retry);
 
         self.sender
-            .send(DriaP2PCommand::Request {
-                data,
-                peer_id,
-                sender,
-            })
+            .send(DriaP2PCommand::Request { request })
             .await
             .wrap_err("could not send")?;
 
-        receiver.await.wrap_err("could not receive")
+        Ok(response)
     }
 
     /// Dials a given peer.
@@ -233,12 +487,14 @@ impl DriaP2PCommander {
     ///
     /// See [`validate_messages`](https://docs.rs/libp2p-gossipsub/latest/libp2p_gossipsub/struct.Config.html#method.validate_messages)
     /// and [`report_message_validation_result`](https://docs.rs/libp2p-gossipsub/latest/libp2p_gossipsub/struct.Behaviour.html#method.report_message_validation_result) for more details.
+    ///
+    /// Returns whether the message was still in the cache, i.e. whether it could be forwarded.
     pub async fn validate_message(
         &mut self,
         msg_id: &gossipsub::MessageId,
         propagation_source: &PeerId,
         acceptance: gossipsub::MessageAcceptance,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         let (sender, receiver) = oneshot::channel();
 
         log::trace!("Validating message ({}): {:?}", msg_id, acceptance);
@@ -258,7 +514,7 @@ impl DriaP2PCommander {
             log::debug!("Validated message was not in cache.");
         }
 
-        Ok(())
+        Ok(msg_was_in_cache)
     }
 
     /// Refreshes the Kademlia DHT using a closest peer query over a random peer.
@@ -299,6 +555,161 @@ impl DriaP2PCommander {
         receiver.await.wrap_err("could not receive")
     }
 
+    /// Get the latest round-trip times of the connected peers, measured with pings.
+    ///
+    /// Peers that could not be pinged or are disconnected are not included.
+    pub async fn peer_latencies(&self) -> Result<HashMap<PeerId, Duration>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::PeerLatencies { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the time from the start of the client to its first inbound connection,
+    /// or `None` if no peer has connected to us yet.
+    pub async fn first_inbound_after(&self) -> Result<Option<Duration>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::FirstInbound { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the outcomes of the outbound requests per peer, since the last call.
+    ///
+    /// The counters are reset within the client, so that each outcome is returned once.
+    pub async fn take_request_outcomes(&self) -> Result<HashMap<PeerId, RequestOutcomes>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::TakeRequestOutcomes { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the stats of the command, message & request channels of the client.
+    pub async fn channel_stats(&self) -> Result<Vec<(&'static str, ChannelStats)>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::ChannelStats { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the IP family that each connected peer is connected over, w.r.t its latest connection.
+    pub async fn peer_families(&self) -> Result<HashMap<PeerId, IpFamily>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::PeerFamilies { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the peers discovered within the local network over mDNS, empty if it is not enabled.
+    pub async fn lan_peers(&self) -> Result<Vec<PeerId>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::LanPeers { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the health of the relays, the best ones first.
+    pub async fn relay_health(&self) -> Result<Vec<RelayHealth>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::RelayHealth { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the redial states of the disconnected RPC nodes, connected ones are left out.
+    pub async fn redial_status(&self) -> Result<HashMap<PeerId, RedialStatus>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::RedialStatus { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Blocks a peer at the swarm level, so that its connections are closed and new ones are denied.
+    ///
+    /// Blocked peers are kept in memory, i.e. a restart unblocks them.
+    pub async fn block_peer(&self, peer_id: PeerId) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+
+        log::debug!("Blocking peer {}", peer_id);
+        self.sender
+            .send(DriaP2PCommand::BlockPeer { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Unblocks a peer, does nothing if it was not blocked.
+    pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+
+        log::debug!("Unblocking peer {}", peer_id);
+        self.sender
+            .send(DriaP2PCommand::UnblockPeer { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Replaces the peers whose connections are kept alive, e.g. when the RPCs change.
+    ///
+    /// The peers that are removed are not redialled anymore either.
+    pub async fn set_keep_alive_peers(&self, peers: HashSet<PeerId>) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::SetKeepAlivePeers { peers, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the blocked peers.
+    pub async fn blocked_peers(&self) -> Result<Vec<PeerId>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::BlockedPeers { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
     /// Sends a shutdown signal to the client.
     pub async fn shutdown(&mut self) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
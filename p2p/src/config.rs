@@ -0,0 +1,156 @@
+use dkn_utils::split_csv_line;
+use eyre::{eyre, Context, Result};
+use libp2p::gossipsub::PeerScoreThresholds;
+
+use crate::{ConnectionPolicy, ListenAddr, P2PTransport, RequestRetry, ResourceUsage};
+
+/// Default limit of the established outgoing connections, which bounds the number of peers as well.
+const DEFAULT_MAX_ESTABLISHED_OUTGOING: u32 = 300;
+/// Default limit of the established connections to a single peer, which can be connected both directly and over a relay.
+const DEFAULT_MAX_ESTABLISHED_PER_PEER: u32 = 4;
+
+/// Limits of the pending & established connections, a connection beyond them is denied.
+///
+/// `None` means that the connections are not limited. The connections of the RPCs & bootstraps
+/// are exempt from the established & pending outgoing limits, and are not counted within them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionCaps {
+    /// Maximum number of pending incoming connections.
+    pub max_pending_incoming: Option<u32>,
+    /// Maximum number of pending outgoing connections.
+    pub max_pending_outgoing: Option<u32>,
+    /// Maximum number of established connections to a single peer.
+    pub max_established_per_peer: Option<u32>,
+    /// Maximum number of established outgoing connections.
+    pub max_established_outgoing: Option<u32>,
+    /// Maximum number of established connections in total.
+    pub max_established: Option<u32>,
+}
+
+impl Default for ConnectionCaps {
+    fn default() -> Self {
+        Self {
+            max_pending_incoming: None,
+            max_pending_outgoing: None,
+            max_established_per_peer: Some(DEFAULT_MAX_ESTABLISHED_PER_PEER),
+            max_established_outgoing: Some(DEFAULT_MAX_ESTABLISHED_OUTGOING),
+            max_established: None,
+        }
+    }
+}
+
+/// Configuration of the peer-to-peer client, apart from its identity and the nodes it connects to.
+#[derive(Debug, Clone)]
+pub struct DriaP2PConfig {
+    /// Addresses to listen on.
+    pub listen_addrs: Vec<ListenAddr>,
+    /// How long the connections are kept w.r.t their peers.
+    pub policy: ConnectionPolicy,
+    /// Gossip mesh size, heartbeat & ping intervals and channel buffers.
+    pub resources: ResourceUsage,
+    /// Transport that the Dria nodes are dialled over.
+    pub transport: P2PTransport,
+    /// Thresholds of the Gossipsub peer scores, peers are not scored if `None`.
+    pub score_thresholds: Option<PeerScoreThresholds>,
+    /// Limits of the pending & established connections.
+    pub limits: ConnectionCaps,
+    /// Timeout & attempts of the outbound requests.
+    pub request_retry: RequestRetry,
+}
+
+impl DriaP2PConfig {
+    /// Creates a config that listens on the given addresses, with defaults for the rest.
+    pub fn new(listen_addrs: Vec<ListenAddr>) -> Self {
+        Self {
+            listen_addrs,
+            policy: ConnectionPolicy::default(),
+            resources: ResourceUsage::default(),
+            transport: P2PTransport::default(),
+            score_thresholds: None,
+            limits: ConnectionCaps::default(),
+            request_retry: RequestRetry::default(),
+        }
+    }
+
+    /// Sets the connection policy, see [`ConnectionPolicy::for_nodes`].
+    pub fn with_policy(mut self, policy: ConnectionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the resources of the client.
+    pub fn with_resources(mut self, resources: ResourceUsage) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Sets the transport that the Dria nodes are dialled over.
+    pub fn with_transport(mut self, transport: P2PTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Enables Gossipsub peer scoring with the given thresholds.
+    pub fn with_score_thresholds(mut self, thresholds: PeerScoreThresholds) -> Self {
+        self.score_thresholds = Some(thresholds);
+        self
+    }
+
+    /// Sets the limits of the connections.
+    pub fn with_limits(mut self, limits: ConnectionCaps) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the timeout & attempts of the outbound requests.
+    pub fn with_request_retry(mut self, request_retry: RequestRetry) -> Self {
+        self.request_retry = request_retry;
+        self
+    }
+}
+
+/// Parses the `gossip,publish,graylist` thresholds of the peer scores, e.g. `-10,-50,-80` which are the defaults.
+///
+/// The thresholds must be non-positive and in decreasing order; the lower they are,
+/// the longer a peer with a bad score is kept before it is ignored.
+pub fn parse_score_thresholds(input: &str) -> Result<PeerScoreThresholds> {
+    let values = split_csv_line(input)
+        .iter()
+        .map(|s| s.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err("invalid score threshold")?;
+    let [gossip_threshold, publish_threshold, graylist_threshold] = values[..] else {
+        return Err(eyre!(
+            "expected 3 score thresholds (gossip, publish, graylist), got {}",
+            values.len()
+        ));
+    };
+
+    let thresholds = PeerScoreThresholds {
+        gossip_threshold,
+        publish_threshold,
+        graylist_threshold,
+        ..Default::default()
+    };
+    thresholds.validate().map_err(|e| eyre!(e))?;
+
+    Ok(thresholds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_score_thresholds() {
+        let thresholds = parse_score_thresholds("-100, -500, -1000").unwrap();
+        assert_eq!(thresholds.gossip_threshold, -100.0);
+        assert_eq!(thresholds.publish_threshold, -500.0);
+        assert_eq!(thresholds.graylist_threshold, -1000.0);
+
+        assert!(parse_score_thresholds("-10,-50").is_err());
+        assert!(parse_score_thresholds("-10,-50,abc").is_err());
+        // graylist must not be above the publish threshold
+        assert!(parse_score_thresholds("-10,-80,-50").is_err());
+    }
+}
@@ -5,8 +5,39 @@ mod behaviour;
 mod client;
 pub use client::DriaP2PClient;
 
+mod chunk;
+pub use chunk::Chunk;
+
 mod commands;
-pub use commands::{DriaP2PCommand, DriaP2PCommander};
+pub use commands::{
+    ChannelStats, DriaP2PCommand, DriaP2PCommander, RequestChannel, RequestOutcomes, TopicInfo,
+};
+
+mod config;
+pub use config::{parse_score_thresholds, ConnectionCaps, DriaP2PConfig};
+
+mod listen;
+pub use listen::{IpFamily, ListenAddr};
+
+mod limits;
+
+mod policy;
+pub use policy::ConnectionPolicy;
+
+mod redial;
+pub use redial::RedialStatus;
+
+mod retry;
+pub use retry::{PendingResponse, RequestError, RequestRetry};
+
+mod relay;
+pub use relay::RelayHealth;
+
+mod transport;
+pub use transport::P2PTransport;
+
+mod resources;
+pub use resources::ResourceUsage;
 
 mod protocol;
 pub use protocol::DriaP2PProtocol;
@@ -17,6 +48,9 @@ pub use network::DriaNetworkType;
 mod nodes;
 pub use nodes::DriaNodes;
 
+#[cfg(feature = "recorder")]
+pub mod recorder;
+
 // re-exports
 pub use libp2p;
 pub use libp2p_identity;
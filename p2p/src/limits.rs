@@ -0,0 +1,223 @@
+use libp2p::connection_limits::{self, ConnectionLimits};
+use libp2p::core::{transport::PortUse, Endpoint};
+use libp2p::swarm::{
+    behaviour::ConnectionEstablished, dummy, ConnectionClosed, ConnectionDenied, ConnectionId,
+    DialFailure, FromSwarm, ListenFailure, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+use std::{
+    collections::HashSet,
+    task::{Context, Poll},
+};
+
+use crate::ConnectionCaps;
+
+/// Connection limits where the connections of the exempt peers, i.e. the RPCs & bootstraps,
+/// are neither denied nor counted, so that other peers can not crowd them out.
+///
+/// Pending incoming connections are still limited, as their peers are not known yet.
+pub struct ExemptConnectionLimits {
+    inner: connection_limits::Behaviour,
+    /// Peers whose connections are kept alive, e.g. the RPCs.
+    keep_alive_peers: HashSet<PeerId>,
+    /// Peers whose connections are short-lived, e.g. the bootstraps.
+    short_lived_peers: HashSet<PeerId>,
+    /// Established connections of the exempt peers, which are not reported to the limits.
+    exempt_connections: HashSet<ConnectionId>,
+}
+
+impl ExemptConnectionLimits {
+    pub fn new(
+        caps: &ConnectionCaps,
+        keep_alive_peers: HashSet<PeerId>,
+        short_lived_peers: HashSet<PeerId>,
+    ) -> Self {
+        let limits = ConnectionLimits::default()
+            .with_max_pending_incoming(caps.max_pending_incoming)
+            .with_max_pending_outgoing(caps.max_pending_outgoing)
+            .with_max_established_per_peer(caps.max_established_per_peer)
+            .with_max_established_outgoing(caps.max_established_outgoing)
+            .with_max_established(caps.max_established);
+
+        Self {
+            inner: connection_limits::Behaviour::new(limits),
+            keep_alive_peers,
+            short_lived_peers,
+            exempt_connections: HashSet::new(),
+        }
+    }
+
+    /// Replaces the kept-alive peers that are exempt, the established connections are counted as they were.
+    pub fn set_keep_alive_peers(&mut self, peers: HashSet<PeerId>) {
+        self.keep_alive_peers = peers;
+    }
+
+    #[inline]
+    fn is_exempt(&self, peer_id: &PeerId) -> bool {
+        self.keep_alive_peers.contains(peer_id) || self.short_lived_peers.contains(peer_id)
+    }
+}
+
+impl NetworkBehaviour for ExemptConnectionLimits {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = <connection_limits::Behaviour as NetworkBehaviour>::ToSwarm;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        // the limits are consulted regardless, so that they stop tracking the pending connection
+        let handler = self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        );
+        if self.is_exempt(&peer) {
+            self.exempt_connections.insert(connection_id);
+            return Ok(dummy::ConnectionHandler);
+        }
+
+        handler
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if maybe_peer.is_some_and(|peer_id| self.is_exempt(&peer_id)) {
+            return Ok(vec![]);
+        }
+
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handler = self.inner.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+            port_use,
+        );
+        if self.is_exempt(&peer) {
+            self.exempt_connections.insert(connection_id);
+            return Ok(dummy::ConnectionHandler);
+        }
+
+        handler
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { connection_id, .. })
+                if self.exempt_connections.contains(&connection_id) => {}
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. })
+                if self.exempt_connections.remove(&connection_id) => {}
+            // the connection may be denied by another behaviour after it is exempted here
+            FromSwarm::DialFailure(DialFailure { connection_id, .. })
+            | FromSwarm::ListenFailure(ListenFailure { connection_id, .. }) => {
+                self.exempt_connections.remove(&connection_id);
+                self.inner.on_swarm_event(event);
+            }
+            event => self.inner.on_swarm_event(event),
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.inner.poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::ConnectedPoint;
+
+    #[test]
+    fn test_exempt_connection_limits() {
+        let caps = ConnectionCaps {
+            max_established: Some(1),
+            ..Default::default()
+        };
+        let (rpc, peer, other) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut limits = ExemptConnectionLimits::new(&caps, HashSet::from([rpc]), HashSet::new());
+
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+        let endpoint = ConnectedPoint::Dialer {
+            address: addr.clone(),
+            role_override: Endpoint::Dialer,
+            port_use: PortUse::Reuse,
+        };
+        let establish = |limits: &mut ExemptConnectionLimits, id: usize, peer_id: PeerId| {
+            let connection_id = ConnectionId::new_unchecked(id);
+            let result = limits.handle_established_outbound_connection(
+                connection_id,
+                peer_id,
+                &addr,
+                Endpoint::Dialer,
+                PortUse::Reuse,
+            );
+            if result.is_ok() {
+                limits.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                    peer_id,
+                    connection_id,
+                    endpoint: &endpoint,
+                    failed_addresses: &[],
+                    other_established: 0,
+                }));
+            }
+            result.is_ok()
+        };
+
+        // the RPC is not counted, so another peer can still connect
+        assert!(establish(&mut limits, 1, rpc));
+        assert!(establish(&mut limits, 2, peer));
+        assert!(!establish(&mut limits, 3, other));
+
+        // the RPC is not denied either once the limit is reached
+        assert!(establish(&mut limits, 4, rpc));
+    }
+}
@@ -0,0 +1,196 @@
+use eyre::{eyre, Context, Result};
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use std::str::FromStr;
+
+/// A listen address of the client, along with its policies.
+///
+/// Parsed from a multiaddr with optional `;key=value` policies, e.g.
+/// `/ip4/0.0.0.0/udp/4002/quic-v1;advertise=false` or `/ip4/0.0.0.0/tcp/4003;enabled=false`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenAddr {
+    /// The address to listen on.
+    pub addr: Multiaddr,
+    /// Whether the client listens on this address at all.
+    pub enabled: bool,
+    /// Whether the external addresses confirmed for this address are advertised to the network.
+    pub advertise: bool,
+}
+
+impl ListenAddr {
+    /// Creates an enabled & advertised listen address.
+    pub fn new(addr: Multiaddr) -> Self {
+        Self {
+            addr,
+            enabled: true,
+            advertise: true,
+        }
+    }
+
+    /// Returns `true` if the given external address is for this listen address,
+    /// i.e. they have the same transport & port, ignoring the IP and the peer id.
+    pub fn matches_external(&self, external: &Multiaddr) -> bool {
+        fn transport(addr: &Multiaddr) -> Vec<Protocol<'_>> {
+            addr.iter()
+                .filter(|p| {
+                    !matches!(
+                        p,
+                        Protocol::Ip4(_)
+                            | Protocol::Ip6(_)
+                            | Protocol::Dns(_)
+                            | Protocol::Dns4(_)
+                            | Protocol::Dns6(_)
+                            | Protocol::P2p(_)
+                    )
+                })
+                .collect()
+        }
+
+        transport(&self.addr) == transport(external)
+    }
+}
+
+impl From<Multiaddr> for ListenAddr {
+    fn from(addr: Multiaddr) -> Self {
+        Self::new(addr)
+    }
+}
+
+impl FromStr for ListenAddr {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split(';').map(str::trim);
+        let addr = parts
+            .next()
+            .unwrap_or_default()
+            .parse::<Multiaddr>()
+            .wrap_err_with(|| format!("invalid listen address {}", s))?;
+
+        let mut listen_addr = Self::new(addr);
+        for policy in parts.filter(|p| !p.is_empty()) {
+            let (key, value) = policy
+                .split_once('=')
+                .ok_or_else(|| eyre!("invalid policy {} for {}", policy, listen_addr.addr))?;
+            let value = value
+                .trim()
+                .parse::<bool>()
+                .wrap_err_with(|| format!("invalid value for {}", key))?;
+            match key.trim() {
+                "enabled" => listen_addr.enabled = value,
+                "advertise" => listen_addr.advertise = value,
+                key => return Err(eyre!("unknown policy {} for {}", key, listen_addr.addr)),
+            }
+        }
+
+        Ok(listen_addr)
+    }
+}
+
+/// IP family of an address, i.e. IPv4 or IPv6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpFamily {
+    Ip4,
+    Ip6,
+}
+
+impl IpFamily {
+    /// Returns the family of the given address w.r.t its first IP or DNS protocol, if any.
+    ///
+    /// For relayed addresses, this is the family of the connection to the relay.
+    pub fn of(addr: &Multiaddr) -> Option<Self> {
+        addr.iter().find_map(|p| match p {
+            Protocol::Ip4(_) | Protocol::Dns4(_) => Some(Self::Ip4),
+            Protocol::Ip6(_) | Protocol::Dns6(_) => Some(Self::Ip6),
+            _ => None,
+        })
+    }
+}
+
+/// Returns `true` if the given address is reachable from the internet w.r.t its first protocol,
+/// i.e. a public IPv4 or IPv6 address, or a DNS name other than `localhost`.
+pub(crate) fn is_public_addr(addr: &Multiaddr) -> bool {
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => {
+            !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified())
+        }
+        Some(Protocol::Ip6(ip)) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local())
+        }
+        Some(Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name)) => {
+            name != "localhost"
+        }
+        _ => false,
+    }
+}
+
+impl std::fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ip4 => write!(f, "ip4"),
+            Self::Ip6 => write!(f, "ip6"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_addr() {
+        let tcp = "/ip4/0.0.0.0/tcp/4001".parse::<ListenAddr>().unwrap();
+        assert!(tcp.enabled && tcp.advertise);
+
+        let quic = "/ip4/0.0.0.0/udp/4002/quic-v1; advertise=false"
+            .parse::<ListenAddr>()
+            .unwrap();
+        assert!(quic.enabled && !quic.advertise);
+        let disabled = "/ip4/0.0.0.0/tcp/4003;enabled=false;advertise=true"
+            .parse::<ListenAddr>()
+            .unwrap();
+        assert!(!disabled.enabled && disabled.advertise);
+
+        assert!("/ip4/0.0.0.0/tcp/4001;advertise"
+            .parse::<ListenAddr>()
+            .is_err());
+        assert!("/ip4/0.0.0.0/tcp/4001;public=true"
+            .parse::<ListenAddr>()
+            .is_err());
+        assert!("0.0.0.0:4001".parse::<ListenAddr>().is_err());
+
+        // external addresses are matched by their transport & port
+        let external = "/ip4/1.2.3.4/udp/4002/quic-v1/p2p/16Uiu2HAmB2GtwLMH1VDKb3J5UGKXc7h8b4hdPdyaNV16jpNPGhUS"
+            .parse()
+            .unwrap();
+        assert!(quic.matches_external(&external));
+        assert!(!tcp.matches_external(&external));
+        assert!(!tcp.matches_external(&"/ip4/1.2.3.4/tcp/4003".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_ip_family() {
+        let family = |addr: &str| IpFamily::of(&addr.parse().unwrap());
+        assert_eq!(family("/ip4/1.2.3.4/tcp/4001"), Some(IpFamily::Ip4));
+        assert_eq!(family("/ip6/::1/udp/4001/quic-v1"), Some(IpFamily::Ip6));
+        assert_eq!(family("/dns6/rpc.dria.co/tcp/443/wss"), Some(IpFamily::Ip6));
+        assert_eq!(family("/dns/rpc.dria.co/tcp/443/wss"), None);
+    }
+
+    #[test]
+    fn test_is_public_addr() {
+        let public = |addr: &str| is_public_addr(&addr.parse().unwrap());
+        assert!(public("/ip4/1.2.3.4/tcp/4001"));
+        assert!(public("/ip6/2001:db8::1/udp/4001/quic-v1"));
+        assert!(public("/dns4/rpc.dria.co/tcp/443/wss"));
+
+        assert!(!public("/ip4/192.168.1.2/tcp/4001"));
+        assert!(!public("/ip4/127.0.0.1/tcp/4001"));
+        assert!(!public("/ip6/::1/tcp/4001"));
+        assert!(!public("/ip6/fe80::1/tcp/4001"));
+        assert!(!public("/ip6/fd00::1/tcp/4001"));
+        assert!(!public("/dns/localhost/tcp/4001"));
+    }
+}
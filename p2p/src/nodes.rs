@@ -1,6 +1,6 @@
 use crate::DriaNetworkType;
 use dkn_utils::{parse_vec, split_csv_line};
-use libp2p::{Multiaddr, PeerId};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
 use std::{collections::HashSet, env, fmt::Debug};
 
 /// Dria-owned nodes within the hybrid P2P network.
@@ -49,11 +49,13 @@ impl DriaNodes {
         self
     }
 
-    /// Parses static bootstrap & relay nodes from environment variables.
+    /// Parses static bootstrap, relay & RPC nodes from environment variables.
     ///
     /// The environment variables are:
     /// - `DRIA_BOOTSTRAP_NODES`: comma-separated list of bootstrap nodes
     /// - `DRIA_RELAY_NODES`: comma-separated list of relay nodes
+    /// - `DKN_RPC_NODES`: comma-separated list of RPC nodes, e.g. their WebSocket addresses,
+    ///   whose peer ids are trusted as RPCs as well
     pub fn with_envs(mut self) -> Self {
         // parse bootstrap nodes
         let bootstrap_nodes = split_csv_line(&env::var("DKN_BOOTSTRAP_NODES").unwrap_or_default());
@@ -75,6 +77,22 @@ impl DriaNodes {
         self.relay_nodes
             .extend(parse_vec(relay_nodes).expect("could not parse relay nodes"));
 
+        // parse rpc nodes, along with their peer ids
+        let rpc_nodes = split_csv_line(&env::var("DKN_RPC_NODES").unwrap_or_default());
+        if rpc_nodes.is_empty() {
+            log::debug!("No additional RPC nodes provided.");
+        } else {
+            log::debug!("Using additional RPC nodes: {:#?}", rpc_nodes);
+        }
+        let rpc_nodes: Vec<Multiaddr> = parse_vec(rpc_nodes).expect("could not parse rpc nodes");
+        self.rpc_peerids.extend(rpc_nodes.iter().filter_map(|addr| {
+            addr.iter().find_map(|p| match p {
+                Protocol::P2p(peer_id) => Some(peer_id),
+                _ => None,
+            })
+        }));
+        self.rpc_nodes.extend(rpc_nodes);
+
         self
     }
 
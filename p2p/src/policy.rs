@@ -0,0 +1,247 @@
+use libp2p::core::{transport::PortUse, upgrade::DeniedUpgrade, Endpoint};
+use libp2p::swarm::{
+    handler::ConnectionEvent, ConnectionClosed, ConnectionDenied, ConnectionHandler,
+    ConnectionHandlerEvent, ConnectionId, FromSwarm, NetworkBehaviour, NotifyHandler,
+    SubstreamProtocol, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    convert::Infallible,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::DriaNodes;
+
+/// Number of seconds before an idle connection is closed.
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 60;
+/// Number of seconds before a connection to a short-lived peer is closed.
+const DEFAULT_SHORT_LIVED_TIMEOUT_SECS: u64 = 30;
+/// Number of attempts to redial a keep-alive peer before giving up on it.
+const DEFAULT_MAX_REDIAL_ATTEMPTS: u32 = 10;
+
+/// How long connections are kept w.r.t the class of their peers.
+///
+/// - Regular peers are closed once they are idle for `idle_timeout`.
+/// - Keep-alive peers (e.g. RPCs) are never closed due to idleness, and are redialled with a backoff
+///   up to `max_redial_attempts` times once disconnected.
+/// - Short-lived peers (e.g. bootstraps) are closed `short_lived_timeout` after they are connected,
+///   as they are only needed to bootstrap the DHT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionPolicy {
+    /// Duration of idleness before a connection is closed.
+    pub idle_timeout: Duration,
+    /// Duration after which the connections to short-lived peers are closed.
+    pub short_lived_timeout: Duration,
+    /// Peers whose connections are kept alive.
+    pub keep_alive_peers: HashSet<PeerId>,
+    /// Peers whose connections are closed soon after they are established.
+    pub short_lived_peers: HashSet<PeerId>,
+    /// Number of attempts to redial a disconnected keep-alive peer, they are not redialled if zero.
+    pub max_redial_attempts: u32,
+}
+
+impl Default for ConnectionPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS),
+            short_lived_timeout: Duration::from_secs(DEFAULT_SHORT_LIVED_TIMEOUT_SECS),
+            keep_alive_peers: HashSet::new(),
+            short_lived_peers: HashSet::new(),
+            max_redial_attempts: DEFAULT_MAX_REDIAL_ATTEMPTS,
+        }
+    }
+}
+
+impl ConnectionPolicy {
+    /// Creates the policy for the given nodes, where RPCs are kept alive and bootstraps are short-lived.
+    pub fn for_nodes(nodes: &DriaNodes) -> Self {
+        let peer_id_of = |addr: &Multiaddr| {
+            addr.iter().find_map(|p| match p {
+                Protocol::P2p(peer_id) => Some(peer_id),
+                _ => None,
+            })
+        };
+
+        Self {
+            keep_alive_peers: nodes.rpc_peerids.clone(),
+            short_lived_peers: nodes
+                .bootstrap_nodes
+                .iter()
+                .filter_map(peer_id_of)
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the idle timeout of the regular peers.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Sets the duration after which the connections to short-lived peers are closed.
+    pub fn with_short_lived_timeout(mut self, short_lived_timeout: Duration) -> Self {
+        self.short_lived_timeout = short_lived_timeout;
+        self
+    }
+
+    /// Sets the number of attempts to redial a disconnected keep-alive peer.
+    pub fn with_max_redial_attempts(mut self, max_redial_attempts: u32) -> Self {
+        self.max_redial_attempts = max_redial_attempts;
+        self
+    }
+}
+
+/// A behaviour that keeps the connections to the given peers alive, regardless of idleness.
+///
+/// It does not handle any protocols, it only votes for keeping the connection alive.
+pub struct KeepAliveBehaviour {
+    peers: HashSet<PeerId>,
+    /// Established connections of each peer, so that their handlers can be told about the changes of `peers`.
+    connections: HashMap<PeerId, HashSet<ConnectionId>>,
+    /// Notifications to the handlers of the peers that are added or removed.
+    pending_events: VecDeque<ToSwarm<Infallible, bool>>,
+}
+
+impl KeepAliveBehaviour {
+    pub fn new(peers: HashSet<PeerId>) -> Self {
+        Self {
+            peers,
+            connections: HashMap::new(),
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    /// Replaces the peers whose connections are kept alive, including the established connections.
+    pub fn set_peers(&mut self, peers: HashSet<PeerId>) {
+        for (peer_id, connections) in &self.connections {
+            let keep_alive = peers.contains(peer_id);
+            if keep_alive == self.peers.contains(peer_id) {
+                continue;
+            }
+
+            for connection_id in connections {
+                self.pending_events.push_back(ToSwarm::NotifyHandler {
+                    peer_id: *peer_id,
+                    handler: NotifyHandler::One(*connection_id),
+                    event: keep_alive,
+                });
+            }
+        }
+        self.peers = peers;
+    }
+}
+
+impl NetworkBehaviour for KeepAliveBehaviour {
+    type ConnectionHandler = KeepAliveHandler;
+    type ToSwarm = Infallible;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.connections
+            .entry(peer)
+            .or_default()
+            .insert(connection_id);
+        Ok(KeepAliveHandler(self.peers.contains(&peer)))
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.connections
+            .entry(peer)
+            .or_default()
+            .insert(connection_id);
+        Ok(KeepAliveHandler(self.peers.contains(&peer)))
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.pending_events.pop_front() {
+            Some(event) => Poll::Ready(event),
+            None => Poll::Pending,
+        }
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionClosed(ConnectionClosed {
+            peer_id,
+            connection_id,
+            ..
+        }) = event
+        {
+            if let Some(connections) = self.connections.get_mut(&peer_id) {
+                connections.remove(&connection_id);
+                if connections.is_empty() {
+                    self.connections.remove(&peer_id);
+                }
+            }
+        }
+    }
+}
+
+/// Connection handler of [`KeepAliveBehaviour`], keeps the connection alive if its flag is set.
+///
+/// The flag is updated by the behaviour when the peer is added to or removed from the kept-alive peers.
+pub struct KeepAliveHandler(bool);
+
+impl ConnectionHandler for KeepAliveHandler {
+    type FromBehaviour = bool;
+    type ToBehaviour = Infallible;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = Infallible;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.0
+    }
+
+    fn on_behaviour_event(&mut self, keep_alive: Self::FromBehaviour) {
+        self.0 = keep_alive;
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        Poll::Pending
+    }
+
+    fn on_connection_event(
+        &mut self,
+        _: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+    }
+}
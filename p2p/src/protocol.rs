@@ -1,6 +1,11 @@
 use libp2p::StreamProtocol;
 use std::env;
 
+/// Default maximum size of a request, in bytes.
+const DEFAULT_MAX_REQUEST_SIZE: u64 = 1024 * 1024;
+/// Default maximum size of a response, in bytes.
+const DEFAULT_MAX_RESPONSE_SIZE: u64 = 10 * 1024 * 1024;
+
 #[derive(Clone, Debug)]
 pub struct DriaP2PProtocol {
     /// Main protocol name, e.g. `dria`.
@@ -24,6 +29,17 @@ pub struct DriaP2PProtocol {
     /// which is mandatory for a `StreamProtocol`.
     ///
     pub request_response: StreamProtocol,
+    /// Request-response protocol for the chunks of the large responses, supported by the peers that
+    /// can reassemble them; peers without it receive the responses in a single frame.
+    ///
+    /// This is usually `/{name}/rr-chunks/{version}`.
+    pub request_response_chunks: StreamProtocol,
+    /// Maximum size of a request-response request on the wire, in bytes.
+    pub max_request_size: u64,
+    /// Maximum size of a request-response response on the wire, in bytes.
+    ///
+    /// Chunked responses are reassembled up to this size as well, see [`Chunk`](crate::Chunk).
+    pub max_response_size: u64,
 }
 
 impl std::fmt::Display for DriaP2PProtocol {
@@ -50,6 +66,8 @@ impl DriaP2PProtocol {
             StreamProtocol::try_from_owned(format!("/{}/kad/{}", name, version)).unwrap();
         let request_response =
             StreamProtocol::try_from_owned(format!("/{}/rr/{}", name, version)).unwrap();
+        let request_response_chunks =
+            StreamProtocol::try_from_owned(format!("/{}/rr-chunks/{}", name, version)).unwrap();
 
         Self {
             name,
@@ -57,9 +75,24 @@ impl DriaP2PProtocol {
             identity,
             kademlia,
             request_response,
+            request_response_chunks,
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
         }
     }
 
+    /// Sets the maximum size of a request-response request, in bytes.
+    pub fn with_max_request_size(mut self, max_request_size: u64) -> Self {
+        self.max_request_size = max_request_size;
+        self
+    }
+
+    /// Sets the maximum size of a request-response response, in bytes.
+    pub fn with_max_response_size(mut self, max_response_size: u64) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
     /// Creates a new instance of the protocol with the given `name` and the current version as per Cargo.toml.
     /// The verison is represented with `major.minor` version numbers.
     pub fn new_major_minor(name: &str) -> Self {
@@ -87,6 +120,11 @@ impl DriaP2PProtocol {
         self.request_response.clone()
     }
 
+    /// Returns the request-response protocol of the chunks, e.g. `/dria/rr-chunks/0.2`.
+    pub fn request_response_chunks(&self) -> StreamProtocol {
+        self.request_response_chunks.clone()
+    }
+
     /// Returns `true` if the given protocol has a matching prefix with out Kademlia protocol.
     /// Otherwise, returns `false`.
     pub fn is_common_kademlia(&self, protocol: &StreamProtocol) -> bool {
@@ -107,6 +145,10 @@ mod tests {
         assert_eq!(protocol.version, "1.0");
         assert_eq!(protocol.identity, "test/1.0");
         assert_eq!(protocol.kademlia.to_string(), "/test/kad/1.0");
+        assert_eq!(
+            protocol.request_response_chunks.to_string(),
+            "/test/rr-chunks/1.0"
+        );
     }
 
     #[test]
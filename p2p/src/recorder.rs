@@ -0,0 +1,411 @@
+//! Recording & replaying of inbound peer-to-peer events, for regression tests.
+//!
+//! Sessions are stored as JSON lines, one event per line in the order they were received.
+//! The responses of the node to the requests are recorded as well, so that a replay can be checked against them.
+
+use dkn_utils::get_current_time_nanos;
+use eyre::{Context, Result};
+use libp2p::gossipsub::{Message, MessageId, TopicHash};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+use crate::RequestChannel;
+
+/// Duration after which the buffered events are written to the file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An inbound event, along with the time it was received relative to the start of the session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RecordedEvent {
+    /// Start of the session, as milliseconds since the Unix epoch.
+    #[serde(rename_all = "camelCase")]
+    Started { unix_ms: u64 },
+    /// A gossipsub message.
+    #[serde(rename_all = "camelCase")]
+    Message {
+        elapsed_ms: u64,
+        propagation_source: String,
+        message_id: Vec<u8>,
+        source: Option<String>,
+        sequence_number: Option<u64>,
+        topic: String,
+        data: Vec<u8>,
+    },
+    /// A request-response request, along with its index among the inbound requests.
+    #[serde(rename_all = "camelCase")]
+    Request {
+        elapsed_ms: u64,
+        #[serde(default)]
+        index: u64,
+        peer_id: String,
+        data: Vec<u8>,
+    },
+    /// A response of the node, to the request with the given index.
+    #[serde(rename_all = "camelCase")]
+    Respond {
+        elapsed_ms: u64,
+        index: u64,
+        peer_id: String,
+        data: Vec<u8>,
+    },
+    /// A request-response response, to a request of the node.
+    #[serde(rename_all = "camelCase")]
+    Response {
+        elapsed_ms: u64,
+        peer_id: String,
+        data: Vec<u8>,
+    },
+}
+
+impl RecordedEvent {
+    /// Time of the event relative to the start of the session.
+    pub fn elapsed(&self) -> Duration {
+        match self {
+            RecordedEvent::Started { .. } => Duration::ZERO,
+            RecordedEvent::Message { elapsed_ms, .. }
+            | RecordedEvent::Request { elapsed_ms, .. }
+            | RecordedEvent::Respond { elapsed_ms, .. }
+            | RecordedEvent::Response { elapsed_ms, .. } => Duration::from_millis(*elapsed_ms),
+        }
+    }
+
+    /// Converts a recorded message back to the form it is received from the swarm.
+    ///
+    /// Returns `None` for the other events.
+    pub fn to_message(&self) -> Result<Option<(PeerId, MessageId, Message)>> {
+        let RecordedEvent::Message {
+            propagation_source,
+            message_id,
+            source,
+            sequence_number,
+            topic,
+            data,
+            ..
+        } = self
+        else {
+            return Ok(None);
+        };
+
+        let propagation_source = propagation_source
+            .parse::<PeerId>()
+            .wrap_err("could not parse propagation source")?;
+        let source = source
+            .as_ref()
+            .map(|source| source.parse::<PeerId>())
+            .transpose()
+            .wrap_err("could not parse source")?;
+
+        Ok(Some((
+            propagation_source,
+            MessageId::new(message_id),
+            Message {
+                source,
+                data: data.clone(),
+                sequence_number: *sequence_number,
+                topic: TopicHash::from_raw(topic),
+            },
+        )))
+    }
+}
+
+/// Records the inbound events of a session to a file.
+///
+/// Events are buffered & written at most `FLUSH_INTERVAL` apart, and the rest are written when the recorder is dropped.
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    started_at: Instant,
+    flushed_at: Instant,
+}
+
+impl SessionRecorder {
+    /// Creates the session file, overwriting an existing one.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path).wrap_err("could not create session file")?;
+        let mut recorder = Self {
+            writer: BufWriter::new(file),
+            started_at: Instant::now(),
+            flushed_at: Instant::now(),
+        };
+        recorder.record(RecordedEvent::Started {
+            unix_ms: (get_current_time_nanos() / 1_000_000) as u64,
+        })?;
+
+        Ok(recorder)
+    }
+
+    /// Records a gossipsub message.
+    pub fn record_message(
+        &mut self,
+        propagation_source: &PeerId,
+        message_id: &MessageId,
+        message: &Message,
+    ) -> Result<()> {
+        self.record(RecordedEvent::Message {
+            elapsed_ms: self.elapsed_ms(),
+            propagation_source: propagation_source.to_string(),
+            message_id: message_id.0.clone(),
+            source: message.source.map(|source| source.to_string()),
+            sequence_number: message.sequence_number,
+            topic: message.topic.to_string(),
+            data: message.data.clone(),
+        })
+    }
+
+    /// Records a request-response request, with its index among the inbound requests.
+    pub fn record_request(&mut self, index: u64, peer_id: &PeerId, data: &[u8]) -> Result<()> {
+        self.record(RecordedEvent::Request {
+            elapsed_ms: self.elapsed_ms(),
+            index,
+            peer_id: peer_id.to_string(),
+            data: data.to_vec(),
+        })
+    }
+
+    /// Records a response of the node, to the request with the given index.
+    pub fn record_respond(&mut self, index: u64, peer_id: &PeerId, data: &[u8]) -> Result<()> {
+        self.record(RecordedEvent::Respond {
+            elapsed_ms: self.elapsed_ms(),
+            index,
+            peer_id: peer_id.to_string(),
+            data: data.to_vec(),
+        })
+    }
+
+    /// Records a request-response response to a request of the node.
+    pub fn record_response(&mut self, peer_id: &PeerId, data: &[u8]) -> Result<()> {
+        self.record(RecordedEvent::Response {
+            elapsed_ms: self.elapsed_ms(),
+            peer_id: peer_id.to_string(),
+            data: data.to_vec(),
+        })
+    }
+
+    fn record(&mut self, event: RecordedEvent) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, &event)?;
+        self.writer.write_all(b"\n")?;
+        // flushed every once in a while, so that a crashing session is mostly recorded as well
+        if self.flushed_at.elapsed() >= FLUSH_INTERVAL {
+            self.writer.flush()?;
+            self.flushed_at = Instant::now();
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn elapsed_ms(&self) -> u64 {
+        self.started_at.elapsed().as_millis() as u64
+    }
+}
+
+/// Reads the events of a recorded session.
+pub fn read_session(path: impl AsRef<Path>) -> Result<Vec<RecordedEvent>> {
+    let file = File::open(path).wrap_err("could not open session file")?;
+    BufReader::new(file)
+        .lines()
+        .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// Returns how far behind the current time the given session was started, in nanoseconds.
+///
+/// Subtracting this from the current time gives the clock of the replay, so that the deadlines of the
+/// replayed messages are checked w.r.t the time they were recorded at, instead of being past.
+pub fn replay_clock_offset(events: &[RecordedEvent]) -> u128 {
+    events
+        .iter()
+        .find_map(|event| match event {
+            RecordedEvent::Started { unix_ms } => Some(*unix_ms as u128 * 1_000_000),
+            _ => None,
+        })
+        .map(|started_at| get_current_time_nanos().saturating_sub(started_at))
+        .unwrap_or_default()
+}
+
+/// A replayed request, along with the response that the node gave to it within the recorded session.
+#[derive(Debug)]
+pub struct ReplayedRequest {
+    pub index: u64,
+    pub peer_id: PeerId,
+    /// Response within the recorded session, `None` if the node did not respond to it.
+    pub recorded: Option<Vec<u8>>,
+    /// Response to the replay, which errors if the node drops the request without responding.
+    pub response: oneshot::Receiver<Vec<u8>>,
+}
+
+/// Feeds the recorded messages & requests to the given channels in their recorded order.
+///
+/// The requests are responded to through the returned receivers instead of the network, so that the responses
+/// can be checked against the recorded ones. Responses to the requests of the node are skipped, as the node makes
+/// those requests anew. If `realtime` is set, the recorded delays between the events are kept as well.
+pub async fn replay_session(
+    events: Vec<RecordedEvent>,
+    msg_tx: mpsc::Sender<(PeerId, MessageId, Message)>,
+    req_tx: mpsc::Sender<(PeerId, Vec<u8>, RequestChannel)>,
+    realtime: bool,
+) -> Result<Vec<ReplayedRequest>> {
+    let mut responds = events
+        .iter()
+        .filter_map(|event| match event {
+            RecordedEvent::Respond { index, data, .. } => Some((*index, data.clone())),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let started_at = Instant::now();
+    let mut replayed = Vec::new();
+    for event in events {
+        if realtime {
+            tokio::time::sleep_until(started_at + event.elapsed()).await;
+        }
+
+        match event {
+            RecordedEvent::Message { .. } => {
+                if let Some(message) = event.to_message()? {
+                    msg_tx
+                        .send(message)
+                        .await
+                        .wrap_err("could not send replayed message")?;
+                }
+            }
+            RecordedEvent::Request {
+                index,
+                peer_id,
+                data,
+                ..
+            } => {
+                let peer_id = peer_id
+                    .parse::<PeerId>()
+                    .wrap_err("could not parse request peer")?;
+                let (sender, response) = oneshot::channel();
+                req_tx
+                    .send((peer_id, data, RequestChannel::Replay(sender)))
+                    .await
+                    .wrap_err("could not send replayed request")?;
+                replayed.push(ReplayedRequest {
+                    index,
+                    peer_id,
+                    recorded: responds.remove(&index),
+                    response,
+                });
+            }
+            event => log::debug!("Skipping recorded event at {:?}", event.elapsed()),
+        }
+    }
+
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("dkn-session-{}.jsonl", PeerId::random()));
+        let (peer_id, source) = (PeerId::random(), PeerId::random());
+        let message_id = MessageId::new(b"message-id");
+        let message = Message {
+            source: Some(source),
+            data: b"{\"hello\":\"world\"}".to_vec(),
+            sequence_number: Some(7),
+            topic: TopicHash::from_raw("ping"),
+        };
+
+        let mut recorder = SessionRecorder::new(&path).unwrap();
+        recorder.record_request(0, &peer_id, b"request").unwrap();
+        recorder.record_response(&peer_id, b"response").unwrap();
+        recorder
+            .record_message(&peer_id, &message_id, &message)
+            .unwrap();
+        recorder.record_request(1, &peer_id, b"dropped").unwrap();
+        recorder.record_respond(0, &peer_id, b"respond").unwrap();
+        drop(recorder);
+
+        let events = read_session(&path).unwrap();
+        assert_eq!(events.len(), 6);
+        assert!(matches!(events[0], RecordedEvent::Started { .. }));
+        assert!(matches!(events[1], RecordedEvent::Request { index: 0, .. }));
+        assert!(matches!(events[2], RecordedEvent::Response { .. }));
+        assert!(matches!(events[5], RecordedEvent::Respond { index: 0, .. }));
+
+        // the message & requests are replayed, with the recorded responses of the node
+        let (msg_tx, mut msg_rx) = mpsc::channel(4);
+        let (req_tx, mut req_rx) = mpsc::channel(4);
+        let replayed = replay_session(events, msg_tx, req_tx, false).await.unwrap();
+        let (replayed_peer_id, replayed_id, replayed_message) = msg_rx.recv().await.unwrap();
+        assert_eq!(replayed_peer_id, peer_id);
+        assert_eq!(replayed_id, message_id);
+        assert_eq!(replayed_message, message);
+
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[0].recorded.as_deref(), Some(b"respond".as_slice()));
+        assert_eq!(replayed[1].recorded, None);
+
+        // the node responds to the first request & drops the second one
+        let (_, data, channel) = req_rx.recv().await.unwrap();
+        assert_eq!(data, b"request");
+        let RequestChannel::Replay(sender) = channel else {
+            panic!("expected a replay channel");
+        };
+        sender.send(b"respond".to_vec()).unwrap();
+        drop(req_rx.recv().await.unwrap());
+
+        let mut replayed = replayed.into_iter();
+        assert_eq!(replayed.next().unwrap().response.await.unwrap(), b"respond");
+        assert!(replayed.next().unwrap().response.await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_clock() {
+        // a session recorded an hour ago, with a ping that was due a minute after its start
+        let started_at = get_current_time_nanos() - Duration::from_secs(60 * 60).as_nanos();
+        let deadline = started_at + Duration::from_secs(60).as_nanos();
+        let events = vec![
+            RecordedEvent::Started {
+                unix_ms: (started_at / 1_000_000) as u64,
+            },
+            RecordedEvent::Message {
+                elapsed_ms: 30_000,
+                propagation_source: PeerId::random().to_string(),
+                message_id: b"ping-id".to_vec(),
+                source: None,
+                sequence_number: None,
+                topic: "ping".to_string(),
+                data: format!("{{\"uuid\":\"abc\",\"deadline\":{}}}", deadline).into_bytes(),
+            },
+        ];
+
+        // the ping is past its deadline w.r.t the current time, but not w.r.t the replay
+        let offset = replay_clock_offset(&events);
+        assert!(get_current_time_nanos() >= deadline);
+        assert!(offset >= Duration::from_secs(60 * 60).as_nanos());
+
+        // the replayed ping arrives 30 seconds into a realtime replay, within its deadline
+        let elapsed = events[1].elapsed();
+        let (msg_tx, mut msg_rx) = mpsc::channel(4);
+        let (req_tx, _req_rx) = mpsc::channel(4);
+        replay_session(events, msg_tx, req_tx, false).await.unwrap();
+        let (_, _, ping) = msg_rx.recv().await.unwrap();
+        let replayed_at = get_current_time_nanos() - offset + elapsed.as_nanos();
+        assert!(replayed_at < deadline);
+        assert!(String::from_utf8(ping.data)
+            .unwrap()
+            .contains(&deadline.to_string()));
+
+        // sessions without a start are replayed on the current clock
+        assert_eq!(replay_clock_offset(&[]), 0);
+    }
+}
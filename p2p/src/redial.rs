@@ -0,0 +1,295 @@
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use std::{
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    hash::BuildHasher,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Delay before the first redial of a peer, doubled for each failed attempt.
+const INITIAL_REDIAL_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound of the delay between the redials of a peer.
+const MAX_REDIAL_DELAY: Duration = Duration::from_secs(5 * 60);
+/// Duration that a connection must stay up for the backoff of its peer to be reset.
+const STABLE_CONNECTION_DURATION: Duration = Duration::from_secs(60);
+
+/// Redial state of a disconnected peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedialStatus {
+    /// Waiting for the next redial, after the given number of failed attempts.
+    Backoff { attempts: u32, next_in: Duration },
+    /// A redial is in progress.
+    Dialling { attempts: u32 },
+    /// Not redialled anymore after the given number of attempts, until the peer is dialled again.
+    GaveUp { attempts: u32 },
+}
+
+#[derive(Debug)]
+struct Redial {
+    /// Number of failed attempts.
+    attempts: u32,
+    /// Time of the next attempt, `None` while dialling or once given up.
+    due: Option<Instant>,
+    /// Whether the attempts are exhausted.
+    gave_up: bool,
+    /// Time that the peer was connected again, until its connection is stable or lost.
+    connected_at: Option<Instant>,
+}
+
+/// Redials the disconnected peers with an exponential backoff & jitter, so that a restarting peer
+/// is not dialled by all of its nodes at once.
+#[derive(Debug)]
+pub(crate) struct RedialScheduler {
+    /// Addresses of the peers that are redialled.
+    addresses: HashMap<PeerId, Vec<Multiaddr>>,
+    /// Peers that are disconnected & being redialled.
+    redials: HashMap<PeerId, Redial>,
+    /// Number of attempts before giving up on a peer, peers are not redialled if zero.
+    max_attempts: u32,
+    /// Source of the jitter.
+    random: RandomState,
+}
+
+impl RedialScheduler {
+    /// Creates a scheduler that redials the peers of the given addresses, addresses without a peer id are skipped.
+    pub fn new<'a>(addrs: impl IntoIterator<Item = &'a Multiaddr>, max_attempts: u32) -> Self {
+        let mut scheduler = Self {
+            addresses: HashMap::new(),
+            redials: HashMap::new(),
+            max_attempts,
+            random: RandomState::new(),
+        };
+        for addr in addrs {
+            if let Some(peer_id) = addr.iter().find_map(|p| match p {
+                Protocol::P2p(peer_id) => Some(peer_id),
+                _ => None,
+            }) {
+                scheduler.track(peer_id, addr.clone());
+            }
+        }
+
+        scheduler
+    }
+
+    /// Redials the peer at the given address from now on, and gives it a fresh set of attempts.
+    pub fn track(&mut self, peer_id: PeerId, addr: Multiaddr) {
+        let addrs = self.addresses.entry(peer_id).or_default();
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+        self.redials.remove(&peer_id);
+    }
+
+    /// Schedules the first redial of a peer that is disconnected, if it is redialled.
+    ///
+    /// A connection that is lost before it is stable counts as a failed attempt, so that a peer that
+    /// accepts & then drops the connections is not redialled every second; such losses alone never give up on it.
+    pub fn disconnected(&mut self, peer_id: PeerId, now: Instant) {
+        if self.max_attempts == 0 || !self.addresses.contains_key(&peer_id) {
+            return;
+        }
+
+        let attempts = match self.redials.get(&peer_id) {
+            None => 0,
+            Some(redial) => match redial.connected_at {
+                // already being redialled
+                None => return,
+                Some(at) if now.duration_since(at) < STABLE_CONNECTION_DURATION => {
+                    (redial.attempts + 1).min(self.max_attempts - 1)
+                }
+                Some(_) => 0,
+            },
+        };
+        if attempts > 0 {
+            log::warn!(
+                "Connection to {} was lost soon after it was established.",
+                peer_id
+            );
+        }
+
+        let due = now + self.backoff(&peer_id, attempts);
+        self.redials.insert(
+            peer_id,
+            Redial {
+                attempts,
+                due: Some(due),
+                gave_up: false,
+                connected_at: None,
+            },
+        );
+    }
+
+    /// Schedules the next redial of a peer whose redial failed, or gives up on it.
+    pub fn dial_failed(&mut self, peer_id: PeerId, now: Instant) {
+        // only the failures of the redials count, not those of the other dials to the peer
+        let Some(redial) = self.redials.get(&peer_id) else {
+            return;
+        };
+        if redial.due.is_some() || redial.gave_up || redial.connected_at.is_some() {
+            return;
+        }
+
+        let attempts = redial.attempts + 1;
+        let gave_up = attempts >= self.max_attempts;
+        let due = match gave_up {
+            true => {
+                log::warn!(
+                    "Giving up redialling {} after {} attempts.",
+                    peer_id,
+                    attempts
+                );
+                None
+            }
+            false => {
+                let backoff = self.backoff(&peer_id, attempts);
+                log::info!("Redialling {} in {}s.", peer_id, backoff.as_secs());
+                Some(now + backoff)
+            }
+        };
+        self.redials.insert(
+            peer_id,
+            Redial {
+                attempts,
+                due,
+                gave_up,
+                connected_at: None,
+            },
+        );
+    }
+
+    /// Stops redialling the peers that are not among the given ones, e.g. RPCs that are not available anymore.
+    pub fn retain(&mut self, peers: &HashSet<PeerId>) {
+        self.addresses.retain(|peer_id, _| peers.contains(peer_id));
+        self.redials.retain(|peer_id, _| peers.contains(peer_id));
+    }
+
+    /// Stops redialling a peer that is connected, its backoff is kept until the connection is stable.
+    pub fn connected(&mut self, peer_id: &PeerId, now: Instant) {
+        if let Some(redial) = self.redials.get_mut(peer_id) {
+            redial.due = None;
+            redial.gave_up = false;
+            redial.connected_at.get_or_insert(now);
+        }
+    }
+
+    /// Returns the peers that are due to be redialled along with their addresses, marking them as dialling.
+    pub fn take_due(&mut self, now: Instant) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        self.redials
+            .iter_mut()
+            .filter(|(_, redial)| redial.due.is_some_and(|due| due <= now))
+            .map(|(peer_id, redial)| {
+                redial.due = None;
+                (*peer_id, self.addresses[peer_id].clone())
+            })
+            .collect()
+    }
+
+    /// Returns the redial states of the disconnected peers.
+    pub fn status(&self, now: Instant) -> HashMap<PeerId, RedialStatus> {
+        self.redials
+            .iter()
+            .filter(|(_, redial)| redial.connected_at.is_none())
+            .map(|(peer_id, redial)| {
+                let status = match redial.due {
+                    _ if redial.gave_up => RedialStatus::GaveUp {
+                        attempts: redial.attempts,
+                    },
+                    Some(due) => RedialStatus::Backoff {
+                        attempts: redial.attempts,
+                        next_in: due.saturating_duration_since(now),
+                    },
+                    None => RedialStatus::Dialling {
+                        attempts: redial.attempts,
+                    },
+                };
+                (*peer_id, status)
+            })
+            .collect()
+    }
+
+    /// Returns the delay before the given attempt, where up to half of it is random.
+    fn backoff(&self, peer_id: &PeerId, attempts: u32) -> Duration {
+        let delay = INITIAL_REDIAL_DELAY
+            .saturating_mul(1 << attempts.min(16))
+            .min(MAX_REDIAL_DELAY);
+        let jitter = (self.random.hash_one((peer_id, attempts)) % 1000) as f64 / 2000.0;
+
+        delay / 2 + delay.mul_f64(jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redial_backoff() {
+        let peer_id = PeerId::random();
+        let addr = "/ip4/10.0.0.1/tcp/4001"
+            .parse::<Multiaddr>()
+            .unwrap()
+            .with(Protocol::P2p(peer_id));
+        let mut scheduler = RedialScheduler::new([&addr], 3);
+        let now = Instant::now();
+
+        // untracked peers are not redialled
+        scheduler.disconnected(PeerId::random(), now);
+        assert!(scheduler.status(now).is_empty());
+
+        // the delays grow exponentially, with jitter
+        scheduler.disconnected(peer_id, now);
+        assert!(scheduler.take_due(now).is_empty());
+        let mut at = now;
+        for attempts in 0..3 {
+            at += Duration::from_secs(1 << attempts);
+            assert_eq!(scheduler.take_due(at), vec![(peer_id, vec![addr.clone()])]);
+            scheduler.dial_failed(peer_id, at);
+        }
+        assert_eq!(
+            scheduler.status(at)[&peer_id],
+            RedialStatus::GaveUp { attempts: 3 }
+        );
+        assert!(scheduler.take_due(at + MAX_REDIAL_DELAY).is_empty());
+
+        // dialling the peer again gives it a fresh set of attempts
+        scheduler.track(peer_id, addr.clone());
+        scheduler.disconnected(peer_id, at);
+        assert!(matches!(
+            scheduler.status(at)[&peer_id],
+            RedialStatus::Backoff { attempts: 0, .. }
+        ));
+        scheduler.connected(&peer_id, at);
+        assert!(scheduler.status(at).is_empty());
+
+        // a connection that is lost soon after it was established backs off further
+        scheduler.disconnected(peer_id, at + Duration::from_secs(1));
+        assert!(matches!(
+            scheduler.status(at)[&peer_id],
+            RedialStatus::Backoff { attempts: 1, .. }
+        ));
+        scheduler.connected(&peer_id, at);
+        scheduler.disconnected(peer_id, at + Duration::from_secs(1));
+        scheduler.connected(&peer_id, at);
+        scheduler.disconnected(peer_id, at + Duration::from_secs(1));
+        assert!(matches!(
+            scheduler.status(at)[&peer_id],
+            RedialStatus::Backoff { attempts: 2, .. }
+        ));
+
+        // while a stable connection resets the backoff
+        scheduler.connected(&peer_id, at);
+        scheduler.disconnected(peer_id, at + STABLE_CONNECTION_DURATION);
+        assert!(matches!(
+            scheduler.status(at)[&peer_id],
+            RedialStatus::Backoff { attempts: 0, .. }
+        ));
+        scheduler.connected(&peer_id, at);
+
+        // peers that are not retained are not redialled anymore
+        scheduler.disconnected(peer_id, at);
+        scheduler.retain(&HashSet::new());
+        assert!(scheduler.status(at).is_empty());
+        scheduler.disconnected(peer_id, at);
+        assert!(scheduler.status(at).is_empty());
+    }
+}
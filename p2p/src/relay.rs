@@ -0,0 +1,177 @@
+use libp2p::{core::transport::ListenerId, multiaddr::Protocol, Multiaddr, PeerId};
+use std::{collections::HashMap, time::Duration};
+
+/// Maximum number of relays that a reservation is held with at once.
+pub(crate) const MAX_ACTIVE_RELAYS: usize = 2;
+/// Latency assumed for a relay that is not measured yet, so that measured relays are preferred.
+const UNMEASURED_RTT: Duration = Duration::from_secs(2);
+/// Latency added to the score of a relay for each reservation that it dropped or denied.
+const DROP_PENALTY: Duration = Duration::from_secs(5);
+
+/// Health of a relay, as seen by this node.
+#[derive(Debug, Clone)]
+pub struct RelayHealth {
+    /// Address of the relay.
+    pub address: Multiaddr,
+    /// Latest round-trip time to the relay, if it was pinged.
+    pub rtt: Option<Duration>,
+    /// Whether a reservation is currently held with the relay.
+    pub reserved: bool,
+    /// Number of reservations accepted by the relay, including renewals.
+    pub accepted: u32,
+    /// Number of reservations that the relay dropped or denied.
+    pub dropped: u32,
+}
+
+impl RelayHealth {
+    /// Returns the score of the relay, lower is better.
+    ///
+    /// Relays do not publish their load, so the reservations they drop or deny are taken as its hint.
+    fn score(&self) -> Duration {
+        self.rtt.unwrap_or(UNMEASURED_RTT) + DROP_PENALTY * self.dropped
+    }
+}
+
+/// Selects the relays to hold a reservation with, by their latency & the reservations they dropped.
+#[derive(Debug, Default)]
+pub(crate) struct RelaySelector {
+    /// Known relays, by their peer ids.
+    relays: HashMap<PeerId, RelayHealth>,
+    /// Relays listened on over a circuit, by their listener.
+    listeners: HashMap<ListenerId, PeerId>,
+}
+
+impl RelaySelector {
+    /// Creates a selector over the given relays, relays without a peer id are skipped.
+    pub fn new<'a>(addrs: impl IntoIterator<Item = &'a Multiaddr>) -> Self {
+        let mut relays = HashMap::new();
+        for addr in addrs {
+            let Some(peer_id) = addr.iter().find_map(|p| match p {
+                Protocol::P2p(peer_id) => Some(peer_id),
+                _ => None,
+            }) else {
+                log::warn!("Missing peerID in relay address: {}", addr);
+                continue;
+            };
+
+            relays.entry(peer_id).or_insert_with(|| RelayHealth {
+                address: addr.clone(),
+                rtt: None,
+                reserved: false,
+                accepted: 0,
+                dropped: 0,
+            });
+        }
+
+        Self {
+            relays,
+            listeners: HashMap::new(),
+        }
+    }
+
+    /// Returns the addresses of all relays, e.g. to dial them so that their latencies are measured.
+    pub fn addresses(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.relays.values().map(|r| &r.address)
+    }
+
+    /// Returns the best relays that are not listened on, so that there are at most [`MAX_ACTIVE_RELAYS`] listened on.
+    pub fn select(&self) -> Vec<(PeerId, Multiaddr)> {
+        let active = self.listeners.len();
+        let mut candidates = self
+            .relays
+            .iter()
+            .filter(|(peer_id, _)| !self.listeners.values().any(|p| p == *peer_id))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(_, relay)| relay.score());
+
+        candidates
+            .into_iter()
+            .take(MAX_ACTIVE_RELAYS.saturating_sub(active))
+            .map(|(peer_id, relay)| (*peer_id, relay.address.clone()))
+            .collect()
+    }
+
+    /// Records that the relay is listened on with the given listener.
+    pub fn listening(&mut self, listener_id: ListenerId, peer_id: PeerId) {
+        self.listeners.insert(listener_id, peer_id);
+    }
+
+    /// Records the latest round-trip time to a peer, if it is a relay.
+    pub fn record_rtt(&mut self, peer_id: &PeerId, rtt: Duration) {
+        if let Some(relay) = self.relays.get_mut(peer_id) {
+            relay.rtt = Some(rtt);
+        }
+    }
+
+    /// Records an accepted reservation.
+    pub fn record_accepted(&mut self, peer_id: &PeerId) {
+        if let Some(relay) = self.relays.get_mut(peer_id) {
+            relay.reserved = true;
+            relay.accepted += 1;
+        }
+    }
+
+    /// Records that the listener is closed, returns the relay if it was listened on.
+    ///
+    /// The reservation is counted as dropped, so that the next selection rotates away from this relay.
+    pub fn record_closed(&mut self, listener_id: &ListenerId) -> Option<PeerId> {
+        let peer_id = self.listeners.remove(listener_id)?;
+        if let Some(relay) = self.relays.get_mut(&peer_id) {
+            relay.reserved = false;
+            relay.dropped += 1;
+        }
+
+        Some(peer_id)
+    }
+
+    /// Returns the health of all relays, sorted by their scores.
+    pub fn health(&self) -> Vec<RelayHealth> {
+        let mut health = self.relays.values().cloned().collect::<Vec<_>>();
+        health.sort_by_key(RelayHealth::score);
+        health
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_selection() {
+        let addrs = (0..3)
+            .map(|i| {
+                format!("/ip4/10.0.0.{}/tcp/4001", i)
+                    .parse::<Multiaddr>()
+                    .unwrap()
+                    .with(Protocol::P2p(PeerId::random()))
+            })
+            .collect::<Vec<_>>();
+        let peer_ids = addrs
+            .iter()
+            .map(|a| match a.iter().last() {
+                Some(Protocol::P2p(peer_id)) => peer_id,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let mut selector = RelaySelector::new(&addrs);
+
+        // the fastest relays are selected
+        selector.record_rtt(&peer_ids[0], Duration::from_millis(300));
+        selector.record_rtt(&peer_ids[1], Duration::from_millis(100));
+        selector.record_rtt(&peer_ids[2], Duration::from_millis(200));
+        let selected = selector.select();
+        assert_eq!(selected.len(), MAX_ACTIVE_RELAYS);
+        assert_eq!(selected[0].0, peer_ids[1]);
+        assert_eq!(selected[1].0, peer_ids[2]);
+
+        // the relay that dropped its reservation is rotated away from
+        let listener_id = ListenerId::next();
+        selector.listening(listener_id, peer_ids[1]);
+        selector.listening(ListenerId::next(), peer_ids[2]);
+        selector.record_accepted(&peer_ids[1]);
+        assert!(selector.select().is_empty());
+        assert_eq!(selector.record_closed(&listener_id), Some(peer_ids[1]));
+        assert_eq!(selector.select(), vec![(peer_ids[0], addrs[0].clone())]);
+        assert_eq!(selector.health().last().unwrap().dropped, 1);
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+/// Target number of peers within the gossip mesh of each topic, the Gossipsub default.
+const DEFAULT_MESH_N: usize = 6;
+/// Gossipsub heartbeat interval in seconds.
+const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 10;
+/// Number of seconds between pings to a peer, default is 15 seconds.
+const DEFAULT_PING_INTERVAL_SECS: u64 = 30;
+/// Buffer size of the channels between the client and the node.
+const DEFAULT_CHANNEL_BUFSIZE: usize = 1024;
+
+/// How much of the device the client uses, which can be lowered for constrained devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// Target number of peers within the gossip mesh of each topic.
+    ///
+    /// The lower & upper bounds of the mesh are derived from this.
+    pub mesh_n: usize,
+    /// Interval of the Gossipsub heartbeats, where the mesh is maintained.
+    pub heartbeat_interval: Duration,
+    /// Interval between the pings to each connected peer.
+    pub ping_interval: Duration,
+    /// Buffer size of the command, message & request channels.
+    pub channel_bufsize: usize,
+}
+
+impl Default for ResourceUsage {
+    fn default() -> Self {
+        Self {
+            mesh_n: DEFAULT_MESH_N,
+            heartbeat_interval: Duration::from_secs(DEFAULT_HEARTBEAT_INTERVAL_SECS),
+            ping_interval: Duration::from_secs(DEFAULT_PING_INTERVAL_SECS),
+            channel_bufsize: DEFAULT_CHANNEL_BUFSIZE,
+        }
+    }
+}
+
+impl ResourceUsage {
+    /// Returns the `(mesh_n_low, mesh_n, mesh_n_high, mesh_outbound_min)` parameters of Gossipsub,
+    /// which are the Gossipsub defaults `(5, 6, 12, 2)` for the default mesh size.
+    pub fn mesh_params(&self) -> (usize, usize, usize, usize) {
+        let mesh_n = self.mesh_n.max(1);
+        let mesh_n_low = (mesh_n - 1).max(1);
+        let mesh_outbound_min = (mesh_n / 2).min(2).min(mesh_n_low);
+
+        (mesh_n_low, mesh_n, mesh_n * 2, mesh_outbound_min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mesh_params() {
+        assert_eq!(ResourceUsage::default().mesh_params(), (5, 6, 12, 2));
+
+        for mesh_n in 0..10 {
+            let (low, n, high, outbound_min) = ResourceUsage {
+                mesh_n,
+                ..Default::default()
+            }
+            .mesh_params();
+
+            // the constraints that Gossipsub checks within its config
+            assert!(outbound_min <= low && low <= n && n <= high);
+            assert!(outbound_min * 2 <= n);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use libp2p::{request_response::OutboundRequestId, PeerId};
+use std::{collections::HashMap, fmt, time::Duration};
+use tokio::{sync::oneshot, time::Instant};
+
+/// Default duration to wait for the response of an attempt.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default number of attempts of a request.
+const DEFAULT_MAX_REQUEST_ATTEMPTS: u32 = 3;
+
+/// Timeout & attempts of the outbound requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestRetry {
+    /// Duration to wait for the response of an attempt, before it is retried.
+    pub timeout: Duration,
+    /// Number of attempts before a request made with retries fails, at least one is made.
+    pub max_attempts: u32,
+}
+
+impl Default for RequestRetry {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            max_attempts: DEFAULT_MAX_REQUEST_ATTEMPTS,
+        }
+    }
+}
+
+/// Error of an outbound request whose attempts have all failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestError {
+    /// No response arrived within the timeout of the last attempt.
+    TimedOut { correlation_id: u64, attempts: u32 },
+    /// The last attempt failed, e.g. the peer is disconnected or does not support the protocol.
+    Failed {
+        correlation_id: u64,
+        attempts: u32,
+        reason: String,
+    },
+    /// The client is shut down before a response.
+    Closed { correlation_id: u64 },
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TimedOut {
+                correlation_id,
+                attempts,
+            } => write!(
+                f,
+                "request {} timed out after {} attempts",
+                correlation_id, attempts
+            ),
+            Self::Failed {
+                correlation_id,
+                attempts,
+                reason,
+            } => write!(
+                f,
+                "request {} failed after {} attempts: {}",
+                correlation_id, attempts, reason
+            ),
+            Self::Closed { correlation_id } => {
+                write!(f, "request {} is dropped by the client", correlation_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// Response of a request that is being made, which can be awaited or dropped if it is not needed.
+#[derive(Debug)]
+pub struct PendingResponse {
+    /// Identifier of the request, same for all of its attempts.
+    pub correlation_id: u64,
+    receiver: oneshot::Receiver<Result<Vec<u8>, RequestError>>,
+}
+
+impl PendingResponse {
+    /// Waits for the response, or the error once all attempts have failed.
+    pub async fn response(self) -> Result<Vec<u8>, RequestError> {
+        self.receiver.await.unwrap_or(Err(RequestError::Closed {
+            correlation_id: self.correlation_id,
+        }))
+    }
+}
+
+/// A request that is being made, along with what it takes to retry it.
+#[derive(Debug)]
+pub struct PendingRequest {
+    pub(crate) correlation_id: u64,
+    pub(crate) peer_id: PeerId,
+    pub(crate) data: Vec<u8>,
+    /// Whether the request is retried on failures, otherwise a single attempt is made.
+    retry: bool,
+    /// Number of attempts made so far.
+    attempts: u32,
+    /// Time after which the latest attempt is considered lost.
+    deadline: Instant,
+    sender: oneshot::Sender<Result<Vec<u8>, RequestError>>,
+}
+
+impl PendingRequest {
+    /// Creates a request that is not attempted yet, along with its pending response.
+    pub(crate) fn new(
+        correlation_id: u64,
+        peer_id: PeerId,
+        data: Vec<u8>,
+        retry: bool,
+    ) -> (Self, PendingResponse) {
+        let (sender, receiver) = oneshot::channel();
+        let request = Self {
+            correlation_id,
+            peer_id,
+            data,
+            retry,
+            attempts: 0,
+            deadline: Instant::now(),
+            sender,
+        };
+
+        (
+            request,
+            PendingResponse {
+                correlation_id,
+                receiver,
+            },
+        )
+    }
+
+    /// Returns `true` if the request has attempts left w.r.t the given retry config.
+    #[inline]
+    fn can_retry(&self, retry: &RequestRetry) -> bool {
+        self.retry && self.attempts < retry.max_attempts
+    }
+}
+
+/// Requests that await their responses, keyed by the id of their latest attempt.
+#[derive(Debug)]
+pub(crate) struct PendingRequests {
+    retry: RequestRetry,
+    requests: HashMap<OutboundRequestId, PendingRequest>,
+}
+
+impl PendingRequests {
+    pub fn new(retry: RequestRetry) -> Self {
+        Self {
+            retry,
+            requests: HashMap::new(),
+        }
+    }
+
+    /// Tracks a new attempt of the request, sent with the given id.
+    pub fn track(
+        &mut self,
+        request_id: OutboundRequestId,
+        mut request: PendingRequest,
+        now: Instant,
+    ) {
+        request.attempts += 1;
+        request.deadline = now + self.retry.timeout;
+        self.requests.insert(request_id, request);
+    }
+
+    /// Returns `true` if the given attempt awaits its response.
+    pub fn contains(&self, request_id: &OutboundRequestId) -> bool {
+        self.requests.contains_key(request_id)
+    }
+
+    /// Completes the request of the given attempt with its response, returns `false` if it was not tracked.
+    pub fn respond(&mut self, request_id: &OutboundRequestId, response: Vec<u8>) -> bool {
+        let Some(request) = self.requests.remove(request_id) else {
+            return false;
+        };
+
+        let _ = request.sender.send(Ok(response));
+        true
+    }
+
+    /// Handles the failure of an attempt, returns the request if it is to be retried.
+    pub fn fail(
+        &mut self,
+        request_id: &OutboundRequestId,
+        reason: String,
+    ) -> Option<PendingRequest> {
+        let request = self.requests.remove(request_id)?;
+        if request.can_retry(&self.retry) {
+            log::warn!(
+                "Request {} to {} failed ({}/{}), retrying: {}",
+                request.correlation_id,
+                request.peer_id,
+                request.attempts,
+                self.retry.max_attempts,
+                reason
+            );
+            return Some(request);
+        }
+
+        let error = RequestError::Failed {
+            correlation_id: request.correlation_id,
+            attempts: request.attempts,
+            reason,
+        };
+        let _ = request.sender.send(Err(error));
+        None
+    }
+
+    /// Removes the attempts that are past their deadline, returns the requests that are to be retried.
+    pub fn expire(&mut self, now: Instant) -> Vec<PendingRequest> {
+        let expired = self
+            .requests
+            .iter()
+            .filter(|(_, request)| request.deadline <= now)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+
+        let mut retries = Vec::new();
+        for request_id in expired {
+            let Some(request) = self.requests.remove(&request_id) else {
+                continue;
+            };
+            if request.can_retry(&self.retry) {
+                log::warn!(
+                    "Request {} to {} timed out ({}/{}), retrying.",
+                    request.correlation_id,
+                    request.peer_id,
+                    request.attempts,
+                    self.retry.max_attempts
+                );
+                retries.push(request);
+            } else {
+                let error = RequestError::TimedOut {
+                    correlation_id: request.correlation_id,
+                    attempts: request.attempts,
+                };
+                let _ = request.sender.send(Err(error));
+            }
+        }
+
+        retries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dropped_request() {
+        let (request, response) = PendingRequest::new(7, PeerId::random(), vec![1, 2, 3], true);
+        assert_eq!(response.correlation_id, 7);
+
+        // a request dropped by the client is reported as closed
+        drop(request);
+        let error = response.response().await.unwrap_err();
+        assert_eq!(error, RequestError::Closed { correlation_id: 7 });
+        assert_eq!(error.to_string(), "request 7 is dropped by the client");
+    }
+
+    #[test]
+    fn test_request_retry() {
+        let retry = RequestRetry {
+            timeout: Duration::from_secs(1),
+            max_attempts: 2,
+        };
+
+        // a retried request is attempted again until its attempts are used up
+        let (mut request, _) = PendingRequest::new(1, PeerId::random(), vec![], true);
+        request.attempts = 1;
+        assert!(request.can_retry(&retry));
+        request.attempts = 2;
+        assert!(!request.can_retry(&retry));
+
+        // a request without retries fails on its first attempt
+        let (mut request, _) = PendingRequest::new(2, PeerId::random(), vec![], false);
+        request.attempts = 1;
+        assert!(!request.can_retry(&retry));
+    }
+}
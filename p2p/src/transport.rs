@@ -0,0 +1,82 @@
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use std::str::FromStr;
+
+/// Transport that the Dria nodes are dialled over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum P2PTransport {
+    /// Plain TCP & QUIC addresses are dialled.
+    #[default]
+    Tcp,
+    /// Only WebSocket addresses (`/ws` or `/wss`) are dialled, for networks that only allow web traffic.
+    WebSocket,
+}
+
+impl P2PTransport {
+    /// Returns `true` if the address is dialled over this transport.
+    pub fn supports(&self, addr: &Multiaddr) -> bool {
+        let is_websocket = addr
+            .iter()
+            .any(|p| matches!(p, Protocol::Ws(_) | Protocol::Wss(_)));
+
+        match self {
+            Self::Tcp => !is_websocket,
+            Self::WebSocket => is_websocket,
+        }
+    }
+
+    /// Returns `true` if this transport is compiled within this build, WebSockets require the `websocket` feature.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Self::Tcp => true,
+            Self::WebSocket => cfg!(feature = "websocket"),
+        }
+    }
+}
+
+impl FromStr for P2PTransport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(Self::Tcp),
+            "websocket" | "ws" => Ok(Self::WebSocket),
+            _ => Err(format!("unknown transport: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for P2PTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp => write!(f, "tcp"),
+            Self::WebSocket => write!(f, "websocket"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transport_supports() {
+        let tcp: Multiaddr = "/ip4/1.2.3.4/tcp/4001".parse().unwrap();
+        let quic: Multiaddr = "/ip4/1.2.3.4/udp/4001/quic-v1".parse().unwrap();
+        let ws: Multiaddr = "/ip4/1.2.3.4/tcp/80/ws".parse().unwrap();
+        let wss: Multiaddr = "/dns4/rpc.dria.co/tcp/443/wss".parse().unwrap();
+
+        assert!(P2PTransport::Tcp.supports(&tcp) && P2PTransport::Tcp.supports(&quic));
+        assert!(!P2PTransport::Tcp.supports(&ws) && !P2PTransport::Tcp.supports(&wss));
+        assert!(P2PTransport::WebSocket.supports(&ws) && P2PTransport::WebSocket.supports(&wss));
+        assert!(!P2PTransport::WebSocket.supports(&tcp));
+
+        // addresses resolved over DNS are dialled over plain TCP
+        let dns: Multiaddr = "/dns4/bootstrap.dria.co/tcp/4001".parse().unwrap();
+        let dnsaddr: Multiaddr = "/dnsaddr/bootstrap.dria.co".parse().unwrap();
+        assert!(P2PTransport::Tcp.supports(&dns) && P2PTransport::Tcp.supports(&dnsaddr));
+
+        assert_eq!("ws".parse(), Ok(P2PTransport::WebSocket));
+        assert_eq!("tcp".parse(), Ok(P2PTransport::Tcp));
+        assert!("udp".parse::<P2PTransport>().is_err());
+    }
+}
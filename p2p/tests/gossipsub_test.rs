@@ -1,4 +1,6 @@
-use dkn_p2p::{DriaNodes, DriaP2PClient, DriaP2PProtocol};
+use dkn_p2p::{
+    ConnectionPolicy, DriaNodes, DriaP2PClient, DriaP2PConfig, DriaP2PProtocol, ListenAddr,
+};
 use eyre::Result;
 use libp2p_identity::Keypair;
 
@@ -22,7 +24,7 @@ async fn test_gossipsub() -> Result<()> {
         // .is_test(true)
         .try_init();
 
-    let listen_addr = "/ip4/0.0.0.0/tcp/4001".parse()?;
+    let listen_addr: ListenAddr = "/ip4/0.0.0.0/tcp/4001".parse()?;
 
     // prepare nodes
     let nodes = DriaNodes::new(dkn_p2p::DriaNetworkType::Community)
@@ -32,14 +34,16 @@ async fn test_gossipsub() -> Result<()> {
     // spawn P2P client in another task
     let (client, mut commander, mut msg_rx, _) = DriaP2PClient::new(
         Keypair::generate_secp256k1(),
-        listen_addr,
         &nodes,
         DriaP2PProtocol::default(),
+        DriaP2PConfig::new(vec![listen_addr]).with_policy(ConnectionPolicy::for_nodes(&nodes)),
     )?;
     let task_handle = tokio::spawn(async move { client.run().await });
 
     // wait for a single gossipsub message on this topic
     commander.subscribe(TOPIC).await?;
+    let subscriptions = commander.subscriptions().await?;
+    assert!(subscriptions.iter().any(|t| t.topic == TOPIC));
     log::info!("Waiting for messages...");
     let message = msg_rx.recv().await;
     match message {
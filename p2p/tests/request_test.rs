@@ -1,7 +1,9 @@
 use std::str::FromStr;
 
 use dkn_p2p::DriaNetworkType::Community;
-use dkn_p2p::{DriaNodes, DriaP2PClient, DriaP2PProtocol};
+use dkn_p2p::{
+    ConnectionPolicy, DriaNodes, DriaP2PClient, DriaP2PConfig, DriaP2PProtocol, ListenAddr,
+};
 use eyre::Result;
 use libp2p::PeerId;
 use libp2p_identity::Keypair;
@@ -23,7 +25,7 @@ async fn test_request_message() -> Result<()> {
         .is_test(true)
         .try_init();
 
-    let listen_addr = "/ip4/0.0.0.0/tcp/4001".parse()?;
+    let listen_addr: ListenAddr = "/ip4/0.0.0.0/tcp/4001".parse()?;
 
     // prepare nodes
     let nodes = DriaNodes::new(Community)
@@ -33,9 +35,9 @@ async fn test_request_message() -> Result<()> {
     // spawn P2P client in another task
     let (client, mut commander, mut msg_rx, mut req_rx) = DriaP2PClient::new(
         Keypair::generate_secp256k1(),
-        listen_addr,
         &nodes,
         DriaP2PProtocol::default(),
+        DriaP2PConfig::new(vec![listen_addr]).with_policy(ConnectionPolicy::for_nodes(&nodes)),
     )
     .expect("could not create p2p client");
 
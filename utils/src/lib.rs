@@ -1,5 +1,15 @@
 use std::{fmt::Debug, str::FromStr, time::SystemTime};
 
+mod version;
+pub use version::SemanticVersion;
+
+pub mod payloads;
+
+pub mod storage;
+
+mod ratelimit;
+pub use ratelimit::RateLimiter;
+
 /// Utility to parse comma-separated string value line.
 ///
 /// - Trims `"` from both ends for the input
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use super::TaskStats;
+
+/// A recorded task, with its input and the output that was published for it.
+///
+/// Such a dump can be re-executed locally to compare the outputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDump {
+    /// The unique identifier of the task.
+    pub task_id: String,
+    /// Input of the task request, i.e. the workflow, models and prompt.
+    pub input: serde_json::Value,
+    /// Name of the model used for this task.
+    pub model: String,
+    /// The published result, if the task was successful.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    /// The published error, if the task has failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Stats of the recorded execution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<TaskStats>,
+}
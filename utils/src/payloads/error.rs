@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use super::TaskStats;
+
+/// Class of a task error, same for all providers, so that the requester can tell
+/// whether the task is worth sending to another node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskErrorClass {
+    /// The provider or the node rate limits the requests.
+    RateLimited,
+    /// The API key of the provider is missing or invalid.
+    Auth,
+    /// The model does not fit into the memory of the node.
+    OutOfMemory,
+    /// The task itself is invalid, and fails on any node.
+    BadRequest,
+    /// The model of the task is not available on this node, but may be on others.
+    ModelUnavailable,
+    /// Any other error, which may not occur on a retry.
+    Transient,
+}
+
+/// A task error response.
+/// Returning this as the payload helps to debug the errors received at client side.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct TaskErrorPayload {
+    /// The unique identifier of the task.
+    pub task_id: String,
+    /// The stringified error object
+    pub error: String,
+    /// Name of the model that caused the error.
+    pub model: String,
+    /// Task statistics.
+    pub stats: TaskStats,
+    /// Number of seconds after which the task can be sent again, if it was rejected due to a rate limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    /// Class of the error, if it is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<TaskErrorClass>,
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Sent to RPC nodes when the node is shutting down or switching away from an RPC,
+/// within a signed message with [`DriaTopic::Goodbye`](super::DriaTopic::Goodbye).
+///
+/// The RPC can re-assign the in-flight tasks immediately instead of waiting for the heartbeats to time out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GoodbyePayload {
+    /// Reason for leaving, e.g. `shutdown`, `restart` if the node exits for an update,
+    /// or `rpc-switch` if the node has selected another RPC.
+    pub reason: String,
+    /// Tasks that were received from this RPC but not yet responded to.
+    pub pending_tasks: Vec<GoodbyeTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GoodbyeTask {
+    /// The unique identifier of the task.
+    pub task_id: String,
+    /// Name of the model used for this task.
+    pub model: String,
+    /// Whether the task was in the batch queue or the single queue.
+    pub batchable: bool,
+}
@@ -0,0 +1,34 @@
+//! Topics & payloads shared between the compute node and the other Dria components.
+
+mod topics;
+pub use topics::DriaTopic;
+
+mod dump;
+pub use dump::TaskDump;
+
+mod error;
+pub use error::{TaskErrorClass, TaskErrorPayload};
+
+mod goodbye;
+pub use goodbye::{GoodbyePayload, GoodbyeTask};
+
+mod nodes;
+pub use nodes::NodesUpdatePayload;
+
+mod stats;
+pub use stats::TaskStats;
+
+/// Returns the JSON schemas of the payloads along with their names, so that other implementations
+/// of the protocol can code against them.
+#[cfg(feature = "schema")]
+pub fn payload_schemas() -> Vec<(&'static str, schemars::schema::RootSchema)> {
+    use schemars::schema_for;
+
+    vec![
+        ("TaskStats", schema_for!(TaskStats)),
+        ("TaskErrorPayload", schema_for!(TaskErrorPayload)),
+        ("TaskDump", schema_for!(TaskDump)),
+        ("GoodbyePayload", schema_for!(GoodbyePayload)),
+        ("NodesUpdatePayload", schema_for!(NodesUpdatePayload)),
+    ]
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Pushed by the RPC nodes within a signed message with [`DriaTopic::NodesUpdate`](super::DriaTopic::NodesUpdate),
+/// so that the compute nodes can update their available nodes without waiting for the next refresh.
+///
+/// The fields are the same as the response of the available nodes API.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct NodesUpdatePayload {
+    /// Bootstrap node addresses.
+    pub bootstraps: Vec<String>,
+    /// Relay node addresses.
+    pub relays: Vec<String>,
+    /// RPC node peer ids.
+    pub rpcs: Vec<String>,
+    /// RPC node addresses.
+    pub rpc_addrs: Vec<String>,
+    /// Whether the RPC nodes replace the existing ones, e.g. during a rotation, instead of being added to them.
+    #[serde(default)]
+    pub replace: bool,
+    /// Time in nanoseconds after which the update is ignored, prevents old updates from being replayed.
+    ///
+    /// The nodes ignore updates whose deadline is more than 10 minutes ahead of their local time.
+    pub deadline: u128,
+}
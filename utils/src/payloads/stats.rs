@@ -1,9 +1,10 @@
-use dkn_utils::get_current_time_nanos;
+use crate::get_current_time_nanos;
 use serde::{Deserialize, Serialize};
 
 /// Task stats for diagnostics.
 /// Returning this as the payload helps to debug the errors received at client side, and latencies.
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct TaskStats {
     /// Timestamp at which the task was received from network & parsed.
@@ -14,6 +15,12 @@ pub struct TaskStats {
     pub execution_started_at: u128,
     /// Timestamp at which the task execution had finished.
     pub execution_ended_at: u128,
+    /// Number of bytes of the task payload received from the network.
+    pub received_bytes: usize,
+    /// Number of bytes of the result, before it is encrypted & published.
+    ///
+    /// Traffic between the executor and the model provider is not included here.
+    pub result_bytes: usize,
 }
 
 impl TaskStats {
@@ -45,6 +52,18 @@ impl TaskStats {
         self.execution_ended_at = get_current_time_nanos();
         self
     }
+
+    /// Records the size of the received task payload within `received_bytes`.
+    pub fn record_received_bytes(mut self, num_bytes: usize) -> Self {
+        self.received_bytes = num_bytes;
+        self
+    }
+
+    /// Records the size of the task result within `result_bytes`.
+    pub fn record_result_bytes(mut self, num_bytes: usize) -> Self {
+        self.result_bytes = num_bytes;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -62,5 +81,10 @@ mod tests {
         assert_eq!(stats.published_at, 0);
         stats = stats.record_published_at();
         assert_ne!(stats.published_at, 0);
+
+        stats = stats.record_received_bytes(128).record_result_bytes(64);
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["receivedBytes"], 128);
+        assert_eq!(json["resultBytes"], 64);
     }
 }
@@ -0,0 +1,77 @@
+use std::{fmt, str::FromStr};
+
+/// Topics of the messages exchanged between the compute nodes & RPCs.
+///
+/// Gossipsub messages use the topic as their name, and signed messages within
+/// request-response carry it as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DriaTopic {
+    /// Heartbeat sent by the RPCs over gossipsub.
+    Ping,
+    /// Response of the compute nodes to a `Ping`, over gossipsub.
+    Pong,
+    /// Sent to the RPCs over request-response when the node is shutting down.
+    Goodbye,
+    /// Pushed by the RPCs over gossipsub when the available nodes change.
+    NodesUpdate,
+    /// Sent by the RPCs over request-response for tasks that compare two models.
+    Eval,
+}
+
+impl DriaTopic {
+    /// Topics that the compute node subscribes to within gossipsub.
+    pub const GOSSIP: [DriaTopic; 3] = [DriaTopic::Ping, DriaTopic::Pong, DriaTopic::NodesUpdate];
+
+    /// Returns the name of the topic.
+    #[inline]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            DriaTopic::Ping => "ping",
+            DriaTopic::Pong => "pong",
+            DriaTopic::Goodbye => "goodbye",
+            DriaTopic::NodesUpdate => "nodes",
+            DriaTopic::Eval => "eval",
+        }
+    }
+}
+
+impl FromStr for DriaTopic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ping" => Ok(DriaTopic::Ping),
+            "pong" => Ok(DriaTopic::Pong),
+            "goodbye" => Ok(DriaTopic::Goodbye),
+            "nodes" => Ok(DriaTopic::NodesUpdate),
+            "eval" => Ok(DriaTopic::Eval),
+            _ => Err(format!("unknown topic: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for DriaTopic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_names() {
+        for topic in [
+            DriaTopic::Ping,
+            DriaTopic::Pong,
+            DriaTopic::Goodbye,
+            DriaTopic::NodesUpdate,
+            DriaTopic::Eval,
+        ] {
+            assert_eq!(topic.as_str().parse::<DriaTopic>(), Ok(topic));
+            assert_eq!(topic.to_string(), topic.as_str());
+        }
+        assert!("heartbeat".parse::<DriaTopic>().is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A token bucket, allowing bursts of up to `capacity` events and `capacity` events per `period` on average.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Maximum number of tokens.
+    capacity: f64,
+    /// Number of tokens refilled per second.
+    refill_rate: f64,
+    /// Number of tokens left.
+    tokens: f64,
+    /// Time of the last refill.
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a full bucket that allows `capacity` events per `period`.
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        let capacity = f64::from(capacity.max(1));
+        Self {
+            capacity,
+            refill_rate: capacity / period.as_secs_f64().max(f64::EPSILON),
+            tokens: capacity,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a token for an event at `now`.
+    ///
+    /// If there are no tokens left, returns the duration after which one will be available.
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.tokens = self.tokens_at(now);
+        self.refilled_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_rate,
+            ))
+        }
+    }
+
+    /// Returns `true` if the bucket is full at `now`, i.e. it is as good as a new one.
+    pub fn is_full(&self, now: Instant) -> bool {
+        self.tokens_at(now) >= self.capacity
+    }
+
+    /// Returns the number of tokens that there are at `now`.
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        (self.tokens + elapsed.as_secs_f64() * self.refill_rate).min(self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        // a burst up to the capacity is allowed
+        assert!(limiter.try_acquire(now).is_ok());
+        assert!(limiter.try_acquire(now).is_ok());
+        let retry_after = limiter.try_acquire(now).unwrap_err();
+        assert_eq!(retry_after.as_secs_f64().round(), 30.0);
+
+        // a token is refilled every 30 seconds
+        assert!(limiter.try_acquire(now + Duration::from_secs(15)).is_err());
+        assert!(limiter.try_acquire(now + Duration::from_secs(31)).is_ok());
+        assert!(limiter.try_acquire(now + Duration::from_secs(32)).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use eyre::{Context, Result};
+
+/// Magic number at the start of each zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compresses the data with zstd at the given level, where `0` is the default level of zstd.
+pub fn compress(data: &[u8], level: i32) -> Result<Vec<u8>> {
+    zstd::encode_all(data, level).wrap_err("could not compress data")
+}
+
+/// Decompresses zstd-compressed data.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(data).wrap_err("could not decompress data")
+}
+
+/// Returns `true` if the data starts with a zstd frame.
+#[inline]
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&ZSTD_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression() {
+        let value = br#"{"result":"hello"}"#.repeat(100);
+        let compressed = compress(&value, 0).unwrap();
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < value.len());
+        assert_eq!(decompress(&compressed).unwrap(), value);
+
+        assert!(!is_compressed(&value));
+        assert!(decompress(&value).is_err());
+    }
+}
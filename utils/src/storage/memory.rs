@@ -0,0 +1,55 @@
+use eyre::{eyre, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+use super::{Storage, StorageIter};
+
+/// A storage that is kept in memory only, useful for tests or ephemeral nodes.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    namespaces: RwLock<HashMap<String, BTreeMap<String, Vec<u8>>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let namespaces = self.namespaces.read().map_err(|_| eyre!("poisoned lock"))?;
+        Ok(namespaces
+            .get(namespace)
+            .and_then(|entries| entries.get(key))
+            .cloned())
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        let mut namespaces = self
+            .namespaces
+            .write()
+            .map_err(|_| eyre!("poisoned lock"))?;
+        namespaces
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<bool> {
+        let mut namespaces = self
+            .namespaces
+            .write()
+            .map_err(|_| eyre!("poisoned lock"))?;
+        Ok(namespaces
+            .get_mut(namespace)
+            .and_then(|entries| entries.remove(key))
+            .is_some())
+    }
+
+    fn iter(&self, namespace: &str) -> StorageIter<'_> {
+        // the entries are copied so that the lock is not held during iteration
+        let entries = match self.namespaces.read() {
+            Ok(namespaces) => namespaces.get(namespace).cloned().unwrap_or_default(),
+            Err(_) => return Box::new(std::iter::once(Err(eyre!("poisoned lock")))),
+        };
+        Box::new(entries.into_iter().map(Ok))
+    }
+}
@@ -0,0 +1,81 @@
+use eyre::Result;
+use std::fmt::Debug;
+
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "compression")]
+pub use compression::{compress, decompress, is_compressed};
+
+mod memory;
+pub use memory::MemoryStorage;
+
+#[cfg(feature = "sled")]
+mod sled;
+#[cfg(feature = "sled")]
+pub use self::sled::SledStorage;
+
+/// Iterator over the key-value pairs of a namespace, ordered by key.
+pub type StorageIter<'a> = Box<dyn Iterator<Item = Result<(String, Vec<u8>)>> + 'a>;
+
+/// A key-value store, where keys are grouped under namespaces.
+///
+/// Different components of a node (or an embedder of it) can share the same storage
+/// by using their own namespaces, e.g. `archive` for the archived tasks.
+pub trait Storage: Debug + Send + Sync {
+    /// Returns the value of a key, if it exists.
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Sets the value of a key, overwriting the existing one.
+    fn put(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()>;
+
+    /// Removes a key, returns `true` if it existed.
+    fn remove(&self, namespace: &str, key: &str) -> Result<bool>;
+
+    /// Iterates over the key-value pairs within a namespace, ordered by key.
+    fn iter(&self, namespace: &str) -> StorageIter<'_>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the same checks for any storage, so that implementations behave the same.
+    fn check_storage(storage: &impl Storage) {
+        assert!(storage.get("a", "key").unwrap().is_none());
+
+        storage.put("a", "key-2", b"two".to_vec()).unwrap();
+        storage.put("a", "key-1", b"one".to_vec()).unwrap();
+        storage.put("b", "key-1", b"other".to_vec()).unwrap();
+        assert_eq!(storage.get("a", "key-1").unwrap(), Some(b"one".to_vec()));
+        assert_eq!(storage.get("b", "key-1").unwrap(), Some(b"other".to_vec()));
+
+        // namespaces are iterated separately, ordered by key
+        let entries = storage.iter("a").collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("key-1".to_string(), b"one".to_vec()),
+                ("key-2".to_string(), b"two".to_vec())
+            ]
+        );
+
+        assert!(storage.remove("a", "key-1").unwrap());
+        assert!(!storage.remove("a", "key-1").unwrap());
+        assert_eq!(storage.iter("a").count(), 1);
+        assert_eq!(storage.iter("c").count(), 0);
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&MemoryStorage::default());
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_storage() {
+        let dir =
+            std::env::temp_dir().join(format!("dkn-storage-{}", crate::get_current_time_nanos()));
+        check_storage(&SledStorage::open(&dir).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+use eyre::{Context, Result};
+use std::path::Path;
+
+use super::{Storage, StorageIter};
+
+/// A storage on disk backed by [`sled`](https://docs.rs/sled), where each namespace is a tree.
+///
+/// The database is locked by the process that opens it, so it can not be shared between processes.
+#[derive(Debug, Clone)]
+pub struct SledStorage {
+    db: sled::Db,
+}
+
+impl SledStorage {
+    /// Opens the database at the given directory, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(dir).wrap_err("could not open storage")?;
+        Ok(Self { db })
+    }
+}
+
+impl Storage for SledStorage {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let value = self.db.open_tree(namespace)?.get(key)?;
+        Ok(value.map(|v| v.to_vec()))
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        self.db.open_tree(namespace)?.insert(key, value)?;
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<bool> {
+        Ok(self.db.open_tree(namespace)?.remove(key)?.is_some())
+    }
+
+    fn iter(&self, namespace: &str) -> StorageIter<'_> {
+        let tree = match self.db.open_tree(namespace) {
+            Ok(tree) => tree,
+            Err(e) => return Box::new(std::iter::once(Err(e.into()))),
+        };
+        Box::new(tree.iter().map(|entry| {
+            let (key, value) = entry?;
+            Ok((String::from_utf8(key.to_vec())?, value.to_vec()))
+        }))
+    }
+}
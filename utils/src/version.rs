@@ -0,0 +1,60 @@
+use std::{fmt, str::FromStr};
+
+/// A `major.minor.patch` version, compared in that order.
+///
+/// A leading `v` and any pre-release or build suffix (e.g. `-rc.1`) are ignored when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SemanticVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FromStr for SemanticVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let core = s
+            .trim()
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default();
+
+        let parts = core
+            .split('.')
+            .map(|p| p.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid version {}: {}", s, e))?;
+        match parts[..] {
+            [major, minor, patch] => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(format!("invalid version {}: expected major.minor.patch", s)),
+        }
+    }
+}
+
+impl fmt::Display for SemanticVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semantic_version() {
+        let version = "v0.3.5-rc.1".parse::<SemanticVersion>().unwrap();
+        assert_eq!(version.to_string(), "0.3.5");
+
+        assert!("0.3.5".parse::<SemanticVersion>().unwrap() < "0.3.10".parse().unwrap());
+        assert!("0.4.0".parse::<SemanticVersion>().unwrap() > "0.3.10".parse().unwrap());
+        assert!("0.3".parse::<SemanticVersion>().is_err());
+        assert!("a.b.c".parse::<SemanticVersion>().is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use dkn_utils::payloads::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Payload test vectors, shared with other implementations.
+const PAYLOAD_VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/vectors/payloads.json");
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayloadVector {
+    /// What this vector is about.
+    description: String,
+    /// Type of the payload, e.g. `goodbye`.
+    kind: String,
+    /// The payload in its canonical JSON form.
+    value: Value,
+}
+
+impl PayloadVector {
+    fn new(description: &str, kind: &str, value: impl Serialize) -> Self {
+        Self {
+            description: description.to_string(),
+            kind: kind.to_string(),
+            value: serde_json::to_value(value).unwrap(),
+        }
+    }
+}
+
+/// Returns the test vectors as created by this implementation.
+fn create_payload_vectors() -> Vec<PayloadVector> {
+    let stats = TaskStats {
+        received_at: 1700000000000000000,
+        published_at: 1700000005000000000,
+        execution_started_at: 1700000001000000000,
+        execution_ended_at: 1700000004000000000,
+        received_bytes: 512,
+        result_bytes: 128,
+    };
+
+    vec![
+        PayloadVector::new(
+            "topic names",
+            "topics",
+            [
+                DriaTopic::Ping,
+                DriaTopic::Pong,
+                DriaTopic::Goodbye,
+                DriaTopic::NodesUpdate,
+                DriaTopic::Eval,
+            ]
+            .map(|t| t.as_str()),
+        ),
+        PayloadVector::new("task stats", "taskStats", &stats),
+        PayloadVector::new(
+            "task error",
+            "taskError",
+            TaskErrorPayload {
+                task_id: "task-1".to_string(),
+                error: "model not found".to_string(),
+                model: "gpt-4o".to_string(),
+                stats: stats.clone(),
+                retry_after_secs: None,
+                error_class: Some(TaskErrorClass::BadRequest),
+            },
+        ),
+        PayloadVector::new(
+            "task dump with a result",
+            "taskDump",
+            TaskDump {
+                task_id: "task-1".to_string(),
+                input: json!({ "workflow": {}, "model": ["gpt-4o"], "prompt": "hi" }),
+                model: "gpt-4o".to_string(),
+                result: Some("hello".to_string()),
+                error: None,
+                stats: Some(stats),
+            },
+        ),
+        PayloadVector::new(
+            "goodbye with pending tasks",
+            "goodbye",
+            GoodbyePayload {
+                reason: "shutdown".to_string(),
+                pending_tasks: vec![
+                    GoodbyeTask {
+                        task_id: "task-1".to_string(),
+                        model: "gpt-4o".to_string(),
+                        batchable: true,
+                    },
+                    GoodbyeTask {
+                        task_id: "task-2".to_string(),
+                        model: "llama3.1:latest".to_string(),
+                        batchable: false,
+                    },
+                ],
+            },
+        ),
+        PayloadVector::new(
+            "goodbye without pending tasks",
+            "goodbye",
+            GoodbyePayload {
+                reason: "update".to_string(),
+                pending_tasks: vec![],
+            },
+        ),
+        PayloadVector::new(
+            "nodes update replacing the RPCs",
+            "nodesUpdate",
+            NodesUpdatePayload {
+                bootstraps: vec![],
+                relays: vec![],
+                rpcs: vec!["16Uiu2HAmMUjGmiUhJeiZgu6ZZnLRkE2VViR2JgjqtW9aTZnHQqgg".to_string()],
+                rpc_addrs: vec!["/ip4/127.0.0.1/tcp/4001/p2p/16Uiu2HAmMUjGmiUhJeiZgu6ZZnLRkE2VViR2JgjqtW9aTZnHQqgg".to_string()],
+                replace: true,
+                deadline: 1700000060000000000,
+            },
+        ),
+    ]
+}
+
+/// Parses the value into the given type and serializes it back, which must be the same.
+fn assert_roundtrip<T: Serialize + DeserializeOwned>(vector: &PayloadVector) {
+    let parsed = serde_json::from_value::<T>(vector.value.clone())
+        .unwrap_or_else(|e| panic!("could not parse {}: {}", vector.description, e));
+    assert_eq!(
+        serde_json::to_value(parsed).unwrap(),
+        vector.value,
+        "{}",
+        vector.description
+    );
+}
+
+/// Checks that the payload vectors are parsed & serialized the same, and that they are up-to-date.
+///
+/// ## Run command
+///
+/// ```sh
+/// cargo test --package dkn-utils --test vectors_test
+/// ```
+#[test]
+fn test_payload_vectors() {
+    let vectors: Vec<PayloadVector> =
+        serde_json::from_str(&std::fs::read_to_string(PAYLOAD_VECTORS_PATH).unwrap()).unwrap();
+    assert_eq!(vectors, create_payload_vectors(), "vectors are outdated");
+
+    for vector in &vectors {
+        match vector.kind.as_str() {
+            "topics" => {
+                for topic in vector.value.as_array().unwrap() {
+                    let topic = topic.as_str().unwrap();
+                    assert_eq!(topic.parse::<DriaTopic>().unwrap().as_str(), topic);
+                }
+            }
+            "taskStats" => assert_roundtrip::<TaskStats>(vector),
+            "taskError" => assert_roundtrip::<TaskErrorPayload>(vector),
+            "taskDump" => assert_roundtrip::<TaskDump>(vector),
+            "goodbye" => assert_roundtrip::<GoodbyePayload>(vector),
+            "nodesUpdate" => assert_roundtrip::<NodesUpdatePayload>(vector),
+            other => panic!("unknown kind: {}", other),
+        }
+    }
+}
+
+/// Writes the payload vectors, to be run after the payloads are changed on purpose.
+///
+/// ## Run command
+///
+/// ```sh
+/// cargo test --package dkn-utils --test vectors_test -- generate_payload_vectors --ignored
+/// ```
+#[test]
+#[ignore = "run this manually"]
+fn generate_payload_vectors() {
+    let mut data = serde_json::to_string_pretty(&create_payload_vectors()).unwrap();
+    data.push('\n');
+    std::fs::write(PAYLOAD_VECTORS_PATH, data).unwrap();
+}
@@ -1,7 +1,7 @@
-use dkn_utils::safe_read_env;
 use eyre::{eyre, Context, Result};
 use reqwest::Client;
-use std::env;
+
+use crate::vault::read_secret;
 
 const ENV_VAR_NAME: &str = "SERPER_API_KEY";
 
@@ -13,10 +13,10 @@ pub struct SerperConfig {
 }
 
 impl SerperConfig {
-    /// Looks at the environment variables for Serper API key.
+    /// Looks at the keychain (if enabled) & environment variables for Serper API key.
     pub fn new() -> Self {
         Self {
-            api_key: safe_read_env(env::var(ENV_VAR_NAME)),
+            api_key: read_secret(ENV_VAR_NAME),
         }
     }
 
@@ -83,6 +83,7 @@ impl SerperConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
 
     #[tokio::test]
     #[ignore = "requires Serper API key"]
@@ -0,0 +1,50 @@
+//! Manages the API keys stored within the OS keychain.
+//!
+//! ```sh
+//! # store a key, the value is read from stdin so that it does not end up in the shell history
+//! cargo run --features keychain --bin keychain -- set OPENAI_API_KEY
+//!
+//! # check whether a key is available, from the keychain or the environment
+//! cargo run --features keychain --bin keychain -- check OPENAI_API_KEY
+//!
+//! # remove a key from the keychain
+//! cargo run --features keychain --bin keychain -- remove OPENAI_API_KEY
+//! ```
+
+use dkn_workflows::vault::{read_secret, remove_secret, store_secret};
+use eyre::{eyre, Result};
+use std::io::BufRead;
+
+const USAGE: &str = "usage: keychain <set | check | remove> <NAME>";
+
+fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["set", name] => {
+            let mut secret = String::new();
+            std::io::stdin().lock().read_line(&mut secret)?;
+            let secret = secret.trim();
+            if secret.is_empty() {
+                return Err(eyre!("no value was given for {}", name));
+            }
+            store_secret(name, secret)?;
+            println!("Stored {} in the keychain.", name);
+        }
+        ["check", name] => match read_secret(name) {
+            Some(_) => println!("{} is available.", name),
+            None => return Err(eyre!("{} is not available", name)),
+        },
+        ["remove", name] => {
+            remove_secret(name)?;
+            println!("Removed {} from the keychain.", name);
+        }
+        _ => return Err(eyre!(USAGE)),
+    }
+
+    Ok(())
+}
@@ -1,11 +1,18 @@
-use crate::{
-    apis::{JinaConfig, SerperConfig},
-    providers::{GeminiConfig, OllamaConfig, OpenAIConfig, OpenRouterConfig},
-    Model, ModelProvider,
-};
-use dkn_utils::split_csv_line;
+#[cfg(feature = "search")]
+use crate::apis::{JinaConfig, SerperConfig};
+#[cfg(feature = "gemini")]
+use crate::providers::GeminiConfig;
+#[cfg(feature = "ollama")]
+use crate::providers::OllamaConfig;
+#[cfg(feature = "openai")]
+use crate::providers::OpenAIConfig;
+#[cfg(feature = "openrouter")]
+use crate::providers::OpenRouterConfig;
+use crate::{is_provider_enabled, Model, ModelProvider, ModelSelection, ProviderRateLimiter};
+use dkn_utils::{safe_read_env, split_csv_line};
 use eyre::{eyre, Result};
 use rand::seq::IteratorRandom; // provides Vec<_>.choose
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
 
 #[derive(Debug, Clone)]
 pub struct DriaWorkflowsConfig {
@@ -13,22 +20,32 @@ pub struct DriaWorkflowsConfig {
     pub models: Vec<(ModelProvider, Model)>,
     /// Ollama configurations, in case Ollama is used.
     /// Otherwise, can be ignored.
+    #[cfg(feature = "ollama")]
     pub ollama: OllamaConfig,
     /// OpenAI configurations, e.g. API key, in case OpenAI is used.
     /// Otherwise, can be ignored.
+    #[cfg(feature = "openai")]
     pub openai: OpenAIConfig,
     /// Gemini configurations, e.g. API key, in case Gemini is used.
     /// Otherwise, can be ignored.
+    #[cfg(feature = "gemini")]
     pub gemini: GeminiConfig,
     /// OpenRouter configurations, e.g. API key, in case OpenRouter is used.
     /// Otherwise, can be ignored.
+    #[cfg(feature = "openrouter")]
     pub openrouter: OpenRouterConfig,
     /// Serper configurations, e.g. API key, in case Serper is given in environment.
     /// Otherwise, can be ignored.
+    #[cfg(feature = "search")]
     pub serper: SerperConfig,
     /// Jina configurations, e.g. API key, in case Jina is used.
     /// Otherwise, can be ignored.
+    #[cfg(feature = "search")]
     pub jina: JinaConfig,
+    /// How a model is chosen when several configured models match a task.
+    pub selection: ModelSelection,
+    /// Rate limiters of the providers, shared by the executors created from this config.
+    pub rate_limits: Vec<(ModelProvider, Arc<ProviderRateLimiter>)>,
 }
 
 impl Default for DriaWorkflowsConfig {
@@ -39,43 +56,113 @@ impl Default for DriaWorkflowsConfig {
 
 impl DriaWorkflowsConfig {
     /// Creates a new config with the given models.
+    ///
+    /// The model selection is read from `DKN_MODEL_SELECTION`, and is random by default;
+    /// the costs of the providers for the prefer-cheapest selection are read from `DKN_PROVIDER_COSTS`, see [`parse_provider_costs`].
+    /// The rate limits are read from `DKN_RATE_LIMITS`, see [`parse_rate_limits`].
     pub fn new(models: Vec<Model>) -> Self {
         let models_and_providers = models
             .into_iter()
             .map(|model| (model.clone().into(), model))
             .collect::<Vec<_>>();
 
+        let selection = safe_read_env(env::var("DKN_MODEL_SELECTION"))
+            .and_then(|s| {
+                s.parse::<ModelSelection>()
+                    .inspect_err(|e| log::warn!("{}, using random selection.", e))
+                    .ok()
+            })
+            .unwrap_or_default()
+            .with_provider_costs(parse_provider_costs(
+                &env::var("DKN_PROVIDER_COSTS").unwrap_or_default(),
+            ));
+        let rate_limits = parse_rate_limits(&env::var("DKN_RATE_LIMITS").unwrap_or_default());
+
         Self {
             models: models_and_providers,
+            #[cfg(feature = "ollama")]
             ollama: OllamaConfig::new(),
+            #[cfg(feature = "openai")]
             openai: OpenAIConfig::new(),
+            #[cfg(feature = "openrouter")]
             openrouter: OpenRouterConfig::new(),
+            #[cfg(feature = "gemini")]
             gemini: GeminiConfig::new(),
+            #[cfg(feature = "search")]
             serper: SerperConfig::new(),
+            #[cfg(feature = "search")]
             jina: JinaConfig::new(),
+            selection,
+            rate_limits,
         }
     }
 
     /// Sets the Ollama configuration for the Workflows config.
+    #[cfg(feature = "ollama")]
     pub fn with_ollama_config(mut self, ollama: OllamaConfig) -> Self {
         self.ollama = ollama;
         self
     }
 
+    /// Limits the timeout for pulling an Ollama model again right before a task,
+    /// e.g. to the time left until the deadline of the task.
+    #[cfg_attr(not(feature = "ollama"), allow(unused_mut, unused_variables))]
+    pub fn with_pull_timeout_limit(mut self, limit: Duration) -> Self {
+        #[cfg(feature = "ollama")]
+        {
+            self.ollama = self.ollama.with_pull_timeout_limit(limit);
+        }
+        self
+    }
+
     /// Sets the OpenAI configuration for the Workflows config.
+    #[cfg(feature = "openai")]
     pub fn with_openai_config(mut self, openai: OpenAIConfig) -> Self {
         self.openai = openai;
         self
     }
 
+    /// Sets how a model is chosen when several configured models match a task.
+    pub fn with_selection(mut self, selection: ModelSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Returns the rate limiter of a provider, if it has a limit.
+    pub fn get_rate_limiter(&self, provider: &ModelProvider) -> Option<Arc<ProviderRateLimiter>> {
+        self.rate_limits
+            .iter()
+            .find(|(p, _)| p == provider)
+            .map(|(_, limiter)| limiter.clone())
+    }
+
     /// Parses Ollama-Workflows compatible models from a comma-separated values string.
+    ///
+    /// Each value is a model selector, and can be one of:
+    ///
+    /// - a model name, e.g. `gpt-4o`
+    /// - a glob over model names with `*` and `?`, e.g. `gpt-4o*`
+    /// - a provider-scoped glob, e.g. `ollama:*q4*` or `openai:*`
+    ///
+    /// A selector prefixed with `!` excludes the matching models instead, e.g. `!gemini-1.5-pro`.
+    /// Exclusions are applied after all inclusions, regardless of their order.
     pub fn new_from_csv(input: &str) -> Self {
-        let models_str = split_csv_line(input);
-
-        let models = models_str
-            .into_iter()
-            .filter_map(|s| Model::try_from(s).ok())
-            .collect();
+        let selectors = split_csv_line(input);
+        let (exclusions, inclusions): (Vec<_>, Vec<_>) =
+            selectors.iter().partition(|s| s.starts_with('!'));
+
+        let mut models = Vec::new();
+        for selector in inclusions {
+            for model in select_models(selector) {
+                if !models.contains(&model) {
+                    models.push(model);
+                }
+            }
+        }
+        for selector in exclusions {
+            let excluded = select_models(&selector[1..]);
+            models.retain(|model| !excluded.contains(model));
+        }
 
         Self::new(models)
     }
@@ -152,7 +239,8 @@ impl DriaWorkflowsConfig {
         }
     }
 
-    /// From a list of model or provider names, return a random matching model & provider.
+    /// From a list of model or provider names, returns a matching model & provider
+    /// w.r.t the model selection of this config.
     pub fn get_any_matching_model(
         &self,
         list_model_or_provider: Vec<String>,
@@ -170,13 +258,29 @@ impl DriaWorkflowsConfig {
                     }
                 }
             })
-            .collect::<Vec<_>>();
+            .fold(Vec::new(), |mut unique, model| {
+                if !unique.contains(&model) {
+                    unique.push(model);
+                }
+                unique
+            });
+
+        let candidates = matching_models.len();
+        let chosen = self
+            .selection
+            .choose(matching_models)
+            .ok_or(eyre!("No matching models found."))?;
+        if candidates > 1 {
+            log::info!(
+                "Chose {} via {} out of {} matching models with {} selection.",
+                chosen.1,
+                chosen.0,
+                candidates,
+                self.selection
+            );
+        }
 
-        // choose random model
-        matching_models
-            .into_iter()
-            .choose(&mut rand::thread_rng())
-            .ok_or(eyre!("No matching models found."))
+        Ok(chosen)
     }
 
     /// Returns the list of unique providers in the config.
@@ -201,6 +305,50 @@ impl DriaWorkflowsConfig {
             .collect()
     }
 
+    /// Returns the tokens per second measured for the Ollama models during their checks, by model name.
+    pub fn measured_tps(&self) -> HashMap<String, f64> {
+        #[cfg(feature = "ollama")]
+        let tps = self.ollama.measured_tps().clone();
+        #[cfg(not(feature = "ollama"))]
+        let tps = HashMap::new();
+
+        tps
+    }
+
+    /// Updates the tokens per second measured for the given Ollama models, e.g. with the ones of a refreshed clone,
+    /// keeping the measurements of other models.
+    pub fn update_measured_tps(&mut self, tps: HashMap<String, f64>) {
+        #[cfg(feature = "ollama")]
+        self.ollama.update_measured_tps(tps);
+        #[cfg(not(feature = "ollama"))]
+        let _ = tps;
+    }
+
+    /// Removes the given models from the config so that they are not served, and returns them
+    /// along with their providers, e.g. to be added back later.
+    pub fn remove_models(&mut self, models: &[Model]) -> Vec<(ModelProvider, Model)> {
+        let (removed, kept) = std::mem::take(&mut self.models)
+            .into_iter()
+            .partition(|(_, model)| models.contains(model));
+        self.models = kept;
+        removed
+    }
+
+    /// Measures the tokens per second of the Ollama models again, see [`OllamaConfig::refresh_tps`].
+    ///
+    /// Returns the models that are now below the minimum TPS.
+    pub async fn refresh_tps(&mut self) -> Vec<Model> {
+        #[cfg(feature = "ollama")]
+        let slow_models = {
+            let models = self.get_models_for_provider(ModelProvider::Ollama);
+            self.ollama.refresh_tps(&models).await
+        };
+        #[cfg(not(feature = "ollama"))]
+        let slow_models = Vec::new();
+
+        slow_models
+    }
+
     /// Check if the required compute services are running.
     ///
     /// - If Ollama models are used, hardcoded models are checked locally, and for
@@ -209,21 +357,34 @@ impl DriaWorkflowsConfig {
     ///
     /// In the end, bad models are filtered out and we simply check if we are left if any valid models at all.
     /// If there are no models left in the end, an error is thrown.
+    ///
+    /// Models of the providers that are not compiled within this build are filtered out as well.
     pub async fn check_services(&mut self) -> Result<()> {
         log::info!("Checking configured services.");
 
-        // check Serper
-        self.serper.check_optional().await?;
+        #[cfg(feature = "search")]
+        {
+            // check Serper
+            self.serper.check_optional().await?;
 
-        // check Jina
-        self.jina.check_optional().await?;
+            // check Jina
+            self.jina.check_optional().await?;
+        }
 
         // TODO: can refactor (provider, model) logic here
         let unique_providers = self.get_providers();
+        for provider in unique_providers.iter().filter(|p| !is_provider_enabled(p)) {
+            log::warn!(
+                "Provider {} is not enabled within this build, ignoring its models.",
+                provider
+            );
+        }
 
+        #[allow(unused_mut)]
         let mut good_models = Vec::new();
 
         // if Ollama is a provider, check that it is running & Ollama models are pulled (or pull them)
+        #[cfg(feature = "ollama")]
         if unique_providers.contains(&ModelProvider::Ollama) {
             let provider_models = self.get_models_for_provider(ModelProvider::Ollama);
             good_models.extend(
@@ -236,6 +397,7 @@ impl DriaWorkflowsConfig {
         }
 
         // if OpenAI is a provider, check that the API key is set & models are available
+        #[cfg(feature = "openai")]
         if unique_providers.contains(&ModelProvider::OpenAI) {
             let provider_models = self.get_models_for_provider(ModelProvider::OpenAI);
             good_models.extend(
@@ -248,6 +410,7 @@ impl DriaWorkflowsConfig {
         }
 
         // if Gemini is a provider, check that the API key is set & models are available
+        #[cfg(feature = "gemini")]
         if unique_providers.contains(&ModelProvider::Gemini) {
             let provider_models = self.get_models_for_provider(ModelProvider::Gemini);
             good_models.extend(
@@ -260,6 +423,7 @@ impl DriaWorkflowsConfig {
         }
 
         // if OpenRouter is a provider, check that the API key is set
+        #[cfg(feature = "openrouter")]
         if unique_providers.contains(&ModelProvider::OpenRouter) {
             let provider_models = self.get_models_for_provider(ModelProvider::OpenRouter);
             good_models.extend(
@@ -281,6 +445,105 @@ impl DriaWorkflowsConfig {
     }
 }
 
+/// Parses the requests per minute of providers from comma-separated values such as `openai=500,gemini=15`.
+///
+/// Invalid values are ignored with a warning, providers without a value are not limited.
+fn parse_rate_limits(input: &str) -> Vec<(ModelProvider, Arc<ProviderRateLimiter>)> {
+    split_csv_line(input)
+        .into_iter()
+        .filter_map(|value| {
+            let parsed = value.split_once('=').and_then(|(provider, rpm)| {
+                let provider = ModelProvider::try_from(provider.trim().to_string()).ok()?;
+                let rpm = rpm.trim().parse::<u32>().ok().filter(|rpm| *rpm != 0)?;
+                Some((provider, Arc::new(ProviderRateLimiter::new(rpm))))
+            });
+            if parsed.is_none() {
+                log::warn!("Ignoring invalid rate limit: {}", value);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Parses the costs of providers from comma-separated values such as `openai=2.5,openrouter=0.6`,
+/// in any unit as long as it is the same for all providers, e.g. dollars per million tokens.
+///
+/// Invalid values are ignored with a warning.
+fn parse_provider_costs(input: &str) -> Vec<(ModelProvider, f64)> {
+    split_csv_line(input)
+        .into_iter()
+        .filter_map(|value| {
+            let parsed = value.split_once('=').and_then(|(provider, cost)| {
+                let provider = ModelProvider::try_from(provider.trim().to_string()).ok()?;
+                let cost = cost
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|cost| cost.is_finite() && *cost >= 0.0)?;
+                Some((provider, cost))
+            });
+            if parsed.is_none() {
+                log::warn!("Ignoring invalid provider cost: {}", value);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Returns the models that match the given selector, see [`DriaWorkflowsConfig::new_from_csv`].
+fn select_models(selector: &str) -> Vec<Model> {
+    // a provider prefix is only considered if it is a valid provider, as model names may contain `:` too
+    let (provider, pattern) = match selector.split_once(':') {
+        Some((provider, pattern)) => match ModelProvider::try_from(provider.to_string()) {
+            Ok(provider) => (Some(provider), pattern),
+            Err(_) => (None, selector),
+        },
+        None => (None, selector),
+    };
+
+    // exact model names are matched directly
+    if provider.is_none() && !pattern.contains(['*', '?']) {
+        return Model::try_from(pattern.to_string()).into_iter().collect();
+    }
+
+    enum_iterator::all::<Model>()
+        .filter(|model| {
+            provider
+                .as_ref()
+                .is_none_or(|p| *p == ModelProvider::from(model.clone()))
+        })
+        .filter(|model| glob_match(pattern, &model.to_string()))
+        .collect()
+}
+
+/// Matches the text against a glob pattern, where `*` matches any sequence and `?` matches a single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // position of the last `*` in pattern, and the text position it was matched at
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // backtrack, let the last `*` consume one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 impl std::fmt::Display for DriaWorkflowsConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let models_str = self
@@ -308,6 +571,70 @@ mod tests {
         assert_eq!(cfg.models.len(), 2);
     }
 
+    #[test]
+    fn test_rate_limits() {
+        let rate_limits = parse_rate_limits("openai=500, gemini = 15,ollama,openrouter=0,foo=1");
+        assert_eq!(rate_limits.len(), 2);
+        assert_eq!(rate_limits[0].0, ModelProvider::OpenAI);
+        assert_eq!(rate_limits[0].1.rpm(), 500);
+        assert_eq!(rate_limits[1].0, ModelProvider::Gemini);
+        assert_eq!(rate_limits[1].1.rpm(), 15);
+    }
+
+    #[test]
+    fn test_provider_costs() {
+        let costs = parse_provider_costs("openai=2.5, openrouter = 0.6,gemini=-1,ollama,foo=1");
+        assert_eq!(
+            costs,
+            vec![
+                (ModelProvider::OpenAI, 2.5),
+                (ModelProvider::OpenRouter, 0.6)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_selectors() {
+        // provider-scoped glob
+        let cfg = DriaWorkflowsConfig::new_from_csv("openai:gpt-4o*");
+        assert_eq!(
+            cfg.get_model_names(),
+            vec![Model::GPT4o.to_string(), Model::GPT4oMini.to_string()]
+        );
+
+        // provider wildcard with an exclusion, in any order
+        let cfg = DriaWorkflowsConfig::new_from_csv("!gemini-1.5-pro,gemini:*");
+        assert!(!cfg.models.is_empty());
+        assert!(cfg.models.iter().all(|(p, _)| *p == ModelProvider::Gemini));
+        assert!(!cfg.models.iter().any(|(_, m)| *m == Model::Gemini15Pro));
+
+        // glob on model names that contain `:`, duplicates are ignored
+        let cfg = DriaWorkflowsConfig::new_from_csv("llama3.1:*,llama3.1:latest,!*fp16");
+        assert!(cfg.models.iter().any(|(_, m)| *m == Model::Llama3_1_8B));
+        assert!(!cfg.models.iter().any(|(_, m)| *m == Model::Llama3_1_8Bf16));
+        assert_eq!(
+            cfg.models
+                .iter()
+                .filter(|(_, m)| *m == Model::Llama3_1_8B)
+                .count(),
+            1
+        );
+
+        // unknown provider prefix is treated as a model name
+        let cfg = DriaWorkflowsConfig::new_from_csv("foo:*");
+        assert!(cfg.models.is_empty());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("gpt-4o*", "gpt-4o-mini"));
+        assert!(glob_match("*q4*", "llama3.1:8b-text-q4_K_M"));
+        assert!(glob_match("phi3.?:*", "phi3.5:3.8b"));
+        assert!(!glob_match("gpt-4o", "gpt-4o-mini"));
+        assert!(!glob_match("*q8", "qwen2.5:7b-instruct-q8_0"));
+    }
+
     #[test]
     fn test_model_matching() {
         let cfg = DriaWorkflowsConfig::new_from_csv("gpt-4o,llama3.1:latest");
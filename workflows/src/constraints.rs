@@ -0,0 +1,280 @@
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{executor::Executor, ExecutionError, Workflow};
+
+/// Minimum ratio of the letters of an output that must be in the script of the expected language.
+const MIN_SCRIPT_RATIO: f64 = 0.8;
+
+/// Constraints on the output of a task, checked locally after the generation.
+///
+/// If the output violates any of them, the model is asked once to correct it;
+/// the task fails if the corrected output still violates them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskConstraints {
+    /// ISO 639-1 code of the language that the output must be written in, e.g. `en` or `ru`.
+    ///
+    /// The language is checked by its script, so languages that share a script (e.g. `en` & `es`) are not told apart.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Maximum length of the output in characters.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// Substrings that must not appear within the output, case-insensitive.
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+}
+
+/// A constraint that an output does not satisfy, displayed as an instruction to fix it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    /// The output is not written in the expected language.
+    Language(String),
+    /// The output is longer than the maximum length.
+    Length { max: usize, actual: usize },
+    /// The output contains a forbidden substring.
+    Forbidden(String),
+}
+
+impl std::fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Language(language) => write!(f, "must be written in language `{}`", language),
+            Self::Length { max, actual } => write!(
+                f,
+                "must be at most {} characters long, but is {} characters",
+                max, actual
+            ),
+            Self::Forbidden(substring) => write!(f, "must not contain \"{}\"", substring),
+        }
+    }
+}
+
+impl TaskConstraints {
+    /// Returns the constraints that the output violates, empty if it satisfies all of them.
+    pub fn violations(&self, output: &str) -> Vec<ConstraintViolation> {
+        let mut violations = Vec::new();
+
+        if let Some(language) = &self.language {
+            match Script::of_language(language) {
+                Some(script) if !script.is_written_in(output) => {
+                    violations.push(ConstraintViolation::Language(language.clone()));
+                }
+                Some(_) => {}
+                None => log::warn!("Language {} can not be checked, ignoring it.", language),
+            }
+        }
+
+        if let Some(max) = self.max_length {
+            let actual = output.chars().count();
+            if actual > max {
+                violations.push(ConstraintViolation::Length { max, actual });
+            }
+        }
+
+        let lowercase = output.to_lowercase();
+        violations.extend(
+            self.forbidden
+                .iter()
+                .filter(|s| !s.is_empty() && lowercase.contains(&s.to_lowercase()))
+                .map(|s| ConstraintViolation::Forbidden(s.clone())),
+        );
+
+        violations
+    }
+
+    /// Returns the output if it satisfies the constraints, otherwise re-prompts the executor
+    /// once to correct it and returns the corrected output if that one satisfies them.
+    pub async fn enforce(
+        &self,
+        executor: &dyn Executor,
+        output: String,
+    ) -> Result<String, ExecutionError> {
+        let violations = self.violations(&output);
+        if violations.is_empty() {
+            return Ok(output);
+        }
+
+        log::info!(
+            "Output violates {} constraint(s), asking for a correction.",
+            violations.len()
+        );
+        let workflow = correction_workflow(&output, &violations)
+            .map_err(|e| ExecutionError::WorkflowFailed(format!("{:#}", e)))?;
+        let mut memory = Default::default();
+        let corrected = executor.execute(None, &workflow, &mut memory).await?;
+
+        let violations = self.violations(&corrected);
+        if violations.is_empty() {
+            Ok(corrected)
+        } else {
+            Err(ExecutionError::GenerationFailed(format!(
+                "output violates the task constraints: it {}",
+                violations
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", it ")
+            )))
+        }
+    }
+}
+
+/// Creates a single-step workflow that asks for the output to be rewritten without the violations.
+fn correction_workflow(output: &str, violations: &[ConstraintViolation]) -> Result<Workflow> {
+    let rules = violations
+        .iter()
+        .map(|v| format!("- The response {}.", v))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "Rewrite the response below so that it follows these rules, keeping its meaning otherwise.\n\
+        Reply only with the rewritten response.\n\n\
+        ### Rules\n{}\n\n### Response\n{}",
+        rules, output
+    );
+
+    serde_json::from_value(json!({
+        "config": {
+            "max_steps": 10,
+            "max_time": 250,
+            "tools": [""]
+        },
+        "tasks": [
+            {
+                "id": "A",
+                "name": "correction",
+                "description": "Correct the response",
+                "operator": "generation",
+                "messages": [{ "role": "user", "content": prompt }],
+                "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+            },
+            {
+                "id": "__end",
+                "name": "end",
+                "description": "End of the task",
+                "operator": "end",
+                "messages": [{ "role": "user", "content": "End of the task" }]
+            }
+        ],
+        "steps": [{ "source": "A", "target": "__end" }],
+        "return_value": { "input": { "type": "read", "key": "result" } }
+    }))
+    .wrap_err("could not create correction workflow")
+}
+
+/// Writing system of a language, used to check the language of an output without a language model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    /// Chinese characters along with the Japanese kana.
+    Cjk,
+}
+
+impl Script {
+    /// Returns the script of the language with the given ISO 639-1 code, if it is known.
+    fn of_language(code: &str) -> Option<Self> {
+        match code.to_lowercase().as_str() {
+            "en" | "es" | "fr" | "de" | "it" | "pt" | "nl" | "tr" | "pl" | "cs" | "ro" | "sv"
+            | "da" | "no" | "fi" | "hu" | "id" | "ms" | "vi" | "tl" | "sw" => Some(Self::Latin),
+            "ru" | "uk" | "bg" | "sr" | "be" | "kk" | "mk" => Some(Self::Cyrillic),
+            "el" => Some(Self::Greek),
+            "he" => Some(Self::Hebrew),
+            "ar" | "fa" | "ur" => Some(Self::Arabic),
+            "hi" | "mr" | "ne" => Some(Self::Devanagari),
+            "th" => Some(Self::Thai),
+            "ko" => Some(Self::Hangul),
+            "zh" | "ja" => Some(Self::Cjk),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the character belongs to this script.
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Latin => {
+                c.is_ascii_alphabetic()
+                    || matches!(c, '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}')
+            }
+            Self::Cyrillic => matches!(c, '\u{0400}'..='\u{04FF}'),
+            Self::Greek => matches!(c, '\u{0370}'..='\u{03FF}'),
+            Self::Hebrew => matches!(c, '\u{0590}'..='\u{05FF}'),
+            Self::Arabic => matches!(c, '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}'),
+            Self::Devanagari => matches!(c, '\u{0900}'..='\u{097F}'),
+            Self::Thai => matches!(c, '\u{0E00}'..='\u{0E7F}'),
+            Self::Hangul => matches!(
+                c,
+                '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}'
+            ),
+            Self::Cjk => matches!(
+                c,
+                '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{3040}'..='\u{30FF}'
+            ),
+        }
+    }
+
+    /// Returns `true` if most of the letters of the text are in this script, or if it has no letters.
+    fn is_written_in(&self, text: &str) -> bool {
+        let (letters, matching) = text
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .fold((0usize, 0usize), |(letters, matching), c| {
+                (letters + 1, matching + self.contains(c) as usize)
+            });
+
+        letters == 0 || matching as f64 >= letters as f64 * MIN_SCRIPT_RATIO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraint_violations() {
+        let constraints = TaskConstraints {
+            language: Some("ru".to_string()),
+            max_length: Some(20),
+            forbidden: vec!["As an AI".to_string()],
+        };
+
+        assert!(constraints.violations("Привет, мир!").is_empty());
+        assert_eq!(
+            constraints.violations("as an ai, hello world"),
+            vec![
+                ConstraintViolation::Language("ru".to_string()),
+                ConstraintViolation::Length {
+                    max: 20,
+                    actual: 21
+                },
+                ConstraintViolation::Forbidden("As an AI".to_string()),
+            ]
+        );
+
+        // unknown languages are not checked
+        let constraints = TaskConstraints {
+            language: Some("xx".to_string()),
+            ..Default::default()
+        };
+        assert!(constraints.violations("anything").is_empty());
+
+        let constraints: TaskConstraints =
+            serde_json::from_value(json!({ "language": "ja", "max_length": 100 })).unwrap();
+        assert!(constraints.violations("こんにちは世界").is_empty());
+        assert!(!constraints.violations("hello world").is_empty());
+    }
+
+    #[test]
+    fn test_correction_workflow() {
+        let violations = [ConstraintViolation::Forbidden("foo".to_string())];
+        assert!(correction_workflow("foo bar", &violations).is_ok());
+    }
+}
@@ -0,0 +1,238 @@
+use async_trait::async_trait;
+use dkn_utils::payloads::TaskErrorClass;
+use eyre::Result;
+use ollama_workflows::{Entry, ExecutionError, Model, ModelProvider, ProgramMemory, Workflow};
+use std::sync::Arc;
+
+use crate::{DriaWorkflowsConfig, ProviderRateLimiter, MODEL_NOT_PULLED};
+
+/// What an executor is able to do, used by the node to route tasks to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutorCapabilities {
+    /// Names of the models served by this executor.
+    pub models: Vec<String>,
+    /// Whether tasks can be executed in parallel, e.g. API calls, or must be run in series, e.g. local models.
+    pub batchable: bool,
+}
+
+/// An executor for workflow tasks.
+///
+/// The node only talks to executors through this trait, and creates them with an [`ExecutorFactory`],
+/// so external crates can plug in their own providers by implementing it, without any changes within this crate.
+#[async_trait]
+pub trait Executor: Send + Sync {
+    /// Executes the workflow with an optional entry.
+    async fn execute(
+        &self,
+        entry: Option<&Entry>,
+        workflow: &Workflow,
+        memory: &mut ProgramMemory,
+    ) -> Result<String, ExecutionError>;
+
+    /// Checks that the executor is ready to execute tasks, e.g. the service is up and the API key is valid.
+    async fn check(&self) -> Result<()>;
+
+    /// Returns the capabilities of this executor.
+    fn capabilities(&self) -> ExecutorCapabilities;
+
+    /// Classifies an execution error of this executor, so that it is handled the same for all providers.
+    fn classify_error(&self, error: &ExecutionError) -> TaskErrorClass {
+        classify_execution_error(error)
+    }
+}
+
+/// Classifies an execution error from its kind & the messages of the built-in providers.
+pub fn classify_execution_error(error: &ExecutionError) -> TaskErrorClass {
+    if matches!(
+        error,
+        ExecutionError::InvalidInput
+            | ExecutionError::StringCheckFailed
+            | ExecutionError::InvalidGetAllError
+    ) {
+        return TaskErrorClass::BadRequest;
+    }
+
+    let message = error.to_string().to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+    if message.contains(MODEL_NOT_PULLED) {
+        TaskErrorClass::ModelUnavailable
+    } else if contains_any(&["rate limit", "too many requests", "quota"]) {
+        TaskErrorClass::RateLimited
+    } else if contains_any(&[
+        "api key",
+        "api_key",
+        "unauthorized",
+        "forbidden",
+        "authentication",
+    ]) {
+        TaskErrorClass::Auth
+    } else if contains_any(&["out of memory", "more system memory", "insufficient memory"]) {
+        TaskErrorClass::OutOfMemory
+    } else if contains_any(&[
+        "bad request",
+        "invalid_request",
+        "context length",
+        "context window",
+    ]) {
+        TaskErrorClass::BadRequest
+    } else {
+        TaskErrorClass::Transient
+    }
+}
+
+/// Creates the executor of a model, with the service configurations within the given config.
+pub type ExecutorFactory =
+    Arc<dyn Fn(&DriaWorkflowsConfig, ModelProvider, Model) -> Box<dyn Executor> + Send + Sync>;
+
+/// An executor backed by [Ollama Workflows](https://github.com/andthattoo/ollama-workflows),
+/// for the built-in model providers.
+#[derive(Debug, Clone)]
+pub struct WorkflowExecutor {
+    /// The underlying Ollama Workflows executor.
+    executor: ollama_workflows::Executor,
+    /// Workflows config with only the model of this executor, used for checks.
+    config: DriaWorkflowsConfig,
+    /// Rate limiter of the provider, if it has a limit.
+    limiter: Option<Arc<ProviderRateLimiter>>,
+}
+
+impl WorkflowExecutor {
+    /// Creates an executor for the given model, using the service configurations within the given config.
+    pub fn new(config: &DriaWorkflowsConfig, provider: ModelProvider, model: Model) -> Self {
+        #[cfg(feature = "ollama")]
+        let executor = if provider == ModelProvider::Ollama {
+            ollama_workflows::Executor::new_at(
+                model.clone(),
+                &config.ollama.host,
+                config.ollama.port,
+            )
+        } else {
+            ollama_workflows::Executor::new(model.clone())
+        };
+        #[cfg(not(feature = "ollama"))]
+        let executor = ollama_workflows::Executor::new(model.clone());
+
+        let limiter = config.get_rate_limiter(&provider);
+        let mut config = config.clone();
+        config.models = vec![(provider, model)];
+
+        Self {
+            executor,
+            config,
+            limiter,
+        }
+    }
+
+    /// Returns a factory that creates a [`WorkflowExecutor`] for each model.
+    pub fn factory() -> ExecutorFactory {
+        Arc::new(|config, provider, model| Box::new(Self::new(config, provider, model)))
+    }
+}
+
+#[async_trait]
+impl Executor for WorkflowExecutor {
+    async fn execute(
+        &self,
+        entry: Option<&Entry>,
+        workflow: &Workflow,
+        memory: &mut ProgramMemory,
+    ) -> Result<String, ExecutionError> {
+        // a local model may be gone since the service checks, which is caught here instead of within the generation
+        #[cfg(feature = "ollama")]
+        for (_, model) in self
+            .config
+            .models
+            .iter()
+            .filter(|(p, _)| *p == ModelProvider::Ollama)
+        {
+            self.config
+                .ollama
+                .ensure_model(model)
+                .await
+                .map_err(|e| ExecutionError::GenerationFailed(format!("{:#}", e)))?;
+        }
+
+        // each execution counts as a single request, as most workflows make one generation
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+        self.executor.execute(entry, workflow, memory).await
+    }
+
+    async fn check(&self) -> Result<()> {
+        self.config.clone().check_services().await
+    }
+
+    fn capabilities(&self) -> ExecutorCapabilities {
+        ExecutorCapabilities {
+            models: self.config.get_model_names(),
+            // Ollama models run on local resources and are not batchable, while API-based ones are
+            batchable: self.config.has_batchable_models(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workflow_executor_capabilities() {
+        let config = DriaWorkflowsConfig::default();
+
+        let executor = WorkflowExecutor::new(&config, ModelProvider::OpenAI, Model::GPT4o);
+        assert_eq!(
+            executor.capabilities(),
+            ExecutorCapabilities {
+                models: vec![Model::GPT4o.to_string()],
+                batchable: true
+            }
+        );
+
+        let executor: Box<dyn Executor> = Box::new(WorkflowExecutor::new(
+            &config,
+            ModelProvider::Ollama,
+            Model::Llama3_1_8B,
+        ));
+        assert!(!executor.capabilities().batchable);
+    }
+
+    #[test]
+    fn test_classify_execution_error() {
+        let cases = [
+            (
+                ExecutionError::GenerationFailed("429 Too Many Requests".into()),
+                TaskErrorClass::RateLimited,
+            ),
+            (
+                ExecutionError::GenerationFailed("Incorrect API key provided".into()),
+                TaskErrorClass::Auth,
+            ),
+            (
+                ExecutionError::GenerationFailed(
+                    "model requires more system memory than is available".into(),
+                ),
+                TaskErrorClass::OutOfMemory,
+            ),
+            (
+                ExecutionError::GenerationFailed("maximum context length is 8192 tokens".into()),
+                TaskErrorClass::BadRequest,
+            ),
+            (ExecutionError::InvalidInput, TaskErrorClass::BadRequest),
+            (
+                ExecutionError::GenerationFailed(format!(
+                    "{}: llama3.1:latest is not pulled in Ollama",
+                    MODEL_NOT_PULLED
+                )),
+                TaskErrorClass::ModelUnavailable,
+            ),
+            (
+                ExecutionError::WorkflowFailed("connection reset".into()),
+                TaskErrorClass::Transient,
+            ),
+        ];
+        for (error, class) in cases {
+            assert_eq!(classify_execution_error(&error), class, "{}", error);
+        }
+    }
+}
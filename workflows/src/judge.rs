@@ -0,0 +1,163 @@
+use eyre::{eyre, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{executor::Executor, Workflow};
+
+/// Highest score that can be given by a judge.
+pub const MAX_JUDGE_SCORE: u8 = 10;
+
+/// Maximum number of characters of the instruction & the result given to the judge,
+/// so that a long result does not cost more than the task itself.
+const MAX_JUDGE_INPUT_CHARS: usize = 8000;
+
+/// Score given by a judge model to the result of a task, w.r.t its instruction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Judgement {
+    /// Name of the judge model.
+    pub model: String,
+    /// Score from 0 to 10, higher is better.
+    pub score: u8,
+    /// Short explanation of the score, as given by the judge.
+    pub rationale: String,
+}
+
+/// A model that scores the results of tasks against their instructions,
+/// preferably a small one as it is run after each task.
+pub struct Judge {
+    /// Name of the judge model.
+    model: String,
+    /// Executor of the judge model.
+    executor: Box<dyn Executor>,
+}
+
+impl Judge {
+    /// Creates a judge that uses the given executor for the model.
+    pub fn new(model: impl ToString, executor: Box<dyn Executor>) -> Self {
+        Self {
+            model: model.to_string(),
+            executor,
+        }
+    }
+
+    /// Returns the name of the judge model.
+    #[inline]
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Asks the judge to score the result against the instruction.
+    pub async fn judge(&self, instruction: &str, result: &str) -> Result<Judgement> {
+        let workflow = judge_workflow(instruction, result)?;
+        let mut memory = Default::default();
+        let output = self
+            .executor
+            .execute(None, &workflow, &mut memory)
+            .await
+            .map_err(|e| eyre!("could not execute judge: {}", e))?;
+        let (score, rationale) = parse_judgement(&output)?;
+
+        Ok(Judgement {
+            model: self.model.clone(),
+            score,
+            rationale,
+        })
+    }
+}
+
+/// Creates a single-step workflow that asks for a score & rationale in JSON.
+fn judge_workflow(instruction: &str, result: &str) -> Result<Workflow> {
+    let truncate = |s: &str| s.chars().take(MAX_JUDGE_INPUT_CHARS).collect::<String>();
+    let prompt = format!(
+        "You are a strict judge. Score how well the response follows the instruction, \
+        from 0 (unrelated or wrong) to {} (complete and correct).\n\
+        Reply only with a JSON object such as {{\"score\": 7, \"rationale\": \"a single sentence\"}}.\n\n\
+        ### Instruction\n{}\n\n### Response\n{}",
+        MAX_JUDGE_SCORE,
+        truncate(instruction),
+        truncate(result)
+    );
+
+    serde_json::from_value(json!({
+        "config": {
+            "max_steps": 10,
+            "max_time": 250,
+            "tools": [""]
+        },
+        "tasks": [
+            {
+                "id": "A",
+                "name": "judge",
+                "description": "Score the response",
+                "operator": "generation",
+                "messages": [{ "role": "user", "content": prompt }],
+                "outputs": [{ "type": "write", "key": "result", "value": "__result" }]
+            },
+            {
+                "id": "__end",
+                "name": "end",
+                "description": "End of the task",
+                "operator": "end",
+                "messages": [{ "role": "user", "content": "End of the task" }]
+            }
+        ],
+        "steps": [{ "source": "A", "target": "__end" }],
+        "return_value": { "input": { "type": "read", "key": "result" } }
+    }))
+    .wrap_err("could not create judge workflow")
+}
+
+/// Parses the score & rationale from the output of the judge.
+///
+/// The JSON object is looked for within the output, as models tend to wrap it in text or code blocks.
+fn parse_judgement(output: &str) -> Result<(u8, String)> {
+    #[derive(Deserialize)]
+    struct RawJudgement {
+        score: f64,
+        #[serde(default)]
+        rationale: String,
+    }
+
+    let (start, end) = output
+        .find('{')
+        .zip(output.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .ok_or_else(|| eyre!("no JSON object in judge output"))?;
+    let raw: RawJudgement =
+        serde_json::from_str(&output[start..=end]).wrap_err("could not parse judge output")?;
+    if !(0.0..=MAX_JUDGE_SCORE as f64).contains(&raw.score) {
+        return Err(eyre!("judge score {} is out of range", raw.score));
+    }
+
+    Ok((raw.score.round() as u8, raw.rationale.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_judgement() {
+        let output =
+            "Here you go:\n```json\n{\"score\": 7, \"rationale\": \" Mostly correct. \"}\n```";
+        assert_eq!(
+            parse_judgement(output).unwrap(),
+            (7, "Mostly correct.".to_string())
+        );
+        assert_eq!(
+            parse_judgement("{\"score\": 8.6}").unwrap(),
+            (9, String::new())
+        );
+
+        assert!(parse_judgement("{\"score\": 11, \"rationale\": \"\"}").is_err());
+        assert!(parse_judgement("{\"score\": \"high\"}").is_err());
+        assert!(parse_judgement("score: 5").is_err());
+        assert!(parse_judgement("} {").is_err());
+    }
+
+    #[test]
+    fn test_judge_workflow() {
+        assert!(judge_workflow("Write a poem.", &"a".repeat(2 * MAX_JUDGE_INPUT_CHARS)).is_ok());
+    }
+}
@@ -1,10 +1,35 @@
 mod providers;
+#[cfg(feature = "ollama")]
 pub use providers::OllamaConfig;
+pub use providers::{is_provider_enabled, MODEL_NOT_PULLED};
 
+#[cfg(feature = "search")]
 mod apis;
+/// Whether the search tools (Jina & Serper) are compiled within this build.
+pub const SEARCH_ENABLED: bool = cfg!(feature = "search");
 
 mod config;
 pub use config::DriaWorkflowsConfig;
 
+mod constraints;
+pub use constraints::{ConstraintViolation, TaskConstraints};
+
+mod judge;
+pub use judge::{Judge, Judgement, MAX_JUDGE_SCORE};
+
+mod limiter;
+pub use limiter::ProviderRateLimiter;
+
+mod selection;
+pub use selection::ModelSelection;
+
+pub mod vault;
+
+pub mod executor;
+// the trait is re-exported under another name, as `Executor` is the Ollama Workflows executor at the root
+pub use executor::{
+    Executor as TaskExecutor, ExecutorCapabilities, ExecutorFactory, WorkflowExecutor,
+};
+
 // re-export Ollama Workflows
 pub use ollama_workflows::*;
@@ -0,0 +1,65 @@
+use dkn_utils::RateLimiter;
+use std::{sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+/// A token bucket that paces the requests to a provider, shared by all executors of that provider.
+///
+/// The bucket holds up to a minute worth of requests, and is refilled continuously.
+#[derive(Debug)]
+pub struct ProviderRateLimiter {
+    /// Maximum number of requests per minute.
+    rpm: u32,
+    /// The bucket of the provider.
+    bucket: Mutex<RateLimiter>,
+}
+
+impl ProviderRateLimiter {
+    /// Creates a full bucket for the given requests per minute, which must be non-zero.
+    pub fn new(rpm: u32) -> Self {
+        assert!(rpm != 0, "rate limit must be non-zero");
+        Self {
+            rpm,
+            bucket: Mutex::new(RateLimiter::new(rpm, Duration::from_secs(60))),
+        }
+    }
+
+    /// Returns the maximum number of requests per minute.
+    #[inline]
+    pub fn rpm(&self) -> u32 {
+        self.rpm
+    }
+
+    /// Takes a token without waiting, returns the time to wait for the next token if there is none.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.try_acquire(Instant::now())
+    }
+
+    /// Waits until a token is available and takes it.
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_provider_rate_limiter() {
+        // one token per 10 milliseconds, bucket of 6000
+        let limiter = ProviderRateLimiter::new(6000);
+        for _ in 0..6000 {
+            assert!(limiter.try_acquire().is_ok());
+        }
+        let wait = limiter.try_acquire().unwrap_err();
+        assert!(wait <= Duration::from_millis(10));
+
+        let started_at = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(started_at.elapsed() >= Duration::from_millis(10));
+    }
+}
@@ -1,11 +1,77 @@
+use ollama_workflows::ModelProvider;
+
+/// Error code of a task whose model is not pulled in Ollama.
+pub const MODEL_NOT_PULLED: &str = "model_not_pulled";
+
+#[cfg(feature = "ollama")]
 mod ollama;
+#[cfg(feature = "ollama")]
 pub use ollama::OllamaConfig;
 
+#[cfg(feature = "openai")]
 mod openai;
+#[cfg(feature = "openai")]
 pub use openai::OpenAIConfig;
 
+#[cfg(feature = "gemini")]
 mod gemini;
+#[cfg(feature = "gemini")]
 pub use gemini::GeminiConfig;
 
+#[cfg(feature = "openrouter")]
 mod openrouter;
+#[cfg(feature = "openrouter")]
 pub use openrouter::OpenRouterConfig;
+
+/// Returns `true` if the provider is compiled within this build, w.r.t the crate features.
+///
+/// Models of a disabled provider can still be parsed, but they never pass the service checks.
+pub fn is_provider_enabled(provider: &ModelProvider) -> bool {
+    [
+        (ModelProvider::Ollama, cfg!(feature = "ollama")),
+        (ModelProvider::OpenAI, cfg!(feature = "openai")),
+        (ModelProvider::Gemini, cfg!(feature = "gemini")),
+        (ModelProvider::OpenRouter, cfg!(feature = "openrouter")),
+    ]
+    .iter()
+    .any(|(p, enabled)| *enabled && p == provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_providers() {
+        let enabled = enum_iterator::all::<ollama_workflows::Model>()
+            .map(ModelProvider::from)
+            .filter(is_provider_enabled)
+            .fold(Vec::new(), |mut unique, provider| {
+                if !unique.contains(&provider) {
+                    unique.push(provider);
+                }
+                unique
+            });
+
+        if cfg!(feature = "full") {
+            assert!(enabled.contains(&ModelProvider::Ollama));
+            assert!(enabled.contains(&ModelProvider::OpenAI));
+            assert!(enabled.contains(&ModelProvider::Gemini));
+            assert!(enabled.contains(&ModelProvider::OpenRouter));
+        }
+
+        // a minimal build has a single provider, which is not Ollama, and no search tools
+        if cfg!(all(
+            feature = "minimal",
+            not(any(
+                feature = "full",
+                feature = "ollama",
+                feature = "gemini",
+                feature = "openrouter",
+                feature = "search"
+            ))
+        )) {
+            assert_eq!(enabled, vec![ModelProvider::OpenAI]);
+        }
+    }
+}
@@ -9,9 +9,12 @@ use ollama_workflows::{
     },
     Model,
 };
+use std::collections::HashMap;
 use std::env;
 use std::time::Duration;
 
+use super::MODEL_NOT_PULLED;
+
 const DEFAULT_OLLAMA_HOST: &str = "http://127.0.0.1";
 const DEFAULT_OLLAMA_PORT: u16 = 11434;
 /// Automatically pull missing models by default?
@@ -20,6 +23,10 @@ const DEFAULT_AUTO_PULL: bool = true;
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(80);
 /// Minimum tokens per second (TPS) for checking model performance during a generation.
 const DEFAULT_MIN_TPS: f64 = 15.0;
+/// Number of sample generations that the TPS of a model is measured over, after it is loaded.
+const TPS_SAMPLES: usize = 3;
+/// Timeout duration for pulling a model again right before a task.
+const DEFAULT_PULL_TIMEOUT: Duration = Duration::from_secs(120);
 
 /// Some models such as small embedding models, are hardcoded into the node.
 const HARDCODED_MODELS: [&str; 1] = ["hellord/mxbai-embed-large-v1:f16"];
@@ -40,6 +47,10 @@ pub struct OllamaConfig {
     timeout: Duration,
     /// Minimum tokens per second (TPS) for checking model performance during a generation.
     min_tps: f64,
+    /// Timeout duration for pulling a model again right before a task.
+    pull_timeout: Duration,
+    /// Tokens per second measured for each model during its latest test, by model name.
+    measured_tps: HashMap<String, f64>,
 }
 
 impl Default for OllamaConfig {
@@ -50,6 +61,8 @@ impl Default for OllamaConfig {
             auto_pull: DEFAULT_AUTO_PULL,
             timeout: DEFAULT_TIMEOUT,
             min_tps: DEFAULT_MIN_TPS,
+            pull_timeout: DEFAULT_PULL_TIMEOUT,
+            measured_tps: HashMap::new(),
         }
     }
 }
@@ -70,10 +83,18 @@ impl OllamaConfig {
             .map(|s| s == "true")
             .unwrap_or(true);
 
+        // minimum tps, models measured below this are not used
+        let min_tps = env::var("OLLAMA_MIN_TPS")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|tps| tps.is_finite() && *tps >= 0.0)
+            .unwrap_or(DEFAULT_MIN_TPS);
+
         Self {
             host,
             port,
             auto_pull,
+            min_tps,
             ..Default::default()
         }
     }
@@ -96,8 +117,89 @@ impl OllamaConfig {
         self
     }
 
+    /// Sets the timeout duration for pulling a model again right before a task.
+    pub fn with_pull_timeout(mut self, pull_timeout: Duration) -> Self {
+        self.pull_timeout = pull_timeout;
+        self
+    }
+
+    /// Limits the timeout duration for pulling a model again, e.g. to the time left until the deadline of a task.
+    pub fn with_pull_timeout_limit(mut self, limit: Duration) -> Self {
+        self.pull_timeout = self.pull_timeout.min(limit);
+        self
+    }
+
+    /// Checks that the model is still pulled right before a task, as it may have been
+    /// deleted by the user or by a disk cleanup since the service checks.
+    ///
+    /// A missing model is pulled again within the pull timeout if auto-pull is enabled,
+    /// otherwise this fails fast with [`MODEL_NOT_PULLED`] instead of a late generation error.
+    pub async fn ensure_model(&self, model: &Model) -> Result<()> {
+        let ollama = Ollama::new(&self.host, self.port);
+        let local_models = ollama
+            .list_local_models()
+            .await
+            .wrap_err("could not fetch local models from Ollama")?;
+        if local_models.iter().any(|m| m.name == model.to_string()) {
+            return Ok(());
+        }
+
+        if !self.auto_pull {
+            return Err(eyre!(
+                "{}: {} is not pulled in Ollama",
+                MODEL_NOT_PULLED,
+                model
+            ));
+        }
+        log::warn!("Model {} is missing in Ollama, pulling it again", model);
+        match tokio::time::timeout(
+            self.pull_timeout,
+            ollama.pull_model(model.to_string(), false),
+        )
+        .await
+        {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(eyre!(
+                "{}: could not pull {}: {}",
+                MODEL_NOT_PULLED,
+                model,
+                e
+            )),
+            Err(_) => Err(eyre!("{}: pulling {} timed out", MODEL_NOT_PULLED, model)),
+        }
+    }
+
+    /// Returns the tokens per second measured for each model during its latest test, by model name.
+    #[inline]
+    pub fn measured_tps(&self) -> &HashMap<String, f64> {
+        &self.measured_tps
+    }
+
+    /// Updates the tokens per second measured for the given models, e.g. with the ones of a refreshed clone,
+    /// keeping the measurements of other models.
+    #[inline]
+    pub fn update_measured_tps(&mut self, measured_tps: HashMap<String, f64>) {
+        self.measured_tps.extend(measured_tps);
+    }
+
+    /// Measures the tokens per second of the given models again, so that the measurements follow
+    /// the changes of the machine, e.g. its drivers or load.
+    ///
+    /// Returns the models that are now below the minimum TPS, or failed to be measured.
+    pub async fn refresh_tps(&mut self, models: &[Model]) -> Vec<Model> {
+        let ollama = Ollama::new(&self.host, self.port);
+        let mut slow_models = Vec::new();
+        for model in models {
+            if !self.test_performance(&ollama, model).await {
+                slow_models.push(model.clone());
+            }
+        }
+
+        slow_models
+    }
+
     /// Check if requested models exist in Ollama, and then tests them using a workflow.
-    pub async fn check(&self, external_models: Vec<Model>) -> Result<Vec<Model>> {
+    pub async fn check(&mut self, external_models: Vec<Model>) -> Result<Vec<Model>> {
         log::info!(
             "Checking Ollama requirements (auto-pull {}, timeout: {}s, min tps: {})",
             if self.auto_pull { "on" } else { "off" },
@@ -177,8 +279,10 @@ impl OllamaConfig {
     /// Runs a small workflow to test Ollama Workflows.
     ///
     /// This is to see if a given system can execute Ollama workflows for their chosen models,
-    /// e.g. if they have enough RAM/CPU and such.
-    pub async fn test_performance(&self, ollama: &Ollama, model: &Model) -> bool {
+    /// e.g. if they have enough RAM/CPU and such. The TPS is measured in the steady state, as the median
+    /// of [`TPS_SAMPLES`] generations after the model is loaded, and is kept for the specs,
+    /// see [`OllamaConfig::measured_tps`].
+    pub async fn test_performance(&mut self, ollama: &Ollama, model: &Model) -> bool {
         log::info!("Testing model {}", model);
 
         // first generate a dummy embedding to load the model into memory (warm-up)
@@ -191,39 +295,46 @@ impl OllamaConfig {
             return false;
         };
 
-        let generation_request = GenerationRequest::new(model.to_string(), TEST_PROMPT.to_string());
-
-        // then, run a sample generation with timeout and measure tps
-        tokio::select! {
-            _ = tokio::time::sleep(self.timeout) => {
-                log::warn!("Ignoring model {}: Workflow timed out", model);
-            },
-            result = ollama.generate(generation_request) => {
-                match result {
-                    Ok(response) => {
-                        let tps = (response.eval_count.unwrap_or_default() as f64)
-                        / (response.eval_duration.unwrap_or(1) as f64)
-                        * 1_000_000_000f64;
-
-                        if tps >= self.min_tps {
-                            log::info!("Model {} passed the test with tps: {}", model, tps);
-                            return true;
+        // then, run the sample generations with timeout and measure tps
+        let mut samples = Vec::with_capacity(TPS_SAMPLES);
+        for _ in 0..TPS_SAMPLES {
+            let generation_request =
+                GenerationRequest::new(model.to_string(), TEST_PROMPT.to_string());
+            tokio::select! {
+                _ = tokio::time::sleep(self.timeout) => {
+                    log::warn!("Ignoring model {}: Workflow timed out", model);
+                    return false;
+                },
+                result = ollama.generate(generation_request) => {
+                    match result {
+                        Ok(response) => samples.push(
+                            (response.eval_count.unwrap_or_default() as f64)
+                                / (response.eval_duration.unwrap_or(1) as f64)
+                                * 1_000_000_000f64,
+                        ),
+                        Err(e) => {
+                            log::warn!("Ignoring model {}: Workflow failed with error {}", model, e);
+                            return false;
                         }
-
-                        log::warn!(
-                            "Ignoring model {}: tps too low ({:.3} < {:.3})",
-                            model,
-                            tps,
-                            self.min_tps
-                        );
-                    }
-                    Err(e) => {
-                        log::warn!("Ignoring model {}: Workflow failed with error {}", model, e);
                     }
                 }
-            }
-        };
+            };
+        }
+
+        samples.sort_by(f64::total_cmp);
+        let tps = samples[samples.len() / 2];
+        self.measured_tps.insert(model.to_string(), tps);
+        if tps >= self.min_tps {
+            log::info!("Model {} passed the test with tps: {}", model, tps);
+            return true;
+        }
 
+        log::warn!(
+            "Ignoring model {}: tps too low ({:.3} < {:.3})",
+            model,
+            tps,
+            self.min_tps
+        );
         false
     }
 }
@@ -1,9 +1,9 @@
-use dkn_utils::safe_read_env;
 use eyre::{eyre, Context, Result};
 use ollama_workflows::Model;
 use reqwest::Client;
 use serde::Deserialize;
-use std::env;
+
+use crate::vault::read_secret;
 
 const ENV_VAR_NAME: &str = "OPENAI_API_KEY";
 
@@ -15,10 +15,10 @@ pub struct OpenAIConfig {
 }
 
 impl OpenAIConfig {
-    /// Looks at the environment variables for OpenAI API key.
+    /// Looks at the keychain (if enabled) & environment variables for OpenAI API key.
     pub fn new() -> Self {
         Self {
-            api_key: safe_read_env(env::var(ENV_VAR_NAME)),
+            api_key: read_secret(ENV_VAR_NAME),
         }
     }
 
@@ -164,6 +164,7 @@ impl OpenAIConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
 
     #[tokio::test]
     #[ignore = "requires OpenAI API key"]
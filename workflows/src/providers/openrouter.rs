@@ -1,8 +1,8 @@
-use dkn_utils::safe_read_env;
 use eyre::{eyre, Context, Result};
 use ollama_workflows::Model;
 use reqwest::Client;
-use std::env;
+
+use crate::vault::read_secret;
 
 const ENV_VAR_NAME: &str = "OPENROUTER_API_KEY";
 
@@ -14,10 +14,10 @@ pub struct OpenRouterConfig {
 }
 
 impl OpenRouterConfig {
-    /// Looks at the environment variables for OpenRouter API key.
+    /// Looks at the keychain (if enabled) & environment variables for OpenRouter API key.
     pub fn new() -> Self {
         Self {
-            api_key: safe_read_env(env::var(ENV_VAR_NAME)),
+            api_key: read_secret(ENV_VAR_NAME),
         }
     }
 
@@ -112,6 +112,7 @@ impl OpenRouterConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
 
     #[tokio::test]
     #[ignore = "requires OpenRouter API key"]
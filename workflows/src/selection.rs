@@ -0,0 +1,162 @@
+use ollama_workflows::{Model, ModelProvider};
+use rand::seq::IteratorRandom;
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// How the executor of a task is chosen when several of the configured models match it,
+/// e.g. when a task accepts a Llama model both via a local Ollama and via OpenRouter.
+#[derive(Debug, Clone, Default)]
+pub enum ModelSelection {
+    /// A random matching model is chosen.
+    #[default]
+    Random,
+    /// A random local (Ollama) model is chosen if there is one, otherwise a random matching model.
+    PreferLocal,
+    /// A random model of the cheapest provider is chosen, w.r.t the given costs of the providers.
+    ///
+    /// Ollama costs nothing unless given otherwise, and providers without a cost are considered the most expensive.
+    PreferCheapest(Arc<Vec<(ModelProvider, f64)>>),
+    /// Matching models are chosen in turns, shared across tasks.
+    RoundRobin(Arc<AtomicUsize>),
+}
+
+impl ModelSelection {
+    /// Sets the costs of the providers, e.g. per million tokens, used by the prefer-cheapest selection.
+    pub fn with_provider_costs(self, costs: Vec<(ModelProvider, f64)>) -> Self {
+        match self {
+            Self::PreferCheapest(_) => Self::PreferCheapest(Arc::new(costs)),
+            selection => selection,
+        }
+    }
+
+    /// Returns the cost of a provider w.r.t the given costs, see [`ModelSelection::PreferCheapest`].
+    fn provider_cost(costs: &[(ModelProvider, f64)], provider: &ModelProvider) -> f64 {
+        match costs.iter().find(|(p, _)| p == provider) {
+            Some((_, cost)) => *cost,
+            None if *provider == ModelProvider::Ollama => 0.0,
+            None => f64::INFINITY,
+        }
+    }
+
+    /// Chooses one of the given models, returns `None` if there are none.
+    pub fn choose(&self, models: Vec<(ModelProvider, Model)>) -> Option<(ModelProvider, Model)> {
+        match self {
+            Self::Random => models.into_iter().choose(&mut rand::thread_rng()),
+            Self::PreferLocal => {
+                let (local, remote): (Vec<_>, Vec<_>) = models
+                    .into_iter()
+                    .partition(|(p, _)| *p == ModelProvider::Ollama);
+                if local.is_empty() {
+                    remote.into_iter().choose(&mut rand::thread_rng())
+                } else {
+                    local.into_iter().choose(&mut rand::thread_rng())
+                }
+            }
+            Self::PreferCheapest(costs) => {
+                let cheapest = models
+                    .iter()
+                    .map(|(p, _)| Self::provider_cost(costs, p))
+                    .min_by(f64::total_cmp)?;
+                models
+                    .into_iter()
+                    .filter(|(p, _)| Self::provider_cost(costs, p) == cheapest)
+                    .choose(&mut rand::thread_rng())
+            }
+            Self::RoundRobin(turn) => {
+                if models.is_empty() {
+                    return None;
+                }
+                let idx = turn.fetch_add(1, Ordering::Relaxed) % models.len();
+                models.into_iter().nth(idx)
+            }
+        }
+    }
+}
+
+impl FromStr for ModelSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self::Random),
+            "prefer-local" => Ok(Self::PreferLocal),
+            "prefer-cheapest" => Ok(Self::PreferCheapest(Arc::default())),
+            "round-robin" => Ok(Self::RoundRobin(Arc::default())),
+            _ => Err(format!("unknown model selection: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for ModelSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Random => write!(f, "random"),
+            Self::PreferLocal => write!(f, "prefer-local"),
+            Self::PreferCheapest(_) => write!(f, "prefer-cheapest"),
+            Self::RoundRobin(_) => write!(f, "round-robin"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_selection() {
+        let models = vec![
+            (ModelProvider::OpenAI, Model::GPT4o),
+            (ModelProvider::Ollama, Model::Llama3_1_8B),
+            (ModelProvider::Gemini, Model::Gemini15Flash),
+        ];
+
+        let selection = "prefer-local".parse::<ModelSelection>().unwrap();
+        for _ in 0..10 {
+            let (provider, _) = selection.choose(models.clone()).unwrap();
+            assert_eq!(provider, ModelProvider::Ollama);
+        }
+        let (provider, _) = selection.choose(models[2..].to_vec()).unwrap();
+        assert_eq!(provider, ModelProvider::Gemini);
+
+        // local models are free unless given otherwise, and providers without a cost come last
+        let selection = "prefer-cheapest".parse::<ModelSelection>().unwrap();
+        let (provider, _) = selection.choose(models.clone()).unwrap();
+        assert_eq!(provider, ModelProvider::Ollama);
+        let selection = selection.with_provider_costs(vec![
+            (ModelProvider::Ollama, 1.0),
+            (ModelProvider::Gemini, 0.5),
+        ]);
+        for _ in 0..10 {
+            let (provider, _) = selection.choose(models.clone()).unwrap();
+            assert_eq!(provider, ModelProvider::Gemini);
+        }
+        let (provider, _) = selection.choose(models[..1].to_vec()).unwrap();
+        assert_eq!(provider, ModelProvider::OpenAI);
+
+        // turns are shared by the clones of a selection
+        let selection = "round-robin".parse::<ModelSelection>().unwrap();
+        let chosen = (0..4)
+            .map(|_| selection.clone().choose(models.clone()).unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chosen,
+            vec![
+                Model::GPT4o,
+                Model::Llama3_1_8B,
+                Model::Gemini15Flash,
+                Model::GPT4o
+            ]
+        );
+
+        assert!(ModelSelection::Random.choose(vec![]).is_none());
+        assert!("cheapest".parse::<ModelSelection>().is_err());
+        assert!(ModelSelection::PreferCheapest(Arc::default())
+            .choose(vec![])
+            .is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use dkn_utils::safe_read_env;
+use std::env;
+
+/// Service name under which the secrets are stored within the OS keychain.
+pub const KEYCHAIN_SERVICE: &str = "dkn-compute";
+
+/// Environment variables of the API keys used by the providers & tools.
+pub const API_KEY_VARS: [&str; 5] = [
+    "OPENAI_API_KEY",
+    "GEMINI_API_KEY",
+    "OPENROUTER_API_KEY",
+    "SERPER_API_KEY",
+    "JINA_API_KEY",
+];
+
+/// Reads a secret such as an API key, by its environment variable name.
+///
+/// With the `keychain` feature, the OS keychain is looked up first under [`KEYCHAIN_SERVICE`],
+/// falling back to the environment variable if the secret is not stored there.
+pub fn read_secret(name: &str) -> Option<String> {
+    #[cfg(feature = "keychain")]
+    if let Some(secret) = read_keychain(name) {
+        return Some(secret);
+    }
+
+    safe_read_env(env::var(name))
+}
+
+/// Sets the API keys stored within the OS keychain as their environment variables.
+///
+/// The executors read the API keys from the environment only, so this is to be called at startup
+/// before they are created, so that they use the same keys as the service checks. Does nothing
+/// without the `keychain` feature.
+pub fn resolve_secrets() {
+    #[cfg(feature = "keychain")]
+    for name in API_KEY_VARS {
+        if let Some(secret) = read_keychain(name) {
+            log::info!("Using {} from the keychain.", name);
+            env::set_var(name, secret);
+        }
+    }
+}
+
+/// Reads a secret from the OS keychain, returns `None` if it is not stored there.
+#[cfg(feature = "keychain")]
+fn read_keychain(name: &str) -> Option<String> {
+    match keyring::Entry::new(KEYCHAIN_SERVICE, name).and_then(|entry| entry.get_password()) {
+        Ok(secret) if !secret.trim().is_empty() => Some(secret.trim().to_string()),
+        Ok(_) | Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            log::warn!("Could not read {} from the keychain: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Stores a secret within the OS keychain, by its environment variable name.
+#[cfg(feature = "keychain")]
+pub fn store_secret(name: &str, secret: &str) -> eyre::Result<()> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, name)?.set_password(secret)?;
+    Ok(())
+}
+
+/// Removes a secret from the OS keychain, by its environment variable name.
+#[cfg(feature = "keychain")]
+pub fn remove_secret(name: &str) -> eyre::Result<()> {
+    match keyring::Entry::new(KEYCHAIN_SERVICE, name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}